@@ -51,6 +51,7 @@ fn main() -> Result<(), DisplayError> {
         legal_title_chars,
         link_trail,
         magic_links,
+        time_zone,
     } = query.general;
 
     let api::MagicLinks { isbn, pmid, rfc } = magic_links;
@@ -102,6 +103,7 @@ fn main() -> Result<(), DisplayError> {
             redirect_magic_words: phf::phf_set! {
                 #(#redirects),*
             },
+            time_zone: #time_zone,
             valid_title_bytes: #legal_title_chars,
             variables: phf::phf_map! {
                 #(#variables),*
@@ -284,6 +286,8 @@ mod api {
         pub link_trail: Cow<'a, str>,
         #[serde(rename = "magiclinks")]
         pub magic_links: MagicLinks,
+        #[serde(borrow, rename = "timezone")]
+        pub time_zone: Cow<'a, str>,
     }
 
     #[derive(serde::Deserialize)]
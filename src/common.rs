@@ -105,9 +105,26 @@ where
 }
 // SPDX-SnippetEnd
 
-/// The i18n dictionary from MediaWiki.
-pub(crate) static MESSAGES: LazyLock<serde_json::Value> =
-    LazyLock::new(|| serde_json::from_str(include_str!("../res/i18n/en.json")).unwrap());
+/// The i18n dictionary from MediaWiki, plus the messages of supported
+/// extensions.
+pub(crate) static MESSAGES: LazyLock<serde_json::Value> = LazyLock::new(|| {
+    let mut messages =
+        serde_json::from_str::<serde_json::Value>(include_str!("../res/i18n/en.json")).unwrap();
+    let cite = serde_json::from_str::<serde_json::Map<_, _>>(include_str!("../res/i18n/cite.json"))
+        .unwrap();
+    messages.as_object_mut().unwrap().extend(cite);
+    let parser_functions = serde_json::from_str::<serde_json::Map<_, _>>(include_str!(
+        "../res/i18n/parser_functions.json"
+    ))
+    .unwrap();
+    messages.as_object_mut().unwrap().extend(parser_functions);
+    let template_data = serde_json::from_str::<serde_json::Map<_, _>>(include_str!(
+        "../res/i18n/templatedata.json"
+    ))
+    .unwrap();
+    messages.as_object_mut().unwrap().extend(template_data);
+    messages
+});
 
 /// Encodes section heading text into a format suitable for use as a URL anchor.
 pub fn anchor_encode(s: &str) -> String {
@@ -266,7 +283,21 @@ pub(crate) fn format_date_strftime(
             Some(b'U') => write!(out, "{:02}", time.sunday_based_week()),
             Some(b'V') => write!(out, "{:02}", time.iso_week()),
             Some(b'W') => write!(out, "{:02}", time.monday_based_week()),
-            Some(b'x' | b'X') => todo!(),
+            // The C locale formats
+            Some(b'x') => write!(
+                out,
+                "{:02}/{:02}/{:02}",
+                u8::from(time.month()),
+                time.day(),
+                time.year() % 100
+            ),
+            Some(b'X') => write!(
+                out,
+                "{:02}:{:02}:{:02}",
+                time.hour(),
+                time.minute(),
+                time.second()
+            ),
             Some(b'y') => write!(out, "{:02}", time.year() % 100),
             Some(b'Y') => write!(out, "{}", time.year()),
             Some(b'z') => write!(
@@ -290,8 +321,8 @@ pub(crate) fn format_date_strftime(
 /// The `date` string is a modified form of the PHP date string format where
 /// a four-digit number is treated as a year instead of a time.
 ///
-/// If `local` is true, the time will be treated as being in the system time
-/// zone; otherwise, it will be treated as UTC.
+/// If `local` is true, the time will be treated as being in the wiki time zone;
+/// otherwise, it will be treated as UTC.
 ///
 /// The value given in `now` will be used as the current time if no `date` is
 /// given.
@@ -314,7 +345,7 @@ pub fn format_date_mediawiki(
     };
 
     let tz = if local {
-        DateTimeZone::local()?
+        DateTimeZone::local()
     } else {
         DateTimeZone::UTC
     };
@@ -1066,6 +1066,7 @@ static CONFIG_SOURCE: ConfigurationSource = ConfigurationSource {
     redirect_magic_words: phf::phf_set! {
         "#redirect"
     },
+    time_zone: "UTC",
     valid_title_bytes: " %!\"$&'()*,\\-.\\/0-9:;=?@A-Z\\\\^_`a-z~\\x80-\\xFF+",
     variables: phf::phf_map! {
         "!" => "!", "=" => "=", "articlepath" => "articlepath", "basepagename" =>
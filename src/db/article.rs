@@ -26,6 +26,8 @@ pub(crate) struct Article {
     /// This is arbitrary text content which must be interpreted according to
     /// the article’s [data model](Self::model).
     pub body: String,
+    /// The date of the article revision, if it came from a dump.
+    pub date: Option<UtcDateTime>,
     /// The data model of the article. This is usually "wikitext", but can be
     /// "json" for JSON data, "Scribunto" for Lua modules, etc.
     pub model: String,
@@ -196,11 +198,11 @@ impl ArticleDatabase {
         let title = try_get_child(article, "title")?.text();
         let revision = try_get_child(article, "revision")?;
         let body = try_get_child(revision, "text")?.text();
-        // TODO: This may be needed eventually, so remember it exists
-        // let date = UtcDateTime::parse(
-        //     &try_get_child(revision, "timestamp")?.text(),
-        //     &Iso8601::DEFAULT,
-        // )?;
+        // A bad timestamp only affects `REVISION*` variables, so it should not
+        // make the whole page unloadable
+        let date = try_get_child(revision, "timestamp")
+            .ok()
+            .and_then(|date| UtcDateTime::parse(&date.text(), &Iso8601::PARSING).ok());
         let model = try_get_child(revision, "model")?.text();
         let redirect = try_get_child(article, "redirect")
             .ok()
@@ -215,6 +217,7 @@ impl ArticleDatabase {
             id,
             title,
             body,
+            date,
             model,
             redirect,
         })
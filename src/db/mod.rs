@@ -183,6 +183,7 @@ impl RawDatabase<'_> {
                 id: 0xdead_beef,
                 title: key.to_owned(),
                 body: (*body).to_owned(),
+                date: None,
                 model: if key.starts_with("Module:") {
                     "Scribunto"
                 } else {
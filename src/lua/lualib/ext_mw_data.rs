@@ -8,36 +8,81 @@
 // SPDX-License-Identifier: GPL-2.0-or-later
 
 use super::prelude::*;
+use crate::{
+    db::{Article, Database},
+    renderer::localize_json_data,
+    title::Title,
+};
+use arc_cell::OptionalArcCell;
+use serde::Serialize as _;
 
 /// MediaWiki `JsonConfig` extension.
 #[derive(gc_arena::Collect, Default)]
 #[collect(require_static)]
-pub(super) struct JCLuaLibrary;
+pub(crate) struct JCLuaLibrary {
+    /// The shared media repository database, where the `Data:` namespace
+    /// lives.
+    db: OptionalArcCell<Database<'static>>,
+}
 
 impl JCLuaLibrary {
-    /// Gets JSON that conforms to the MediaWiki Commons JSON schema from
-    /// the MediaWiki Commons with the given title and language.
-    ///
-    /// Since Commons data is not included in dumps from other MW installations,
-    /// this function can never work in an offline context, and always returns a
-    /// stub.
+    /// Gets the data from a tabular (`.tab`) or map (`.map`) page in the
+    /// `Data:` namespace of the shared media repository, localised into the
+    /// given language. If the language is `"_"`, the data is returned with
+    /// all of its translations. Returns `false` if the page does not exist.
     fn get<'gc>(
         &self,
         ctx: Context<'gc>,
         (title, lang): (VmString<'gc>, Option<VmString<'gc>>),
-    ) -> Result<Table<'gc>, VmError<'gc>> {
-        log::warn!("stub: JCLuaLibrary.get({title:?}, {lang:?})");
-        Ok(table! {
-            using ctx;
+    ) -> Result<Value<'gc>, VmError<'gc>> {
+        let lang = lang.map(|lang| lang.to_str()).transpose()?;
+        let data = load_data(title.to_str()?, lang, |title| {
+            self.db.get().unwrap().get(title).ok()
+        })?;
 
-            data = Table::new(&ctx),
-            schema = table! {
-                using ctx;
+        let Some(data) = data else {
+            return Ok(Value::Boolean(false));
+        };
 
-                fields = Table::new(&ctx),
-            }
-        })
+        let ser = piccolo_util::serde::ser::Serializer::new(ctx, <_>::default());
+        Ok(data.serialize(ser)?)
+    }
+
+    /// Sets the shared media repository database.
+    pub(crate) fn set_db(&self, db: &Arc<Database<'static>>) {
+        self.db.set(Some(Arc::clone(db)));
+    }
+}
+
+/// Loads the data page `name`, using `page` to get the page from the shared
+/// media repository, and localises it into `lang` like
+/// [`JCLuaLibrary::get`]. Returns `None` if the page does not exist.
+fn load_data(
+    name: &str,
+    lang: Option<&str>,
+    page: impl FnOnce(&Title) -> Option<Arc<Article>>,
+) -> Result<Option<serde_json::Value>, anyhow::Error> {
+    let name = name.trim();
+    let name = name.strip_prefix("Data:").unwrap_or(name);
+    if !name.ends_with(".tab") && !name.ends_with(".map") {
+        return Err(anyhow::anyhow!(
+            "bad argument #1 to 'get' ('{name}' is not a valid title)"
+        ));
     }
+
+    let title = Title::new(&format!("Data:{name}"), None);
+    let Some(article) = page(&title) else {
+        log::warn!("mw.ext.data.get: could not find {}", title.key());
+        return Ok(None);
+    };
+
+    let data = serde_json::from_str(&article.body)?;
+
+    // The content language is always English
+    Ok(Some(match lang {
+        Some("_") => data,
+        lang => localize_json_data(data, lang.unwrap_or("en")),
+    }))
 }
 
 impl MwInterface for JCLuaLibrary {
@@ -56,3 +101,65 @@ impl MwInterface for JCLuaLibrary {
         Ok(Table::new(&ctx))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Gets a test data page.
+    fn page(title: &Title) -> Option<Arc<Article>> {
+        (title.key() == "Data:Fruit.tab").then(|| {
+            Arc::new(Article {
+                id: 1,
+                title: title.key().to_owned(),
+                body: json!({
+                    "license": "CC0-1.0",
+                    "description": { "en": "Fruit", "fr": "Fruits" },
+                    "schema": { "fields": [{ "name": "name", "type": "localized" }] },
+                    "data": [[{ "en": "apple", "fr": "pomme" }]],
+                })
+                .to_string(),
+                date: None,
+                model: "Tabular.JsonConfig".to_owned(),
+                redirect: None,
+            })
+        })
+    }
+
+    #[test]
+    fn localized() {
+        let data = load_data("Fruit.tab", Some("fr"), page).unwrap().unwrap();
+        assert_eq!(data["description"], "Fruits");
+        assert_eq!(data["data"], json!([["pomme"]]));
+        assert_eq!(
+            data["license"]["text"],
+            "Creative Commons Zero v1.0 Universal"
+        );
+
+        let data = load_data(" Data:Fruit.tab ", None, page).unwrap().unwrap();
+        assert_eq!(data["description"], "Fruit");
+        assert_eq!(data["data"], json!([["apple"]]));
+    }
+
+    #[test]
+    fn all_languages() {
+        let data = load_data("Fruit.tab", Some("_"), page).unwrap().unwrap();
+        assert_eq!(
+            data["description"],
+            json!({ "en": "Fruit", "fr": "Fruits" })
+        );
+        assert_eq!(data["data"], json!([[{ "en": "apple", "fr": "pomme" }]]));
+        assert_eq!(data["license"], "CC0-1.0");
+    }
+
+    #[test]
+    fn missing_page() {
+        assert!(load_data("Vegetables.tab", None, page).unwrap().is_none());
+    }
+
+    #[test]
+    fn invalid_name() {
+        assert!(load_data("Fruit.json", None, page).is_err());
+    }
+}
@@ -6,6 +6,7 @@
     reason = "implementing an interface invisible to clippy"
 )]
 
+pub(super) use ext_mw_data::JCLuaLibrary;
 use gc_arena::{Collect, Rootable};
 pub(super) use mw::{LuaEngine, run_host_call};
 pub(super) use mw_language::LanguageLibrary;
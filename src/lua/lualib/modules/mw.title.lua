@@ -198,6 +198,7 @@ local function makeTitleObject( data )
 		contentModel = 'e',
 		id = 'e',
 		redirectTarget = true,
+		pageProperties = true,
 	}
 	for k in pairs( data ) do
 		readOnlyFields[k] = true
@@ -314,6 +315,14 @@ local function makeTitleObject( data )
 				end
 				return data.categories
 			end
+			-- Not part of Scribunto; page properties are set by behavior
+			-- switches like `__DISAMBIG__`
+			if k == 'pageProperties' then
+				if data.pageProperties == nil then
+					data.pageProperties = php.getPageProperties( data.prefixedText )
+				end
+				return data.pageProperties
+			end
 
 			if data[k] == nil then
 				data[k] = php.getAttributeValue( data.prefixedText, k )
@@ -12,7 +12,7 @@ use crate::{
     db::Database,
     lua::{HostCall, LuaFrame},
     renderer::{
-        CachedValue, ExpandMode, ExpandTemplates, Kv, StackFrame, State, Surrogate as _,
+        CachedValue, ExpandMode, ExpandTemplates, Kv, StackFrame, State, Surrogate as _, TraceKind,
         call_parser_fn, call_template, resolve_callee,
     },
     title::{Namespace, Title},
@@ -517,7 +517,10 @@ fn call_parser_function(
         let callee = resolve_callee(args.is_empty(), true, &callee).ok_or_else(|| {
             anyhow::anyhow!("callParserFunction: function \"{callee}\" was not found")
         })?;
-        call_parser_fn(&mut result, state, sp, None, callee, &args)?;
+        state.trace_enter(TraceKind::ParserFn, callee, sp, None, &args);
+        let call = call_parser_fn(&mut result, state, sp, None, callee, &args);
+        state.trace_exit(&result);
+        call?;
         Ok(state
             .statics
             .vm
@@ -544,13 +547,17 @@ fn expand_template(
 
     with_sp(&frame_id, Some(sp), |sp| {
         let mut result = String::new();
-        call_template(
-            &mut result,
-            state,
+        let title = Title::new(&title, Namespace::find_by_id(Namespace::TEMPLATE));
+        state.trace_enter(
+            TraceKind::Template,
+            &title.to_string(),
             sp,
-            &Title::new(&title, Namespace::find_by_id(Namespace::TEMPLATE)),
+            None,
             &arguments,
-        )?;
+        );
+        let call = call_template(&mut result, state, sp, &title, &arguments);
+        state.trace_exit(&result);
+        call?;
         Ok(state
             .statics
             .vm
@@ -646,9 +653,12 @@ fn preprocess(
         let source = FileMap::new(&text);
         let root = state.statics.parser.parse(&source, true)?;
         let sp = sp.clone_with_source(source);
+        state.trace_enter(TraceKind::Preprocess, "frame:preprocess", &sp, None, &[]);
         let mut expand = ExpandTemplates::new(ExpandMode::Include);
-        expand.adopt_output(state, &sp, &root)?;
+        let adopted = expand.adopt_output(state, &sp, &root);
         let result = expand.finish();
+        state.trace_exit(&result);
+        adopted?;
         Ok(state
             .statics
             .vm
@@ -11,7 +11,9 @@ use super::prelude::*;
 use crate::{
     common::{make_url, url_encode, url_encode_bytes},
     db::Database,
+    renderer::PageProperties,
     title::{Namespace, Title},
+    wikitext::Parser,
 };
 use arc_cell::OptionalArcCell;
 use axum::http::Uri;
@@ -30,6 +32,8 @@ pub(crate) struct TitleLibrary {
     base_uri: RefCell<Option<Uri>>,
     /// The article database.
     db: OptionalArcCell<Database<'static>>,
+    /// The Wikitext parser.
+    parser: RefCell<Option<Parser<'static>>>,
     /// The title of the current article being rendered.
     this_title: Cell<Option<StashedTable>>,
 }
@@ -42,9 +46,15 @@ impl TitleLibrary {
     }
 
     /// Sets static shared state required for the library to function.
-    pub fn set_shared(&self, base_uri: &Uri, db: &Arc<Database<'static>>) {
+    pub fn set_shared(
+        &self,
+        base_uri: &Uri,
+        db: &Arc<Database<'static>>,
+        parser: &Parser<'static>,
+    ) {
         *self.base_uri.borrow_mut() = Some(base_uri.clone());
         self.db.set(Some(Arc::clone(db)));
+        *self.parser.borrow_mut() = Some(parser.clone());
     }
 
     mw_unimplemented! {
@@ -173,6 +183,26 @@ impl TitleLibrary {
         )
     }
 
+    /// Gets the page properties of the article with the given title text, as
+    /// a table where each property is set to an empty string, like the
+    /// `pageprops` of the MediaWiki API.
+    fn get_page_properties<'gc>(
+        &self,
+        ctx: Context<'gc>,
+        text: VmString<'_>,
+    ) -> Result<Table<'gc>, VmError<'gc>> {
+        let title = Title::new(text.to_str()?, None);
+        let parser = Ref::filter_map(self.parser.borrow(), Option::as_ref)
+            .map_err(|_| "missing parser".into_value(ctx))?;
+        let properties = PageProperties::from_article(&self.db.get().unwrap(), &parser, &title);
+
+        let table = Table::new(&ctx);
+        for name in properties.iter() {
+            table.set(ctx, ctx.intern(name.as_bytes()), "")?;
+        }
+        Ok(table)
+    }
+
     /// Creates a URL for an article with the given title text and optional
     /// query string.
     ///
@@ -335,6 +365,7 @@ impl MwInterface for TitleLibrary {
             getExpensiveData = get_expensive_data,
             getFileInfo = get_file_info,
             getPageLangCode = get_page_lang_code,
+            getPageProperties = get_page_properties,
             getUrl = get_url,
             makeTitle = make_title,
             newTitle = new_title,
@@ -10,10 +10,10 @@ use crate::{
 use axum::http::Uri;
 use core::{ops::ControlFlow, pin::Pin};
 use gc_arena::Rootable;
-use lualib::{LanguageLibrary, LuaEngine, TitleLibrary, UriLibrary};
+use lualib::{JCLuaLibrary, LanguageLibrary, LuaEngine, TitleLibrary, UriLibrary};
 use piccolo::{
     Executor, ExecutorMode, ExternError, Fuel, Function, Lua, RuntimeError, StashedClosure,
-    StashedString, StashedTable, TypeError, thread::BadExecutorMode,
+    StashedExecutor, StashedString, StashedTable, TypeError, thread::BadExecutorMode,
 };
 use prelude::*;
 use std::{sync::Arc, time::Instant};
@@ -116,10 +116,11 @@ pub(super) fn new_vm_core() -> Result<Lua, ExternError> {
     Ok(vm)
 }
 
-/// Creates a new Lua VM.
+/// Creates a new Lua VM. Data pages are loaded from `shared_db`.
 pub(super) fn new_vm(
     base_uri: &Uri,
     db: &Arc<Database<'static>>,
+    shared_db: &Arc<Database<'static>>,
     parser: &Parser<'static>,
 ) -> Result<Lua, ExternError> {
     let mut vm = new_vm_core()?;
@@ -128,8 +129,11 @@ pub(super) fn new_vm(
         let mw = ctx.singleton::<Rootable![LuaEngine]>();
         mw.set_db(db);
 
+        let mw_ext_data = ctx.singleton::<Rootable![JCLuaLibrary]>();
+        mw_ext_data.set_db(shared_db);
+
         let mw_title = ctx.singleton::<Rootable![TitleLibrary]>();
-        mw_title.set_shared(base_uri, db);
+        mw_title.set_shared(base_uri, db, parser);
 
         let mw_uri = ctx.singleton::<Rootable![UriLibrary]>();
         mw_uri.set_parser(parser.clone());
@@ -158,8 +162,73 @@ pub(super) fn run_vm(
     code: &Arc<Article>,
     fn_name: &str,
 ) -> Result<String, ExternError> {
-    let (module, env) = fetch_module(state, sp, code)?;
+    let (module, env) = fetch_module(state, sp, code, false)?;
+
+    let ex = state.statics.vm.try_enter(|ctx| {
+        let module = ctx.fetch(&module);
+        let env = ctx.fetch(&env);
+        let mw = ctx.get_global::<Table<'_>>("mw")?;
+        let mw_exec = mw.get::<_, Function<'_>>(ctx, "executeFunction")?;
+        Ok(ctx.stash(Executor::start(
+            ctx,
+            mw_exec,
+            (module, ctx.intern(fn_name.trim().as_bytes()), env),
+        )))
+    })?;
+
+    run_executor(state, sp, &ex)
+}
+
+/// Loads a Scribunto module and calls one of its functions directly with
+/// JSON-encoded `args`, returning the JSON-encoded result.
+///
+/// This is for extensions which call into modules without `#invoke`, so the
+/// called function receives plain values instead of a frame.
+pub(super) fn call_json_function(
+    state: &mut State<'_>,
+    sp: Pin<&StackFrame<'_>>,
+    code: &Arc<Article>,
+    fn_name: &str,
+    args: &str,
+) -> Result<String, ExternError> {
+    /// Decodes the arguments, calls the function, and encodes the result.
+    const CALL_JSON_FUNCTION: &str = "\
+        local chunk, name, args = ...
+        local result = chunk()[name](unpack(mw.text.jsonDecode(args)))
+        return mw.text.jsonEncode(result)";
+
+    let (module, env) = fetch_module(state, sp, code, true)?;
 
+    let ex = state.statics.vm.try_enter(|ctx| {
+        let module = ctx.fetch(&module);
+        let env = ctx.fetch(&env);
+        let call = Closure::load_with_env(
+            ctx,
+            Some("callJsonFunction"),
+            CALL_JSON_FUNCTION.as_bytes(),
+            env,
+        )?;
+        Ok(ctx.stash(Executor::start(
+            ctx,
+            Function::Closure(call),
+            (
+                module,
+                ctx.intern(fn_name.trim().as_bytes()),
+                ctx.intern(args.as_bytes()),
+            ),
+        )))
+    })?;
+
+    run_executor(state, sp, &ex)
+}
+
+/// Runs a started executor to completion, servicing any host calls, and
+/// returns its string result.
+fn run_executor(
+    state: &mut State<'_>,
+    sp: Pin<&StackFrame<'_>>,
+    ex: &StashedExecutor,
+) -> Result<String, ExternError> {
     let mut state = {
         let old_sp = state.statics.vm.enter(|ctx| {
             let engine = ctx.singleton::<Rootable![lualib::LuaEngine]>();
@@ -177,18 +246,6 @@ pub(super) fn run_vm(
         })
     };
 
-    let ex = state.statics.vm.try_enter(|ctx| {
-        let module = ctx.fetch(&module);
-        let env = ctx.fetch(&env);
-        let mw = ctx.get_global::<Table<'_>>("mw")?;
-        let mw_exec = mw.get::<_, Function<'_>>(ctx, "executeFunction")?;
-        Ok(ctx.stash(Executor::start(
-            ctx,
-            mw_exec,
-            (module, ctx.intern(fn_name.trim().as_bytes()), env),
-        )))
-    })?;
-
     // TODO: This time limit should probably exclude time spent loading from the
     // database.
     let start = Instant::now();
@@ -201,7 +258,7 @@ pub(super) fn run_vm(
             match state
                 .statics
                 .vm
-                .enter(|ctx| ctx.fetch(&ex).step(ctx, &mut fuel))
+                .enter(|ctx| ctx.fetch(ex).step(ctx, &mut fuel))
             {
                 Ok(true) => break,
                 Ok(false) => {
@@ -222,7 +279,7 @@ pub(super) fn run_vm(
         }
 
         let result = state.statics.vm.try_enter(|ctx| {
-            let ex = ctx.fetch(&ex);
+            let ex = ctx.fetch(ex);
             if ex.mode() == ExecutorMode::Result {
                 let result = ex.take_result::<Value<'_>>(ctx)??;
                 if let Value::String(result) = result {
@@ -251,7 +308,7 @@ pub(super) fn run_vm(
             ControlFlow::Continue(host_call) => {
                 let result = lualib::run_host_call(&mut state, &sp, &host_call)?;
                 state.statics.vm.try_enter(|ctx| {
-                    let ex = ctx.fetch(&ex);
+                    let ex = ctx.fetch(ex);
                     let result = ctx.fetch(&result);
                     ex.resume(ctx, result)?;
                     Ok(())
@@ -262,39 +319,21 @@ pub(super) fn run_vm(
     }
 }
 
-/// Fetches a possibly cached Lua module for execution.
+/// Fetches a possibly cached Lua module for execution. If `shared` is true,
+/// the module was loaded from the shared media repository database.
 fn fetch_module(
     state: &mut State<'_>,
     sp: Pin<&StackFrame<'_>>,
     code: &Arc<Article>,
+    shared: bool,
 ) -> Result<(StashedClosure, StashedTable), ExternError> {
-    let VmCacheEntry { module, env } = if let Some(cached) = state.statics.vm_cache.get(&code.id) {
+    let key = (shared && state.statics.commons_db.is_some(), code.id);
+    let VmCacheEntry { module, env } = if let Some(cached) = state.statics.vm_cache.get(&key) {
         cached.clone()
     } else {
-        let ex = state.statics.vm.try_enter(|ctx| {
-            let mw = ctx.get_global::<Table<'_>>("mw")?;
-            let make_env = mw.get::<_, Function<'_>>(ctx, "makeEnv")?;
-            Ok(ctx.stash(Executor::start(ctx, make_env, Value::Nil)))
-        })?;
-
-        state.statics.vm.finish(&ex).map_err(RuntimeError::from)?;
-
-        // Too many modules rely on their closure being re-executed on every
-        // invocation, so that is what `mw.executeFunction` does. Some
-        // modules also expect that `packageCache` will be reset, but
-        // wiki.rs does *not* do that and instead gives those modules some
-        // free therapy in `crate::db::HACKS` until they learn to work well
-        // with others
-        let (module, env) = state.statics.vm.try_enter(|ctx| {
-            let env = ctx.fetch(&ex).take_result::<Table<'_>>(ctx)??;
-            let module =
-                Closure::load_with_env(ctx, Some(sp.name.key()), code.body.as_bytes(), env)?;
-
-            Ok((ctx.stash(module), ctx.stash(env)))
-        })?;
-
+        let (module, env) = load_module(state, sp, code)?;
         let entry = VmCacheEntry { module, env };
-        state.statics.vm_cache.insert(code.id, entry.clone());
+        state.statics.vm_cache.insert(key, entry.clone());
 
         if memory_exceeded(state) {
             return Err(RuntimeError::new(anyhow::anyhow!("memory limit exceeded")).into());
@@ -305,6 +344,34 @@ fn fetch_module(
     Ok((module, env))
 }
 
+/// Loads a Lua module into a new sandbox environment.
+fn load_module(
+    state: &mut State<'_>,
+    sp: Pin<&StackFrame<'_>>,
+    code: &Arc<Article>,
+) -> Result<(StashedClosure, StashedTable), ExternError> {
+    let ex = state.statics.vm.try_enter(|ctx| {
+        let mw = ctx.get_global::<Table<'_>>("mw")?;
+        let make_env = mw.get::<_, Function<'_>>(ctx, "makeEnv")?;
+        Ok(ctx.stash(Executor::start(ctx, make_env, Value::Nil)))
+    })?;
+
+    state.statics.vm.finish(&ex).map_err(RuntimeError::from)?;
+
+    // Too many modules rely on their closure being re-executed on every
+    // invocation, so that is what `mw.executeFunction` does. Some
+    // modules also expect that `packageCache` will be reset, but
+    // wiki.rs does *not* do that and instead gives those modules some
+    // free therapy in `crate::db::HACKS` until they learn to work well
+    // with others
+    state.statics.vm.try_enter(|ctx| {
+        let env = ctx.fetch(&ex).take_result::<Table<'_>>(ctx)??;
+        let module = Closure::load_with_env(ctx, Some(sp.name.key()), code.body.as_bytes(), env)?;
+
+        Ok((ctx.stash(module), ctx.stash(env)))
+    })
+}
+
 /// Naïvely reduces memory pressure on the VM if needed by running garbage
 /// collection and evicting cached modules. Returns true if the VM’s memory
 /// usage still exceeds the limit after doing everything possible to reduce
@@ -28,7 +28,7 @@ pub fn load_os(ctx: Context<'_>) {
             let (start, offset) = if format.first() == Some(&b'!') {
                 (1, DateTimeZone::UTC)
             } else {
-                (0, DateTimeZone::local()?)
+                (0, DateTimeZone::local())
             };
 
             let time = time
@@ -89,6 +89,10 @@ type AppState = Arc<WikiState>;
 struct Limits {
     /// Database decompression cache size limit, in bytes. One per process.
     db_cache: usize,
+    /// Persistent cache directory size limit, in bytes. One per directory.
+    disk_cache: usize,
+    /// Extension tag output cache size limit, in bytes. One per process.
+    ext_cache: usize,
     /// Template token tree cache size limit, in bytes. One per process.
     template_cache: usize,
     /// Maximum number of renderer threads.
@@ -106,10 +110,14 @@ impl core::fmt::Display for Limits {
     )]
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let db_cache = self.db_cache as f64 / 1024.0;
+        let disk_cache = self.disk_cache as f64 / 1024.0;
+        let ext_cache = self.ext_cache as f64 / 1024.0;
         let template_cache = self.template_cache as f64 / 1024.0;
         let vm_total_mem = self.vm_total_mem as f64 / 1024.;
         writeln!(f, "Resource limits:")?;
         writeln!(f, "  Database cache:         {db_cache:.2}KiB")?;
+        writeln!(f, "  Disk cache (per dir):   {disk_cache:.2}KiB")?;
+        writeln!(f, "  Extension tag cache:    {ext_cache:.2}KiB")?;
         writeln!(f, "  Template cache:         {template_cache:.2}KiB")?;
         writeln!(f, "  Threads:                {}", self.threads)?;
         writeln!(f, "  VM memory (per thread): {vm_total_mem:.2}KiB")?;
@@ -159,6 +167,12 @@ impl core::str::FromStr for LoadMode {
 /// Errors that may occur when parsing arguments.
 #[derive(Debug, thiserror::Error)]
 enum ArgsError {
+    /// Date parsing error.
+    #[error("invalid --as-of time: {0}")]
+    AsOf(#[from] php::DateTimeError),
+    /// Only one half of the Commons database arguments was given.
+    #[error("--commons-index and --commons-db must be given together")]
+    Commons,
     /// Byte size conversion error.
     #[error(
         "unknown byte size unit '{0}' (should be '', 'b', 'B', 'k', 'K', 'm', 'M', 'g', or 'G')"
@@ -182,23 +196,47 @@ enum ArgsError {
     /// Some other parsing error.
     #[error(transparent)]
     Pico(#[from] pico_args::Error),
+    /// Unknown time zone.
+    #[error("unknown time zone '{0}' (should be an IANA time zone name like 'Europe/Berlin')")]
+    TimeZone(String),
 }
 
 /// Command-line arguments.
 struct Args {
     /// The path to `database.xml.bz2`.
     articles_path: String,
+    /// The time to render pages as of, instead of the database creation time.
+    as_of: Option<php::DateTime>,
     /// The base URI used when generating links to resources. Useful if you
     /// decide to put this behind a web proxy for some reason.
     base_uri: Option<String>,
     /// The bind address for the web server.
     bind: String,
+    /// The paths to `index.txt` and `database.xml.bz2` for a Wikimedia Commons
+    /// dump, used for `Data:` pages.
+    commons: Option<(String, String)>,
+    /// The directory where extension tag output is persisted, if any.
+    ext_cache_dir: Option<String>,
+    /// The paths to fallback font files for text in graphs with characters
+    /// that the built-in font does not support.
+    fonts: Vec<String>,
     /// The path to `index.txt`.
     index_path: String,
     /// Configurable resource limits.
     limits: Limits,
     /// The default strategy for loading pages.
     load_mode: LoadMode,
+    /// The path to a GeoJSON file to use as a basemap for `<mapframe>` and
+    /// `<maplink>` maps.
+    map_basemap: Option<String>,
+    /// The maximum size of the rendered image cache, in bytes. If `None`,
+    /// images are not rendered to PNG.
+    png_cache: Option<usize>,
+    /// The directory where rendered images are persisted, if any.
+    png_cache_dir: Option<String>,
+    /// The local time zone of the wiki, overriding the one from the
+    /// configuration.
+    time_zone: Option<String>,
 }
 
 impl Args {
@@ -218,6 +256,16 @@ impl Args {
         }
     }
 
+    /// Parses a date and time in any format supported by PHP’s `strtotime`.
+    /// Times without a time zone are in UTC.
+    fn parse_as_of(value: &str) -> Result<php::DateTime, ArgsError> {
+        Ok(php::DateTime::new(
+            value,
+            Some(&php::DateTimeZone::UTC),
+            None,
+        )?)
+    }
+
     /// Parses a time duration string in the format `\d+(\.\d+)?\s*(m?s)`.
     fn parse_duration(value: &str) -> Result<Duration, ArgsError> {
         let (number, unit) = Self::parse_number_with_unit(value)?;
@@ -267,6 +315,15 @@ impl Args {
         } as usize)
     }
 
+    /// Validates an IANA time zone name.
+    fn parse_time_zone(value: &str) -> Result<String, ArgsError> {
+        if php::DateTimeZone::named(value).is_some() {
+            Ok(value.to_owned())
+        } else {
+            Err(ArgsError::TimeZone(value.to_owned()))
+        }
+    }
+
     /// Tries to create an [`Args`] from the given command line arguments and
     /// environment variables.
     fn new() -> Result<Args, ArgsError> {
@@ -274,8 +331,23 @@ impl Args {
         let bind = args
             .opt_value_from_str("--bind")?
             .unwrap_or_else(|| "127.0.0.1:3000".to_owned());
+        let as_of = args.opt_value_from_fn("--as-of", Self::parse_as_of)?;
         let base_uri = args.opt_value_from_str("--base-uri")?;
+        let commons = match (
+            args.opt_value_from_str("--commons-index")?,
+            args.opt_value_from_str("--commons-db")?,
+        ) {
+            (Some(index_path), Some(articles_path)) => Some((index_path, articles_path)),
+            (None, None) => None,
+            _ => return Err(ArgsError::Commons),
+        };
+        let ext_cache_dir = args.opt_value_from_str("--ext-cache-dir")?;
+        let fonts = args.values_from_str("--font")?;
         let load_mode = args.opt_value_from_str("--mode")?.unwrap_or_default();
+        let map_basemap = args.opt_value_from_str("--map-basemap")?;
+        let png_cache = args.opt_value_from_fn("--png-cache", Self::parse_size)?;
+        let png_cache_dir = args.opt_value_from_str("--png-cache-dir")?;
+        let time_zone = args.opt_value_from_fn("--time-zone", Self::parse_time_zone)?;
         let _ = args.contains("--");
         let index_path = Self::free_arg(&mut args, "WIKI_INDEX_FILE", ArgsError::Index)?;
         let articles_path = Self::free_arg(&mut args, "WIKI_ARTICLE_DB", ArgsError::Database)?;
@@ -283,6 +355,12 @@ impl Args {
         let db_cache = args
             .opt_value_from_fn("--db-cache", Self::parse_size)?
             .unwrap_or(32 * 1024 * 1024);
+        let disk_cache = args
+            .opt_value_from_fn("--disk-cache", Self::parse_size)?
+            .unwrap_or(1024 * 1024 * 1024);
+        let ext_cache = args
+            .opt_value_from_fn("--ext-cache", Self::parse_size)?
+            .unwrap_or(32 * 1024 * 1024);
         let template_cache = args
             .opt_value_from_fn("--template-cache", Self::parse_size)?
             .unwrap_or(32 * 1024 * 1024);
@@ -301,17 +379,27 @@ impl Args {
 
         Ok(Self {
             articles_path,
+            as_of,
             base_uri,
             bind,
+            commons,
+            ext_cache_dir,
+            fonts,
             index_path,
             limits: Limits {
                 db_cache,
+                disk_cache,
+                ext_cache,
                 template_cache,
                 threads,
                 vm_time,
                 vm_total_mem,
             },
             load_mode,
+            map_basemap,
+            png_cache,
+            png_cache_dir,
+            time_zone,
         })
     }
 }
@@ -321,7 +409,8 @@ impl Args {
 fn usage() {
     let exe = std::env::args().next().unwrap_or_default();
     eprintln!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
-    eprintln!("Usage: {exe} [options] <index.txt> <database.xml.bz2>\n");
+    eprintln!("Usage: {exe} [options] <index.txt> <database.xml.bz2>");
+    eprintln!("       {exe} parse [--include] [--pretty] <file.wiki | ->\n");
     eprintln!("or, use environment variables:");
     eprintln!("  WIKI_INDEX_FILE");
     eprintln!("  WIKI_ARTICLE_DB\n");
@@ -338,8 +427,62 @@ fn usage() {
     eprintln!("    --vm-time: Max Lua VM single call execution time (default: 10s)");
     eprintln!("  Memory:");
     eprintln!("    --db-cache: Max decompressed article cache size (default: 32M)");
+    eprintln!("    --disk-cache: Max size of each cache directory (default: 1G)");
+    eprintln!("    --ext-cache: Max extension tag output cache size (default: 32M)");
+    eprintln!("    --ext-cache-dir <dir>: Also persist extension tag output here (default: none)");
+    eprintln!("    --png-cache: Max PNG render cache size; enables PNG renders (default: off)");
+    eprintln!("    --png-cache-dir <dir>: Also persist PNG renders here (default: none)");
     eprintln!("    --template-cache: Max template cache size (default: 32M)");
     eprintln!("    --vm-total-mem: Max Lua VM memory usage (per thread) (default: 128M)");
+    eprintln!("  Wiki:");
+    eprintln!("    --as-of: Render pages as of this time (default: database creation time)");
+    eprintln!("    --commons-db <file>: Commons database.xml.bz2 for Data: pages");
+    eprintln!("    --commons-index <file>: Commons index.txt for Data: pages");
+    eprintln!("    --font <file>: Fallback font for graph text; may be repeated (default: none)");
+    eprintln!("    --map-basemap <file>: GeoJSON file drawn under maps (default: none)");
+    eprintln!("    --time-zone: Local time zone of the wiki (default: from configuration)");
+    eprintln!("  Parse:");
+    eprintln!("    --include: Parse the Wikitext in include mode");
+    eprintln!("    --pretty: Pretty-print the JSON syntax tree");
+}
+
+/// Parses a Wikitext file (or stdin, if the path is `-`) and writes its syntax
+/// tree to stdout using the [JSON schema](wikitext::json).
+fn parse() -> Result<(), Box<dyn core::error::Error>> {
+    let mut args = pico_args::Arguments::from_env();
+    let _ = args.subcommand()?;
+    let include = args.contains("--include");
+    let pretty = args.contains("--pretty");
+    let _ = args.contains("--");
+    let path = match args.free_from_str::<String>() {
+        Ok(path) => path,
+        Err(err) => {
+            usage();
+            return Err(ArgsError::from(err))?;
+        }
+    };
+
+    let rest = args.finish();
+    if !rest.is_empty() {
+        usage();
+        return Err(ArgsError::Extra(rest.join(OsStr::new(" "))))?;
+    }
+
+    let text = if path == "-" {
+        std::io::read_to_string(std::io::stdin())?
+    } else {
+        std::fs::read_to_string(&path)?
+    };
+    let source = wikitext::FileMap::new(&text);
+    let output = wikitext::Parser::new(&config::CONFIG).parse(&source, include)?;
+    let document = wikitext::json::to_json(&source, &output);
+    let stdout = std::io::stdout().lock();
+    if pretty {
+        serde_json::to_writer_pretty(stdout, &document)?;
+    } else {
+        serde_json::to_writer(stdout, &document)?;
+    }
+    Ok(())
 }
 
 /// Don’t run this. You’ve been warned!
@@ -363,10 +506,50 @@ async fn run() -> Result<(), Box<dyn core::error::Error>> {
         args.bind.parse()
     }?;
 
+    let time_zone = args
+        .time_zone
+        .as_deref()
+        .unwrap_or(config::CONFIG.time_zone);
+    if !php::date_default_timezone_set(time_zone) {
+        return Err(ArgsError::TimeZone(time_zone.to_owned()))?;
+    }
+
+    log::info!("Local time zone: {time_zone}");
+
+    if let Some(path) = args.map_basemap {
+        renderer::set_map_basemap(std::fs::read_to_string(&path)?)?;
+        log::info!("Map basemap: {path}");
+    }
+
+    if !args.fonts.is_empty() {
+        let fonts = args
+            .fonts
+            .iter()
+            .map(std::fs::read)
+            .collect::<Result<Vec<_>, _>>()?;
+        renderer::set_fallback_fonts(fonts)?;
+        log::info!("Fallback fonts: {}", args.fonts.join(", "));
+    }
+
+    if let Some(png_cache) = args.png_cache {
+        renderer::set_render_cache(
+            png_cache,
+            args.png_cache_dir.map(Into::into),
+            args.limits.disk_cache as u64,
+        )?;
+        log::info!("PNG rendering enabled");
+    }
+
     let limits = args.limits;
 
     log::info!("{limits}");
 
+    renderer::set_ext_cache(
+        limits.ext_cache,
+        args.ext_cache_dir.map(Into::into),
+        limits.disk_cache as u64,
+    )?;
+
     let database = Arc::new(Database::from_file(
         &args.index_path,
         &args.articles_path,
@@ -375,6 +558,18 @@ async fn run() -> Result<(), Box<dyn core::error::Error>> {
 
     log::info!("Opened database {}", database.name());
 
+    let commons_database = if let Some((index_path, articles_path)) = &args.commons {
+        let database = Arc::new(Database::from_file(
+            index_path,
+            articles_path,
+            limits.db_cache,
+        )?);
+        log::info!("Opened Commons database {}", database.name());
+        Some(database)
+    } else {
+        None
+    };
+
     // The siteinfo in MediaWiki dumps does not provide enough information to
     // actually build the configuration from the dump, but it does at least
     // allow a sanity check of what namespace information is recorded.
@@ -395,24 +590,39 @@ async fn run() -> Result<(), Box<dyn core::error::Error>> {
         }
     }
 
-    if let Some(date) = database.creation_date() {
+    let creation_date = database.creation_date();
+    if let Some(date) = creation_date {
         log::info!("Database version (guessed from filename): {}", date.date());
+    }
+
+    let base_time = if let Some(as_of) = args.as_of {
+        log::info!("Rendering pages as of {}", *as_of);
+        as_of
+    } else if let Some(date) = creation_date {
+        php::DateTime::from(date)
     } else {
         // Some things, like 'Module:Selected recent additions', use the
         // “current” date to look up articles that it assumes will always exist
         // for the “current” month, so this needs to be mocked for those things
         // to work properly
         log::warn!(
-            "Could not determine the database creation date; some pages may be missing data"
+            "Could not determine the database creation date; some pages may be missing data (use --as-of to set one)"
         );
-    }
+        php::DateTime::now()?
+    };
 
     let renderer = r2d2::Builder::new()
         .max_size(limits.threads)
         .test_on_check_out(false)
         .max_lifetime(None)
         .idle_timeout(None)
-        .build_unchecked(RenderManager::new(&base_uri, &database, limits));
+        .build_unchecked(RenderManager::new(
+            &base_uri,
+            &database,
+            commons_database.as_ref(),
+            base_time,
+            limits,
+        ));
     let state = AppState::new(WikiState {
         base_uri,
         database,
@@ -426,7 +636,9 @@ async fn run() -> Result<(), Box<dyn core::error::Error>> {
         .route("/external/{*target}", get(pages::external))
         .route("/favicon.ico", get(pages::favicon))
         .route("/fonts/{*font}", get(pages::fonts))
+        .route("/lint/{*name}", get(pages::lint))
         .route("/media/{*image}", get(pages::media))
+        .route("/render/{*file}", get(pages::render))
         .route("/search", get(pages::search))
         .route("/source/{*name}", get(pages::source))
         .route("/styles.css", get(pages::styles))
@@ -456,5 +668,12 @@ impl<E: Into<Box<dyn core::error::Error>>> From<E> for DisplayError {
 }
 
 fn main() -> Result<(), DisplayError> {
-    run().map_err(Into::into)
+    if std::env::args_os()
+        .nth(1)
+        .is_some_and(|command| command == "parse")
+    {
+        parse().map_err(Into::into)
+    } else {
+        run().map_err(Into::into)
+    }
 }
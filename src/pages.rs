@@ -2,15 +2,16 @@
 
 use crate::{
     AppState, LoadMode,
-    common::make_url,
+    common::{make_url, url_encode},
     config::CONFIG,
     db,
+    php::{DateTime, DateTimeError, DateTimeZone},
     renderer::{self, RenderOutput},
     title::Title,
-    wikitext::{FileMap, Parser, inspect},
+    wikitext::{FileMap, Parser, inspect, json, lint},
 };
 use axum::{
-    Form,
+    Form, Json,
     body::Body,
     extract::{Path, Query, RawQuery, State},
     http::{HeaderMap, StatusCode, header},
@@ -20,6 +21,7 @@ use core::num::NonZeroUsize;
 use rayon::{iter::ParallelIterator as _, slice::ParallelSliceMut as _};
 use sailfish::TemplateSimple;
 use std::{
+    collections::BTreeMap,
     sync::{Arc, mpsc},
     time::Instant,
 };
@@ -54,6 +56,9 @@ pub(crate) enum Error {
     /// An renderer thread pool management error.
     #[error(transparent)]
     Pool(#[from] r2d2::Error),
+    /// An invalid time was requested.
+    #[error(transparent)]
+    Time(#[from] DateTimeError),
     /// A non-utf-8 header could not be converted to a string.
     #[error(transparent)]
     ToStr(#[from] axum::http::header::ToStrError),
@@ -74,6 +79,7 @@ impl IntoResponse for Error {
             Error::RenderTx(error) => (StatusCode::INTERNAL_SERVER_ERROR, format!("{error}")),
             Error::RenderRx(error) => (StatusCode::INTERNAL_SERVER_ERROR, format!("{error}")),
             Error::Pool(error) => (StatusCode::INTERNAL_SERVER_ERROR, format!("{error}")),
+            Error::Time(error) => (StatusCode::BAD_REQUEST, format!("{error}")),
             Error::ToStr(error) => (StatusCode::INTERNAL_SERVER_ERROR, format!("{error}")),
         }
         .into_response()
@@ -88,6 +94,14 @@ pub(crate) enum ArticleAction {
     View,
     /// Render. Treated the same as [`View`](ArticleAction::View).
     Tree,
+    /// Show the template expansion tree instead of the article.
+    Trace,
+    /// Show the article as Parsoid-compatible annotated HTML.
+    Parsoid,
+    /// Show the page properties of the article as JSON.
+    PageProps,
+    /// Show the TemplateData of the article as JSON.
+    TemplateData,
 }
 
 /// Query options for `/article`.
@@ -95,12 +109,15 @@ pub(crate) enum ArticleAction {
 pub(crate) struct ArticleQuery {
     /// The page action.
     ///
-    /// This is defined, but not actually used, because most actions are not
-    /// relevant to wiki.rs, but templates will still generate links using other
-    /// actions, and *something* should happen in that case (which currently
-    /// is just returning an error page that the action is invalid).
-    #[serde(rename(deserialize = "action"))]
-    _action: Option<ArticleAction>,
+    /// Most actions are not relevant to wiki.rs, but templates will still
+    /// generate links using other actions, and *something* should happen in
+    /// that case (which currently is just returning an error page that the
+    /// action is invalid).
+    action: Option<ArticleAction>,
+
+    /// The time to render the article as of, in any format supported by PHP’s
+    /// `strtotime`.
+    asof: Option<String>,
 
     /// The redirect source.
     from: Option<String>,
@@ -117,7 +134,8 @@ pub(crate) async fn article(
     State(state): State<AppState>,
     Path(name): Path<String>,
     Query(ArticleQuery {
-        _action,
+        action,
+        asof,
         from,
         mode: load_mode,
         redirect,
@@ -147,26 +165,60 @@ pub(crate) async fn article(
         // so it is necessary to parse the article to actually get that.
         let target = call_renderer(&state, renderer::Command::Redirect { article })?;
         let target = Title::new(&target.content, None);
-        let query = format!("from={}", title.partial_url());
+        let mut query = format!("from={}", title.partial_url());
+        if let Some(asof) = &asof {
+            query += "&asof=";
+            query += &url_encode(asof).to_string();
+        }
         return make_url(None, &state.base_uri, &target, Some(&query), true)
             .map(|s| Redirect::permanent(&s))
             .map(IntoResponse::into_response)
             .map_err(Into::into);
     }
 
+    let as_of = asof
+        .map(|asof| DateTime::new(&asof, Some(&DateTimeZone::UTC), None))
+        .transpose()?;
+
     let start = Instant::now();
     let load_mode = load_mode.unwrap_or(state.load_mode);
+    let trace = matches!(action, Some(ArticleAction::Trace));
+    let parsoid = matches!(action, Some(ArticleAction::Parsoid));
 
     let command = renderer::Command::Article {
         article: Arc::clone(&article),
+        as_of,
         load_mode,
+        parsoid,
         redirect,
+        trace,
     };
 
     let output = call_renderer(&state, command)?;
 
     log::trace!("Rendered article in {:.2?}", start.elapsed());
 
+    if let Some(trace) = &output.trace {
+        return trace_page(&state, &article.title, trace);
+    } else if parsoid {
+        return parsoid_page(&state, &article.title, &output);
+    } else if matches!(action, Some(ArticleAction::PageProps)) {
+        let pageprops = output
+            .properties
+            .iter()
+            .map(|name| (name.to_owned(), serde_json::Value::from("")))
+            .collect::<serde_json::Map<_, _>>();
+        return Ok(Json(serde_json::json!({
+            "pageid": article.id,
+            "ns": title.namespace().id,
+            "title": article.title,
+            "pageprops": pageprops,
+        }))
+        .into_response());
+    } else if matches!(action, Some(ArticleAction::TemplateData)) {
+        return Ok(template_data_json(&title, &article, output).into_response());
+    }
+
     ArticleTemplate {
         base_path: state.base_uri.path(),
         from: from.as_deref(),
@@ -180,6 +232,116 @@ pub(crate) async fn article(
     .map_err(Into::into)
 }
 
+/// Converts the TemplateData of an article to the same format as the
+/// MediaWiki `action=templatedata` API.
+fn template_data_json(
+    title: &Title,
+    article: &db::Article,
+    output: RenderOutput,
+) -> impl IntoResponse {
+    /// A page in the response.
+    #[derive(serde::Serialize)]
+    struct Page {
+        /// The title of the page.
+        title: String,
+        /// The namespace ID of the page.
+        ns: i32,
+        /// The TemplateData of the page.
+        #[serde(flatten)]
+        data: renderer::TemplateData,
+    }
+
+    /// The response.
+    #[derive(serde::Serialize)]
+    struct TemplateDataResponse {
+        /// Pages with TemplateData, by page ID.
+        pages: BTreeMap<u64, Page>,
+    }
+
+    let pages = output
+        .template_data
+        .map(|data| {
+            (
+                article.id,
+                Page {
+                    title: article.title.clone(),
+                    ns: title.namespace().id,
+                    data,
+                },
+            )
+        })
+        .into_iter()
+        .collect();
+
+    Json(TemplateDataResponse { pages })
+}
+
+/// Renders the template expansion tree of an article.
+fn trace_page(
+    state: &crate::WikiState,
+    title: &str,
+    trace: &renderer::Trace,
+) -> Result<Response, Error> {
+    #[derive(TemplateSimple)]
+    #[template(path = "trace.html")]
+    struct TraceTemplate<'a> {
+        /// The base path for URLs.
+        base_path: &'a str,
+        /// The name of the wiki.
+        site: &'a str,
+        /// The title of the article.
+        title: &'a str,
+        /// The expansion tree, as HTML.
+        tree: String,
+    }
+
+    let base_path = state.base_uri.path();
+    let mut tree = String::new();
+    trace.write_html(&mut tree, base_path)?;
+
+    TraceTemplate {
+        base_path,
+        site: state.database.name(),
+        title,
+        tree,
+    }
+    .render_once()
+    .map(html_result)
+    .map(IntoResponse::into_response)
+    .map_err(Into::into)
+}
+
+/// Renders an article as a Parsoid HTML document.
+fn parsoid_page(
+    state: &crate::WikiState,
+    title: &str,
+    output: &RenderOutput,
+) -> Result<Response, Error> {
+    #[derive(TemplateSimple)]
+    #[template(path = "parsoid.html")]
+    struct ParsoidTemplate<'a> {
+        /// The base path for URLs.
+        base_path: &'a str,
+        /// The namespace ID of the article.
+        namespace: i32,
+        /// The Wikitext renderer output.
+        output: &'a RenderOutput,
+        /// The title of the article.
+        title: &'a str,
+    }
+
+    ParsoidTemplate {
+        base_path: state.base_uri.path(),
+        namespace: Title::new(title, None).namespace().id,
+        output,
+        title,
+    }
+    .render_once()
+    .map(html_result)
+    .map(IntoResponse::into_response)
+    .map_err(Into::into)
+}
+
 /// The ad-hoc Wikitext expression evaluator, initial page.
 pub(crate) async fn eval_get(State(state): State<AppState>) -> Result<impl IntoResponse, Error> {
     raw_source(state.base_uri.path(), "", "html", Some(<_>::default()))
@@ -352,6 +514,24 @@ pub(crate) async fn media(Path(_): Path<String>) -> impl IntoResponse {
     )
 }
 
+/// The rendered image route handler.
+pub(crate) async fn render(Path(file): Path<String>) -> impl IntoResponse {
+    match renderer::render_png(&file) {
+        Ok(png) => Ok((
+            [
+                (header::CACHE_CONTROL, "max-age=806400, public, immutable"),
+                (header::CONTENT_TYPE, "image/png"),
+            ],
+            Body::from(png.to_vec()),
+        )),
+        Err(renderer::RenderError::NotFound) => Err(StatusCode::NOT_FOUND),
+        Err(err) => {
+            log::error!("Could not render {file}: {err}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
 /// The index page route handler.
 pub(crate) async fn index_page(State(state): State<AppState>) -> Result<impl IntoResponse, Error> {
     #[derive(TemplateSimple)]
@@ -372,6 +552,73 @@ pub(crate) async fn index_page(State(state): State<AppState>) -> Result<impl Int
     .map_err(Into::into)
 }
 
+/// Output formats for `/lint`.
+#[derive(Clone, Copy, Default, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum LintFormat {
+    /// A human-readable report.
+    #[default]
+    Html,
+    /// A machine-readable report.
+    Json,
+}
+
+/// Query options for `/lint`.
+#[derive(serde::Deserialize)]
+pub(crate) struct LintQuery {
+    /// The output format.
+    format: Option<LintFormat>,
+}
+
+/// The Wikitext lint report route handler.
+pub(crate) async fn lint(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(LintQuery { format }): Query<LintQuery>,
+) -> Result<Response, Error> {
+    #[derive(TemplateSimple)]
+    #[template(path = "lint.html")]
+    struct LintReport<'a> {
+        /// The base path for URLs.
+        base_path: &'a str,
+        /// The problems found in the article.
+        issues: &'a [lint::Issue],
+        /// The name of the wiki.
+        site: &'a str,
+        /// The source text of the article.
+        source: &'a str,
+        /// The title of the article.
+        title: &'a str,
+    }
+
+    let title = Title::new(&name, None);
+    let article = state.database.get(&title)?;
+    let source = FileMap::new(&article.body);
+    let tree = Parser::new(&CONFIG)
+        .parse(&source, false)
+        .map_err(renderer::Error::from)?;
+    let issues = lint::lint(&source, &tree);
+
+    match format.unwrap_or_default() {
+        LintFormat::Html => LintReport {
+            base_path: state.base_uri.path(),
+            issues: &issues,
+            site: state.database.name(),
+            source: &article.body,
+            title: &article.title,
+        }
+        .render_once()
+        .map(html_result)
+        .map(IntoResponse::into_response)
+        .map_err(Into::into),
+        LintFormat::Json => Ok(Json(serde_json::json!({
+            "title": article.title,
+            "issues": issues,
+        }))
+        .into_response()),
+    }
+}
+
 /// Query options for `/search`.
 #[derive(serde::Deserialize)]
 pub(crate) struct SearchQuery {
@@ -514,6 +761,8 @@ fn single_result<'a>(results: &[&'a str]) -> Option<&'a str> {
 #[derive(serde::Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub(crate) enum SourceMode {
+    /// Parser tree, as versioned JSON.
+    Json,
     /// Raw text.
     Raw,
     /// Parser tree.
@@ -525,7 +774,8 @@ pub(crate) enum SourceMode {
 pub(crate) struct SourceQuery {
     /// The view mode.
     mode: Option<SourceMode>,
-    /// When in tree view, whether to process the Wikitext in include mode.
+    /// When in tree or JSON view, whether to process the Wikitext in include
+    /// mode.
     include: Option<String>,
 }
 
@@ -543,6 +793,13 @@ pub(crate) async fn source(
             raw_source(state.base_uri.path(), &article.body, &article.model, None)
                 .map(IntoResponse::into_response)
         }
+        Some(SourceMode::Json) => {
+            let source = FileMap::new(&article.body);
+            let tree = Parser::new(&CONFIG)
+                .parse(&source, include.is_some())
+                .map_err(renderer::Error::from)?;
+            Ok(Json(json::to_json(&source, &tree)).into_response())
+        }
         Some(SourceMode::Tree) => {
             let source = FileMap::new(&article.body);
             let tree = Parser::new(&CONFIG)
@@ -0,0 +1,624 @@
+//! Calendar systems and numeral systems used by the MediaWiki extended time
+//! format.
+//!
+//! These are ports of the conversion functions in the MediaWiki `Language`
+//! class, including their quirks, so that dates come out the same as they do on
+//! the real wiki.
+
+use time::Date;
+
+/// The English names of the months of the Iranian (Solar Hijri) calendar.
+const IRANIAN_MONTHS: [&str; 12] = [
+    "Farvardin",
+    "Ordibehesht",
+    "Khordad",
+    "Tir",
+    "Mordad",
+    "Shahrivar",
+    "Mehr",
+    "Aban",
+    "Azar",
+    "Dey",
+    "Bahman",
+    "Esfand",
+];
+
+/// The English names of the months of the (lunar) Hijri calendar.
+const HIJRI_MONTHS: [&str; 12] = [
+    "Muharram",
+    "Safar",
+    "Rabi' al-awwal",
+    "Rabi' al-thani",
+    "Jumada al-awwal",
+    "Jumada al-thani",
+    "Rajab",
+    "Sha'aban",
+    "Ramadan",
+    "Shawwal",
+    "Dhu al-Qi'dah",
+    "Dhu al-Hijjah",
+];
+
+/// The English names of the months of the Hebrew calendar. The last two months
+/// are Adar I and Adar II, which replace Adar in leap years.
+const HEBREW_MONTHS: [&str; 14] = [
+    "Tishrei", "Cheshvan", "Kislev", "Tevet", "Shevat", "Adar", "Nisan", "Iyar", "Sivan", "Tamuz",
+    "Av", "Elul", "Adar I", "Adar II",
+];
+
+/// The number of days in each month of a non-leap Gregorian year.
+const GREGORIAN_DAYS: [i64; 12] = [31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+
+/// The number of days in each month of a non-leap Iranian year.
+const IRANIAN_DAYS: [i64; 12] = [31, 31, 31, 31, 31, 31, 30, 30, 30, 30, 30, 29];
+
+/// A date in the Iranian calendar.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) struct IranianDate {
+    /// The year.
+    pub year: i64,
+    /// The 1-indexed month.
+    pub month: i64,
+    /// The 1-indexed day of the month.
+    pub day: i64,
+    /// The 0-indexed day of the year.
+    pub ordinal: i64,
+}
+
+impl IranianDate {
+    /// The number of days in the month.
+    pub fn month_length(&self) -> i64 {
+        IRANIAN_DAYS[month_index(self.month)]
+    }
+
+    /// The English name of the month.
+    pub fn month_name(&self) -> &'static str {
+        IRANIAN_MONTHS[month_index(self.month)]
+    }
+}
+
+impl From<Date> for IranianDate {
+    /// Converts a Gregorian date to the Iranian calendar, like
+    /// `Language::tsToIranian`.
+    fn from(date: Date) -> Self {
+        let gy = i64::from(date.year()) - 1600;
+        let gm = i64::from(u8::from(date.month())) - 1;
+        let gd = i64::from(date.day()) - 1;
+
+        let mut g_day_no = 365 * gy + (gy + 3).div_euclid(4) - (gy + 99).div_euclid(100)
+            + (gy + 399).div_euclid(400);
+        g_day_no += GREGORIAN_DAYS[..usize::from(u8::from(date.month()) - 1)]
+            .iter()
+            .sum::<i64>();
+        if gm > 1 && ((gy % 4 == 0 && gy % 100 != 0) || gy % 400 == 0) {
+            g_day_no += 1;
+        }
+        g_day_no += gd;
+
+        let mut j_day_no = g_day_no - 79;
+        let j_np = j_day_no.div_euclid(12053);
+        j_day_no %= 12053;
+
+        let mut year = 979 + 33 * j_np + 4 * j_day_no.div_euclid(1461);
+        j_day_no %= 1461;
+
+        if j_day_no >= 366 {
+            year += (j_day_no - 1).div_euclid(365);
+            j_day_no = (j_day_no - 1) % 365;
+        }
+
+        let ordinal = j_day_no;
+        let mut month = 1;
+        for days in &IRANIAN_DAYS[..11] {
+            if j_day_no < *days {
+                break;
+            }
+            j_day_no -= days;
+            month += 1;
+        }
+
+        Self {
+            year,
+            month,
+            day: j_day_no + 1,
+            ordinal,
+        }
+    }
+}
+
+/// A date in the (tabular, lunar) Hijri calendar.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) struct HijriDate {
+    /// The year.
+    pub year: i64,
+    /// The 1-indexed month.
+    pub month: i64,
+    /// The 1-indexed day of the month.
+    pub day: i64,
+}
+
+impl HijriDate {
+    /// The English name of the month.
+    pub fn month_name(&self) -> &'static str {
+        HIJRI_MONTHS[month_index(self.month)]
+    }
+}
+
+impl From<Date> for HijriDate {
+    /// Converts a Gregorian date to the Hijri calendar, like
+    /// `Language::tsToHijri`.
+    fn from(date: Date) -> Self {
+        let zy = i64::from(date.year());
+        let zm = i64::from(u8::from(date.month()));
+        let zd = i64::from(date.day());
+
+        // Julian day number, switching to the Julian calendar before the
+        // Gregorian reform
+        let zjd = if (zy, zm, zd) > (1582, 10, 14) {
+            (1461 * (zy + 4800 + (zm - 14) / 12)) / 4
+                + (367 * (zm - 2 - 12 * ((zm - 14) / 12))) / 12
+                - (3 * ((zy + 4900 + (zm - 14) / 12) / 100)) / 4
+                + zd
+                - 32075
+        } else {
+            367 * zy - (7 * (zy + 5001 + (zm - 9) / 7)) / 4 + (275 * zm) / 9 + zd + 1_729_777
+        };
+
+        let zl = zjd - 1_948_440 + 10632;
+        let zn = (zl - 1) / 10631;
+        let zl = zl - 10631 * zn + 354;
+        let zj = ((10985 - zl) / 5316) * ((50 * zl) / 17719) + (zl / 5670) * ((43 * zl) / 15238);
+        let zl = zl - ((30 - zj) / 15) * ((17719 * zj) / 50) - (zj / 16) * ((15238 * zj) / 43) + 29;
+        let month = (24 * zl) / 709;
+
+        Self {
+            year: 30 * zn + zj - 30,
+            month,
+            day: zl - (709 * month) / 24,
+        }
+    }
+}
+
+/// A date in the Hebrew calendar.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) struct HebrewDate {
+    /// The year.
+    pub year: i64,
+    /// The 1-indexed month, starting from Tishrei. In leap years, Adar I is
+    /// month 13 and Adar II is month 14.
+    pub month: i64,
+    /// The 1-indexed day of the month.
+    pub day: i64,
+    /// The number of days in the month.
+    pub month_length: i64,
+}
+
+impl HebrewDate {
+    /// The English name of the month.
+    pub fn month_name(&self) -> &'static str {
+        HEBREW_MONTHS[month_index(self.month)]
+    }
+}
+
+impl From<Date> for HebrewDate {
+    /// Converts a Gregorian date to the Hebrew calendar, like
+    /// `Language::tsToHebrew`.
+    fn from(date: Date) -> Self {
+        let mut year = i64::from(date.year());
+        let mut month = i64::from(u8::from(date.month()));
+        let day = i64::from(date.day());
+
+        let mut hebrew_year = year + 3760;
+
+        // Month number when September = 1, August = 12
+        month += 4;
+        if month > 12 {
+            month -= 12;
+            year += 1;
+            hebrew_year += 1;
+        }
+
+        // Day of year from 1 September
+        let mut day_of_year = day;
+        for i in 1..month {
+            day_of_year += match i {
+                6 => 28 + i64::from(is_leap_year(year)),
+                1 | 3 | 8 | 10 => 30,
+                _ => 31,
+            };
+        }
+
+        let mut start = hebrew_year_start(hebrew_year);
+        let next_start = if day_of_year <= start {
+            // The day is before the start of the Hebrew year, so it is
+            // actually in the previous year
+            let next_start = start;
+            year -= 1;
+            hebrew_year -= 1;
+            day_of_year += 365 + i64::from(is_leap_year(year));
+            start = hebrew_year_start(hebrew_year);
+            next_start
+        } else {
+            hebrew_year_start(hebrew_year + 1)
+        };
+
+        let mut hebrew_day = day_of_year - start;
+
+        // Adding 12 (or 13) days removes the difference between the Hebrew and
+        // Gregorian years so that only the year type is left
+        let diff = next_start - start + 12 + i64::from(is_leap_year(year));
+        // 0 is an incomplete year, 1 is a regular year, 2 is a complete year
+        let year_pattern = diff % 30;
+        let is_leap = diff >= 30;
+
+        let mut hebrew_month = 1;
+        let mut days = 0;
+        while hebrew_month <= 12 {
+            if is_leap && hebrew_month == 6 {
+                // Adar I has 30 days, Adar II has 29 days
+                days = 30;
+                if hebrew_day <= days {
+                    hebrew_month = 13;
+                } else {
+                    hebrew_day -= days;
+                    days = 29;
+                    if hebrew_day <= days {
+                        hebrew_month = 14;
+                    }
+                }
+            } else if hebrew_month == 2 && year_pattern == 2 {
+                // Cheshvan in a complete year
+                days = 30;
+            } else if hebrew_month == 3 && year_pattern == 0 {
+                // Kislev in an incomplete year
+                days = 29;
+            } else {
+                days = 30 - (hebrew_month - 1) % 2;
+            }
+
+            if hebrew_day <= days {
+                break;
+            }
+
+            hebrew_day -= days;
+            hebrew_month += 1;
+        }
+
+        Self {
+            year: hebrew_year,
+            month: hebrew_month,
+            day: hebrew_day,
+            month_length: days,
+        }
+    }
+}
+
+/// Calculates the start of the given Hebrew year as a day number counted from
+/// 1 September of the Gregorian year in which it starts, like
+/// `Language::hebrewYearStart`.
+#[expect(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    reason = "Hebrew years are small enough to be represented exactly"
+)]
+fn hebrew_year_start(year: i64) -> i64 {
+    let a = (12 * (year - 1) + 17) % 19;
+    let b = (year - 1) % 4;
+    let mut m = 32.044_093_161_144 + 1.554_241_796_621_2 * a as f64 + b as f64 / 4.0
+        - 0.003_177_794_022_092_3 * (year - 1) as f64;
+    if m < 0.0 {
+        m -= 1.0;
+    }
+    let mut mar = m as i64;
+    if m < 0.0 {
+        m += 1.0;
+    }
+    m -= mar as f64;
+
+    let c = (mar + 3 * (year - 1) + 5 * b + 5) % 7;
+    if c == 0 && a > 11 && m >= 0.897_723_765_432_10 {
+        mar += 1;
+    } else if c == 1 && a > 6 && m >= 0.632_870_370_370_37 {
+        mar += 2;
+    } else if matches!(c, 2 | 4 | 6) {
+        mar += 1;
+    }
+
+    mar + (year - 3761) / 100 - (year - 3761) / 400 - 24
+}
+
+/// Converts a 1-indexed month number to a 0-indexed month name table index.
+fn month_index(month: i64) -> usize {
+    usize::try_from(month - 1).unwrap_or(0)
+}
+
+/// Returns true if the given Gregorian year is a leap year.
+fn is_leap_year(year: i64) -> bool {
+    year % 400 == 0 || (year % 100 != 0 && year % 4 == 0)
+}
+
+/// Converts a Gregorian date to a year in the Thai solar calendar.
+pub(super) fn thai_year(date: Date) -> i64 {
+    let year = i64::from(date.year());
+    // Before 1941, the Thai new year was on 1 April
+    if (1912..=1940).contains(&year) && u8::from(date.month()) <= 3 {
+        year + 542
+    } else {
+        year + 543
+    }
+}
+
+/// Converts a Gregorian date to a year in the Minguo calendar.
+pub(super) fn minguo_year(date: Date) -> i64 {
+    i64::from(date.year()) - 1911
+}
+
+/// Converts a Gregorian date to a Japanese era (nengō) year, starting from
+/// the Meiji era.
+pub(super) fn nengo_year(date: Date) -> String {
+    /// The first day of each era, and its name.
+    const ERAS: [((i32, u8, u8), &str); 4] = [
+        ((2019, 5, 1), "令和"),
+        ((1989, 1, 8), "平成"),
+        ((1926, 12, 26), "昭和"),
+        ((1912, 7, 31), "大正"),
+    ];
+
+    let today = (date.year(), u8::from(date.month()), date.day());
+    let (start, era) = ERAS
+        .into_iter()
+        .find(|(start, _)| today >= *start)
+        .unwrap_or(((1868, 1, 1), "明治"));
+
+    // The first year of an era is called gannen instead of year 1
+    let year = today.0 - start.0 + 1;
+    if year == 1 {
+        format!("{era}元")
+    } else {
+        format!("{era}{year}")
+    }
+}
+
+/// Formats a number from 1 to 10000 as a Roman numeral. Other numbers are
+/// formatted as decimals.
+#[expect(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    reason = "value range is 1..=10"
+)]
+pub(super) fn roman_numeral(num: i64) -> String {
+    /// The numerals for each power of ten, from ones to thousands.
+    const TABLE: [[&str; 11]; 4] = [
+        [
+            "", "I", "II", "III", "IV", "V", "VI", "VII", "VIII", "IX", "X",
+        ],
+        [
+            "", "X", "XX", "XXX", "XL", "L", "LX", "LXX", "LXXX", "XC", "C",
+        ],
+        [
+            "", "C", "CC", "CCC", "CD", "D", "DC", "DCC", "DCCC", "CM", "M",
+        ],
+        [
+            "",
+            "M",
+            "MM",
+            "MMM",
+            "MMMM",
+            "MMMMM",
+            "MMMMMM",
+            "MMMMMMM",
+            "MMMMMMMM",
+            "MMMMMMMMM",
+            "MMMMMMMMMM",
+        ],
+    ];
+
+    if !(1..=10000).contains(&num) {
+        return num.to_string();
+    }
+
+    let mut num = num;
+    let mut out = String::new();
+    for (digits, pow10) in TABLE.iter().rev().zip([1000, 100, 10, 1]) {
+        if num >= pow10 {
+            out += digits[(num / pow10) as usize];
+        }
+        num %= pow10;
+    }
+    out
+}
+
+/// Formats a number from 1 to 9999 as a Hebrew numeral. Other numbers are
+/// formatted as decimals.
+#[expect(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    reason = "value range is 1..=9"
+)]
+pub(super) fn hebrew_numeral(num: i64) -> String {
+    /// The letters for each power of ten, from ones to thousands.
+    const TABLE: [[&str; 11]; 4] = [
+        ["", "א", "ב", "ג", "ד", "ה", "ו", "ז", "ח", "ט", "י"],
+        ["", "י", "כ", "ל", "מ", "נ", "ס", "ע", "פ", "צ", "ק"],
+        ["", "ק", "ר", "ש", "ת", "תק", "תר", "תש", "תת", "תתק", "תתר"],
+        ["", "א", "ב", "ג", "ד", "ה", "ו", "ז", "ח", "ט", "י"],
+    ];
+
+    if !(1..=9999).contains(&num) {
+        return num.to_string();
+    }
+
+    // Round thousands have special notations
+    if num == 1000 {
+        return "א' אלף".into();
+    } else if num % 1000 == 0 {
+        return format!("{}' אלפים", TABLE[0][(num / 1000) as usize]);
+    }
+
+    let mut num = num;
+    let mut letters = Vec::<char>::new();
+    for (i, pow10) in [(3, 1000), (2, 100), (1, 10), (0, 1)] {
+        if num >= pow10 {
+            // 15 and 16 are written as 9 + 6 and 9 + 7 to avoid spelling a
+            // name of God
+            if num == 15 || num == 16 {
+                letters.extend(TABLE[0][9].chars());
+                letters.extend(TABLE[0][(num - 9) as usize].chars());
+                num = 0;
+            } else {
+                letters.extend(TABLE[i][(num / pow10) as usize].chars());
+                if pow10 == 1000 {
+                    letters.push('\'');
+                }
+            }
+        }
+        num %= pow10;
+    }
+
+    if letters.len() == 1 {
+        // Geresh on one-letter numbers
+        letters.push('\'');
+    } else {
+        let last = letters.len() - 1;
+        letters[last] = match letters[last] {
+            'כ' => 'ך',
+            'מ' => 'ם',
+            'נ' => 'ן',
+            'פ' => 'ף',
+            'צ' => 'ץ',
+            c => c,
+        };
+
+        // Gershayim on multiple-letter numbers, except for those with only
+        // one letter after the thousands
+        if letters[1] == '\'' && letters.len() == 3 {
+            letters.push('\'');
+        } else {
+            letters.insert(last, '"');
+        }
+    }
+
+    letters.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::Month;
+
+    /// Makes a Gregorian date.
+    fn date(year: i32, month: u8, day: u8) -> Date {
+        Date::from_calendar_date(year, Month::try_from(month).unwrap(), day).unwrap()
+    }
+
+    #[test]
+    fn iranian() {
+        assert_eq!(
+            IranianDate::from(date(2007, 10, 28)),
+            IranianDate {
+                year: 1386,
+                month: 8,
+                day: 6,
+                ordinal: 221,
+            }
+        );
+        assert_eq!(
+            IranianDate::from(date(2024, 3, 20)),
+            IranianDate {
+                year: 1403,
+                month: 1,
+                day: 1,
+                ordinal: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn hijri() {
+        assert_eq!(
+            HijriDate::from(date(2007, 10, 28)),
+            HijriDate {
+                year: 1428,
+                month: 10,
+                day: 16,
+            }
+        );
+    }
+
+    #[test]
+    fn hebrew() {
+        assert_eq!(
+            HebrewDate::from(date(2007, 10, 28)),
+            HebrewDate {
+                year: 5768,
+                month: 2,
+                day: 16,
+                month_length: 29,
+            }
+        );
+        assert_eq!(
+            HebrewDate::from(date(2008, 2, 10)),
+            HebrewDate {
+                year: 5768,
+                month: 13,
+                day: 4,
+                month_length: 30,
+            }
+        );
+        assert_eq!(
+            HebrewDate::from(date(2008, 3, 20)),
+            HebrewDate {
+                year: 5768,
+                month: 14,
+                day: 13,
+                month_length: 29,
+            }
+        );
+        assert_eq!(
+            HebrewDate::from(date(2024, 9, 1)),
+            HebrewDate {
+                year: 5784,
+                month: 11,
+                day: 28,
+                month_length: 30,
+            }
+        );
+    }
+
+    #[test]
+    fn years() {
+        assert_eq!(thai_year(date(2007, 10, 28)), 2550);
+        assert_eq!(thai_year(date(1920, 2, 1)), 2462);
+        assert_eq!(minguo_year(date(2007, 10, 28)), 96);
+        assert_eq!(nengo_year(date(2007, 10, 28)), "平成19");
+        assert_eq!(nengo_year(date(2019, 5, 1)), "令和元");
+        assert_eq!(nengo_year(date(1989, 1, 7)), "昭和64");
+        assert_eq!(nengo_year(date(1900, 1, 1)), "明治33");
+    }
+
+    #[test]
+    fn roman() {
+        assert_eq!(roman_numeral(1), "I");
+        assert_eq!(roman_numeral(2007), "MMVII");
+        assert_eq!(roman_numeral(1999), "MCMXCIX");
+        assert_eq!(roman_numeral(10000), "MMMMMMMMMM");
+        assert_eq!(roman_numeral(0), "0");
+        assert_eq!(roman_numeral(10001), "10001");
+    }
+
+    #[test]
+    fn hebrew_numerals() {
+        assert_eq!(hebrew_numeral(1), "א'");
+        assert_eq!(hebrew_numeral(15), "ט\"ו");
+        assert_eq!(hebrew_numeral(16), "ט\"ז");
+        assert_eq!(hebrew_numeral(20), "כ'");
+        assert_eq!(hebrew_numeral(42), "מ\"ב");
+        assert_eq!(hebrew_numeral(60), "ס'");
+        assert_eq!(hebrew_numeral(1000), "א' אלף");
+        assert_eq!(hebrew_numeral(2000), "ב' אלפים");
+        assert_eq!(hebrew_numeral(1001), "א'א'");
+        assert_eq!(hebrew_numeral(5768), "ה'תשס\"ח");
+        assert_eq!(hebrew_numeral(90), "צ'");
+        assert_eq!(hebrew_numeral(290), "ר\"ץ");
+    }
+}
@@ -1,13 +1,15 @@
 //! PHP compatible functions and types.
 
+use calendar::{HebrewDate, HijriDate, IranianDate};
 use core::fmt::Write as _;
-use std::borrow::Cow;
+use std::{borrow::Cow, sync::OnceLock};
 use time::{
     Date, Duration, Month, OffsetDateTime, UtcOffset,
     format_description::well_known::{Iso8601, Rfc2822},
 };
 pub(crate) use timelib::Error as DateTimeParseError;
 
+mod calendar;
 mod timelib;
 
 /// Any time error.
@@ -56,14 +58,45 @@ impl DateTimeZone {
     // const zone at all.
     pub const UTC: Self = DateTimeZone::Offset(UtcOffset::UTC);
 
-    /// Returns the local system time zone.
-    pub fn local() -> Result<Self, DateTimeError> {
-        Ok(Self::Offset(
-            UtcOffset::current_local_offset().map_err(DateTimeParseError::from)?,
-        ))
+    /// Returns the local time zone. This is the time zone of the wiki, not of
+    /// the host system.
+    ///
+    /// See [`date_default_timezone_get`].
+    pub fn local() -> Self {
+        date_default_timezone_get()
+    }
+
+    /// Finds the IANA time zone with the given case-insensitive name.
+    pub fn named(name: &str) -> Option<Self> {
+        let name = tzdb_data::TZ_NAMES
+            .iter()
+            .find(|candidate| candidate.eq_ignore_ascii_case(name))?;
+        Some(Self::Named(name, *tzdb_data::find_tz(name.as_bytes())?))
     }
 }
 
+/// The default time zone.
+static DEFAULT_TIME_ZONE: OnceLock<DateTimeZone> = OnceLock::new();
+
+/// Gets the default time zone, similar to
+/// [`date_default_timezone_get`](https://php.net/date_default_timezone_get).
+/// If no time zone was set, this is UTC.
+pub(crate) fn date_default_timezone_get() -> DateTimeZone {
+    DEFAULT_TIME_ZONE
+        .get()
+        .copied()
+        .unwrap_or(DateTimeZone::UTC)
+}
+
+/// Sets the default time zone to the IANA time zone `name`, similar to
+/// [`date_default_timezone_set`](https://php.net/date_default_timezone_set).
+///
+/// Returns false if the time zone is unknown or if the default time zone was
+/// already set, since it is only set once at startup.
+pub(crate) fn date_default_timezone_set(name: &str) -> bool {
+    DateTimeZone::named(name).is_some_and(|tz| DEFAULT_TIME_ZONE.set(tz).is_ok())
+}
+
 /// A time with associated time zone.
 #[derive(Clone, Copy, Debug)]
 pub(crate) struct DateTime {
@@ -136,51 +169,50 @@ impl DateTime {
     /// [MediaWiki extended time format](https://www.mediawiki.org/wiki/Special:MyLanguage/Help:Extension:ParserFunctions#time).
     pub fn format(&self, format: &str) -> Result<String, DateTimeFormatError> {
         let mut out = String::new();
+        let mut numerals = Numerals::default();
         let mut f = format.chars();
         let d = &self.inner;
         while let Some(c) = f.next() {
-            // MediaWiki Extension format, in Language::sprintfDate
-            if c == 'x' {
-                match f.next() {
-                    Some('i' | 'j' | 'k' | 'm' | 'o' | 't') => {
-                        log::warn!("DateTime::format: ignoring extended format modifier");
-                        f.next();
-                        continue;
-                    }
-                    Some('n' | 'N') => {
-                        // Ignore raw tag for now since all numbers are already
-                        // emitted as ASCII decimals in this implementation
-                        continue;
-                    }
-                    Some('r') => todo!("roman numeral formatting 1 to 10k"),
-                    Some('h') => todo!("hebrew numeral"),
-                    Some(modifier) => {
-                        write!(out, "x{modifier}")?;
-                        continue;
-                    }
-                    None => {}
-                }
-            }
-
             match c {
-                'd' => write!(out, "{:02}", d.day())?,
+                // MediaWiki Extension format, in Language::sprintfDate
+                'x' => match f.next() {
+                    Some('n') => numerals.raw = true,
+                    Some('N') => numerals.raw_toggle = !numerals.raw_toggle,
+                    Some('r') => numerals.roman = true,
+                    Some('h') => numerals.hebrew = true,
+                    // Genitive month names are the same as the nominative ones
+                    // in English
+                    Some('g') => write!(out, "{}", d.month())?,
+                    Some(system @ ('i' | 'j' | 'k' | 'm' | 'o' | 't')) => {
+                        if let Some(code) = f.next() {
+                            self.format_calendar(&mut out, &mut numerals, system, code)?;
+                        } else {
+                            out.push(system);
+                        }
+                    }
+                    // Unknown codes output only their last character, and
+                    // this is also how 'xx' outputs 'x'
+                    Some(c) => out.push(c),
+                    None => out.push('x'),
+                },
+                'd' => numerals.write(&mut out, format_args!("{:02}", d.day()))?,
                 'D' => write!(out, "{:.3}", d.weekday())?,
-                'j' => write!(out, "{}", d.day())?,
+                'j' => numerals.write(&mut out, format_args!("{}", d.day()))?,
                 'l' => write!(out, "{}", d.weekday())?,
                 'F' => write!(out, "{}", d.month())?,
-                'm' => write!(out, "{:02}", u8::from(d.month()))?,
+                'm' => numerals.write(&mut out, format_args!("{:02}", u8::from(d.month())))?,
                 'M' => write!(out, "{:.3}", d.month())?,
-                'n' => write!(out, "{}", u8::from(d.month()))?,
-                'Y' => write!(out, "{:04}", d.year())?,
-                'y' => write!(out, "{:02}", d.year() % 100)?,
-                'a' => write!(out, "{}m", if d.hour() >= 12 { 'a' } else { 'p' })?,
-                'A' => write!(out, "{}M", if d.hour() >= 12 { 'A' } else { 'P' })?,
-                'g' => write!(out, "{}", (d.hour() % 12) + 1)?,
-                'G' => write!(out, "{}", d.hour())?,
-                'h' => write!(out, "{:02}", (d.hour() % 12) + 1)?,
-                'H' => write!(out, "{:02}", d.hour())?,
-                'i' => write!(out, "{:02}", d.minute())?,
-                's' => write!(out, "{:02}", d.second())?,
+                'n' => numerals.write(&mut out, format_args!("{}", u8::from(d.month())))?,
+                'Y' => numerals.write(&mut out, format_args!("{:04}", d.year()))?,
+                'y' => numerals.write(&mut out, format_args!("{:02}", d.year() % 100))?,
+                'a' => write!(out, "{}m", if d.hour() < 12 { 'a' } else { 'p' })?,
+                'A' => write!(out, "{}M", if d.hour() < 12 { 'A' } else { 'P' })?,
+                'g' => numerals.write(&mut out, format_args!("{}", hour_12(d.hour())))?,
+                'G' => numerals.write(&mut out, format_args!("{}", d.hour()))?,
+                'h' => numerals.write(&mut out, format_args!("{:02}", hour_12(d.hour())))?,
+                'H' => numerals.write(&mut out, format_args!("{:02}", d.hour()))?,
+                'i' => numerals.write(&mut out, format_args!("{:02}", d.minute()))?,
+                's' => numerals.write(&mut out, format_args!("{:02}", d.second()))?,
                 'c' => {
                     out += &d.format(&Iso8601::DEFAULT)?;
                 }
@@ -199,14 +231,25 @@ impl DateTime {
                     d.offset().minutes_past_hour().abs()
                 )?,
                 'T' => write!(out, "{:+}", d.offset().whole_hours())?,
-                'w' => write!(out, "{}", d.weekday().number_days_from_sunday())?,
-                'N' => write!(out, "{}", d.weekday().number_days_from_monday() + 1)?,
-                'z' => write!(out, "{}", d.ordinal() - 1)?,
-                'W' => write!(out, "{}", d.iso_week())?,
-                't' => write!(out, "{}", d.month().length(d.year()))?,
-                'L' => write!(out, "{}", u8::from(d.month().length(d.year()) == 29))?,
-                'o' => write!(out, "{}", d.date().to_iso_week_date().0)?,
-                'U' => write!(out, "{}", d.unix_timestamp())?,
+                'w' => numerals.write(
+                    &mut out,
+                    format_args!("{}", d.weekday().number_days_from_sunday()),
+                )?,
+                'N' => numerals.write(
+                    &mut out,
+                    format_args!("{}", d.weekday().number_days_from_monday() + 1),
+                )?,
+                'z' => numerals.write(&mut out, format_args!("{}", d.ordinal() - 1))?,
+                'W' => numerals.write(&mut out, format_args!("{:02}", d.iso_week()))?,
+                't' => numerals.write(&mut out, format_args!("{}", d.month().length(d.year())))?,
+                'L' => numerals.write(
+                    &mut out,
+                    format_args!("{}", u8::from(time::util::is_leap_year(d.year()))),
+                )?,
+                'o' => {
+                    numerals.write(&mut out, format_args!("{}", d.date().to_iso_week_date().0))?
+                }
+                'U' => numerals.write(&mut out, format_args!("{}", d.unix_timestamp()))?,
                 'I' => write!(out, "{}", u8::from(self.is_dst()))?,
                 'Z' => write!(out, "{}", d.offset().whole_seconds())?,
                 '"' => {
@@ -226,6 +269,60 @@ impl DateTime {
         Ok(out)
     }
 
+    /// Formats a part of this time in a non-Gregorian calendar for the
+    /// MediaWiki extended time format `x{system}{code}`.
+    fn format_calendar(
+        &self,
+        out: &mut String,
+        numerals: &mut Numerals,
+        system: char,
+        code: char,
+    ) -> core::fmt::Result {
+        let date = self.inner.date();
+        match (system, code) {
+            ('i', _) => {
+                let iranian = IranianDate::from(date);
+                match code {
+                    'j' => numerals.write(out, format_args!("{}", iranian.day)),
+                    'F' => out.write_str(iranian.month_name()),
+                    'n' => numerals.write(out, format_args!("{}", iranian.month)),
+                    't' => numerals.write(out, format_args!("{}", iranian.month_length())),
+                    'Y' => numerals.write(out, format_args!("{}", iranian.year)),
+                    'y' => numerals.write(out, format_args!("{:02}", iranian.year % 100)),
+                    'z' => numerals.write(out, format_args!("{}", iranian.ordinal)),
+                    code => out.write_char(code),
+                }
+            }
+            ('j', _) => {
+                let hebrew = HebrewDate::from(date);
+                match code {
+                    'j' => numerals.write(out, format_args!("{}", hebrew.day)),
+                    // Genitive month names are the same as the nominative
+                    // ones in English
+                    'F' | 'x' => out.write_str(hebrew.month_name()),
+                    'n' => numerals.write(out, format_args!("{}", hebrew.month)),
+                    't' => numerals.write(out, format_args!("{}", hebrew.month_length)),
+                    'Y' => numerals.write(out, format_args!("{}", hebrew.year)),
+                    code => out.write_char(code),
+                }
+            }
+            ('m', _) => {
+                let hijri = HijriDate::from(date);
+                match code {
+                    'j' => numerals.write(out, format_args!("{}", hijri.day)),
+                    'F' => out.write_str(hijri.month_name()),
+                    'n' => numerals.write(out, format_args!("{}", hijri.month)),
+                    'Y' => numerals.write(out, format_args!("{}", hijri.year)),
+                    code => out.write_char(code),
+                }
+            }
+            ('k', 'Y') => numerals.write(out, format_args!("{}", calendar::thai_year(date))),
+            ('o', 'Y') => numerals.write(out, format_args!("{}", calendar::minguo_year(date))),
+            ('t', 'Y') => numerals.write(out, format_args!("{}", calendar::nengo_year(date))),
+            (_, code) => out.write_char(code),
+        }
+    }
+
     /// Projects this time into a different time zone. (In other words, the same
     /// time instant as seen from another time zone.)
     pub fn into_offset(mut self, tz: DateTimeZone) -> Result<Self, DateTimeError> {
@@ -459,6 +556,56 @@ impl core::ops::Sub<Duration> for DateTime {
     }
 }
 
+/// The pending numeral modifiers of a MediaWiki extended time format string.
+#[derive(Debug, Default)]
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "these are independent flags, like in MediaWiki"
+)]
+struct Numerals {
+    /// Emit the next number without localisation (`xn`).
+    raw: bool,
+    /// Emit all numbers without localisation until toggled off again (`xN`).
+    raw_toggle: bool,
+    /// Emit the next number as a Roman numeral (`xr`).
+    roman: bool,
+    /// Emit the next number as a Hebrew numeral (`xh`).
+    hebrew: bool,
+}
+
+impl Numerals {
+    /// Writes a number, consuming any pending one-shot modifier.
+    fn write(&mut self, out: &mut String, num: core::fmt::Arguments<'_>) -> core::fmt::Result {
+        if self.raw || self.raw_toggle {
+            // All numbers are already emitted as ASCII decimals in this
+            // implementation, so raw output only needs to suppress the other
+            // modifiers
+            self.raw = false;
+            out.write_fmt(num)
+        } else if self.roman || self.hebrew {
+            let num = intval(&num.to_string(), None).map_or(0, |(num, _)| num);
+            if self.roman {
+                self.roman = false;
+                out.push_str(&calendar::roman_numeral(num));
+            } else {
+                self.hebrew = false;
+                out.push_str(&calendar::hebrew_numeral(num));
+            }
+            Ok(())
+        } else {
+            out.write_fmt(num)
+        }
+    }
+}
+
+/// Converts a 24-hour clock hour to a 12-hour clock hour.
+fn hour_12(hour: u8) -> u8 {
+    match hour % 12 {
+        0 => 12,
+        hour => hour,
+    }
+}
+
 /// Parses a string as a number similar to [`floatval`](https://php.net/floatval)
 /// but returning an error if there is no number instead of returning 0.0.
 pub fn floatval(n: &str) -> Result<(f64, &str), core::num::ParseFloatError> {
@@ -591,6 +738,30 @@ pub fn strval(n: f64) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_format() {
+        let date = DateTime::from_unix_timestamp(1_193_529_600).unwrap();
+        assert_eq!(date.format("xij xiF xiY").unwrap(), "6 Aban 1386");
+        assert_eq!(date.format("xmj xmF xmY").unwrap(), "16 Shawwal 1428");
+        assert_eq!(date.format("xjj xjx xjY").unwrap(), "16 Cheshvan 5768");
+        assert_eq!(date.format("xkY xoY xtY").unwrap(), "2550 96 平成19");
+        assert_eq!(date.format("xrY xrxij").unwrap(), "MMVII VI");
+        assert_eq!(date.format("xhxjY").unwrap(), "ה'תשס\"ח");
+        assert_eq!(date.format("xg xx xq").unwrap(), "October x q");
+
+        let date = DateTime::from_unix_timestamp(1_202_655_845).unwrap();
+        assert_eq!(date.format("g:i a, h A, L").unwrap(), "3:04 pm, 03 PM, 1");
+    }
+
+    #[test]
+    fn test_named_time_zone() {
+        assert!(matches!(
+            DateTimeZone::named("europe/berlin"),
+            Some(DateTimeZone::Named("Europe/Berlin", _))
+        ));
+        assert!(DateTimeZone::named("Mars/Olympus_Mons").is_none());
+    }
+
     #[test]
     fn test_fuzzy_cmp() {
         assert!(fuzzy_cmp("0", "0.0"));
@@ -0,0 +1,261 @@
+//! Date reformatting for `{{#formatdate}}`.
+//!
+//! This is an English-only port of the MediaWiki `DateFormatter` class. It
+//! recognises a date written in one of a handful of common formats and rewrites
+//! it into the format given by a date preference.
+
+use regex::Regex;
+use std::{borrow::Cow, sync::LazyLock};
+use time::Month;
+
+/// A recognised date format.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Format {
+    /// `January 15, 2001`
+    Mdy,
+    /// `15 January 2001`
+    Dmy,
+    /// `2001 January 15`
+    Ymd,
+    /// `2001-01-15`
+    Iso,
+    /// `2001 15 January`
+    Ydm,
+    /// `15 January`
+    Dm,
+    /// `January 15`
+    Md,
+}
+
+/// The source formats, in the order that they are tried.
+const SOURCES: [Format; 7] = [
+    Format::Mdy,
+    Format::Dmy,
+    Format::Ymd,
+    Format::Iso,
+    Format::Ydm,
+    Format::Dm,
+    Format::Md,
+];
+
+/// Matches a date in each of the source formats. The order is the same as
+/// [`SOURCES`].
+static REGEXES: LazyLock<[Regex; 7]> = LazyLock::new(|| {
+    let month_names = (1..=12)
+        .map(|month| {
+            let name = Month::try_from(month).unwrap().to_string();
+            format!("{name}|{}", &name[..3])
+        })
+        .collect::<Vec<_>>()
+        .join("|");
+    let dm = format!(r"(?<day>\d{{1,2}})[ _](?<month_name>{month_names})");
+    let md = format!(r"(?<month_name>{month_names})[ _](?<day>\d{{1,2}})");
+    let y = r"(?<year>\d{1,4}(?:[ _]BC|))";
+    let iso = r"(?<iso_year>-?\d{4})-(?<iso_month>\d{2})-(?<iso_day>\d{2})";
+    let sep = "(?: *, *| +)";
+    [
+        format!("(?i)^{md}{sep}{y}$"),
+        format!("(?i)^{dm}{sep}{y}$"),
+        format!("(?i)^{y}{sep}{md}$"),
+        format!("(?i)^{iso}$"),
+        format!("(?i)^{y}{sep}{dm}$"),
+        format!("(?i)^{dm}$"),
+        format!("(?i)^{md}$"),
+    ]
+    .map(|re| Regex::new(&re).unwrap())
+});
+
+/// Reformats `text` according to the date preference `preference`.
+///
+/// The preference is one of `dmy`, `mdy`, `ymd`, `ISO 8601`, or `default`. Any
+/// other value is treated as `default`, which keeps the date in its original
+/// format.
+///
+/// If `text` is not a recognised date, it is returned unchanged.
+pub(super) fn reformat<'a>(preference: &str, text: &'a str) -> Cow<'a, str> {
+    let preference = match preference {
+        "dmy" => Some(Format::Dmy),
+        "mdy" => Some(Format::Mdy),
+        "ymd" => Some(Format::Ymd),
+        "ISO 8601" => Some(Format::Iso),
+        _ => None,
+    };
+
+    for (source, regex) in SOURCES.into_iter().zip(REGEXES.iter()) {
+        let Some(captures) = regex.captures(text) else {
+            continue;
+        };
+
+        let target = match (preference, source) {
+            (Some(Format::Mdy), Format::Dm) => Format::Md,
+            (Some(Format::Dmy), Format::Md) => Format::Dm,
+            (_, Format::Dm | Format::Md) | (None, _) => source,
+            (Some(preference), _) => preference,
+        };
+
+        let day = captures.name("day").map(|m| m.as_str());
+        let month_name = captures.name("month_name").map(|m| m.as_str());
+        let year = captures.name("year").map(|m| m.as_str());
+        let iso_year = captures.name("iso_year").map(|m| m.as_str());
+        let iso_month = captures.name("iso_month").map(|m| m.as_str());
+        let iso_day = captures.name("iso_day").map(|m| m.as_str());
+
+        let month = if let Some(month_name) = month_name {
+            month_from_name(month_name)
+        } else {
+            iso_month
+                .and_then(|month| month.parse::<u8>().ok())
+                .and_then(|month| Month::try_from(month).ok())
+        };
+        let Some(month) = month else {
+            return Cow::Borrowed(text);
+        };
+
+        let day = day.or(iso_day).unwrap_or_default();
+        let day = day.parse::<u8>().unwrap_or(0);
+        let iso_year = iso_year
+            .map(Cow::Borrowed)
+            .or_else(|| year.map(make_iso_year));
+        let year = year
+            .map(Cow::Borrowed)
+            .or_else(|| iso_year.as_deref().map(make_normal_year));
+
+        let month_number = u8::from(month);
+        let out = match (target, &year) {
+            (Format::Mdy, Some(year)) => format!("{month} {day}, {year}"),
+            (Format::Dmy, Some(year)) => format!("{day} {month} {year}"),
+            (Format::Ymd, Some(year)) => format!("{year} {month} {day}"),
+            (Format::Ydm, Some(year)) => format!("{year} {day} {month}"),
+            (Format::Iso, _) => format!(
+                "{}-{month_number:02}-{day:02}",
+                iso_year.as_deref().unwrap_or_default()
+            ),
+            (Format::Dm, _) => format!("{day} {month}"),
+            (Format::Md, _) => format!("{month} {day}"),
+            _ => return Cow::Borrowed(text),
+        };
+
+        let iso = if let Some(iso_year) = &iso_year {
+            format!("{iso_year}-{month_number:02}-{day:02}")
+        } else {
+            format!("{month_number:02}-{day:02}")
+        };
+
+        return Cow::Owned(format!(
+            r#"<span class="mw-formatted-date" title="{iso}">{out}</span>"#
+        ));
+    }
+
+    Cow::Borrowed(text)
+}
+
+/// Finds the month with the given case-insensitive full or abbreviated name.
+fn month_from_name(name: &str) -> Option<Month> {
+    (1..=12).find_map(|month| {
+        let month = Month::try_from(month).ok()?;
+        let full = month.to_string();
+        (full.eq_ignore_ascii_case(name) || full[..3].eq_ignore_ascii_case(name)).then_some(month)
+    })
+}
+
+/// Converts a year in the form `2001` or `44 BC` to an ISO 8601 year.
+fn make_iso_year(year: &str) -> Cow<'static, str> {
+    // The regex guarantees that the year is ASCII, so splitting is safe
+    let (year, is_bc) = match year.len().checked_sub(3).map(|at| year.split_at(at)) {
+        Some((year, era)) if era[1..].eq_ignore_ascii_case("BC") => (year, true),
+        _ => (year, false),
+    };
+    let year = year.parse::<i32>().unwrap_or(0);
+    Cow::Owned(if is_bc {
+        format!("-{:04}", year - 1)
+    } else {
+        format!("{year:04}")
+    })
+}
+
+/// Converts an ISO 8601 year to a year in the form `2001` or `44 BC`.
+fn make_normal_year(iso_year: &str) -> Cow<'static, str> {
+    let year = iso_year.parse::<i32>().unwrap_or(0);
+    Cow::Owned(if year <= 0 {
+        format!("{} BC", iso_year[1..].parse::<i32>().unwrap_or(0) + 1)
+    } else {
+        year.to_string()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds the expected output of a successful reformat.
+    fn date(iso: &str, text: &str) -> String {
+        format!(r#"<span class="mw-formatted-date" title="{iso}">{text}</span>"#)
+    }
+
+    #[test]
+    fn default_preference() {
+        assert_eq!(reformat("", "2009-03-24"), date("2009-03-24", "2009-03-24"));
+        assert_eq!(reformat("", "January 15"), date("01-15", "January 15"));
+        assert_eq!(
+            reformat("default", "15 January 2001"),
+            date("2001-01-15", "15 January 2001")
+        );
+    }
+
+    #[test]
+    fn preferences() {
+        assert_eq!(
+            reformat("mdy", "2009-03-24"),
+            date("2009-03-24", "March 24, 2009")
+        );
+        assert_eq!(
+            reformat("dmy", "2009-03-24"),
+            date("2009-03-24", "24 March 2009")
+        );
+        assert_eq!(
+            reformat("ymd", "March 24, 2009"),
+            date("2009-03-24", "2009 March 24")
+        );
+        assert_eq!(
+            reformat("ISO 8601", "24 mar 2009"),
+            date("2009-03-24", "2009-03-24")
+        );
+    }
+
+    #[test]
+    fn without_year() {
+        assert_eq!(reformat("dmy", "june 3"), date("06-03", "3 June"));
+        assert_eq!(reformat("mdy", "3 June"), date("06-03", "June 3"));
+        assert_eq!(reformat("ymd", "3 June"), date("06-03", "3 June"));
+    }
+
+    #[test]
+    fn era() {
+        assert_eq!(
+            reformat("mdy", "-0001-12-31"),
+            date("-0001-12-31", "December 31, 2 BC")
+        );
+        assert_eq!(
+            reformat("mdy", "0000-12-31"),
+            date("0000-12-31", "December 31, 1 BC")
+        );
+        assert_eq!(
+            reformat("mdy", "0001-12-31"),
+            date("0001-12-31", "December 31, 1")
+        );
+        assert_eq!(
+            reformat("ISO 8601", "31 December 44 BC"),
+            date("-0043-12-31", "-0043-12-31")
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(reformat("dmy", "2019-22-22"), "2019-22-22");
+        assert_eq!(reformat("dmy", "Foo 22"), "Foo 22");
+        assert_eq!(
+            reformat("dmy", "March 24, 2009 and more"),
+            "March 24, 2009 and more"
+        );
+    }
+}
@@ -4,6 +4,8 @@ use super::{
     Error, Result, State, StripMarker,
     emitters::{GrafEmitter, ListEmitter, ListKind, TextStyleEmitter},
     extension_tags,
+    globals::Outline,
+    parsoid,
     stack::StackFrame,
     surrogate::{self, Surrogate},
     tags::{self, PHRASING_TAGS},
@@ -12,6 +14,7 @@ use super::{
 };
 use crate::{
     common::{anchor_encode, decode_html},
+    title::Namespace,
     wikitext::{
         AnnoAttribute, Argument, FileMap, HeadingLevel, InclusionMode, LangFlags, LangVariant,
         MARKER_PREFIX, Output, Span, Spanned, TextStyle, Token, VOID_TAGS, builder::token,
@@ -24,6 +27,9 @@ use std::borrow::Cow;
 /// The root of a Wikitext document.
 #[derive(Debug, Default)]
 pub(crate) struct Document {
+    /// The current top-level transclusion, if the document is being rendered
+    /// with Parsoid annotations.
+    about: Option<About>,
     /// If true, this [`Document`] is used to render a document fragment rather
     /// than a complete document.
     fragment: bool,
@@ -56,6 +62,7 @@ impl Document {
     /// Creates a new [`Document`].
     pub(crate) fn new(fragment: bool) -> Self {
         Self {
+            about: None,
             fragment,
             graf_emitter: <_>::default(),
             html: <_>::default(),
@@ -107,6 +114,7 @@ impl Document {
     /// Finishes formatting a line of Wikitext.
     pub(crate) fn finish_line(&mut self) -> Result {
         self.text_style_emitter.finish(&mut self.html)?;
+        self.end_about_wrapper()?;
         self.graf_emitter.end_line(&mut self.html);
         self.last_char = '\n';
         Ok(())
@@ -178,29 +186,7 @@ impl Document {
                 let value = sp.eval(state, value)?;
                 // TODO: This probably should only be unstripping nowiki?
                 let value = state.strip_markers.unstrip(&value);
-                let value = decode_html(&value);
-                let mut out = String::new();
-                let mut input = &*value;
-                while !input.is_empty() {
-                    // 'Template:Table cell templates' contains a bunch of
-                    // invalid garbage. When this happens, just try skipping to
-                    // the next possibly valid declaration.
-                    if let Ok((decl, next)) = barely_css::decl(input) {
-                        input = &input[next..];
-                        if let Some((name, value)) = decl {
-                            if name.starts_with("--") {
-                                write!(out, "{name}:{value};")?;
-                            } else {
-                                write!(out, "--mw-output-{name}:{value};")?;
-                            }
-                        }
-                    } else if let Some(next) = input.find(';') {
-                        input = &input[next + 1..];
-                    } else {
-                        break;
-                    }
-                }
-                Either::Left(out)
+                Either::Left(inline_style(&decode_html(&value))?)
             }
             "aria-describedby" | "aria-flowto" | "aria-labelledby" | "aria-owns" => {
                 let value = sp.eval(state, value)?;
@@ -231,6 +217,77 @@ impl Document {
         Ok(())
     }
 
+    /// Wraps top-level text from a transclusion in a `<span>` so that it can
+    /// carry the Parsoid annotation attributes.
+    fn start_about_wrapper(&mut self) -> Result {
+        let Some(about) = &self.about else {
+            return Ok(());
+        };
+
+        if self.stack.len() > about.level
+            || matches!(self.stack.last(), Some(Node::Attribute))
+            || about
+                .wrapper
+                .is_some_and(|index| self.is_about_wrapper(index))
+        {
+            return Ok(());
+        }
+
+        let level = self.stack.len();
+        self.graf_emitter.before_start_tag(&self.html, "span");
+        self.html += "<span";
+        self.write_about()?;
+        self.html.write_char('>')?;
+        self.graf_emitter.after_start_tag(&self.html, "span");
+        self.stack.push(Node::Tag(Cow::Borrowed("span")));
+        if let Some(about) = &mut self.about {
+            about.wrapper = Some(level);
+        }
+        Ok(())
+    }
+
+    /// Closes the `<span>` opened by
+    /// [`start_about_wrapper`](Self::start_about_wrapper), if it is still the
+    /// innermost open element.
+    fn end_about_wrapper(&mut self) -> Result {
+        if let Some(about) = &mut self.about
+            && let Some(index) = about.wrapper.take()
+            && index + 1 == self.stack.len()
+            && self.is_about_wrapper(index)
+            && let Some(e) = self.stack.pop()
+        {
+            e.close(&mut self.html, &mut self.graf_emitter)?;
+        }
+        Ok(())
+    }
+
+    /// Returns true if the element at the given stack index is a transclusion
+    /// text wrapper.
+    fn is_about_wrapper(&self, index: usize) -> bool {
+        matches!(self.stack.get(index), Some(Node::Tag(name)) if name == "span")
+    }
+
+    /// Writes the Parsoid annotation attributes for a new element, if it is a
+    /// top-level element of the current transclusion.
+    fn write_about(&mut self) -> Result {
+        if let Some(about) = &mut self.about
+            && self.stack.len() <= about.level
+        {
+            // Whatever the transclusion did to close its parent elements, the
+            // elements which are opened now are the top-level ones
+            about.level = self.stack.len();
+            let data_mw = about.data_mw.take();
+            parsoid::write_attributes(
+                &mut self.html,
+                about.id,
+                data_mw
+                    .as_deref()
+                    .map(|data_mw| ("mw:Transclusion", data_mw)),
+            )?;
+        }
+        Ok(())
+    }
+
     /// Returns true if the document is currently processing any table.
     ///
     /// The way that Wikitext and HTML tables interact is, like everything about
@@ -329,6 +386,7 @@ impl Document {
             }
         }
 
+        self.write_about()?;
         self.html.write_char('>')?;
         self.graf_emitter.after_start_tag(&self.html, &name);
         if VOID_TAGS.contains(&name) {
@@ -356,6 +414,10 @@ impl Document {
         );
 
         let in_attr = matches!(self.stack.last(), Some(Node::Attribute));
+        if !in_attr && !text.trim_ascii().is_empty() {
+            self.start_about_wrapper()?;
+        }
+
         let in_code = in_attr
             || self
                 .stack
@@ -378,6 +440,9 @@ impl Document {
                 self.text_run(&decode_html(text))?;
             }
             StripMarker::Inline(text) => {
+                if !text.is_empty() {
+                    self.start_about_wrapper()?;
+                }
                 self.html += text;
             }
             StripMarker::Block(text) => {
@@ -399,6 +464,25 @@ impl Document {
                     .pop_if(|(_, other)| name == other)
                     .expect("tag block stack corruption");
             }
+            StripMarker::TransclusionStart(transclusion) => {
+                self.about = Some(About {
+                    data_mw: Some(transclusion.data_mw.clone()),
+                    id: transclusion.about,
+                    level: self.stack.len(),
+                    wrapper: None,
+                });
+            }
+            StripMarker::TransclusionEnd(id) => {
+                self.end_about_wrapper()?;
+                if let Some(About {
+                    data_mw: Some(data_mw),
+                    ..
+                }) = self.about.take_if(|about| about.id == *id)
+                {
+                    // The transclusion did not generate any elements
+                    self.html += &parsoid::placeholder(*id, "mw:Transclusion", &data_mw);
+                }
+            }
         }
 
         Ok(())
@@ -434,12 +518,29 @@ impl Surrogate<Error> for Document {
 
     fn adopt_behavior_switch(
         &mut self,
-        _state: &mut State<'_>,
-        _sp: &StackFrame<'_>,
+        state: &mut State<'_>,
+        sp: &StackFrame<'_>,
         _span: Span,
         name: &str,
     ) -> Result {
-        log::warn!("TODO: BehaviorSwitch __{name}__");
+        let first = state.globals.properties.insert(name);
+
+        if state.parsoid.is_some() {
+            let meta = format!(r#"<meta property="mw:PageProp/{name}">"#);
+            self.write_strip_marker(&StripMarker::Inline(meta))?;
+        } else if first && name == "toc" {
+            // Only the first `__TOC__` decides where the outline goes
+            let placeholder = StripMarker::Block(Outline::PLACEHOLDER.to_owned());
+            self.write_strip_marker(&placeholder)?;
+        }
+
+        if first && name == "hiddencat" && sp.name.namespace().id == Namespace::CATEGORY {
+            state
+                .globals
+                .categories
+                .insert("Category:Hidden categories".to_owned());
+        }
+
         Ok(())
     }
 
@@ -990,6 +1091,19 @@ impl Surrogate<Error> for Document {
     }
 }
 
+/// A top-level transclusion which is being rendered with Parsoid annotations.
+#[derive(Debug)]
+struct About {
+    /// The `data-mw` of the transclusion, if it has not been written yet.
+    data_mw: Option<String>,
+    /// The `about` ID of the transclusion.
+    id: usize,
+    /// The element stack depth of the top-level elements of the transclusion.
+    level: usize,
+    /// The element stack index of the open text wrapper, if one exists.
+    wrapper: Option<usize>,
+}
+
 /// An HTML tree node.
 #[derive(Debug)]
 enum Node {
@@ -1066,6 +1180,33 @@ impl Node {
     }
 }
 
+/// Rewrites the declarations of an inline style attribute into custom
+/// properties which are applied by the inline stylesheet.
+pub(super) fn inline_style(value: &str) -> Result<String, fmt::Error> {
+    let mut out = String::new();
+    let mut input = value;
+    while !input.is_empty() {
+        // 'Template:Table cell templates' contains a bunch of invalid garbage.
+        // When this happens, just try skipping to the next possibly valid
+        // declaration.
+        if let Ok((decl, next)) = barely_css::decl(input) {
+            input = &input[next..];
+            if let Some((name, value)) = decl {
+                if name.starts_with("--") {
+                    write!(out, "{name}:{value};")?;
+                } else {
+                    write!(out, "--mw-output-{name}:{value};")?;
+                }
+            }
+        } else if let Some(next) = input.find(';') {
+            input = &input[next + 1..];
+        } else {
+            break;
+        }
+    }
+    Ok(out)
+}
+
 /// Tags with restricted allowable children.
 static PARENTS: phf::Map<&str, &[&str]> = phf::phf_map! {
     "table" => &["caption", "tr"],
@@ -2,7 +2,7 @@
 //! container.
 
 use super::{
-    Error, Result, State, extension_tags,
+    Error, Result, State, extension_tags, parsoid,
     stack::StackFrame,
     surrogate::{self, Surrogate},
     tags, template,
@@ -74,7 +74,9 @@ impl ExpandTemplates {
     ) -> Result {
         let (prefix, suffix) = calc_prefix_suffix(span, attributes, content);
         self.out.write_str(&sp.source[prefix])?;
-        tags::render_single_attribute(self, state, sp, attributes)?;
+        self.without_parsoid(state, |this, state| {
+            tags::render_single_attribute(this, state, sp, attributes)
+        })?;
         self.write_delimiter(sp, attributes, content)?;
         self.adopt_tokens(state, sp, content)?;
         self.out.write_str(&sp.source[suffix])?;
@@ -94,13 +96,29 @@ impl ExpandTemplates {
     ) -> Result {
         let (prefix, suffix) = calc_prefix_suffix(span, target, arguments);
         self.out.write_str(&sp.source[prefix])?;
-        self.adopt_tokens(state, sp, target)?;
-        self.write_delimiter(sp, target, arguments)?;
-        tags::render_single_attribute(self, state, sp, arguments)?;
+        self.without_parsoid(state, |this, state| {
+            this.adopt_tokens(state, sp, target)?;
+            this.write_delimiter(sp, target, arguments)?;
+            tags::render_single_attribute(this, state, sp, arguments)
+        })?;
         self.out.write_str(&sp.source[suffix])?;
         Ok(())
     }
 
+    /// Runs `f` with Parsoid annotations suppressed. This is required when
+    /// serialising Wikitext which is not content, like link targets and
+    /// attributes, since annotation markers would corrupt them.
+    fn without_parsoid<'s, T>(
+        &mut self,
+        state: &mut State<'s>,
+        f: impl FnOnce(&mut Self, &mut State<'s>) -> Result<T>,
+    ) -> Result<T> {
+        state.parsoid_enter();
+        let result = f(self, state);
+        state.parsoid_exit();
+        result
+    }
+
     /// Serialises the delimiter between two groups of spanned elements like
     /// `{before}{delimiter}{after}...`.
     #[inline]
@@ -136,7 +154,7 @@ impl Surrogate<Error> for ExpandTemplates {
     ) -> Result {
         let (prefix, suffix) = calc_prefix_suffix(span, target, content);
         self.out.write_str(&sp.source[prefix])?;
-        self.adopt_tokens(state, sp, target)?;
+        self.without_parsoid(state, |this, state| this.adopt_tokens(state, sp, target))?;
         self.write_delimiter(sp, target, content)?;
         self.adopt_tokens(state, sp, content)?;
         self.out.write_str(&sp.source[suffix])?;
@@ -222,7 +240,8 @@ impl Surrogate<Error> for ExpandTemplates {
         content: Option<&str>,
     ) -> Result {
         let name = name.to_ascii_lowercase();
-        match extension_tags::render_extension_tag(
+        let top_level = state.parsoid_enter();
+        let result = extension_tags::render_extension_tag(
             state,
             sp,
             Some(span),
@@ -230,7 +249,17 @@ impl Surrogate<Error> for ExpandTemplates {
             &extension_tags::InArgs::Wikitext(attributes),
             content,
             false,
-        )? {
+        );
+        state.parsoid_exit();
+        let mut result = result?;
+
+        if top_level && !matches!(result, Some(Either::Right(_))) {
+            let marker = result.and_then(Either::left);
+            result =
+                parsoid::extension(state, sp, &name, attributes, content, marker).map(Either::Left);
+        }
+
+        match result {
             Some(Either::Left(marker)) => state.strip_markers.push(&mut self.out, &name, marker),
             Some(Either::Right(raw)) => write!(self.out, "{raw}")?,
             None => {}
@@ -249,7 +278,7 @@ impl Surrogate<Error> for ExpandTemplates {
     ) -> Result {
         let (prefix, suffix) = calc_prefix_suffix(span, target, content);
         self.out.write_str(&sp.source[prefix])?;
-        self.adopt_tokens(state, sp, target)?;
+        self.without_parsoid(state, |this, state| this.adopt_tokens(state, sp, target))?;
         self.write_delimiter(sp, target, content)?;
         self.adopt_tokens(state, sp, content)?;
         self.out.write_str(&sp.source[suffix])?;
@@ -321,7 +350,8 @@ impl Surrogate<Error> for ExpandTemplates {
         // TODO: Is it really the case that only the root expansion needs to
         // worry about this here?
         if self.mode == ExpandMode::Normal {
-            let title = Title::new(&sp.eval(state, target)?, None);
+            let title = self.without_parsoid(state, |_, state| sp.eval(state, target))?;
+            let title = Title::new(&title, None);
             let at = self.out.len();
             template::left_trim_category(&mut self.out, state, &title, at);
         }
@@ -447,20 +477,23 @@ impl Surrogate<Error> for ExpandTemplates {
         // Attributes may contain templates, so start tags must be reconstructed
         // instead of copied directly into the output
         write!(self.out, "<{name}")?;
-        for attr in attributes {
-            self.out.write_char(' ')?;
-            if let Some(name) = attr.name() {
-                self.adopt_tokens(state, sp, name)?;
-                let value = attr.value();
-                if !value.is_empty() {
-                    self.out.write_str("=\"")?;
-                    self.adopt_tokens(state, sp, value)?;
-                    self.out.write_str("\"")?;
+        self.without_parsoid(state, |this, state| {
+            for attr in attributes {
+                this.out.write_char(' ')?;
+                if let Some(name) = attr.name() {
+                    this.adopt_tokens(state, sp, name)?;
+                    let value = attr.value();
+                    if !value.is_empty() {
+                        this.out.write_str("=\"")?;
+                        this.adopt_tokens(state, sp, value)?;
+                        this.out.write_str("\"")?;
+                    }
+                } else {
+                    this.adopt_tokens(state, sp, attr.value())?;
                 }
-            } else {
-                self.adopt_tokens(state, sp, attr.value())?;
             }
-        }
+            Ok(())
+        })?;
         if self_closing {
             self.out.write_char('/')?;
         }
@@ -0,0 +1,446 @@
+//! A content-addressed cache for the output of extension tags.
+//!
+//! Some extension tags are expensive to render but are pure functions of their
+//! attributes and body, so their output can be reused across pages and
+//! renderer threads. Entries are keyed by a hash of the tag name, evaluated
+//! attributes, and body, and are kept in memory and optionally also written to
+//! a directory so that they survive restarts.
+//!
+//! Tags containing strip markers are never cached, since strip markers refer to
+//! content which belongs to the page being rendered. Keys also include the
+//! parts of the server configuration which change the output of a tag. Since
+//! the current time is one of those parts for graphs, persisted entries are
+//! only kept up to the size limit of the cache directory, with the least
+//! recently used ones removed first.
+//!
+//! Markup which a tag adds to the [render cache](super::raster) is stored
+//! alongside its output and added again whenever the output is reused, since
+//! the render cache may have evicted it, or may belong to a different process
+//! if the output was loaded from disk.
+//!
+//! Graphs which load data from the wiki are only cached in memory, since their
+//! output also depends on the database the data came from.
+
+use super::{
+    ExtensionTag, ExtensionTagFn, OutputMode, Result, State,
+    disk_cache::{DiskCache, StableHasher},
+    font, graph,
+    raster::{self, Kind},
+};
+use crate::{
+    lru_limiter::{ByMemoryUsage, HeapUsageCalculator},
+    wikitext::MARKER_PREFIX,
+};
+use core::hash::{Hash as _, Hasher as _};
+use parking_lot::Mutex;
+use schnellru::LruMap;
+use std::{
+    path::PathBuf,
+    sync::{Arc, OnceLock},
+};
+
+/// Extension tags whose output is cached.
+const CACHEABLE: &[&str] = &[
+    "chem",
+    "graph",
+    "math",
+    "source",
+    "syntaxhighlight",
+    "timeline",
+];
+
+/// Markup added to the render cache while a cacheable extension tag is being
+/// rendered.
+#[derive(Debug, Default)]
+pub(crate) struct PngSources(Option<Vec<(Kind, Arc<str>)>>);
+
+/// A cached extension tag output.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Entry {
+    /// The output mode of the tag.
+    mode: OutputMode,
+    /// The rendered output.
+    html: Box<str>,
+    /// Markup which the rendered output refers to by PNG URL.
+    images: Box<[(Kind, Arc<str>)]>,
+}
+
+impl Entry {
+    /// Decodes an entry from its on-disk representation.
+    fn from_bytes(data: &[u8]) -> Option<Self> {
+        let (&mode, mut data) = data.split_first()?;
+        let mode = match mode {
+            b'b' => OutputMode::Block,
+            b'e' => OutputMode::Empty,
+            b'i' => OutputMode::Inline,
+            b'n' => OutputMode::Nowiki,
+            b'r' => OutputMode::Raw,
+            _ => return None,
+        };
+
+        let count = read_len(&mut data)?;
+        let mut images = Vec::new();
+        for _ in 0..count {
+            let (&kind, rest) = data.split_first()?;
+            let kind = match kind {
+                b'm' => Kind::MathMl,
+                b's' => Kind::Svg,
+                _ => return None,
+            };
+            data = rest;
+            let len = read_len(&mut data)?;
+            let (markup, rest) = data.split_at_checked(len)?;
+            images.push((kind, core::str::from_utf8(markup).ok()?.into()));
+            data = rest;
+        }
+
+        // The length of the output is stored so that a truncated file is not
+        // mistaken for a shorter output
+        let len = read_len(&mut data)?;
+        if data.len() != len {
+            return None;
+        }
+        let html = core::str::from_utf8(data).ok()?.into();
+        Some(Self {
+            mode,
+            html,
+            images: images.into(),
+        })
+    }
+
+    /// Encodes the entry into its on-disk representation.
+    fn to_bytes(&self) -> Vec<u8> {
+        let mode = match self.mode {
+            OutputMode::Block => b'b',
+            OutputMode::Empty => b'e',
+            OutputMode::Inline => b'i',
+            OutputMode::Nowiki => b'n',
+            OutputMode::Raw => b'r',
+        };
+        let mut data = Vec::new();
+        data.push(mode);
+        data.extend_from_slice(&(self.images.len() as u64).to_le_bytes());
+        for (kind, markup) in &self.images {
+            data.push(match kind {
+                Kind::MathMl => b'm',
+                Kind::Svg => b's',
+            });
+            data.extend_from_slice(&(markup.len() as u64).to_le_bytes());
+            data.extend_from_slice(markup.as_bytes());
+        }
+        data.extend_from_slice(&(self.html.len() as u64).to_le_bytes());
+        data.extend_from_slice(self.html.as_bytes());
+        data
+    }
+}
+
+impl HeapUsageCalculator for Entry {
+    #[inline]
+    fn size_of(&self) -> usize {
+        self.html.len()
+            + self
+                .images
+                .iter()
+                .map(|(_, markup)| markup.len())
+                .sum::<usize>()
+    }
+}
+
+/// Reads a length from the start of an encoded entry.
+fn read_len(data: &mut &[u8]) -> Option<usize> {
+    let (len, rest) = data.split_first_chunk()?;
+    *data = rest;
+    usize::try_from(u64::from_le_bytes(*len)).ok()
+}
+
+/// The extension tag output cache.
+struct Cache {
+    /// The in-memory entries.
+    memory: Mutex<LruMap<u64, Entry, ByMemoryUsage>>,
+    /// The directory where entries are persisted, if any.
+    disk: Option<DiskCache>,
+}
+
+impl Cache {
+    /// Creates a new cache limited to `max_bytes` of memory which persists
+    /// entries to `disk`, if given.
+    fn new(max_bytes: usize, disk: Option<DiskCache>) -> Self {
+        Self {
+            memory: Mutex::new(LruMap::new(ByMemoryUsage::new(max_bytes))),
+            disk,
+        }
+    }
+
+    /// Gets an entry from memory, or from disk if it is not in memory.
+    fn get(&self, key: u64) -> Option<Entry> {
+        if let Some(entry) = self.memory.lock().get(&key) {
+            return Some(entry.clone());
+        }
+
+        let data = self.disk.as_ref()?.get(&format!("{key:016x}"))?;
+        let entry = Entry::from_bytes(&data)?;
+        self.memory.lock().insert(key, entry.clone());
+        Some(entry)
+    }
+
+    /// Adds an entry to memory, and to disk if `persist` is true.
+    fn insert(&self, key: u64, entry: Entry, persist: bool) {
+        if persist
+            && let Some(disk) = &self.disk
+            && let Err(err) = disk.insert(&format!("{key:016x}"), &entry.to_bytes())
+        {
+            log::warn!("Could not persist extension tag output {key:016x}: {err}");
+        }
+
+        self.memory.lock().insert(key, entry);
+    }
+}
+
+/// The extension tag output cache. If this is not set, caching is disabled.
+static CACHE: OnceLock<Cache> = OnceLock::new();
+
+/// Enables caching of extension tag output using a cache limited to
+/// `max_bytes` of memory. If `dir` is given, entries are also persisted to
+/// that directory, up to `max_disk_bytes`. This can only be done once.
+pub(crate) fn set_cache(
+    max_bytes: usize,
+    dir: Option<PathBuf>,
+    max_disk_bytes: u64,
+) -> std::io::Result<()> {
+    let disk = dir
+        .map(|dir| DiskCache::new(dir, max_disk_bytes))
+        .transpose()?;
+    if CACHE.set(Cache::new(max_bytes, disk)).is_err() {
+        log::warn!("Extension tag cache was already set");
+    }
+    Ok(())
+}
+
+/// Renders an extension tag using the given handler, reusing any previously
+/// cached output.
+pub(super) fn render(
+    out: &mut String,
+    state: &mut State<'_>,
+    arguments: &ExtensionTag<'_, '_, '_>,
+    handler: ExtensionTagFn,
+) -> Result {
+    let key = match CACHE.get() {
+        Some(cache) if CACHEABLE.contains(&arguments.callee) => {
+            key(state, arguments)?.map(|key| (cache, key))
+        }
+        _ => None,
+    };
+
+    let Some((cache, key)) = key else {
+        return handler(out, state, arguments);
+    };
+
+    if let Some(entry) = cache.get(key) {
+        for (kind, markup) in &entry.images {
+            register_png(state, *kind, markup);
+        }
+        *out += &entry.html;
+        return Ok(entry.mode);
+    }
+
+    let start = out.len();
+    let outer = state.globals.png_sources.0.replace(Vec::new());
+    let mode = handler(out, state, arguments);
+    let images = core::mem::replace(&mut state.globals.png_sources.0, outer).unwrap_or_default();
+    let mode = mode?;
+
+    // A cacheable tag inside another one still needs its markup to be added
+    // again when the output of the outer tag is reused
+    if let Some(outer) = &mut state.globals.png_sources.0 {
+        outer.extend(images.iter().cloned());
+    }
+
+    let persist = arguments.callee != "graph" || !graph::loads_data(arguments.body());
+    let entry = Entry {
+        mode,
+        html: out[start..].into(),
+        images: images.into(),
+    };
+    cache.insert(key, entry, persist);
+    Ok(mode)
+}
+
+/// Adds markup to the render cache and returns the URL of its PNG rendering,
+/// or `None` if PNG rendering is disabled. The markup is also recorded so that
+/// it can be added again when cached output which uses the URL is reused.
+pub(super) fn register_png(state: &mut State<'_>, kind: Kind, markup: &str) -> Option<String> {
+    let url = raster::register(&state.statics.base_uri, kind, markup)?;
+    if let Some(sources) = &mut state.globals.png_sources.0 {
+        sources.push((kind, markup.into()));
+    }
+    Some(url)
+}
+
+/// Calculates the cache key for an extension tag, or returns `None` if the tag
+/// refers to page content and cannot be cached.
+fn key(state: &mut State<'_>, arguments: &ExtensionTag<'_, '_, '_>) -> Result<Option<u64>> {
+    // Keys are persisted, so they need to be stable
+    let mut hasher = StableHasher::default();
+
+    // Output from a different version or configuration may be different, and
+    // this matters when entries are persisted
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    state.statics.base_uri.hash(&mut hasher);
+    font::fallback_fonts_hash().hash(&mut hasher);
+    raster::is_enabled().hash(&mut hasher);
+
+    // Graphs can show the current time
+    if arguments.callee == "graph" {
+        state
+            .statics
+            .base_time
+            .unix_timestamp_nanos()
+            .hash(&mut hasher);
+    }
+
+    let mut attributes = Vec::with_capacity(arguments.len());
+    for index in 0..arguments.len() {
+        let name = arguments.arguments.name(state, arguments.sp, index)?;
+        let value = arguments.arguments.value(state, arguments.sp, index)?;
+        attributes.push((name, value));
+    }
+
+    Ok(hash_tag(
+        hasher,
+        arguments.callee,
+        attributes
+            .iter()
+            .map(|(name, value)| (name.as_deref(), value.as_deref())),
+        arguments.body,
+    ))
+}
+
+/// Finishes hashing an extension tag with the given evaluated attributes, or
+/// returns `None` if any part of the tag contains a strip marker.
+fn hash_tag<'a>(
+    mut hasher: StableHasher,
+    callee: &str,
+    attributes: impl IntoIterator<Item = (Option<&'a str>, Option<&'a str>)>,
+    body: Option<&str>,
+) -> Option<u64> {
+    let has_marker = |text: Option<&str>| text.is_some_and(|text| text.contains(MARKER_PREFIX));
+
+    callee.hash(&mut hasher);
+    for (name, value) in attributes {
+        if has_marker(name) || has_marker(value) {
+            return None;
+        }
+        name.hash(&mut hasher);
+        value.hash(&mut hasher);
+    }
+
+    if has_marker(body) {
+        return None;
+    }
+    body.hash(&mut hasher);
+
+    Some(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates an entry with one image of each kind.
+    fn entry(mode: OutputMode) -> Entry {
+        Entry {
+            mode,
+            html: r#"<svg data-png="/render/1.png"/>é"#.into(),
+            images: Box::new([
+                (Kind::Svg, r#"<svg width="1"/>"#.into()),
+                (Kind::MathMl, "<math><mi>é</mi></math>".into()),
+            ]),
+        }
+    }
+
+    #[test]
+    fn entry_bytes() {
+        for mode in [
+            OutputMode::Block,
+            OutputMode::Empty,
+            OutputMode::Inline,
+            OutputMode::Nowiki,
+            OutputMode::Raw,
+        ] {
+            let entry = entry(mode);
+            assert_eq!(Entry::from_bytes(&entry.to_bytes()), Some(entry));
+        }
+
+        let empty = Entry {
+            mode: OutputMode::Inline,
+            html: "".into(),
+            images: Box::new([]),
+        };
+        assert_eq!(Entry::from_bytes(&empty.to_bytes()), Some(empty));
+
+        let data = entry(OutputMode::Block).to_bytes();
+        assert_eq!(Entry::from_bytes(&[]), None);
+        assert_eq!(Entry::from_bytes(b"x"), None);
+        assert_eq!(Entry::from_bytes(&data[..5]), None);
+        assert_eq!(Entry::from_bytes(&data[..20]), None);
+        assert_eq!(Entry::from_bytes(&data[..data.len() - 1]), None);
+        let mut long = data.clone();
+        long.push(b'x');
+        assert_eq!(Entry::from_bytes(&long), None);
+        let mut bad_kind = data.clone();
+        bad_kind[9] = b'x';
+        assert_eq!(Entry::from_bytes(&bad_kind), None);
+    }
+
+    #[test]
+    fn hit_keeps_mode() {
+        let dir = std::env::temp_dir().join(format!("wiki-rs-ext-cache-{}", std::process::id()));
+        let disk = || Some(DiskCache::new(dir.clone(), 1 << 20).unwrap());
+
+        let cache = Cache::new(1 << 20, disk());
+        assert_eq!(cache.get(1), None);
+        cache.insert(1, entry(OutputMode::Inline), true);
+        cache.insert(2, entry(OutputMode::Raw), false);
+        assert_eq!(cache.get(1), Some(entry(OutputMode::Inline)));
+        assert_eq!(cache.get(2), Some(entry(OutputMode::Raw)));
+
+        // Only persisted entries are loaded by a new process
+        let cache = Cache::new(1 << 20, disk());
+        assert_eq!(cache.get(1), Some(entry(OutputMode::Inline)));
+        assert_eq!(cache.get(2), None);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn strip_markers() {
+        let marker = format!("{MARKER_PREFIX}-nowiki-00000000");
+        let hash = |attributes: &[(Option<&str>, Option<&str>)], body| {
+            hash_tag(
+                StableHasher::default(),
+                "math",
+                attributes.iter().copied(),
+                body,
+            )
+        };
+
+        let display = [(Some("display"), Some("block"))];
+        let key = hash(&display, Some("x^2"));
+        assert!(key.is_some());
+        assert_eq!(hash(&display, Some("x^2")), key);
+        assert_ne!(hash(&display, Some("x^3")), key);
+        assert_ne!(hash(&[], Some("x^2")), key);
+        assert_ne!(hash(&display, None), hash(&display, Some("")));
+
+        assert_eq!(hash(&display, Some(&marker)), None);
+        assert_eq!(hash(&[(Some("display"), Some(&marker))], None), None);
+        assert_eq!(hash(&[(Some(&marker), None)], Some("x^2")), None);
+
+        let mut config = StableHasher::default();
+        "https://example.com".hash(&mut config);
+        assert_ne!(
+            hash_tag(config, "math", display.iter().copied(), Some("x^2")),
+            key
+        );
+    }
+}
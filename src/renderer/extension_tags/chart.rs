@@ -0,0 +1,679 @@
+//! The `#chart` parser function.
+//! <https://www.mediawiki.org/wiki/Extension:Chart>
+//!
+//! The Chart extension draws a chart from a chart definition (`.chart`) page
+//! and a tabular data (`.tab`) page, both in the `Data:` namespace of the
+//! shared media repository, optionally passing the data through a Lua
+//! transform first. Rather than using a JavaScript charting library, the chart
+//! is converted into a Graph specification and drawn by the Graph extension’s
+//! renderer.
+
+use super::{StackFrame, State, cache, graph, json_config, raster};
+use crate::{
+    lua::call_json_function,
+    renderer::resolve_redirects,
+    title::{Namespace, Title},
+};
+use core::{fmt::Write as _, pin::pin};
+use serde_json::{Map, Value, json};
+use std::borrow::Cow;
+
+/// The width of the chart area, in pixels.
+const WIDTH: f64 = 500.0;
+
+/// The height of the chart area, in pixels.
+const HEIGHT: f64 = 300.0;
+
+/// A chart error.
+#[derive(Debug, thiserror::Error)]
+enum Error {
+    /// A chart or data page could not be parsed.
+    #[error("invalid JSON in {0}: {1}")]
+    Json(String, serde_json::Error),
+    /// A chart or data page could not be loaded.
+    #[error("could not load {0}: {1}")]
+    Load(String, crate::db::Error),
+    /// A page name has the wrong extension.
+    #[error("'{0}' is not a {1} page")]
+    Name(String, &'static str),
+    /// The data has no columns to plot.
+    #[error("{0} has no numeric columns to plot")]
+    NoSeries(String),
+    /// Rendering the chart failed.
+    #[error(transparent)]
+    Render(#[from] graph::Error),
+    /// The transform module failed.
+    #[error("transform {0}: {1}")]
+    Transform(String, Box<crate::renderer::Error>),
+}
+
+/// A chart definition page.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Definition {
+    /// The kind of chart.
+    #[serde(rename = "type")]
+    kind: Kind,
+    /// The line interpolation mode. If this is `"curve"`, line charts are
+    /// drawn as smooth curves.
+    #[serde(default)]
+    interpolate: Option<String>,
+    /// Categories to add to pages which use the chart.
+    #[serde(default)]
+    mediawiki_categories: Vec<Category>,
+    /// If true, line charts have symbols at each data point.
+    #[serde(default)]
+    show_symbols: bool,
+    /// The name of the tabular data page.
+    source: String,
+    /// The localised chart title.
+    #[serde(default)]
+    title: Value,
+    /// A Lua transform to apply to the data.
+    #[serde(default)]
+    transform: Option<Transform>,
+    /// The x-axis options.
+    #[serde(default)]
+    x_axis: Axis,
+    /// The y-axis options.
+    #[serde(default)]
+    y_axis: Axis,
+}
+
+/// A kind of chart.
+#[derive(Clone, Copy, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Kind {
+    /// An area chart.
+    Area,
+    /// A bar chart.
+    Bar,
+    /// A line chart.
+    Line,
+    /// A pie chart.
+    Pie,
+}
+
+/// Chart axis options.
+#[derive(Default, serde::Deserialize)]
+struct Axis {
+    /// The maximum axis value.
+    #[serde(default)]
+    max: Option<f64>,
+    /// The minimum axis value.
+    #[serde(default)]
+    min: Option<f64>,
+    /// The localised axis title.
+    #[serde(default)]
+    title: Value,
+}
+
+/// A category added by a chart.
+#[derive(serde::Deserialize)]
+struct Category {
+    /// The name of the category, without namespace.
+    name: String,
+}
+
+/// A Lua data transform.
+#[derive(serde::Deserialize)]
+struct Transform {
+    /// The name of the function to call.
+    function: String,
+    /// The name of the module, without namespace.
+    module: String,
+    /// Default arguments to the function.
+    #[serde(default)]
+    args: Map<String, Value>,
+}
+
+/// Renders a chart from the chart definition page `name` as HTML, using the
+/// tabular data page `data` instead of the one from the definition if given.
+/// `args` are passed to the chart’s transform.
+pub(crate) fn render_chart(
+    state: &mut State<'_>,
+    sp: &StackFrame<'_>,
+    name: &str,
+    data: Option<&str>,
+    args: Map<String, Value>,
+) -> Result<String, crate::renderer::Error> {
+    let mut out = String::new();
+    match chart(state, sp, name, data, args) {
+        Ok((title, svg)) => {
+            write!(out, r#"<div class="ext-chart">"#)?;
+            if !title.is_empty() {
+                write!(
+                    out,
+                    r#"<p class="ext-chart-title">{}</p>"#,
+                    html_escape::encode_text(&title)
+                )?;
+            }
+            let png = cache::register_png(state, raster::Kind::Svg, &svg);
+            let svg = raster::with_png_url(&svg, png.as_deref());
+            write!(out, "{svg}</div>")?;
+        }
+        Err(err) => {
+            log::warn!("#chart: {err}");
+            write!(
+                out,
+                r#"<div class="ext-chart-error error">{}</div>"#,
+                html_escape::encode_text(&err.to_string())
+            )?;
+        }
+    }
+    Ok(out)
+}
+
+/// Loads, transforms, and draws a chart, returning its localised title and
+/// SVG.
+fn chart(
+    state: &mut State<'_>,
+    sp: &StackFrame<'_>,
+    name: &str,
+    data: Option<&str>,
+    args: Map<String, Value>,
+) -> Result<(String, String), Error> {
+    let definition = load(state, name, ".chart")?;
+    let definition = serde_json::from_value::<Definition>(definition)
+        .map_err(|err| Error::Json(name.to_owned(), err))?;
+
+    for category in &definition.mediawiki_categories {
+        let title = Title::new(&format!("Category:{}", category.name), None);
+        state.globals.categories.insert(title.key().to_owned());
+    }
+
+    let source = data.unwrap_or(definition.source.as_str());
+    let mut data = json_config::localize(load(state, source, ".tab")?);
+
+    if let Some(transform) = &definition.transform {
+        let mut transform_args = transform.args.clone();
+        transform_args.extend(args);
+        data = run_transform(state, sp, transform, &data, transform_args)?;
+    }
+
+    let spec = spec(&definition, &data).ok_or_else(|| Error::NoSeries(source.to_owned()))?;
+    let svg = graph::spec_to_svg(&spec.to_string(), state.statics.base_time, state.statics)?;
+    Ok((json_config::text(&definition.title).into_owned(), svg))
+}
+
+/// Loads a JSON page with the given extension from the `Data:` namespace of
+/// the shared media repository.
+fn load(state: &State<'_>, name: &str, extension: &'static str) -> Result<Value, Error> {
+    let name = name.trim();
+    let name = name.strip_prefix("Data:").unwrap_or(name);
+    if !name.ends_with(extension) {
+        return Err(Error::Name(name.to_owned(), extension));
+    }
+
+    let title = Title::new(&format!("Data:{name}"), None);
+    let article = state
+        .statics
+        .shared_db()
+        .get(&title)
+        .map_err(|err| Error::Load(title.key().to_owned(), err))?;
+    serde_json::from_str(&article.body).map_err(|err| Error::Json(title.key().to_owned(), err))
+}
+
+/// Runs the Lua transform of a chart over its data.
+fn run_transform(
+    state: &mut State<'_>,
+    sp: &StackFrame<'_>,
+    transform: &Transform,
+    data: &Value,
+    args: Map<String, Value>,
+) -> Result<Value, Error> {
+    let name = format!("{}.{}", transform.module, transform.function);
+    let err = |err: crate::renderer::Error| Error::Transform(name.clone(), Box::new(err));
+
+    let callee = Title::new(&transform.module, Namespace::find_by_id(Namespace::MODULE));
+    let code = state
+        .statics
+        .shared_db()
+        .get(&callee)
+        .map_err(crate::renderer::Error::from)
+        .and_then(|code| resolve_redirects(state.statics.shared_db(), code))
+        .map_err(err)?;
+
+    let sp = sp.chain(callee, sp.source.clone(), &[]).map_err(err)?;
+    let args = json!([data, args]).to_string();
+    let result = call_json_function(state, pin!(&sp), &code, &transform.function, &args)
+        .map_err(|error| err(error.into()))?;
+
+    serde_json::from_str(&result)
+        .map(json_config::localize)
+        .map_err(|error| Error::Json(name.clone(), error))
+}
+
+/// A column of tabular data.
+struct Field<'a> {
+    /// The name of the column.
+    name: &'a str,
+    /// The localised title of the column.
+    title: Cow<'a, str>,
+    /// The type of the column.
+    kind: &'a str,
+}
+
+/// Converts a chart definition and its localised tabular data into a Graph
+/// specification. Returns `None` if there is nothing to plot.
+///
+/// The first column of the data is the x-axis (or the slice labels of a pie
+/// chart), and every other numeric column is a series.
+fn spec(definition: &Definition, data: &Value) -> Option<Value> {
+    let fields = data
+        .pointer("/schema/fields")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|field| {
+            let get = |key: &str| field.get(key).and_then(Value::as_str).unwrap_or_default();
+            let name = get("name");
+            let title = json_config::text(&field["title"]);
+            Field {
+                name,
+                title: if title.is_empty() {
+                    Cow::Borrowed(name)
+                } else {
+                    title
+                },
+                kind: get("type"),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let (x, series) = fields.split_first()?;
+    let series = series
+        .iter()
+        .filter(|field| field.kind == "number")
+        .collect::<Vec<_>>();
+    if series.is_empty() {
+        return None;
+    }
+
+    // The data is reshaped into one row per point, which is the shape that
+    // both faceting and pie layout want
+    let rows = json_config::rows(data);
+    let values = rows
+        .iter()
+        .flat_map(|row| {
+            let series = if definition.kind == Kind::Pie {
+                &series[..1]
+            } else {
+                &series[..]
+            };
+            series.iter().filter_map(|field| {
+                let value = row.get(field.name).filter(|value| !value.is_null())?;
+                let x = row.get(x.name).unwrap_or(&Value::Null);
+                let label = if definition.kind == Kind::Pie {
+                    json_config::text(x)
+                } else {
+                    Cow::Borrowed(&*field.title)
+                };
+                Some(json!({ "x": x, "series": label, "value": value }))
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut spec = json!({
+        "version": 2,
+        "width": WIDTH,
+        "height": HEIGHT,
+        "data": [{ "name": "table", "values": values }],
+    });
+
+    if definition.kind == Kind::Pie {
+        pie(&mut spec);
+    } else {
+        xy(&mut spec, definition, x, &series);
+    }
+
+    Some(spec)
+}
+
+/// Adds the scales, axes, legend, and marks of a line, area, or bar chart to
+/// `spec`.
+fn xy(spec: &mut Value, definition: &Definition, x: &Field<'_>, series: &[&Field<'_>]) {
+    let x_scale = if definition.kind != Kind::Bar && x.kind == "number" {
+        json!({ "type": "linear", "zero": false })
+    } else if definition.kind == Kind::Bar {
+        json!({ "type": "ordinal", "padding": 0.2 })
+    } else {
+        json!({ "type": "ordinal", "points": true })
+    };
+    let mut x_scale = merge(
+        x_scale,
+        json!({ "name": "x", "range": "width", "domain": { "data": "table", "field": "x" } }),
+    );
+    if let Some(min) = definition.x_axis.min {
+        x_scale["domainMin"] = min.into();
+    }
+    if let Some(max) = definition.x_axis.max {
+        x_scale["domainMax"] = max.into();
+    }
+
+    let mut y_scale = json!({
+        "name": "y",
+        "type": "linear",
+        "range": "height",
+        "nice": true,
+        "domain": { "data": "table", "field": "value" },
+    });
+    if let Some(min) = definition.y_axis.min {
+        y_scale["domainMin"] = min.into();
+    }
+    if let Some(max) = definition.y_axis.max {
+        y_scale["domainMax"] = max.into();
+    }
+
+    let colors = series.iter().map(|field| &*field.title).collect::<Vec<_>>();
+    spec["scales"] = json!([
+        x_scale,
+        y_scale,
+        { "name": "color", "type": "ordinal", "range": "category10", "domain": colors },
+    ]);
+
+    spec["axes"] = json!([
+        { "type": "x", "scale": "x", "title": json_config::text(&definition.x_axis.title) },
+        {
+            "type": "y",
+            "scale": "y",
+            "grid": true,
+            "title": json_config::text(&definition.y_axis.title),
+        },
+    ]);
+
+    spec["marks"] = if definition.kind == Kind::Bar {
+        json!([bars(series.len() > 1)])
+    } else {
+        lines(definition).into()
+    };
+
+    if series.len() > 1 {
+        let legend = if definition.kind == Kind::Line {
+            json!({ "stroke": "color" })
+        } else {
+            json!({ "fill": "color" })
+        };
+        spec["legends"] = json!([legend]);
+    }
+}
+
+/// Creates the marks for a line or area chart, with one line or area for each
+/// series.
+fn lines(definition: &Definition) -> Vec<Value> {
+    let enter = if definition.kind == Kind::Area {
+        json!({
+            "y2": { "scale": "y", "value": 0 },
+            "fill": { "scale": "color", "field": "series" },
+            "fillOpacity": { "value": 0.5 },
+        })
+    } else {
+        json!({
+            "stroke": { "scale": "color", "field": "series" },
+            "strokeWidth": { "value": 2 },
+        })
+    };
+    let enter = merge(
+        json!({
+            "x": { "scale": "x", "field": "x" },
+            "y": { "scale": "y", "field": "value" },
+            "interpolate": { "value": interpolate(definition) },
+        }),
+        enter,
+    );
+
+    let mut marks = vec![json!({
+        "type": "group",
+        "from": { "data": "table", "transform": [{ "type": "facet", "groupby": ["series"] }] },
+        "marks": [{
+            "type": if definition.kind == Kind::Area { "area" } else { "line" },
+            "properties": { "enter": enter },
+        }],
+    })];
+
+    if definition.show_symbols {
+        marks.push(json!({
+            "type": "symbol",
+            "from": { "data": "table" },
+            "properties": { "enter": {
+                "x": { "scale": "x", "field": "x" },
+                "y": { "scale": "y", "field": "value" },
+                "fill": { "scale": "color", "field": "series" },
+                "size": { "value": 30 },
+            } },
+        }));
+    }
+
+    marks
+}
+
+/// Creates the marks for a bar chart, with bars for each series side by side
+/// if `grouped` is true.
+fn bars(grouped: bool) -> Value {
+    let bar = |x, width| {
+        json!({
+            "type": "rect",
+            "properties": { "enter": {
+                "x": x,
+                "width": width,
+                "y": { "scale": "y", "field": "value" },
+                "y2": { "scale": "y", "value": 0 },
+                "fill": { "scale": "color", "field": "series" },
+            } },
+        })
+    };
+
+    if grouped {
+        json!({
+            "type": "group",
+            "from": { "data": "table", "transform": [{ "type": "facet", "groupby": ["x"] }] },
+            "properties": { "enter": {
+                "x": { "scale": "x", "field": "x" },
+                "width": { "scale": "x", "band": true },
+            } },
+            "scales": [{
+                "name": "series",
+                "type": "ordinal",
+                "range": "width",
+                "domain": { "field": "series" },
+            }],
+            "marks": [bar(
+                json!({ "scale": "series", "field": "series" }),
+                json!({ "scale": "series", "band": true }),
+            )],
+        })
+    } else {
+        merge(
+            bar(
+                json!({ "scale": "x", "field": "x" }),
+                json!({ "scale": "x", "band": true }),
+            ),
+            json!({ "from": { "data": "table" } }),
+        )
+    }
+}
+
+/// Adds the layout, scales, legend, and marks of a pie chart to `spec`.
+fn pie(spec: &mut Value) {
+    let radius = WIDTH.min(HEIGHT) / 2.0;
+    spec["data"][0]["transform"] = json!([{ "type": "pie", "field": "value" }]);
+    spec["scales"] = json!([{
+        "name": "color",
+        "type": "ordinal",
+        "range": "category10",
+        "domain": { "data": "table", "field": "series" },
+    }]);
+    spec["legends"] = json!([{ "fill": "color" }]);
+    spec["marks"] = json!([{
+        "type": "arc",
+        "from": { "data": "table" },
+        "properties": { "enter": {
+            "x": { "field": { "group": "width" }, "mult": 0.5 },
+            "y": { "field": { "group": "height" }, "mult": 0.5 },
+            "startAngle": { "field": "layout_start" },
+            "endAngle": { "field": "layout_end" },
+            "innerRadius": { "value": 0 },
+            "outerRadius": { "value": radius },
+            "stroke": { "value": "#fff" },
+            "fill": { "scale": "color", "field": "series" },
+        } },
+    }]);
+}
+
+/// Gets the line interpolation mode for a chart.
+fn interpolate(definition: &Definition) -> &'static str {
+    if definition.interpolate.as_deref() == Some("curve") {
+        "monotone"
+    } else {
+        "linear"
+    }
+}
+
+/// Merges the properties of the object `extra` into the object `value`.
+fn merge(mut value: Value, extra: Value) -> Value {
+    if let (Value::Object(value), Value::Object(extra)) = (&mut value, extra) {
+        value.extend(extra);
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses a chart definition.
+    fn definition(definition: Value) -> Definition {
+        serde_json::from_value(definition).unwrap()
+    }
+
+    /// Creates localised tabular data with a numeric x column, two series,
+    /// and a string column.
+    fn table() -> Value {
+        json!({
+            "schema": { "fields": [
+                { "name": "year", "type": "number" },
+                { "name": "apples", "type": "number", "title": "Apples" },
+                { "name": "note", "type": "string" },
+                { "name": "pears", "type": "number" },
+            ] },
+            "data": [[2020, 1, "a", 4], [2021, null, "b", 5]],
+        })
+    }
+
+    #[test]
+    fn line() {
+        let definition = definition(json!({ "type": "line", "source": "Fruit.tab" }));
+        let spec = spec(&definition, &table()).unwrap();
+
+        assert_eq!(spec["version"], 2);
+        assert_eq!(
+            spec["data"][0]["values"],
+            json!([
+                { "x": 2020, "series": "Apples", "value": 1 },
+                { "x": 2020, "series": "pears", "value": 4 },
+                { "x": 2021, "series": "pears", "value": 5 },
+            ])
+        );
+        assert_eq!(
+            spec["scales"][0],
+            json!({
+                "name": "x",
+                "type": "linear",
+                "zero": false,
+                "range": "width",
+                "domain": { "data": "table", "field": "x" },
+            })
+        );
+        assert_eq!(spec["scales"][2]["domain"], json!(["Apples", "pears"]));
+        assert_eq!(spec["legends"], json!([{ "stroke": "color" }]));
+        assert_eq!(spec["marks"][0]["marks"][0]["type"], "line");
+        assert_eq!(spec["marks"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn area_with_symbols() {
+        let definition = definition(json!({
+            "type": "area",
+            "source": "Fruit.tab",
+            "interpolate": "curve",
+            "showSymbols": true,
+        }));
+        let spec = spec(&definition, &table()).unwrap();
+
+        let area = &spec["marks"][0]["marks"][0];
+        assert_eq!(area["type"], "area");
+        assert_eq!(
+            area["properties"]["enter"]["interpolate"]["value"],
+            "monotone"
+        );
+        assert_eq!(spec["marks"][1]["type"], "symbol");
+        assert_eq!(spec["legends"], json!([{ "fill": "color" }]));
+    }
+
+    #[test]
+    fn bar_axes() {
+        let definition = definition(json!({
+            "type": "bar",
+            "source": "Fruit.tab",
+            "xAxis": { "min": 2000, "max": 2030, "title": { "en": "Year" } },
+            "yAxis": { "min": 0, "title": "Count" },
+        }));
+        let spec = spec(&definition, &table()).unwrap();
+
+        let x_scale = &spec["scales"][0];
+        assert_eq!(x_scale["type"], "ordinal");
+        assert_eq!(x_scale["padding"], 0.2);
+        assert_eq!(x_scale["domainMin"], 2000.0);
+        assert_eq!(x_scale["domainMax"], 2030.0);
+        assert_eq!(spec["scales"][1]["domainMin"], 0.0);
+        assert_eq!(spec["axes"][0]["title"], "Year");
+        assert_eq!(spec["axes"][1]["title"], "Count");
+
+        let bars = &spec["marks"][0];
+        assert_eq!(bars["type"], "group");
+        assert_eq!(
+            bars["from"]["transform"],
+            json!([{ "type": "facet", "groupby": ["x"] }])
+        );
+        assert_eq!(bars["marks"][0]["type"], "rect");
+    }
+
+    #[test]
+    fn single_bar() {
+        let definition = definition(json!({ "type": "bar", "source": "Fruit.tab" }));
+        let mut data = table();
+        data["schema"]["fields"][3]["type"] = "string".into();
+        let spec = spec(&definition, &data).unwrap();
+
+        assert_eq!(spec["marks"][0]["type"], "rect");
+        assert_eq!(spec["marks"][0]["from"], json!({ "data": "table" }));
+        assert!(spec.get("legends").is_none());
+    }
+
+    #[test]
+    fn pie() {
+        let definition = definition(json!({ "type": "pie", "source": "Fruit.tab" }));
+        let spec = spec(&definition, &table()).unwrap();
+
+        assert_eq!(
+            spec["data"][0],
+            json!({
+                "name": "table",
+                "values": [{ "x": 2020, "series": "2020", "value": 1 }],
+                "transform": [{ "type": "pie", "field": "value" }],
+            })
+        );
+        assert_eq!(spec["marks"][0]["type"], "arc");
+        assert_eq!(spec["legends"], json!([{ "fill": "color" }]));
+    }
+
+    #[test]
+    fn no_series() {
+        let definition = definition(json!({ "type": "line", "source": "Fruit.tab" }));
+        let mut data = table();
+        data["schema"]["fields"][1]["type"] = "string".into();
+        data["schema"]["fields"][3]["type"] = "string".into();
+        assert!(spec(&definition, &data).is_none());
+        assert!(spec(&definition, &json!({})).is_none());
+    }
+}
@@ -0,0 +1,955 @@
+//! The `<ref>` and `<references>` extension tags.
+//! <https://www.mediawiki.org/wiki/Special:MyLanguage/Extension:Cite>
+//!
+//! ## Ordering
+//!
+//! References from the original Wikitext are collected during template
+//! expansion, but `<references>` lists are not rendered until the final
+//! document is rendered, since scripts can emit `<ref>` tags which are only
+//! processed at that time (see the parent module for details).
+//!
+//! To make sure each list only gets the references which come before it (and
+//! after the previous list of the same group), every group is split into
+//! sections, one for each list. The references collected during template
+//! expansion and the references collected during rendering each go into the
+//! section of the next list which has not yet been seen in that phase.
+
+use super::{ExtensionTag, OutputMode, Result, State, eval_string, render_raw};
+use crate::common::{anchor_encode, format_message};
+use core::{convert::Infallible, fmt::Write as _};
+use numerals::roman::Roman;
+use std::{borrow::Cow, collections::HashMap};
+
+/// The attributes which are valid for `<ref>`.
+const REF_ATTRIBUTES: &[&str] = &["details", "dir", "follow", "group", "name"];
+
+/// The attributes which are valid for `<references>`.
+const REFERENCES_ATTRIBUTES: &[&str] = &["group", "responsive"];
+
+/// The number of references above which a responsive reference list is split
+/// into columns.
+const RESPONSIVE_THRESHOLD: usize = 10;
+
+/// A single reference.
+#[derive(Debug, Default)]
+struct Reference {
+    /// The text direction of the reference.
+    dir: Option<&'static str>,
+    /// Rendered errors to show in the reference list.
+    errors: Vec<String>,
+    /// The page-unique key of the reference.
+    key: usize,
+    /// If true, the reference was defined inside of `<references>`.
+    list_defined: bool,
+    /// The name of the reference.
+    name: Option<String>,
+    /// The number of the reference in its list, assigned the first time it is
+    /// used. References which are never used have no number.
+    number: Option<usize>,
+    /// Sub-references which add details to this reference.
+    subreferences: Vec<SubReference>,
+    /// The rendered content of the reference, if it was ever defined.
+    text: Option<String>,
+    /// The number of times the reference is used.
+    uses: usize,
+}
+
+impl Reference {
+    /// Returns the ID of the reference list item for this reference.
+    fn note_id(&self) -> String {
+        if let Some(name) = &self.name {
+            anchor_encode(&format!("cite_note-{name}-{}", self.key))
+        } else {
+            format!("cite_note-{}", self.key)
+        }
+    }
+
+    /// Returns the ID of the marker for the given use of this reference.
+    fn ref_id(&self, index: usize) -> String {
+        if let Some(name) = &self.name {
+            anchor_encode(&format!("cite_ref-{name}_{}-{index}", self.key))
+        } else {
+            format!("cite_ref-{}", self.key)
+        }
+    }
+
+    /// Adds content to a reference which may already have content, recording
+    /// an error if the two are different.
+    fn define(&mut self, text: String) {
+        match &self.text {
+            None => self.text = Some(text),
+            Some(old) if *old != text => {
+                let name = html_escape::encode_text(self.name.as_deref().unwrap_or_default());
+                let error = cite_error("cite_error_references_duplicate_key", &[&name]);
+                self.errors.push(error);
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+/// A sub-reference, which adds details (like a page number) to a main
+/// reference.
+#[derive(Debug)]
+struct SubReference {
+    /// The rendered details.
+    details: String,
+    /// The page-unique key of the sub-reference.
+    key: usize,
+    /// The number of times the sub-reference is used.
+    uses: usize,
+}
+
+impl SubReference {
+    /// Returns the ID of the reference list item for this sub-reference.
+    fn note_id(&self) -> String {
+        format!("cite_note-{}", self.key)
+    }
+
+    /// Returns the ID of the marker for the given use of this sub-reference.
+    fn ref_id(&self, index: usize) -> String {
+        format!("cite_ref-{}-{index}", self.key)
+    }
+}
+
+/// The references of a group which belong to a single reference list.
+#[derive(Debug, Default)]
+struct Section {
+    /// Rendered errors to show after the reference list.
+    errors: Vec<String>,
+    /// The last number assigned to a reference.
+    last_number: usize,
+    /// Named references. Value is an index into `references`.
+    named: HashMap<String, usize>,
+    /// The references, in definition order.
+    references: Vec<Reference>,
+}
+
+/// A reference group.
+#[derive(Debug, Default)]
+struct Group {
+    /// The number of reference lists for this group which were seen during
+    /// template expansion.
+    expanded: usize,
+    /// The number of reference lists for this group which were rendered.
+    rendered: usize,
+    /// The sections of the group, one for each reference list.
+    sections: Vec<Section>,
+}
+
+/// The location of a reference marker.
+struct Marker {
+    /// The visible label of the marker.
+    label: String,
+    /// The ID of the reference list item.
+    note_id: String,
+    /// The ID of the marker itself.
+    ref_id: String,
+}
+
+/// Stored citation references.
+#[derive(Debug, Default)]
+pub(crate) struct References {
+    /// The group of the `<references>` tag whose content is currently being
+    /// evaluated, if any.
+    context: Option<String>,
+    /// References by group name. For compatibility, the default group is an
+    /// empty string.
+    groups: HashMap<String, Group>,
+    /// The last used page-unique key.
+    last_key: usize,
+}
+
+impl References {
+    /// Renders all the references which were not rendered by some
+    /// `<references>` list. The default group is rendered as if there was a
+    /// `<references />` at the end of the article, and every other group gets
+    /// an error.
+    pub fn finish(mut self, out: &mut String) -> Result<()> {
+        let mut names = self.groups.keys().cloned().collect::<Vec<_>>();
+        names.sort_unstable();
+        for name in names {
+            let group = self.groups.get_mut(&name).unwrap();
+            let rendered = group.rendered.min(group.sections.len());
+            for section in group.sections.drain(rendered..) {
+                if name.is_empty() {
+                    write_list(out, &name, section, true)?;
+                } else if !section.references.is_empty() {
+                    let name = html_escape::encode_text(&name);
+                    let error = cite_error("cite_error_group_refs_without_references", &[&name]);
+                    write!(out, "<p>{error}</p>")?;
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the section of `group` which the next reference in the current
+    /// phase belongs to.
+    fn section<'a>(
+        groups: &'a mut HashMap<String, Group>,
+        group: &str,
+        in_document: bool,
+    ) -> &'a mut Section {
+        let group = groups.entry(group.to_owned()).or_default();
+        let index = if in_document {
+            group.rendered
+        } else {
+            group.expanded
+        };
+        if group.sections.len() <= index {
+            group.sections.resize_with(index + 1, <_>::default);
+        }
+        &mut group.sections[index]
+    }
+
+    /// Finds or creates a reference in the current section of `group`,
+    /// returning its index.
+    fn reference(&mut self, group: &str, in_document: bool, name: Option<&str>) -> usize {
+        let section = Self::section(&mut self.groups, group, in_document);
+        if let Some(index) = name.and_then(|name| section.named.get(name)) {
+            return *index;
+        }
+
+        self.last_key += 1;
+        let index = section.references.len();
+        section.references.push(Reference {
+            key: self.last_key,
+            name: name.map(ToOwned::to_owned),
+            ..<_>::default()
+        });
+        if let Some(name) = name {
+            section.named.insert(name.to_owned(), index);
+        }
+        index
+    }
+
+    /// Records a use of a reference, returning its marker.
+    fn use_reference(
+        &mut self,
+        group: &str,
+        in_document: bool,
+        name: Option<&str>,
+        text: Option<String>,
+        dir: Option<&'static str>,
+    ) -> Marker {
+        let index = self.reference(group, in_document, name);
+        let section = Self::section(&mut self.groups, group, in_document);
+        let reference = &mut section.references[index];
+        if let Some(text) = text {
+            reference.define(text);
+        }
+        if dir.is_some() {
+            reference.dir = dir;
+        }
+
+        let number = *reference.number.get_or_insert_with(|| {
+            section.last_number += 1;
+            section.last_number
+        });
+        reference.uses += 1;
+
+        Marker {
+            label: make_label(group, number),
+            note_id: reference.note_id(),
+            ref_id: reference.ref_id(reference.uses - 1),
+        }
+    }
+
+    /// Records a use of a sub-reference, returning its marker.
+    fn use_subreference(
+        &mut self,
+        group: &str,
+        in_document: bool,
+        name: Option<&str>,
+        text: Option<String>,
+        details: String,
+    ) -> Marker {
+        let index = self.reference(group, in_document, name);
+        let section = Self::section(&mut self.groups, group, in_document);
+        let reference = &mut section.references[index];
+        if let Some(text) = text {
+            reference.define(text);
+        }
+
+        let number = *reference.number.get_or_insert_with(|| {
+            section.last_number += 1;
+            section.last_number
+        });
+
+        let subindex = if let Some(subindex) = reference
+            .subreferences
+            .iter()
+            .position(|sub| sub.details == details)
+        {
+            subindex
+        } else {
+            self.last_key += 1;
+            reference.subreferences.push(SubReference {
+                details,
+                key: self.last_key,
+                uses: 0,
+            });
+            reference.subreferences.len() - 1
+        };
+
+        let subreference = &mut reference.subreferences[subindex];
+        subreference.uses += 1;
+
+        Marker {
+            label: format!("{}.{}", make_label(group, number), subindex + 1),
+            note_id: subreference.note_id(),
+            ref_id: subreference.ref_id(subreference.uses - 1),
+        }
+    }
+
+    /// Appends text to the reference with the given name. If there is no such
+    /// reference, the text is added to the list as an unnumbered reference.
+    fn follow(&mut self, group: &str, in_document: bool, name: &str, text: &str) {
+        let section = Self::section(&mut self.groups, group, in_document);
+        if let Some(index) = section.named.get(name) {
+            let reference = &mut section.references[*index];
+            if let Some(old) = &mut reference.text
+                && !old.is_empty()
+            {
+                old.push(' ');
+                *old += text;
+            } else {
+                reference.text = Some(text.to_owned());
+            }
+        } else {
+            self.last_key += 1;
+            section.references.push(Reference {
+                key: self.last_key,
+                text: Some(text.to_owned()),
+                ..<_>::default()
+            });
+        }
+    }
+
+    /// Defines a reference from inside of `<references>`.
+    fn define_in_list(&mut self, group: &str, in_document: bool, name: &str, text: String) {
+        let index = self.reference(group, in_document, Some(name));
+        let section = Self::section(&mut self.groups, group, in_document);
+        let reference = &mut section.references[index];
+        reference.list_defined = true;
+        reference.define(text);
+    }
+
+    /// Records an error which should be shown after the current list of
+    /// `group`.
+    fn push_error(&mut self, group: &str, in_document: bool, error: String) {
+        Self::section(&mut self.groups, group, in_document)
+            .errors
+            .push(error);
+    }
+
+    /// Removes and returns the next unrendered section of `group`.
+    fn take_section(&mut self, group: &str) -> Section {
+        let group = self.groups.entry(group.to_owned()).or_default();
+        let section = group
+            .sections
+            .get_mut(group.rendered)
+            .map(core::mem::take)
+            .unwrap_or_default();
+        group.rendered += 1;
+        section
+    }
+}
+
+/// The `<ref>` extension tag.
+pub(super) fn r#ref(
+    out: &mut String,
+    state: &mut State<'_>,
+    arguments: &ExtensionTag<'_, '_, '_>,
+) -> Result {
+    if has_invalid_attributes(state, arguments, REF_ATTRIBUTES)? {
+        return write_error(out, "cite_error_ref_too_many_keys", &[]);
+    }
+
+    let group = get_trimmed(state, arguments, "group")?.unwrap_or_default();
+    let name = get_trimmed(state, arguments, "name")?;
+    let follow = get_trimmed(state, arguments, "follow")?;
+    let details = get_trimmed(state, arguments, "details")?;
+    let dir = match get_trimmed(state, arguments, "dir")?
+        .map(|dir| dir.to_ascii_lowercase())
+        .as_deref()
+    {
+        None => None,
+        Some("ltr") => Some("ltr"),
+        Some("rtl") => Some("rtl"),
+        Some(dir) => {
+            let dir = html_escape::encode_text(dir);
+            return write_error(out, "cite_error_ref_invalid_dir", &[&dir]);
+        }
+    };
+
+    // Due to transclusion it is necessary to render immediately instead of
+    // storing the node list for later, since rendering later would require
+    // retaining the stack frame too
+    let text = match arguments.body.map(str::trim_ascii) {
+        Some(body) if !body.is_empty() => Some(eval_string(state, arguments.sp, body)?),
+        _ => None,
+    };
+
+    if let Some(list_group) = state.globals.references.context.clone() {
+        return define_in_list(state, arguments, &list_group, &group, name, text);
+    }
+
+    if name
+        .as_deref()
+        .is_some_and(|name| name.bytes().all(|b| b.is_ascii_digit()))
+    {
+        return write_error(out, "cite_error_ref_numeric_key", &[]);
+    }
+
+    if let Some(follow) = follow {
+        if name.is_some() {
+            return write_error(out, "cite_error_ref_follow_conflicts", &[]);
+        }
+        if let Some(text) = text {
+            let in_document = arguments.in_document;
+            state
+                .globals
+                .references
+                .follow(&group, in_document, &follow, &text);
+        }
+        return Ok(OutputMode::Empty);
+    }
+
+    if name.is_none() && text.is_none() {
+        let key = if arguments.body.is_some() {
+            "cite_error_ref_no_input"
+        } else {
+            "cite_error_ref_no_key"
+        };
+        return write_error(out, key, &[]);
+    }
+
+    let in_document = arguments.in_document;
+    let marker = if let Some(details) = details {
+        let details = eval_string(state, arguments.sp, &details)?;
+        state.globals.references.use_subreference(
+            &group,
+            in_document,
+            name.as_deref(),
+            text,
+            details,
+        )
+    } else {
+        state
+            .globals
+            .references
+            .use_reference(&group, in_document, name.as_deref(), text, dir)
+    };
+
+    let Marker {
+        label,
+        note_id,
+        ref_id,
+    } = marker;
+    write!(
+        out,
+        r##"<span class="reference" id="{ref_id}"><a href="#{note_id}">{label}</a></span>"##
+    )?;
+    Ok(OutputMode::Inline)
+}
+
+/// Handles a `<ref>` which is inside the content of a `<references>` tag.
+fn define_in_list(
+    state: &mut State<'_>,
+    arguments: &ExtensionTag<'_, '_, '_>,
+    list_group: &str,
+    group: &str,
+    name: Option<String>,
+    text: Option<String>,
+) -> Result {
+    let in_document = arguments.in_document;
+    let references = &mut state.globals.references;
+
+    // The group of the list is used even if the group of the reference is
+    // wrong, since the error needs to go somewhere
+    let error = if !group.is_empty() && group != list_group {
+        let group = html_escape::encode_text(group);
+        Some(cite_error(
+            "cite_error_references_group_mismatch",
+            &[&group],
+        ))
+    } else if let Some(name) = &name {
+        if let Some(text) = text {
+            references.define_in_list(list_group, in_document, name, text);
+            None
+        } else {
+            let name = html_escape::encode_text(name);
+            Some(cite_error("cite_error_empty_references_define", &[&name]))
+        }
+    } else {
+        Some(cite_error("cite_error_references_no_key", &[]))
+    };
+
+    if let Some(error) = error {
+        references.push_error(list_group, in_document, error);
+    }
+
+    Ok(OutputMode::Empty)
+}
+
+/// The `<references>` extension tag.
+pub(super) fn references(
+    out: &mut String,
+    state: &mut State<'_>,
+    arguments: &ExtensionTag<'_, '_, '_>,
+) -> Result {
+    let group = get_trimmed(state, arguments, "group")?.unwrap_or_default();
+
+    // Here, someone vibed the idea that the references tag -- which is supposed
+    // to be an output -- should also accept content, which we must now evaluate
+    // purely for the side effect that someone shoved a `<ref>` in there. This
+    // is codified behaviour by 'Template:Reflist' having a `refs` property, and
+    // is used on the page 'Donkey' (probably among thousands of others).
+    // The Cite extension actually maintains a stack, which feels concerning to
+    // me, since it means someone thought about what happens when you have refs
+    // inside refs, and that sounds like a cursed thing to have to think about.
+    if arguments.body.is_some() {
+        let context = state.globals.references.context.replace(group.clone());
+        let result = arguments.eval_body(state);
+        state.globals.references.context = context;
+        result?;
+    }
+
+    // If some template expansion emits a `<ref>` tag, the content of that
+    // reference will be lost if `<references>` is also processed during
+    // template expansion. Because wiki.rs converts `#tag` calls from scripts
+    // into XML tags to be processed during the final pass, scripts that call
+    // that also will break if `<references>` processing is not deferred.
+    if !arguments.in_document {
+        state
+            .globals
+            .references
+            .groups
+            .entry(group)
+            .or_default()
+            .expanded += 1;
+        return render_raw(
+            state,
+            arguments.sp,
+            arguments.callee,
+            // The body was processed, only the output remains.
+            None,
+            // buffalo buffalo buffalo 😵‍💫
+            &arguments.arguments.arguments,
+            out,
+        );
+    }
+
+    if has_invalid_attributes(state, arguments, REFERENCES_ATTRIBUTES)? {
+        write_error(out, "cite_error_references_invalid_parameters", &[])?;
+    }
+
+    let responsive = arguments
+        .get(state, "responsive")?
+        .is_none_or(|value| value.trim_ascii() != "0");
+    let section = state.globals.references.take_section(&group);
+    write_list(out, &group, section, responsive)
+}
+
+/// Writes a reference list for a section of a reference group.
+fn write_list(out: &mut String, group: &str, mut section: Section, responsive: bool) -> Result {
+    // Unused references have no number, so go at the end, in the order that
+    // they were defined
+    section
+        .references
+        .sort_by_key(|reference| reference.number.unwrap_or(usize::MAX));
+
+    let mut items = String::new();
+    let mut count = 0;
+    for reference in &section.references {
+        if reference.list_defined && reference.number.is_none() {
+            let name = html_escape::encode_text(reference.name.as_deref().unwrap_or_default());
+            let error = cite_error("cite_error_references_missing_key", &[&name]);
+            section.errors.push(error);
+            continue;
+        }
+
+        count += 1;
+        write_item(&mut items, group, reference)?;
+    }
+
+    if items.is_empty() && section.errors.is_empty() {
+        return Ok(OutputMode::Empty);
+    }
+
+    if !items.is_empty() {
+        let columns = if responsive && count > RESPONSIVE_THRESHOLD {
+            " mw-references-columns"
+        } else {
+            ""
+        };
+        write!(
+            out,
+            r#"<div class="mw-references-wrap{columns}"><ol class="references">{items}</ol></div>"#
+        )?;
+    }
+
+    for error in section.errors {
+        write!(out, "<p>{error}</p>")?;
+    }
+
+    Ok(OutputMode::Block)
+}
+
+/// Writes a single reference list item.
+fn write_item(out: &mut String, group: &str, reference: &Reference) -> Result<()> {
+    let note_id = reference.note_id();
+    let dir = reference
+        .dir
+        .map(|dir| format!(" mw-cite-dir-{dir}"))
+        .unwrap_or_default();
+    if let Some(number) = reference.number {
+        write!(
+            out,
+            r#"<li value="{number}" id="{note_id}" class="mw-cite-backlink{dir}">"#
+        )?;
+    } else {
+        write!(out, r#"<li id="{note_id}" class="mw-cite-backlink{dir}">"#)?;
+    }
+
+    write_backlinks(out, reference.uses, |index| reference.ref_id(index))?;
+
+    if let Some(text) = &reference.text {
+        write!(out, " {text}")?;
+    } else {
+        let name = html_escape::encode_text(reference.name.as_deref().unwrap_or_default());
+        let error = cite_error("cite_error_references_no_text", &[&name]);
+        write!(out, " {error}")?;
+    }
+
+    for error in &reference.errors {
+        write!(out, " {error}")?;
+    }
+
+    if !reference.subreferences.is_empty() {
+        write!(out, r#"<ol class="mw-subreference-list">"#)?;
+        for (index, subreference) in reference.subreferences.iter().enumerate() {
+            let label = format!(
+                "{}.{}",
+                make_label(group, reference.number.unwrap_or_default()),
+                index + 1
+            );
+            write!(
+                out,
+                r#"<li id="{}" class="mw-cite-backlink" data-mw-footnote-number="{label}">"#,
+                subreference.note_id()
+            )?;
+            write_backlinks(out, subreference.uses, |index| subreference.ref_id(index))?;
+            write!(out, " {}</li>", subreference.details)?;
+        }
+        write!(out, "</ol>")?;
+    }
+
+    write!(out, "</li>")?;
+    Ok(())
+}
+
+/// Writes the backlinks from a reference list item to the markers which use
+/// it. A reference which is used more than once gets one lettered link for
+/// each use.
+fn write_backlinks(out: &mut String, uses: usize, ref_id: impl Fn(usize) -> String) -> Result<()> {
+    match uses {
+        0 => {}
+        1 => write!(out, r##"<a href="#{}">^</a>"##, ref_id(0))?,
+        _ => {
+            let labels = message("cite_references_link_many_format_backlink_labels", &[]);
+            let mut labels = labels.split_ascii_whitespace();
+            write!(out, "<span>^")?;
+            for index in 0..uses {
+                if let Some(label) = labels.next() {
+                    write!(
+                        out,
+                        r##" <a href="#{}"><sup><i><b>{label}</b></i></sup></a>"##,
+                        ref_id(index)
+                    )?;
+                } else {
+                    let error = cite_error("cite_error_references_no_backlink_label", &[]);
+                    write!(out, " {error}")?;
+                    break;
+                }
+            }
+            write!(out, "</span>")?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if any of the attributes of an extension tag are not in
+/// `allowed`.
+fn has_invalid_attributes(
+    state: &mut State<'_>,
+    arguments: &ExtensionTag<'_, '_, '_>,
+    allowed: &[&str],
+) -> Result<bool> {
+    for attribute in arguments.iter() {
+        if let Some(name) = attribute.name(state, arguments.sp)?
+            && !allowed.contains(&name.trim_ascii())
+        {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Returns the trimmed value of an attribute, or `None` if the attribute does
+/// not exist or is empty.
+fn get_trimmed(
+    state: &mut State<'_>,
+    arguments: &ExtensionTag<'_, '_, '_>,
+    key: &str,
+) -> Result<Option<String>> {
+    Ok(arguments
+        .get(state, key)?
+        .map(|value| value.trim_ascii().to_owned())
+        .filter(|value| !value.is_empty()))
+}
+
+/// Writes a Cite error message.
+fn write_error(out: &mut String, key: &str, params: &[&str]) -> Result {
+    out.push_str(&cite_error(key, params));
+    Ok(OutputMode::Inline)
+}
+
+/// Formats a Cite error message.
+fn cite_error(key: &str, params: &[&str]) -> String {
+    let error = message(key, params);
+    let error = message("cite_error", &[&error]);
+    format!(r#"<span class="error mw-ext-cite-error" lang="en" dir="ltr">{error}</span>"#)
+}
+
+/// Formats an interface message with the given parameters.
+fn message<'a>(key: &str, params: &[&'a str]) -> Cow<'static, str> {
+    let Ok(message) = format_message([key], |index| {
+        Ok::<_, Infallible>(
+            index
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| params.get(index.checked_sub(1)?))
+                .map(|param| Cow::<'a, str>::Borrowed(param)),
+        )
+    });
+    message
+}
+
+/// The letters used by the `lower-greek` group, skipping final sigma.
+const LOWER_GREEK: &[char] = &[
+    'α', 'β', 'γ', 'δ', 'ε', 'ζ', 'η', 'θ', 'ι', 'κ', 'λ', 'μ', 'ν', 'ξ', 'ο', 'π', 'ρ', 'σ', 'τ',
+    'υ', 'φ', 'χ', 'ψ', 'ω',
+];
+
+/// The letters used by the `upper-greek` group.
+const UPPER_GREEK: &[char] = &[
+    'Α', 'Β', 'Γ', 'Δ', 'Ε', 'Ζ', 'Η', 'Θ', 'Ι', 'Κ', 'Λ', 'Μ', 'Ν', 'Ξ', 'Ο', 'Π', 'Ρ', 'Σ', 'Τ',
+    'Υ', 'Φ', 'Χ', 'Ψ', 'Ω',
+];
+
+/// The letters used by the `lower-alpha` group.
+const LOWER_ALPHA: &[char] = &[
+    'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's',
+    't', 'u', 'v', 'w', 'x', 'y', 'z',
+];
+
+/// The letters used by the `upper-alpha` group.
+const UPPER_ALPHA: &[char] = &[
+    'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R', 'S',
+    'T', 'U', 'V', 'W', 'X', 'Y', 'Z',
+];
+
+/// Makes a visible label for a reference number in a group.
+fn make_label(group: &str, number: usize) -> String {
+    match group {
+        "" | "decimal" => number.to_string(),
+        "lower-alpha" => make_label_alpha(number - 1, LOWER_ALPHA),
+        "upper-alpha" => make_label_alpha(number - 1, UPPER_ALPHA),
+        "lower-greek" => make_label_alpha(number - 1, LOWER_GREEK),
+        "upper-greek" => make_label_alpha(number - 1, UPPER_GREEK),
+        "lower-roman" => make_label_roman(number, false),
+        "upper-roman" => make_label_roman(number, true),
+        _ => format!("{group} {number}"),
+    }
+}
+
+/// Makes a Roman numeral label. Numbers which cannot be written as a Roman
+/// numeral fall back to decimal.
+fn make_label_roman(number: usize, upper: bool) -> String {
+    match i16::try_from(number) {
+        Ok(number @ 1..=3999) if upper => format!("{:X}", Roman::from(number)),
+        Ok(number @ 1..=3999) => format!("{:x}", Roman::from(number)),
+        _ => number.to_string(),
+    }
+}
+
+/// Makes an alphabetic ordinal label.
+fn make_label_alpha(mut n: usize, alphabet: &[char]) -> String {
+    let mut buf = ['\0'; 16];
+    let mut index = 0;
+    loop {
+        buf[index] = alphabet[n % alphabet.len()];
+        n /= alphabet.len();
+        index += 1;
+        if n == 0 {
+            break;
+        }
+    }
+    buf[..index].iter().rev().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels() {
+        assert_eq!(make_label("", 3), "3");
+        assert_eq!(make_label("lower-alpha", 1), "a");
+        assert_eq!(make_label("lower-alpha", 27), "ba");
+        assert_eq!(make_label("upper-roman", 4), "IV");
+        assert_eq!(make_label("lower-roman", 3999), "mmmcmxcix");
+        assert_eq!(make_label("lower-roman", 4000), "4000");
+        assert_eq!(make_label("upper-roman", 40000), "40000");
+        assert_eq!(make_label("note", 2), "note 2");
+    }
+
+    #[test]
+    fn greek_labels() {
+        assert_eq!(make_label("lower-greek", 18), "σ");
+        assert_eq!(make_label("lower-greek", 19), "τ");
+        assert_eq!(make_label("lower-greek", 24), "ω");
+        assert_eq!(make_label("lower-greek", 25), "βα");
+        assert_eq!(make_label("upper-greek", 18), "Σ");
+        assert_eq!(make_label("upper-greek", 24), "Ω");
+        assert_eq!(
+            make_label_alpha(usize::MAX, LOWER_GREEK).chars().count(),
+            14
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            cite_error("cite_error_ref_follow_conflicts", &[]),
+            r#"<span class="error mw-ext-cite-error" lang="en" dir="ltr">Cite error: Invalid <code>&lt;ref&gt;</code> tag; a ref that follows another ref cannot also have a name</span>"#
+        );
+        assert_eq!(
+            cite_error("cite_error_references_no_text", &["x"]),
+            r#"<span class="error mw-ext-cite-error" lang="en" dir="ltr">Cite error: Invalid <code>&lt;ref&gt;</code> tag; no text was provided for refs named <code>x</code></span>"#
+        );
+    }
+
+    #[test]
+    fn backlinks() {
+        let mut out = String::new();
+        write_backlinks(&mut out, 1, |index| format!("r-{index}")).unwrap();
+        assert_eq!(out, r##"<a href="#r-0">^</a>"##);
+
+        out.clear();
+        write_backlinks(&mut out, 2, |index| format!("r-{index}")).unwrap();
+        assert_eq!(
+            out,
+            r##"<span>^ <a href="#r-0"><sup><i><b>a</b></i></sup></a> <a href="#r-1"><sup><i><b>b</b></i></sup></a></span>"##
+        );
+    }
+
+    #[test]
+    fn sections() {
+        let mut references = References::default();
+        let first = references.use_reference("", false, Some("a"), Some("A".into()), None);
+        let again = references.use_reference("", false, Some("a"), None, None);
+        assert_eq!(first.note_id, again.note_id);
+        assert_ne!(first.ref_id, again.ref_id);
+        assert_eq!(again.label, "1");
+
+        // A new list restarts numbering
+        references.groups.get_mut("").unwrap().expanded += 1;
+        let next = references.use_reference("", false, None, Some("B".into()), None);
+        assert_eq!(next.label, "1");
+        assert_ne!(next.note_id, first.note_id);
+    }
+
+    #[test]
+    fn follow() {
+        let mut references = References::default();
+        references.follow("", false, "missing", "orphan");
+        references.use_reference("", false, Some("a"), Some("A".into()), None);
+        references.follow("", false, "a", "more");
+
+        let section = references.take_section("");
+        assert_eq!(section.references[0].text.as_deref(), Some("orphan"));
+        assert_eq!(section.references[0].number, None);
+        assert_eq!(section.references[1].text.as_deref(), Some("A more"));
+
+        // The orphan has no number, so it goes after the numbered references
+        let mut out = String::new();
+        write_list(&mut out, "", section, true).unwrap();
+        let numbered = out.find(r#"value="1""#).unwrap();
+        let orphan = out.find(" orphan</li>").unwrap();
+        assert!(numbered < orphan);
+    }
+
+    #[test]
+    fn details() {
+        let mut references = References::default();
+        let first =
+            references.use_subreference("", false, Some("a"), Some("A".into()), "p. 1".into());
+        let again = references.use_subreference("", false, Some("a"), None, "p. 1".into());
+        let other = references.use_subreference("", false, Some("a"), None, "p. 2".into());
+        let main = references.use_reference("", false, Some("a"), None, None);
+        assert_eq!(first.label, "1.1");
+        assert_eq!(again.label, "1.1");
+        assert_eq!(first.note_id, again.note_id);
+        assert_ne!(first.ref_id, again.ref_id);
+        assert_eq!(other.label, "1.2");
+        assert_eq!(main.label, "1");
+
+        let mut out = String::new();
+        write_list(&mut out, "", references.take_section(""), true).unwrap();
+        assert!(out.contains(r#"<ol class="mw-subreference-list">"#));
+        assert!(out.contains(r#"data-mw-footnote-number="1.1">"#));
+        assert!(out.contains(r#"data-mw-footnote-number="1.2">"#));
+        assert!(out.contains(" p. 2</li>"));
+    }
+
+    #[test]
+    fn list_defined() {
+        let mut references = References::default();
+        let marker = references.use_reference("", true, Some("a"), None, None);
+        references.define_in_list("", true, "a", "A".into());
+        references.define_in_list("", true, "unused", "B".into());
+
+        let mut out = String::new();
+        write_list(&mut out, "", references.take_section(""), true).unwrap();
+        assert!(out.contains(&format!(r#"id="{}""#, marker.note_id)));
+        assert!(out.contains(" A</li>"));
+        assert!(!out.contains(" B</li>"));
+        let error = cite_error("cite_error_references_missing_key", &["unused"]);
+        assert!(out.ends_with(&format!("<p>{error}</p>")));
+    }
+
+    #[test]
+    fn finish() {
+        let mut references = References::default();
+        references.use_reference("", true, None, Some("A".into()), None);
+        references.use_reference("<note>", true, None, Some("B".into()), None);
+        references.use_reference("<note>", true, None, Some("C".into()), None);
+
+        let mut out = String::new();
+        references.finish(&mut out).unwrap();
+        assert!(out.starts_with(r#"<div class="mw-references-wrap"><ol class="references">"#));
+        assert!(out.contains(" A</li>"));
+        assert!(!out.contains(" B</li>"));
+        let error = cite_error(
+            "cite_error_group_refs_without_references",
+            &["&lt;note&gt;"],
+        );
+        assert!(out.ends_with(&format!("<p>{error}</p>")));
+        assert_eq!(out.matches("mw-ext-cite-error").count(), 1);
+    }
+}
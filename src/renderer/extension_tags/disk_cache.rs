@@ -0,0 +1,257 @@
+//! A size-limited directory of files which lets caches of extension tag output
+//! survive restarts.
+//!
+//! When the directory grows over its size limit, the least recently used
+//! files are removed, using the modification time of each file as its last
+//! use time.
+
+use core::{
+    hash::Hasher,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+use parking_lot::Mutex;
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A 64-bit FNV-1a hasher.
+///
+/// Unlike [`std::hash::DefaultHasher`], the output of this hasher is the same
+/// on every Rust release, so it can be used for names of files which outlive
+/// the process.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct StableHasher(u64);
+
+impl StableHasher {
+    /// The FNV-1a 64-bit offset basis.
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    /// The FNV-1a 64-bit prime.
+    const PRIME: u64 = 0x0100_0000_01b3;
+}
+
+impl Default for StableHasher {
+    #[inline]
+    fn default() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+}
+
+impl Hasher for StableHasher {
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    // Integers are written in native byte order and size by default, which
+    // would make hashes depend on the machine
+
+    #[inline]
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    #[inline]
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    #[inline]
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    #[inline]
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    #[inline]
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+}
+
+/// A size-limited cache directory.
+#[derive(Debug)]
+pub(super) struct DiskCache {
+    /// The directory.
+    dir: PathBuf,
+    /// The maximum total size of the files in the directory, in bytes.
+    max_bytes: u64,
+    /// Held while the directory is being pruned.
+    pruning: Mutex<()>,
+    /// The approximate total size of the files in the directory, in bytes.
+    size: AtomicU64,
+}
+
+impl DiskCache {
+    /// Opens a cache directory limited to `max_bytes`, creating it if it does
+    /// not exist.
+    pub fn new(dir: PathBuf, max_bytes: u64) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let cache = Self {
+            dir,
+            max_bytes,
+            pruning: Mutex::new(()),
+            size: AtomicU64::new(0),
+        };
+        cache.prune();
+        Ok(cache)
+    }
+
+    /// Reads a file from the cache and marks it as recently used.
+    pub fn get(&self, name: &str) -> Option<Vec<u8>> {
+        let path = self.dir.join(name);
+        let data = fs::read(&path).ok()?;
+
+        // If this fails, the file is only more likely to be pruned early
+        let _ = fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+
+        Some(data)
+    }
+
+    /// Writes a file to the cache, replacing any existing file with the same
+    /// name.
+    pub fn insert(&self, name: &str, data: &[u8]) -> io::Result<()> {
+        /// A counter which makes temporary file names unique in the process.
+        static TEMP_ID: AtomicUsize = AtomicUsize::new(0);
+
+        // Files are written to a temporary file first so that a reader never
+        // sees a partially written file, and the temporary file is unique so
+        // that two writers of the same file never write to it at once
+        let path = self.dir.join(name);
+        let tmp = self.dir.join(format!(
+            "{name}.{}.{}.tmp",
+            std::process::id(),
+            TEMP_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        if let Err(err) = fs::write(&tmp, data).and_then(|()| fs::rename(&tmp, &path)) {
+            let _ = fs::remove_file(&tmp);
+            return Err(err);
+        }
+
+        let len = data.len() as u64;
+        if self.size.fetch_add(len, Ordering::Relaxed) + len > self.max_bytes {
+            self.prune();
+        }
+        Ok(())
+    }
+
+    /// Recalculates the size of the directory and, if it is over the limit,
+    /// removes the least recently used files until it is at three quarters of
+    /// the limit, so that it does not need to be pruned again right away.
+    fn prune(&self) {
+        // Whoever is already pruning will take care of it
+        let Some(_guard) = self.pruning.try_lock() else {
+            return;
+        };
+
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) => {
+                log::warn!(
+                    "Could not read cache directory {}: {err}",
+                    self.dir.display()
+                );
+                return;
+            }
+        };
+
+        let mut files = entries
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let metadata = entry.metadata().ok()?;
+                metadata.is_file().then(|| {
+                    let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+                    (modified, metadata.len(), entry.path())
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut size = files.iter().map(|(_, len, _)| len).sum::<u64>();
+        if size > self.max_bytes {
+            files.sort_unstable_by_key(|(modified, ..)| *modified);
+            let target = self.max_bytes / 4 * 3;
+            for (_, len, path) in files {
+                if size <= target {
+                    break;
+                }
+                if fs::remove_file(&path).is_ok() {
+                    size -= len;
+                }
+            }
+        }
+
+        self.size.store(size, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::{hash::Hash as _, time::Duration};
+
+    #[test]
+    fn stable_hash() {
+        let hash = |value: &[u8]| {
+            let mut hasher = StableHasher::default();
+            hasher.write(value);
+            hasher.finish()
+        };
+        assert_eq!(hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(hash(b"foobar"), 0x8594_4171_f739_67e8);
+
+        let mut hasher = StableHasher::default();
+        1_u64.hash(&mut hasher);
+        assert_eq!(hasher.finish(), hash(&[1, 0, 0, 0, 0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn prune_oldest() {
+        let dir = std::env::temp_dir().join(format!("wiki-rs-disk-cache-{}", std::process::id()));
+        let cache = DiskCache::new(dir.clone(), 100).unwrap();
+        assert_eq!(cache.get("a"), None);
+
+        let old = SystemTime::now() - Duration::from_mins(1);
+        for name in ["a", "b", "c"] {
+            cache.insert(name, &[0; 30]).unwrap();
+            fs::File::options()
+                .write(true)
+                .open(dir.join(name))
+                .unwrap()
+                .set_modified(old)
+                .unwrap();
+        }
+
+        // Reading a file makes it the most recently used one
+        assert_eq!(cache.get("a"), Some(vec![0; 30]));
+        cache.insert("d", &[1; 30]).unwrap();
+
+        let mut names = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        assert_eq!(names, ["a", "d"]);
+
+        // A new process sees the size of the existing files
+        let cache = DiskCache::new(dir.clone(), 100).unwrap();
+        assert_eq!(cache.size.load(Ordering::Relaxed), 60);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
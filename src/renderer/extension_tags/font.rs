@@ -0,0 +1,383 @@
+//! Text shaping for extension tags which draw text into SVG images.
+//!
+//! Text is split into runs using the Unicode bidirectional algorithm, then
+//! split again into runs of characters which are supported by the same font
+//! face, and then each run is shaped for its own script and direction. The
+//! built-in sans-serif face only covers Latin, Greek, and Cyrillic, so other
+//! scripts use fallback faces loaded from font files given at startup.
+
+use super::disk_cache::StableHasher;
+use core::hash::{Hash as _, Hasher as _};
+use rustybuzz::{
+    Direction, Face, GlyphBuffer, UnicodeBuffer,
+    ttf_parser::{GlyphId, OutlineBuilder, Tag, fonts_in_collection},
+};
+use std::sync::{LazyLock, OnceLock};
+use tiny_skia::{Path, PathBuilder};
+use unicode_bidi::BidiInfo;
+use unicode_general_category::{GeneralCategory, get_general_category};
+
+/// The variable font weight used for normal text.
+const NORMAL_WEIGHT: f32 = 380.0;
+
+/// The variable font weight used for bold text.
+const BOLD_WEIGHT: f32 = 650.0;
+
+/// Font loading errors.
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    /// The font file could not be read as a font.
+    #[error("font #{0} is not a valid TrueType, OpenType, WOFF, or WOFF2 font")]
+    Invalid(usize),
+}
+
+/// Fallback font faces, in order of preference.
+static FALLBACK_FACES: OnceLock<Vec<Face<'static>>> = OnceLock::new();
+
+/// A hash of the fallback font files.
+static FALLBACK_HASH: OnceLock<u64> = OnceLock::new();
+
+/// Sets the fallback fonts used for characters which are not supported by the
+/// built-in font. This can only be done once, and must be done before any text
+/// is shaped.
+pub(crate) fn set_fallback_fonts(fonts: Vec<Vec<u8>>) -> Result<(), Error> {
+    // The hash is part of persisted cache keys, so it needs to be stable
+    let mut hasher = StableHasher::default();
+    fonts.hash(&mut hasher);
+    let hash = hasher.finish();
+
+    let mut faces = Vec::new();
+    for (index, data) in fonts.into_iter().enumerate() {
+        let data = if data.starts_with(b"wOF2") {
+            wuff::decompress_woff2(&data).ok()
+        } else if data.starts_with(b"wOFF") {
+            wuff::decompress_woff1(&data).ok()
+        } else {
+            Some(data)
+        }
+        .ok_or(Error::Invalid(index))?;
+
+        let data = Box::leak(data.into_boxed_slice());
+        let count = fonts_in_collection(data).unwrap_or(1);
+        let len = faces.len();
+        faces.extend((0..count).filter_map(|face_index| Face::from_slice(data, face_index)));
+        if faces.len() == len {
+            return Err(Error::Invalid(index));
+        }
+    }
+
+    if FALLBACK_FACES.set(faces).is_err() || FALLBACK_HASH.set(hash).is_err() {
+        log::warn!("Fallback fonts were already set");
+    }
+    Ok(())
+}
+
+/// Returns a hash of the fallback font files, or `None` if there are no
+/// fallback fonts.
+pub(super) fn fallback_fonts_hash() -> Option<u64> {
+    FALLBACK_HASH.get().copied()
+}
+
+/// Returns true if the base direction of the given text is right-to-left,
+/// according to its first strong directional character.
+pub(super) fn is_rtl(text: &str) -> bool {
+    matches!(
+        unicode_bidi::get_base_direction(text),
+        unicode_bidi::Direction::Rtl
+    )
+}
+
+/// A line of shaped text.
+pub(super) struct ShapedText {
+    /// The shaped runs of the text, in visual order.
+    runs: Vec<(&'static Face<'static>, GlyphBuffer)>,
+}
+
+impl ShapedText {
+    /// Converts the text into a path at the given font size. The origin of the
+    /// path is the left edge of the alphabetic baseline.
+    #[expect(
+        clippy::cast_possible_truncation,
+        reason = "any value out of range of f32 is malformed and UB is fine"
+    )]
+    pub fn to_path(&self, font_size: f64) -> Option<Path> {
+        let mut path = GlyphPath::default();
+        for (face, buffer) in &self.runs {
+            path.scale = (font_size / f64::from(face.units_per_em())) as f32;
+            path.push(face, buffer);
+        }
+        path.path.finish()
+    }
+
+    /// Calculates the width of the text at the given font size.
+    pub fn width(&self, font_size: f64) -> f64 {
+        self.runs
+            .iter()
+            .map(|(face, buffer)| {
+                let upem = f64::from(face.units_per_em());
+                buffer
+                    .glyph_positions()
+                    .iter()
+                    .map(|pos| f64::from(pos.x_advance) * font_size / upem)
+                    .sum::<f64>()
+            })
+            .sum()
+    }
+}
+
+/// Shapes a line of text using wiki.rs’s built-in sans-serif font face and any
+/// configured fallback faces.
+pub(super) fn shape_text(text: &str, bold: bool, italic: bool) -> ShapedText {
+    /// The font faces for each style, with the built-in face first.
+    struct Faces {
+        /// Regular faces.
+        normal: Vec<Face<'static>>,
+        /// Bold faces.
+        bold: Vec<Face<'static>>,
+        /// Italic faces.
+        italic: Vec<Face<'static>>,
+        /// Bold italic faces.
+        bold_italic: Vec<Face<'static>>,
+    }
+
+    // TODO: Reuse these already included resources
+    static NORMAL_DATA: LazyLock<Vec<u8>> = LazyLock::new(|| {
+        const NORMAL: &[u8] = include_bytes!("../../../res/fonts/Archivo.woff2");
+        wuff::decompress_woff2(NORMAL).unwrap()
+    });
+    static ITALIC_DATA: LazyLock<Vec<u8>> = LazyLock::new(|| {
+        const ITALIC: &[u8] = include_bytes!("../../../res/fonts/Archivo-Italic.woff2");
+        wuff::decompress_woff2(ITALIC).unwrap()
+    });
+
+    static FACES: LazyLock<Faces> = LazyLock::new(|| {
+        // Using a two-step process here because, although this should not fail
+        // (because whoever is writing this code should not be so dumb to try a
+        // random, non-existent, font face index), if it *does* fail, it is
+        // useful to see the error. This is otherwise exactly equivalent to
+        // `from_slice`.
+        let normal_face = rustybuzz::ttf_parser::Face::parse(&NORMAL_DATA, 0).unwrap();
+        let normal_face = Face::from_face(normal_face);
+        let italic_face = rustybuzz::ttf_parser::Face::parse(&ITALIC_DATA, 0).unwrap();
+        let italic_face = Face::from_face(italic_face);
+
+        let fallback = FALLBACK_FACES.get().map_or(&[][..], Vec::as_slice);
+
+        // Fallback faces get the same weights as the built-in faces if they
+        // are variable fonts; otherwise, they are used as-is
+        let style = |face: &Face<'static>, weight: f32| {
+            let mut faces = Vec::with_capacity(fallback.len() + 1);
+            faces.extend(core::iter::once(face).chain(fallback).map(|face| {
+                let mut face = face.clone();
+                face.set_variation(Tag::from_bytes(b"wght"), weight);
+                face
+            }));
+            faces
+        };
+
+        Faces {
+            normal: style(&normal_face, NORMAL_WEIGHT),
+            bold: style(&normal_face, BOLD_WEIGHT),
+            italic: style(&italic_face, NORMAL_WEIGHT),
+            bold_italic: style(&italic_face, BOLD_WEIGHT),
+        }
+    });
+
+    let faces = match (bold, italic) {
+        (true, true) => &FACES.bold_italic,
+        (true, false) => &FACES.bold,
+        (false, true) => &FACES.italic,
+        (false, false) => &FACES.normal,
+    };
+
+    let mut runs = Vec::new();
+    let bidi = BidiInfo::new(text, None);
+    for paragraph in &bidi.paragraphs {
+        let (levels, level_runs) = bidi.visual_runs(paragraph, paragraph.range.clone());
+        for range in level_runs {
+            let rtl = levels[range.start].is_rtl();
+            let direction = if rtl {
+                Direction::RightToLeft
+            } else {
+                Direction::LeftToRight
+            };
+
+            let start = runs.len();
+            for (face, run) in split_by_face(faces, &text[range]) {
+                let mut buffer = UnicodeBuffer::new();
+                buffer.push_str(run);
+                buffer.set_direction(direction);
+                buffer.guess_segment_properties();
+                runs.push((face, rustybuzz::shape(face, &[], buffer)));
+            }
+
+            // Each face run is shaped in visual order already, but the face runs
+            // themselves are in logical order
+            if rtl {
+                runs[start..].reverse();
+            }
+        }
+    }
+
+    ShapedText { runs }
+}
+
+/// Splits text into runs of characters which are supported by the same font
+/// face, preferring earlier faces. Characters which are not supported by any
+/// face use the first face.
+///
+/// Characters which have no glyphs of their own (spaces, combining marks, and
+/// so on) are kept with the preceding run so that they are shaped together.
+fn split_by_face<'a>(
+    faces: &'static [Face<'static>],
+    text: &'a str,
+) -> impl Iterator<Item = (&'static Face<'static>, &'a str)> {
+    let face_for = |c: char| {
+        if c.is_whitespace() || is_mark(c) {
+            None
+        } else {
+            Some(
+                faces
+                    .iter()
+                    .position(|face| face.glyph_index(c).is_some())
+                    .unwrap_or(0),
+            )
+        }
+    };
+
+    let mut rest = text;
+    core::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+
+        let mut face = None;
+        let end = rest
+            .char_indices()
+            .find(|&(_, c)| match (face, face_for(c)) {
+                (None, next) => {
+                    face = next;
+                    false
+                }
+                (Some(current), Some(next)) => current != next,
+                (Some(_), None) => false,
+            })
+            .map_or(rest.len(), |(index, _)| index);
+
+        let (run, next) = rest.split_at(end);
+        rest = next;
+        Some((&faces[face.unwrap_or(0)], run))
+    })
+}
+
+/// Returns true if the given character is a combining mark or a format
+/// control, which must be shaped with its base character.
+fn is_mark(c: char) -> bool {
+    matches!(
+        get_general_category(c),
+        GeneralCategory::NonspacingMark
+            | GeneralCategory::SpacingMark
+            | GeneralCategory::EnclosingMark
+            | GeneralCategory::Format
+    )
+}
+
+/// A glyph-to-path converter for runs of text.
+#[derive(Default)]
+struct GlyphPath {
+    /// The origin of the current glyph, after applying its offset from the
+    /// x-position, in output units.
+    origin: (f32, f32),
+    /// The path for the combined text run.
+    path: PathBuilder,
+    /// The scale from font units to output units for the current run.
+    scale: f32,
+    /// The currently processing glyph.
+    segment: PathBuilder,
+    /// The x-position for the current segment, in output units.
+    x: f32,
+}
+
+impl GlyphPath {
+    /// Adds the glyphs of a shaped run to the path.
+    #[expect(
+        clippy::cast_precision_loss,
+        reason = "if a glyph position is ≥2**24, something sure happened"
+    )]
+    fn push(&mut self, face: &Face<'_>, buffer: &GlyphBuffer) {
+        for (info, pos) in buffer.glyph_infos().iter().zip(buffer.glyph_positions()) {
+            // There is a helper function in rustybuzz to do this conversion,
+            // which as of 0.20 is uselessly locked behind `pub(crate)` for no
+            // reason. :-(
+            #[expect(
+                clippy::cast_possible_truncation,
+                reason = "`info.glyph_id` is guaranteed to be ≤u16::MAX"
+            )]
+            let glyph_id = GlyphId(info.glyph_id as u16);
+
+            // Marks are positioned relative to their base glyph using these
+            // offsets, so ignoring them stacks every diacritic at the pen
+            self.origin = (
+                self.x + pos.x_offset as f32 * self.scale,
+                -pos.y_offset as f32 * self.scale,
+            );
+
+            // “… since ttf-parser is a pull parser, OutlineBuilder will emit
+            // segments even when outline is partially malformed. You must check
+            // outline_glyph() result before using OutlineBuilder's output.”
+            if face.outline_glyph(glyph_id, self).is_some()
+                // “Returns None when Path is empty or has invalid bounds.”
+                && let Some(segment) = core::mem::take(&mut self.segment).finish()
+            {
+                self.path.push_path(&segment);
+            } else {
+                self.segment.clear();
+            }
+
+            self.x += pos.x_advance as f32 * self.scale;
+        }
+    }
+
+    /// Converts a point in font units into output units for the current glyph.
+    #[inline]
+    fn point(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            x * self.scale + self.origin.0,
+            -y * self.scale + self.origin.1,
+        )
+    }
+}
+
+impl OutlineBuilder for GlyphPath {
+    #[inline]
+    fn move_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.point(x, y);
+        self.segment.move_to(x, y);
+    }
+
+    #[inline]
+    fn line_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.point(x, y);
+        self.segment.line_to(x, y);
+    }
+
+    #[inline]
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (x1, y1) = self.point(x1, y1);
+        let (x, y) = self.point(x, y);
+        self.segment.quad_to(x1, y1, x, y);
+    }
+
+    #[inline]
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (x1, y1) = self.point(x1, y1);
+        let (x2, y2) = self.point(x2, y2);
+        let (x, y) = self.point(x, y);
+        self.segment.cubic_to(x1, y1, x2, y2, x, y);
+    }
+
+    #[inline]
+    fn close(&mut self) {
+        self.segment.close();
+    }
+}
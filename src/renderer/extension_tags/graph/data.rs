@@ -109,6 +109,8 @@ impl<'s> ValueExt<'s> for Value<'s> {
         // steps. This “should” be equivalent
         match (self, other) {
             (Value::Null, Value::Null) => true,
+            // `null` is only loosely equal to itself and `undefined`
+            (Value::Null, _) | (_, Value::Null) => false,
             (Value::Bool(lhs), Value::Bool(rhs)) => lhs == rhs,
             (Value::Number(lhs), Value::Number(rhs)) => lhs == rhs,
             (Value::Array(lhs), Value::Array(rhs)) => core::ptr::eq(lhs, rhs),
@@ -186,7 +188,7 @@ impl<'s> ValueExt<'s> for Value<'s> {
             if utc {
                 date.into_offset(DateTimeZone::UTC)?
             } else {
-                date.into_offset(DateTimeZone::local()?)?
+                date.into_offset(DateTimeZone::local())?
             }
         })
     }
@@ -287,6 +289,32 @@ pub(super) struct Data<'s> {
 }
 
 impl<'s> Data<'s> {
+    /// Replaces a URL source with its data from the given map of loaded
+    /// `sources`, keyed by URL.
+    pub fn attach(&mut self, sources: &'s HashMap<String, String>) {
+        let Source::Url(url) = &self.source else {
+            return;
+        };
+
+        let Some(text) = sources.get(url.as_ref()) else {
+            return;
+        };
+
+        let data = if self.format.as_ref().is_some_and(Format::is_text) {
+            Value::Str(Cow::Borrowed(text))
+        } else {
+            match serde_json::from_str(text) {
+                Ok(data) => data,
+                Err(err) => {
+                    log::warn!("Could not parse data set '{}' from {url}: {err}", self.name);
+                    return;
+                }
+            }
+        };
+
+        self.source = Source::Values(data);
+    }
+
     /// Retrieves the raw source value from this data definition.
     pub fn source<'b>(&'b self, node: &'b Node<'s, '_>) -> Option<Cow<'b, [Value<'s>]>> {
         match &self.source {
@@ -299,10 +327,8 @@ impl<'s> Data<'s> {
                 .spec
                 .data(name)
                 .map(|data| Cow::Borrowed(data.values(node))),
-            Source::Url(_) => {
-                log::warn!("Remote data fetching is not supported");
-                None
-            }
+            // The loader already warned about whatever went wrong
+            Source::Url(_) => None,
         }
     }
 
@@ -385,9 +411,10 @@ enum Source<'s> {
     /// The name of another data set.
     #[serde(borrow, rename = "source")]
     Named(Cow<'s, str>),
-    /// A URL to a data file.
+    /// A URL to a data file. This is replaced by [`Self::Values`] once the
+    /// data has been loaded.
     #[serde(borrow)]
-    Url(IgnoredAny<Cow<'s, str>>),
+    Url(Cow<'s, str>),
 }
 
 /// A TopoJSON data source specification.
@@ -563,14 +590,14 @@ enum Format<'s> {
     /// Comma-separated values.
     Csv {
         /// The expected data types for the fields of the data.
-        #[serde(borrow, rename = "parse")]
-        _parse: FormatTypes<'s>,
+        #[serde(borrow, default)]
+        parse: FormatTypes<'s>,
     },
     /// Tab-separated values.
     Tsv {
         /// The expected data types for the fields of the data.
-        #[serde(borrow, rename = "parse")]
-        _parse: FormatTypes<'s>,
+        #[serde(borrow, default)]
+        parse: FormatTypes<'s>,
     },
     /// Topographical JSON.
     #[serde(borrow)]
@@ -585,9 +612,10 @@ impl<'s> Format<'s> {
     fn format(&self, data: &Value<'s>) -> Vec<Value<'s>> {
         match self {
             Format::Json { parse, property } => format_json(data, parse, property.as_deref()),
+            Format::Csv { parse } => format_dsv(data, b',', parse),
+            Format::Tsv { parse } => format_dsv(data, b'\t', parse),
             Format::TopoJson(topojson) => topojson.format(data),
             Format::TreeJson(treejson) => treejson.format(data),
-            _ => todo!(),
         }
     }
 
@@ -601,6 +629,11 @@ impl<'s> Format<'s> {
             }
         )
     }
+
+    /// Returns true if this format is for plain text instead of JSON.
+    fn is_text(&self) -> bool {
+        matches!(self, Self::Csv { .. } | Self::Tsv { .. })
+    }
 }
 
 /// A data type specifier.
@@ -642,6 +675,139 @@ fn format_json<'s>(
     }
 }
 
+/// Converts delimiter-separated text into data objects, using the first row as
+/// the field names.
+fn format_dsv<'s>(data: &Value<'s>, delimiter: u8, parse: &FormatTypes<'s>) -> Vec<Value<'s>> {
+    let Value::Str(text) = data else {
+        // Already parsed data can only have come from inline values
+        return format_json(data, parse, None);
+    };
+
+    let rows = if let Cow::Borrowed(text) = *text {
+        parse_dsv(text, delimiter)
+    } else {
+        parse_dsv(text, delimiter)
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|field| Cow::Owned(field.into_owned()))
+                    .collect()
+            })
+            .collect()
+    };
+
+    let mut rows = rows.into_iter();
+    let Some(names) = rows.next() else {
+        return vec![];
+    };
+
+    let no_types = FieldTypes::default();
+    let types = match parse {
+        FormatTypes::Auto => &no_types,
+        FormatTypes::Explicit(types) => types,
+    };
+
+    rows.map(|row| {
+        // Like d3-dsv, missing fields are left undefined and extra fields are
+        // discarded
+        names
+            .iter()
+            .zip(row)
+            .map(|(name, field)| {
+                let value = Value::Str(field);
+                let value = if let Some(kind) = types.get(name) {
+                    kind.map(value)
+                } else {
+                    value
+                };
+                (name.clone(), value)
+            })
+            .collect()
+    })
+    .collect()
+}
+
+/// Splits delimiter-separated text into rows of fields using the RFC 4180
+/// quoting rules. Blank lines are skipped.
+fn parse_dsv(text: &str, delimiter: u8) -> Vec<Vec<Cow<'_, str>>> {
+    let bytes = text.as_bytes();
+    let is_terminator = |b: u8| b == delimiter || b == b'\n' || b == b'\r';
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        if bytes[pos] == b'"' {
+            let start = pos + 1;
+            let mut end = start;
+            let mut escaped = false;
+            loop {
+                match memchr::memchr(b'"', &bytes[end..]) {
+                    Some(offset) if bytes.get(end + offset + 1) == Some(&b'"') => {
+                        escaped = true;
+                        end += offset + 2;
+                    }
+                    Some(offset) => {
+                        end += offset;
+                        break;
+                    }
+                    None => {
+                        end = bytes.len();
+                        break;
+                    }
+                }
+            }
+
+            let field = &text[start..end];
+            row.push(if escaped {
+                Cow::Owned(field.replace("\"\"", "\""))
+            } else {
+                Cow::Borrowed(field)
+            });
+
+            // Anything between the closing quote and the next delimiter is
+            // junk, which d3-dsv also ignores
+            pos = bytes[end..]
+                .iter()
+                .position(|&b| is_terminator(b))
+                .map_or(bytes.len(), |offset| end + offset);
+        } else {
+            let end = bytes[pos..]
+                .iter()
+                .position(|&b| is_terminator(b))
+                .map_or(bytes.len(), |offset| pos + offset);
+            row.push(Cow::Borrowed(&text[pos..end]));
+            pos = end;
+        }
+
+        match bytes.get(pos) {
+            Some(&b) if b == delimiter => {
+                pos += 1;
+                if pos == bytes.len() {
+                    row.push(Cow::Borrowed(""));
+                }
+            }
+            Some(&(b'\r' | b'\n')) => {
+                if bytes[pos] == b'\r' && bytes.get(pos + 1) == Some(&b'\n') {
+                    pos += 1;
+                }
+                pos += 1;
+                let line = core::mem::take(&mut row);
+                if !matches!(line.as_slice(), [field] if field.is_empty()) {
+                    rows.push(line);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if !row.is_empty() {
+        rows.push(row);
+    }
+
+    rows
+}
+
 /// Gets a value from a data object using a nested path key.
 pub(super) fn get_nested_value<'b, 's>(item: &'b Value<'s>, key: &str) -> Option<&'b Value<'s>> {
     if key.contains(['[']) {
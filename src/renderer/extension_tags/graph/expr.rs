@@ -1113,7 +1113,7 @@ mod built_ins {
             if UTC {
                 node.now
             } else {
-                node.now.into_offset(DateTimeZone::local()?)?
+                node.now.into_offset(DateTimeZone::local())?
             }
         } else if let [timestamp] = args {
             DateTime::from_f64(timestamp.to_f64(), UTC)
@@ -1640,6 +1640,9 @@ impl<'s> ValueExt<'s> for VmValue<'s> {
             (Self::Date(lhs), Self::Date(rhs)) => core::ptr::eq(lhs, rhs),
             (Self::Object(lhs), Self::Object(rhs)) => core::ptr::eq(lhs, rhs),
             (Self::Regex(lhs, _), Self::Regex(rhs, _)) => core::ptr::eq(lhs, rhs),
+            (Self::Value(Value::Null), Self::Value(Value::Null)) => true,
+            // `null` is only loosely equal to itself and `undefined`
+            (Self::Value(Value::Null), _) | (_, Self::Value(Value::Null)) => false,
             (Self::Value(Value::Number(n)), other) | (other, Self::Value(Value::Number(n))) => {
                 other.to_f64() == n.as_f64().unwrap_or(f64::NAN)
             }
@@ -1717,7 +1720,7 @@ impl<'s> ValueExt<'s> for VmValue<'s> {
             if utc {
                 date.into_offset(DateTimeZone::UTC)?
             } else {
-                date.into_offset(DateTimeZone::local()?)?
+                date.into_offset(DateTimeZone::local())?
             }
         })
     }
@@ -1054,6 +1054,22 @@ impl ProjectorSettings {
         }
     }
 
+    /// Creates a new `ProjectorSettings` for a Web Mercator projection centred
+    /// on `center`, in degrees longitude/latitude, at the given slippy map
+    /// `zoom` level, and clipped to an image of the given `size`.
+    pub(super) fn web_mercator(center: [f64; 2], zoom: f64, size: [f64; 2]) -> Self {
+        /// The size of a slippy map tile, in pixels.
+        const TILE_SIZE: f64 = 256.0;
+
+        Self {
+            center: Some(center),
+            clip_extent: Some([[0.0, 0.0], size]),
+            scale: Some(TILE_SIZE * zoom.exp2() / TAU),
+            translate: Some([size[0] / 2.0, size[1] / 2.0]),
+            ..Self::baseline(ProjectionSettings::Mercator)
+        }
+    }
+
     /// Creates a new `ProjectorSettings` by overwriting any properties of
     /// `self` with non-`None` properties of `other`.
     fn merge(&self, other: &Self) -> Self {
@@ -0,0 +1,205 @@
+//! Data loading for the custom URL protocols of the Graph extension.
+//!
+//! The Graph extension does not allow data to be loaded from arbitrary URLs.
+//! Instead, data comes from the wiki using one of these protocols:
+//!
+//! * `wikiraw:///Title`: The raw content of a page.
+//! * `tabular:///Name.tab`: A tabular data page from the shared repository.
+//! * `map:///Name.map`: A map data page from the shared repository.
+//! * `wikiapi:///?query`: The result of a MediaWiki Action API query.
+//!
+//! The host part of the URL is the wiki to load from. Commons is the shared
+//! repository; every other host is treated as the local wiki.
+
+use super::{super::json_config, Loader, Result};
+use crate::{common::url_decode, db::Article, title::Title};
+use serde_json::{Value, json};
+use std::{borrow::Cow, collections::HashMap, sync::Arc};
+
+/// The host name of the shared media repository.
+const SHARED_HOST: &str = "commons.wikimedia.org";
+
+/// The parts of a spec which are needed to find its data URLs.
+#[derive(serde::Deserialize)]
+struct Urls<'s> {
+    /// Data sets.
+    #[serde(borrow, default)]
+    data: Vec<DataUrl<'s>>,
+}
+
+/// The part of a data set which is needed to find its data URL.
+#[derive(serde::Deserialize)]
+struct DataUrl<'s> {
+    /// The URL of the data set.
+    #[serde(borrow, default)]
+    url: Option<Cow<'s, str>>,
+}
+
+/// Loads the data for every data set in the given `spec` which has a URL
+/// source, returning a map of URLs to raw data.
+pub(super) fn load_all(spec: &str, loader: &dyn Loader) -> Result<HashMap<String, String>> {
+    let urls = serde_json::from_str::<Urls<'_>>(spec)?;
+    Ok(urls
+        .data
+        .into_iter()
+        .filter_map(|data| {
+            let url = data.url?;
+            let text = load(&url, loader)?;
+            Some((url.into_owned(), text))
+        })
+        .collect())
+}
+
+/// Returns true if any data set in the given `spec` has a URL source.
+pub(super) fn has_urls(spec: &str) -> bool {
+    serde_json::from_str::<Urls<'_>>(spec)
+        .is_ok_and(|urls| urls.data.iter().any(|data| data.url.is_some()))
+}
+
+/// Loads the raw data for a URL.
+fn load(url: &str, loader: &dyn Loader) -> Option<String> {
+    let Some((protocol, rest)) = url.split_once(':') else {
+        log::warn!("Graph data URL '{url}' has no protocol");
+        return None;
+    };
+
+    let (host, path) = rest.strip_prefix("//").map_or(("", rest), |rest| {
+        rest.split_once('/').unwrap_or((rest, ""))
+    });
+    let (path, query) = path.split_once('?').unwrap_or((path, ""));
+    let path = url_decode(path);
+    let shared = host.eq_ignore_ascii_case(SHARED_HOST);
+
+    match protocol {
+        "wikiraw" => {
+            get_page(loader, shared, &Title::new(&path, None)).map(|article| article.body.clone())
+        }
+        "tabular" | "map" => {
+            let extension = if protocol == "tabular" {
+                ".tab"
+            } else {
+                ".map"
+            };
+            if !path.ends_with(extension) {
+                log::warn!("Graph data URL '{url}' does not end with '{extension}'");
+                return None;
+            }
+            let title = Title::new(&format!("Data:{path}"), None);
+            let data = serde_json::from_str::<Value>(&loader.shared_page(&title)?.body)
+                .inspect_err(|err| log::warn!("Could not parse {}: {err}", title.key()))
+                .ok()?;
+            let data = json_config::localize(data);
+            Some(if protocol == "tabular" {
+                tabular(&data).to_string()
+            } else {
+                data.to_string()
+            })
+        }
+        "wikiapi" => wikiapi(loader, shared, query),
+        _ => {
+            log::warn!("Unsupported graph data URL '{url}'");
+            None
+        }
+    }
+}
+
+/// Gets a page from either the local wiki or the shared repository.
+fn get_page(loader: &dyn Loader, shared: bool, title: &Title) -> Option<Arc<Article>> {
+    if shared {
+        loader.shared_page(title)
+    } else {
+        loader.page(title)
+    }
+}
+
+/// Converts a localised tabular data page into the structure used by the
+/// Graph extension, where each row is an object keyed by field name.
+fn tabular(data: &Value) -> Value {
+    let license = &data["license"];
+    json!({
+        "meta": [{
+            "description": data["description"],
+            "license_code": license["code"],
+            "license_text": license["text"],
+            "license_url": license["url"],
+            "sources": data["sources"],
+        }],
+        "fields": data["schema"]["fields"],
+        "data": json_config::rows(data),
+    })
+}
+
+/// Emulates a MediaWiki Action API query.
+///
+/// Only queries for page content (`action=query&prop=revisions&rvprop=content`)
+/// can be answered offline.
+fn wikiapi(loader: &dyn Loader, shared: bool, query: &str) -> Option<String> {
+    /// Decodes a URL query string component.
+    fn decode(part: &str) -> String {
+        url_decode(&part.replace('+', " ")).into_owned()
+    }
+
+    let params = query
+        .split('&')
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            (decode(key), decode(value))
+        })
+        .collect::<HashMap<_, _>>();
+    let get = |key: &str| params.get(key).map(String::as_str);
+    let has =
+        |key: &str, value: &str| get(key).is_some_and(|list| list.split('|').any(|v| v == value));
+
+    if get("action") != Some("query") || !has("prop", "revisions") || !has("rvprop", "content") {
+        log::warn!("Unsupported graph API query '{query}'");
+        return None;
+    }
+
+    let v2 = get("formatversion") == Some("2");
+    let content_key = if v2 { "content" } else { "*" };
+    let slots = params.contains_key("rvslots");
+
+    let pages = get("titles")
+        .unwrap_or_default()
+        .split('|')
+        .filter(|title| !title.is_empty())
+        .enumerate()
+        .map(|(index, title)| {
+            let title = Title::new(title, None);
+            let mut page = json!({ "ns": title.namespace().id, "title": title.key() });
+            if let Some(article) = get_page(loader, shared, &title) {
+                let format = match article.model.as_str() {
+                    "json" => "application/json",
+                    "wikitext" => "text/x-wiki",
+                    "css" => "text/css",
+                    "javascript" => "text/javascript",
+                    _ => "text/plain",
+                };
+                let mut revision = json!({
+                    "contentformat": format,
+                    "contentmodel": article.model,
+                    (content_key): article.body,
+                });
+                if slots {
+                    revision = json!({ "slots": { "main": revision } });
+                }
+                page["pageid"] = article.id.into();
+                page["title"] = article.title.clone().into();
+                page["revisions"] = json!([revision]);
+                (article.id.to_string(), page)
+            } else {
+                page["missing"] = if v2 { true.into() } else { "".into() };
+                (format!("-{}", index + 1), page)
+            }
+        });
+
+    let pages = if v2 {
+        Value::Array(pages.map(|(_, page)| page).collect())
+    } else {
+        Value::Object(pages.collect())
+    };
+
+    let batch_complete = if v2 { Value::Bool(true) } else { "".into() };
+    Some(json!({ "batchcomplete": batch_complete, "query": { "pages": pages } }).to_string())
+}
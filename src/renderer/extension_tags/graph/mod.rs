@@ -39,12 +39,16 @@
 //! ₂. Signals are not supported or implemented since this is a static image
 //!    generator, and signals are entirely about event-driven data updates.
 
-use crate::php::{DateTime, DateTimeZone};
+use crate::{
+    db::Article,
+    php::{DateTime, DateTimeZone},
+    title::Title,
+};
 use core::cell::RefCell;
 use either::Either;
 use rand::rngs::SmallRng;
 use serde_json_borrow::Value;
-use std::borrow::Cow;
+use std::{borrow::Cow, sync::Arc};
 use time::Duration;
 
 mod axis;
@@ -52,6 +56,7 @@ mod data;
 mod expr;
 mod geo;
 mod legend;
+mod loader;
 mod mark;
 mod predicate;
 mod propset;
@@ -90,14 +95,25 @@ pub(super) enum Error {
 /// The Graph result type.
 pub(super) type Result<T, E = Error> = core::result::Result<T, E>;
 
-/// Converts a Graph specification to an SVG.
-pub(super) fn spec_to_svg(spec: &str, now: DateTime) -> Result<String> {
-    let spec = serde_json::from_str::<spec::Spec<'_>>(spec)?;
+/// A source of wiki pages for data URLs.
+pub(super) trait Loader {
+    /// Gets the page with the given title from the wiki.
+    fn page(&self, title: &Title) -> Option<Arc<Article>>;
 
-    if let Some(version) = spec.version
-        && version != 2
-    {
-        return Err(Error::Version(version));
+    /// Gets the page with the given title from the shared media repository.
+    fn shared_page(&self, title: &Title) -> Option<Arc<Article>>;
+}
+
+/// Converts a Graph specification to an SVG, using `loader` to retrieve any
+/// data sets loaded from URLs.
+pub(super) fn spec_to_svg(spec: &str, now: DateTime, loader: &dyn Loader) -> Result<String> {
+    let text = spec::normalize(spec)?;
+    // Loaded data must outlive the spec since the data sets borrow from it
+    let sources = loader::load_all(&text, loader)?;
+    let mut spec = serde_json::from_str::<spec::Spec<'_>>(&text)?;
+
+    for data in &mut spec.data {
+        data.attach(&sources);
     }
 
     let svg = renderer::render(&spec, now)?;
@@ -108,6 +124,38 @@ pub(super) fn spec_to_svg(spec: &str, now: DateTime) -> Result<String> {
     Ok(unsafe { String::from_utf8_unchecked(out) })
 }
 
+/// Returns true if the given Graph specification loads data sets from URLs, in
+/// which case its output also depends on the pages the data came from.
+pub(super) fn loads_data(spec: &str) -> bool {
+    loader::has_urls(spec)
+}
+
+/// A Web Mercator projection for drawing slippy map style images of GeoJSON
+/// objects.
+pub(super) struct WebMercator(geo::Projector);
+
+impl WebMercator {
+    /// Creates a new projection centred on `center`, in degrees
+    /// longitude/latitude, at the given slippy map `zoom` level, for an image
+    /// of the given `size`.
+    pub fn new(center: [f64; 2], zoom: f64, size: [f64; 2]) -> Self {
+        Self(geo::Projector::new(&geo::ProjectorSettings::web_mercator(
+            center, zoom, size,
+        )))
+    }
+
+    /// Converts a GeoJSON object into an SVG path.
+    pub fn path(&self, object: &Value<'_>) -> String {
+        self.0.path(object)
+    }
+
+    /// Projects a point, in degrees longitude/latitude, into image coordinates.
+    pub fn point(&self, [x, y]: [f64; 2]) -> [f64; 2] {
+        let point = self.0.projection_degrees(renderer::Vec2::new(x, y));
+        [point.x, point.y]
+    }
+}
+
 /// Zero epsilon.
 const EPSILON: f64 = 1.0e-6;
 
@@ -410,7 +458,7 @@ impl TimeExt for DateTime {
         if utc {
             date
         } else {
-            date.into_offset(DateTimeZone::local().unwrap()).unwrap()
+            date.into_offset(DateTimeZone::local()).unwrap()
         }
     }
 
@@ -199,6 +199,14 @@ bag_o_crap! {
         /// given in [`x`](Self::x) and [`y`](Self::y). 0 is “north”.
         #[serde(borrow, default)]
         pub theta: Option<NumberProperty<'s>>,
+        /// For marks. Text shown by the browser when the pointer is over the
+        /// mark, drawn as an SVG `<title>`.
+        ///
+        /// This is a wiki.rs extension which is not part of Vega 2, so graphs
+        /// from the wiki do not normally use it. It has the same name as the
+        /// `tooltip` encoding channel of later Vega versions.
+        #[serde(borrow, default)]
+        pub tooltip: Option<StringProperty<'s>>,
         /// For images. The URL of the image.
         #[serde(borrow, default)]
         pub url: Option<StringProperty<'s>>,
@@ -2,18 +2,19 @@
 
 use super::{
     super::{
+        super::font,
         DoubleSizeIterator, Node, Result,
         mark::{Kind as MarkKind, Mark, Orient, Shape},
         propset::{Align, Baseline, Getter as _, Kind as PropsetKind, Propset},
     },
-    NS_SVG, Rect, TextMetrics, ValueDisplay as _, Vec2, defaults, draw_container, interp, n,
+    HOVER_ITEMS, HOVER_SELF, NS_SVG, Rect, TextMetrics, ValueDisplay as _, Vec2, defaults,
+    draw_container, hover_style, interp, n,
     path::{Command, SvgPath, SvgPathIterator, arc_path},
     text_metrics, to_svg,
 };
-use core::{cell::Cell, fmt::Write as _};
+use core::fmt::Write as _;
 use minidom::{Element, ElementBuilder};
-use rustybuzz::{Face, GlyphBuffer, ShapePlan, UnicodeBuffer, ttf_parser::Tag};
-use std::{borrow::Cow, sync::LazyLock};
+use std::borrow::Cow;
 
 /// Creates an SVG element for a single mark.
 pub(super) fn draw_mark<'s>(
@@ -61,7 +62,10 @@ pub(super) fn draw_mark<'s>(
     if let Some(name) = &mark.name {
         let _ = write!(class, " {name}");
     }
-    let g = g.attr(n!("class"), class).build();
+    let mut g = g.attr(n!("class"), class).build();
+    if hover.is_some() && !matches!(mark.kind, MarkKind::Group(_)) {
+        share_hover_style(&mut g);
+    }
     Ok(if g.nodes().next().is_none() {
         None
     } else {
@@ -126,6 +130,9 @@ pub(super) fn calculate_bounds<'s>(mark: &Mark<'s>, node: &Node<'s, '_>) -> Rect
                         Command::ArcTo {
                             point: (x2, y2), ..
                         }
+                        | Command::CurveTo {
+                            point: (x2, y2), ..
+                        }
                         | Command::LineTo(x2, y2)
                         | Command::MoveTo(x2, y2) => {
                             x = x2;
@@ -239,116 +246,40 @@ fn calculate_text_bounds<'s>(propset: &Propset<'s>, node: &Node<'s, '_>) -> Opti
 }
 
 /// Calculates the width and height of a line of text with the given font
-/// weight, style, and size using wiki.rs’s built-in sans-serif font face.
+/// weight, style, and size using wiki.rs’s built-in sans-serif font face and
+/// any fallback faces.
 fn calculate_text_dims(text: &str, bold: bool, italic: bool, font_size: f64) -> (f64, f64) {
-    shape_text(text, bold, italic, |face, buffer| {
-        let upem = f64::from(face.units_per_em());
-        let width = buffer
-            .glyph_positions()
-            .iter()
-            .map(|pos| f64::from(pos.x_advance) * font_size / upem)
-            .sum();
-        (width, font_size)
-    })
+    let width = font::shape_text(text, bold, italic).width(font_size);
+    (width, font_size)
 }
 
-/// Calculates the shape of a line of text using wiki.rs’s built-in sans-serif
-/// font face, passing this information to a callback and returning the result
-/// of the callback.
-pub(crate) fn shape_text<F, T>(text: &str, bold: bool, italic: bool, mut f: F) -> T
-where
-    F: FnMut(&Face<'_>, &GlyphBuffer) -> T,
-{
-    struct Shaper {
-        normal: (Face<'static>, ShapePlan),
-        bold: (Face<'static>, ShapePlan),
-        italic: (Face<'static>, ShapePlan),
-        bold_italic: (Face<'static>, ShapePlan),
-    }
-
-    // TODO: Reuse these already included resources
-    static NORMAL_DATA: LazyLock<Vec<u8>> = LazyLock::new(|| {
-        const NORMAL: &[u8] = include_bytes!("../../../../../res/fonts/Archivo.woff2");
-        wuff::decompress_woff2(NORMAL).unwrap()
-    });
-    static ITALIC_DATA: LazyLock<Vec<u8>> = LazyLock::new(|| {
-        const ITALIC: &[u8] = include_bytes!("../../../../../res/fonts/Archivo-Italic.woff2");
-        wuff::decompress_woff2(ITALIC).unwrap()
-    });
-
-    static SHAPER: LazyLock<Shaper> = LazyLock::new(|| {
-        // Using a two-step process here because, although this should not fail
-        // (because whoever is writing this code should not be so dumb to try a
-        // random, non-existent, font face index), if it *does* fail, it is
-        // useful to see the error. This is otherwise exactly equivalent to
-        // `from_slice`.
-        let normal_face = rustybuzz::ttf_parser::Face::parse(&NORMAL_DATA, 0).unwrap();
-        let mut normal_face = Face::from_face(normal_face);
-        let italic_face = rustybuzz::ttf_parser::Face::parse(&ITALIC_DATA, 0).unwrap();
-        let mut italic_face = Face::from_face(italic_face);
-
-        normal_face.set_variation(Tag::from_bytes(b"wght"), 380.0);
-        italic_face.set_variation(Tag::from_bytes(b"wght"), 380.0);
-
-        let mut bold_face = normal_face.clone();
-        bold_face.set_variation(Tag::from_bytes(b"wght"), 650.0);
-        let mut bold_italic_face = italic_face.clone();
-        bold_italic_face.set_variation(Tag::from_bytes(b"wght"), 650.0);
-
-        let normal_plan = ShapePlan::new(
-            &normal_face,
-            rustybuzz::Direction::LeftToRight,
-            Some(rustybuzz::script::LATIN),
-            None,
-            &[],
-        );
-        let bold_plan = ShapePlan::new(
-            &bold_face,
-            rustybuzz::Direction::LeftToRight,
-            Some(rustybuzz::script::LATIN),
-            None,
-            &[],
-        );
-        let italic_plan = ShapePlan::new(
-            &italic_face,
-            rustybuzz::Direction::LeftToRight,
-            Some(rustybuzz::script::LATIN),
-            None,
-            &[],
-        );
-        let bold_italic_plan = ShapePlan::new(
-            &bold_italic_face,
-            rustybuzz::Direction::LeftToRight,
-            Some(rustybuzz::script::LATIN),
-            None,
-            &[],
-        );
+/// Replaces the hover styles of the items of a mark with one style on the mark
+/// itself, if every item has the same hover style.
+///
+/// Group marks are not handled since their hover styles are on the background
+/// of each item instead of the item itself.
+fn share_hover_style(g: &mut Element) {
+    let style_of = |item: &Element| {
+        item.children()
+            .filter(|child| child.is("style", NS_SVG))
+            .map(Element::text)
+            .collect::<String>()
+    };
 
-        Shaper {
-            normal: (normal_face, normal_plan),
-            bold: (bold_face, bold_plan),
-            italic: (italic_face, italic_plan),
-            bold_italic: (bold_italic_face, bold_italic_plan),
-        }
-    });
+    let mut items = g.children();
+    let Some(style) = items.next().map(style_of) else {
+        return;
+    };
+    if style.is_empty() || items.any(|item| style_of(item) != style) {
+        return;
+    }
 
-    thread_local! {
-        static BUFFER: Cell<UnicodeBuffer> = Cell::new(UnicodeBuffer::new());
+    for item in g.children_mut() {
+        while item.remove_child("style", NS_SVG).is_some() {}
     }
 
-    let (face, plan) = match (bold, italic) {
-        (true, true) => &SHAPER.bold_italic,
-        (true, false) => &SHAPER.bold,
-        (false, true) => &SHAPER.italic,
-        (false, false) => &SHAPER.normal,
-    };
-    let mut buffer = BUFFER.take();
-    buffer.set_script(rustybuzz::script::LATIN);
-    buffer.push_str(text);
-    let buffer = rustybuzz::shape_with_plan(face, plan, buffer);
-    let result = f(face, &buffer);
-    BUFFER.set(buffer.clear());
-    result
+    let style = style.replace(HOVER_SELF, HOVER_ITEMS);
+    g.append_child(Element::builder("style", NS_SVG).append(style).build());
 }
 
 /// Creates an SVG element for an arc mark.
@@ -744,19 +675,32 @@ fn draw_text<'s>(
             .attr(n!("transform"), transform)
             .attr(n!("font-size"), font_size.v());
 
-        if hover.is_none() {
+        if hover.is_none() && propset.tooltip.is_none() {
             element = element.attr(n!("pointer-events"), "none");
         }
 
-        Some(
-            to_svg!(element, propset, hover, node, {
-                align => "text-anchor",
-                font => "font-family",
-                font_style => "font-style",
-                font_weight => "font-weight"
-            })
-            .build(),
-        )
+        let mut element = to_svg!(element, propset, hover, node, {
+            align => "text-anchor",
+            font => "font-family",
+            font_style => "font-style",
+            font_weight => "font-weight"
+        });
+
+        // In right-to-left text, the start anchor is on the right, so the
+        // anchor has to be flipped to keep the text on the same side of its
+        // origin
+        if font::is_rtl(&text) {
+            let align = match propset.align.get(node).unwrap_or_default() {
+                Align::Left => Align::Right,
+                Align::Center => Align::Center,
+                Align::Right => Align::Left,
+            };
+            element = element
+                .attr(n!("direction"), "rtl")
+                .attr(n!("text-anchor"), align.to_string());
+        }
+
+        Some(element.build())
     })
 }
 
@@ -767,7 +711,14 @@ fn new_element<'s>(
     hover: Option<&Propset<'s>>,
     node: &Node<'s, '_>,
 ) -> ElementBuilder {
-    let element = to_svg!(Element::builder(name, NS_SVG), propset, hover, node, {
+    let mut element = Element::builder(name, NS_SVG);
+    if let Some(tooltip) = propset.tooltip.get(node)
+        && !tooltip.is_empty()
+    {
+        element = element.append(Element::builder("title", NS_SVG).append(tooltip.to_string()));
+    }
+
+    let element = to_svg!(element, propset, hover, node, {
         cursor => "cursor",
         fill => "fill",
         fill_opacity => "fill-opacity",
@@ -786,3 +737,64 @@ fn new_element<'s>(
         element
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a mark group containing items with the given hover styles.
+    fn mark(styles: &[Option<&str>]) -> Element {
+        Element::builder("g", NS_SVG)
+            .append_all(styles.iter().map(|style| {
+                Element::builder("rect", NS_SVG)
+                    .append_all(style.map(hover_style))
+                    .build()
+            }))
+            .build()
+    }
+
+    /// Gets the text of the style elements which are children of `element`.
+    fn styles(element: &Element) -> Vec<String> {
+        element
+            .children()
+            .filter(|child| child.is("style", NS_SVG))
+            .map(Element::text)
+            .collect()
+    }
+
+    #[test]
+    fn shared_hover_style() {
+        let mut g = mark(&[Some("fill:red;"), Some("fill:red;")]);
+        share_hover_style(&mut g);
+        assert!(
+            g.children()
+                .filter(|item| item.is("rect", NS_SVG))
+                .all(|item| styles(item).is_empty())
+        );
+        assert_eq!(styles(&g), ["@scope{:scope :hover{fill:red;}}"]);
+    }
+
+    #[test]
+    fn different_hover_styles() {
+        for items in [
+            &[Some("fill:red;"), Some("fill:blue;")][..],
+            &[Some("fill:red;"), None],
+            &[None, None],
+        ] {
+            let mut g = mark(items);
+            share_hover_style(&mut g);
+            assert!(styles(&g).is_empty());
+            let item_styles = g.children().map(styles).collect::<Vec<_>>();
+            let expected = items
+                .iter()
+                .map(|style| {
+                    style
+                        .map(|style| format!("@scope{{:scope:hover{{{style}}}}}"))
+                        .into_iter()
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(item_styles, expected);
+        }
+    }
+}
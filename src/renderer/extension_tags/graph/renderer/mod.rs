@@ -11,11 +11,8 @@ use super::{
 use crate::php::DateTime;
 use core::{cell::RefCell, f64::consts::FRAC_PI_2};
 pub(crate) use format::{Error as FormatError, NumberFormatter};
-pub(crate) use mark::shape_text;
 use minidom::Element;
 use rand::{SeedableRng as _, rngs::SmallRng};
-use rustybuzz::{Face, GlyphBuffer, ttf_parser::GlyphId};
-use tiny_skia::{Path, PathBuilder};
 
 mod axis;
 mod format;
@@ -131,7 +128,6 @@ fn draw_container<'s, 'b>(
     container: &'b Container<'s>,
     node: &'b Node<'s, '_>,
 ) -> Result<(Rect, Rect)> {
-    // TODO: Each mark needs to do hover effects by writing CSS.
     let is_root = node.parent.is_none();
 
     // Even though scene is allowed on group marks, it does not seem to actually
@@ -211,82 +207,6 @@ fn draw_legend<'b, 's>(
     Ok(bounds)
 }
 
-/// Converts shaped text from a rustybuzz [`GlyphBuffer`] into a tiny-skia
-/// [`Path`].
-pub(super) fn buffer_to_path(face: &Face<'_>, buffer: &GlyphBuffer) -> Option<Path> {
-    /// A glyph-to-path converter for runs of text.
-    #[derive(Default)]
-    struct GlyphPath {
-        /// The path for the combined text run.
-        path: PathBuilder,
-        /// The currently processing glyph.
-        segment: PathBuilder,
-        /// The x-position for the current segment.
-        x: f32,
-    }
-
-    impl rustybuzz::ttf_parser::OutlineBuilder for GlyphPath {
-        #[inline]
-        fn move_to(&mut self, x: f32, y: f32) {
-            self.segment.move_to(x + self.x, -y);
-        }
-
-        #[inline]
-        fn line_to(&mut self, x: f32, y: f32) {
-            self.segment.line_to(x + self.x, -y);
-        }
-
-        #[inline]
-        fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
-            self.segment.quad_to(x1 + self.x, -y1, x + self.x, -y);
-        }
-
-        #[inline]
-        fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
-            self.segment
-                .cubic_to(x1 + self.x, -y1, x2 + self.x, -y2, x + self.x, -y);
-        }
-
-        #[inline]
-        fn close(&mut self) {
-            self.segment.close();
-        }
-    }
-
-    let mut path = GlyphPath::default();
-    for (info, pos) in buffer.glyph_infos().iter().zip(buffer.glyph_positions()) {
-        // There is a helper function in rustybuzz to do this conversion, which
-        // as of 0.20 is uselessly locked behind `pub(crate)` for no reason. :-(
-        #[expect(
-            clippy::cast_possible_truncation,
-            reason = "`info.glyph_id` is guaranteed to be ≤u16::MAX"
-        )]
-        let glyph_id = GlyphId(info.glyph_id as u16);
-
-        // “… since ttf-parser is a pull parser, OutlineBuilder will emit
-        // segments even when outline is partially malformed. You must check
-        // outline_glyph() result before using OutlineBuilder's output.”
-        if face.outline_glyph(glyph_id, &mut path).is_some()
-            // “Returns None when Path is empty or has invalid bounds.”
-            && let Some(segment) = core::mem::take(&mut path.segment).finish()
-        {
-            path.path.push_path(&segment);
-        } else {
-            path.segment.clear();
-        }
-
-        #[expect(
-            clippy::cast_precision_loss,
-            reason = "if x-advance is ≥2**24, something sure happened"
-        )]
-        {
-            path.x += pos.x_advance as f32;
-        }
-    }
-
-    path.path.finish()
-}
-
 /// A trait for items which can be contained inside a `Rect`.
 pub(super) trait Containable {
     /// Returns `true` if `self` is fully inside `container`.
@@ -1029,6 +949,25 @@ impl core::ops::Sub for Vec3 {
     }
 }
 
+/// The CSS selector for a hover style which applies to the element containing
+/// the style.
+const HOVER_SELF: &str = ":scope:hover";
+
+/// The CSS selector for a hover style which applies to each item of the mark
+/// containing the style.
+const HOVER_ITEMS: &str = ":scope :hover";
+
+/// Creates a style element which applies the given CSS declarations to its
+/// parent element while the pointer is over it.
+///
+/// Graphs are inlined into articles whose content security policy forbids
+/// scripts, so this is the only way to do Vega hover effects.
+fn hover_style(declarations: &str) -> Element {
+    Element::builder("style", NS_SVG)
+        .append(format!("@scope{{{HOVER_SELF}{{{declarations}}}}}"))
+        .build()
+}
+
 /// Shorthand for applying a [`Propset`] or [`Scene`] to an SVG element.
 macro_rules! to_svg {
     ($element:expr, $scene:ident, {
@@ -1046,31 +985,23 @@ macro_rules! to_svg {
     ($element:expr, $propset:ident, $hover:ident, $node:ident, {
         $($prop:ident => $attr:literal),* $(,)?
     }) => {{
-        use $crate::renderer::extension_tags::svg::NS_SVG;
         use ::core::{write, option::Option::Some, fmt::Write as _};
-        use ::minidom::Element;
         use ::std::string::String;
 
         let mut element = $element;
-        // TODO: Hover styles that are the same for every item in a mark should
-        // be applied to the parent scope, since browser parsing all this CSS is
-        // slow.
-        let mut style = String::from("@scope{:scope:hover{");
-        let mut has_style = false;
         $(if let Some(value) = $propset.$prop.get($node).map(|value| value.to_string()) {
               element = element.attr(n!($attr), value);
         })*
+        let mut style = String::new();
         if let Some(hover) = $hover {
             $(if let Some(value) = hover.$prop.get($node).map(|value| value.to_string()) {
-                has_style = true;
                 let _ = write!(style, "{}:{value};", $attr);
             })*
         }
-        style.push_str("}}");
-        if has_style {
-            element.append(Element::builder("style", NS_SVG).append(style))
-        } else {
+        if style.is_empty() {
             element
+        } else {
+            element.append(hover_style(&style))
         }
     }}
 }
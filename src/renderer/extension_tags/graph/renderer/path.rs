@@ -38,6 +38,15 @@ pub(super) enum Command {
     },
     /// Close the path.
     Close,
+    /// Cubic Bézier curve to absolute `(x, y)` coordinate.
+    CurveTo {
+        /// The control point at the start of the curve.
+        _start: (f64, f64),
+        /// The control point at the end of the curve.
+        _end: (f64, f64),
+        /// The final coordinate.
+        point: (f64, f64),
+    },
     /// Horizontal line to absolute x-coordinate.
     HorizontalTo(f64),
     /// Line to absolute `(x, y)` coordinate.
@@ -484,6 +493,7 @@ peg::parser! {grammar svg_path() for str {
     = arc()
     / arc_to()
     / close()
+    / curve_to()
     / horizontal_to()
     / line_to()
     / move()
@@ -491,37 +501,44 @@ peg::parser! {grammar svg_path() for str {
     / vertical_to()
 
     rule arc() -> Command
-    = "a" _ rx:number() "," ry:number() "," angle:number() "," large:bool() "," sweep:bool() "," x:number() "," y:number()
+    = "a" _ rx:number() sep() ry:number() sep() angle:number() sep() large:bool() sep() sweep:bool() sep() x:number() sep() y:number()
     { Command::Arc { _radius: (rx, ry), _angle: angle, _large: large, _sweep: sweep, point: (x, y) } }
 
     rule arc_to() -> Command
-    = "A" _ rx:number() "," ry:number() "," angle:number() "," large:bool() "," sweep:bool() "," x:number() "," y:number()
+    = "A" _ rx:number() sep() ry:number() sep() angle:number() sep() large:bool() sep() sweep:bool() sep() x:number() sep() y:number()
     { Command::ArcTo { _radius: (rx, ry), _angle: angle, _large: large, _sweep: sweep, point: (x, y) } }
 
     rule close() -> Command
     = ['Z'|'z'] _
     { Command::Close }
 
+    rule curve_to() -> Command
+    = "C" _ x1:number() sep() y1:number() sep() x2:number() sep() y2:number() sep() x:number() sep() y:number()
+    { Command::CurveTo { _start: (x1, y1), _end: (x2, y2), point: (x, y) } }
+
     rule horizontal_to() -> Command
     = "H" _ x:number()
     { Command::HorizontalTo(x) }
 
     rule line_to() -> Command
-    = "L" _ x:number() "," y:number()
+    = "L" _ x:number() sep() y:number()
     { Command::LineTo(x, y) }
 
     rule move() -> Command
-    = "m" _ x:number() "," y:number()
+    = "m" _ x:number() sep() y:number()
     { Command::Move(x, y) }
 
     rule move_to() -> Command
-    = "M" _ x:number() "," y:number()
+    = "M" _ x:number() sep() y:number()
     { Command::MoveTo(x, y) }
 
     rule vertical_to() -> Command
     = "V" _ y:number()
     { Command::VerticalTo(y) }
 
+    rule sep()
+    = _ ","? _
+
     rule _
     = [c if c.is_ascii_whitespace()]*
 
@@ -1,6 +1,7 @@
 //! Types and functions for the root of a visualisation.
 
 use super::{
+    Error, Result,
     axis::Axis,
     data::{Data, IgnoredAny},
     legend::Legend,
@@ -9,6 +10,8 @@ use super::{
     propset::StrokeDashArray,
     scale::Scale,
 };
+use core::fmt::Write as _;
+use serde_json::{Map, Value, json};
 use std::borrow::Cow;
 
 /// The root of a visualisation.
@@ -181,3 +184,926 @@ pub(super) struct Scene<'s> {
     #[serde(default)]
     pub stroke_width: Option<f64>,
 }
+
+/// The part of a spec needed to decide whether it must be normalised.
+#[derive(serde::Deserialize)]
+struct Header {
+    /// The spec version.
+    #[serde(default)]
+    version: Option<Value>,
+}
+
+/// Detects the version of a spec and, if it is a Vega 1 spec, rewrites it into
+/// the Vega 2 model used by the renderer. Unsupported versions are rejected.
+///
+/// The renderer implements Vega 2, which is what the Graph extension used for
+/// almost all graphs, so it is Vega 1 that gets translated. Only the version is
+/// read from a versioned Vega 2 spec; the full JSON tree is built only when the
+/// spec may need rewriting.
+///
+/// Specs without a `version` are treated as Vega 1 only if they use syntax
+/// which exists only in Vega 1. The Graph extension rendered unversioned specs
+/// using its default version (`$wgGraphDefaultVegaVer`), which was 2 on
+/// Wikimedia wikis, so most unversioned specs are Vega 2 and the rest are old
+/// graphs written for Vega 1.
+pub(super) fn normalize(spec: &str) -> Result<Cow<'_, str>> {
+    let version = serde_json::from_str::<Header>(spec)?
+        .version
+        .as_ref()
+        .and_then(Value::as_i64);
+
+    match version {
+        Some(1) | None => {}
+        Some(2) => return Ok(Cow::Borrowed(spec)),
+        Some(version) => {
+            return Err(Error::Version(i32::try_from(version).unwrap_or(i32::MAX)));
+        }
+    }
+
+    let mut value = serde_json::from_str::<Value>(spec)?;
+    Ok(if version.is_some() || is_vega1(&value) {
+        rewrite(&mut value);
+        // Some Vega 1 rules still apply to the rewritten spec
+        value["version"] = 1.into();
+        Cow::Owned(value.to_string())
+    } else {
+        Cow::Borrowed(spec)
+    })
+}
+
+/// Returns true if the given part of an unversioned spec uses Vega 1 syntax.
+fn is_vega1(value: &Value) -> bool {
+    match value {
+        Value::Array(items) => items.iter().any(is_vega1),
+        Value::Object(object) => object.iter().any(|(key, value)| match key.as_str() {
+            "values" => false,
+            "field" => is_vega1_field(value),
+            "test" | "expr" => value.as_str().is_some_and(|expr| expr.contains("d.data.")),
+            "transform" => {
+                value
+                    .as_array()
+                    .is_some_and(|transforms| transforms.iter().any(is_vega1_transform))
+                    || is_vega1(value)
+            }
+            _ => is_vega1(value),
+        }),
+        _ => false,
+    }
+}
+
+/// Returns true if the given field reference uses a Vega 1 `data.` field path.
+fn is_vega1_field(value: &Value) -> bool {
+    match value {
+        Value::String(field) => field.starts_with("data."),
+        Value::Array(items) => items.iter().any(is_vega1_field),
+        Value::Object(object) => object.values().any(is_vega1_field),
+        _ => false,
+    }
+}
+
+/// Returns true if the given transform uses a Vega 1 transform type or
+/// parameter.
+fn is_vega1_transform(transform: &Value) -> bool {
+    let has = |key: &str| transform.get(key).is_some();
+    match transform.get("type").and_then(Value::as_str) {
+        Some("array" | "copy" | "flatten" | "link" | "stats" | "unique" | "window" | "zip") => true,
+        Some("facet") => has("keys"),
+        Some("geopath" | "pie" | "treemap") => has("value"),
+        Some("stack") => has("point") || has("height"),
+        _ => false,
+    }
+}
+
+/// Rewrites the given part of a Vega 1 spec into Vega 2 syntax.
+///
+/// Vega 1 wraps every ingested data object as `{ data: object, index }`, so
+/// field paths start with `data.`. Vega 2 uses the objects directly, so this
+/// prefix is removed from every field reference.
+fn rewrite(value: &mut Value) {
+    match value {
+        Value::Array(items) => items.iter_mut().for_each(rewrite),
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                match key.as_str() {
+                    // Inline data is not part of the spec syntax
+                    "values" => {}
+                    "field" => rewrite_field_ref(value),
+                    "template" => {
+                        if let Value::String(template) = value {
+                            *template = template
+                                .replace("datum.data.", "datum.")
+                                .replace("{{data.", "{{datum.");
+                        }
+                    }
+                    "transform" => {
+                        if let Value::Array(transforms) = value {
+                            *transforms = rewrite_transforms(core::mem::take(transforms));
+                        }
+                    }
+                    _ => rewrite(value),
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Removes the Vega 1 `data.` prefix from a field name, keeping any leading
+/// `-` sort order prefix.
+fn field(name: &str) -> String {
+    if let Some(name) = name.strip_prefix('-') {
+        format!("-{}", field(name))
+    } else {
+        name.strip_prefix("data.").unwrap_or(name).to_owned()
+    }
+}
+
+/// Rewrites every field name in a field reference, list of field names, or
+/// group field reference.
+fn rewrite_field_ref(value: &mut Value) {
+    match value {
+        Value::String(name) => *name = field(name),
+        Value::Array(items) => items.iter_mut().for_each(rewrite_field_ref),
+        Value::Object(object) => object.values_mut().for_each(rewrite_field_ref),
+        _ => {}
+    }
+}
+
+/// Gets a string parameter from a transform.
+fn param<'a>(transform: &'a Map<String, Value>, key: &str) -> Option<&'a str> {
+    transform.get(key).and_then(Value::as_str)
+}
+
+/// Moves a transform parameter to a new name.
+fn rename(transform: &mut Map<String, Value>, from: &str, to: &str) {
+    if let Some(value) = transform.remove(from) {
+        transform.insert(to.into(), value);
+    }
+}
+
+/// Rewrites a Vega 1 transform pipeline into a Vega 2 transform pipeline.
+///
+/// In Vega 1, transforms following a facet operate on the values of each
+/// facet, except for `stack`, which operates across all facets, and `stats`,
+/// which summarises each facet. In Vega 2, these are expressed as nested facet
+/// transforms, a stack which precedes the facet, and facet summaries.
+fn rewrite_transforms(transforms: Vec<Value>) -> Vec<Value> {
+    let mut out = Vec::<Value>::with_capacity(transforms.len());
+    let mut facet = None;
+
+    for transform in transforms {
+        let Value::Object(mut transform) = transform else {
+            out.push(transform);
+            continue;
+        };
+
+        match param(&transform, "type") {
+            Some("facet") => {
+                rename(&mut transform, "keys", "groupby");
+                if transform.remove("sort").is_some() {
+                    log::warn!("Vega 1 facet sort is not supported");
+                }
+                rewrite_params(&mut transform);
+                facet = Some(out.len());
+                out.push(transform.into());
+            }
+            Some("stats") => {
+                let summarize = stats(&transform);
+                if let Some(facet) = facet {
+                    out[facet]["summarize"] = summarize;
+                } else {
+                    out.push(json!({ "type": "aggregate", "summarize": summarize }));
+                }
+            }
+            Some("stack") => {
+                let keys = facet.and_then(|facet| out[facet].get("groupby").cloned());
+                let stack = stack(&transform, keys.as_ref());
+                if let Some(facet) = &mut facet {
+                    out.insert(*facet, stack);
+                    *facet += 1;
+                } else {
+                    out.push(stack);
+                }
+            }
+            _ => {
+                let transforms = rewrite_transform(transform);
+                if let Some(facet) = facet {
+                    let nested = &mut out[facet]["transform"];
+                    if let Value::Array(nested) = nested {
+                        nested.extend(transforms);
+                    } else {
+                        *nested = transforms.into();
+                    }
+                } else {
+                    out.extend(transforms);
+                }
+            }
+        }
+    }
+
+    out
+}
+
+/// Rewrites a single Vega 1 transform into zero or more Vega 2 transforms.
+fn rewrite_transform(mut transform: Map<String, Value>) -> Vec<Value> {
+    let kind = param(&transform, "type").unwrap_or_default().to_owned();
+    match kind.as_str() {
+        "array" | "flatten" | "window" => {
+            log::warn!("Vega 1 {kind} transform is not supported");
+            return vec![];
+        }
+        "copy" => return copy(&transform),
+        "link" => return vec![link(&transform)],
+        "unique" => return unique(&transform),
+        "zip" => return zip(&transform),
+        "bin" => {
+            let output = transform
+                .get("output")
+                .and_then(|output| output.get("bin"))
+                .cloned()
+                .unwrap_or_else(|| "bin".into());
+            transform.insert("output".into(), json!({ "start": output }));
+        }
+        "cross" | "filter" | "formula" => {
+            for key in ["expr", "filter", "test"] {
+                if let Some(Value::String(expr)) = transform.get_mut(key) {
+                    *expr = rewrite_expr(expr);
+                }
+            }
+        }
+        "force" | "geo" => {
+            transform.insert("output".into(), json!({ "x": "x", "y": "y" }));
+        }
+        "geopath" => {
+            rename(&mut transform, "value", "field");
+            transform.insert("output".into(), json!({ "path": "path" }));
+        }
+        "pie" => {
+            rename(&mut transform, "value", "field");
+            transform.insert(
+                "output".into(),
+                json!({ "start": "startAngle", "end": "endAngle", "mid": "midAngle" }),
+            );
+        }
+        "treemap" => {
+            rename(&mut transform, "value", "field");
+            transform.insert(
+                "output".into(),
+                json!({
+                    "x": "x",
+                    "y": "y",
+                    "width": "width",
+                    "height": "height",
+                    "depth": "depth",
+                }),
+            );
+        }
+        "wordcloud" => wordcloud(&mut transform),
+        _ => {}
+    }
+
+    rewrite_params(&mut transform);
+    vec![transform.into()]
+}
+
+/// Rewrites the field name parameters of a transform.
+fn rewrite_params(transform: &mut Map<String, Value>) {
+    for key in [
+        "by",
+        "charge",
+        "field",
+        "fields",
+        "groupby",
+        "lat",
+        "linkDistance",
+        "linkStrength",
+        "lon",
+    ] {
+        if let Some(value) = transform.get_mut(key) {
+            rewrite_field_ref(value);
+        }
+    }
+}
+
+/// Converts a Vega 1 stats transform into a Vega 2 summary.
+fn stats(transform: &Map<String, Value>) -> Value {
+    if transform.get("assign").and_then(Value::as_bool) == Some(true) {
+        log::warn!("Vega 1 stats assign is not supported");
+    }
+
+    let mut ops = vec!["count", "min", "max", "sum", "mean", "variance", "stdev"];
+    if transform.get("median").and_then(Value::as_bool) == Some(true) {
+        ops.push("median");
+    }
+
+    let value = field(param(transform, "value").unwrap_or("data"));
+    json!([{ "field": value, "ops": ops, "as": ops }])
+}
+
+/// Converts a Vega 1 stack transform, which follows a facet with the given
+/// group `keys`, into a Vega 2 stack transform.
+fn stack(transform: &Map<String, Value>, keys: Option<&Value>) -> Value {
+    let keys = keys
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str);
+    let sortby = match param(transform, "order") {
+        Some("reverse") => keys.map(|key| format!("-{key}")).collect::<Vec<_>>(),
+        Some("inside-out") => {
+            log::warn!("Vega 1 stack order 'inside-out' is not supported");
+            vec![]
+        }
+        _ => vec![],
+    };
+
+    let offset = match param(transform, "offset") {
+        Some("silhouette") => "center",
+        Some("expand") => "normalize",
+        Some("wiggle") => {
+            log::warn!("Vega 1 stack offset 'wiggle' is not supported");
+            "zero"
+        }
+        _ => "zero",
+    };
+
+    json!({
+        "type": "stack",
+        "groupby": [field(param(transform, "point").unwrap_or_default())],
+        "field": field(param(transform, "height").unwrap_or_default()),
+        "sortby": sortby,
+        "offset": offset,
+        "output": { "start": "y2", "end": "y", "mid": "cy" },
+    })
+}
+
+/// Converts a Vega 1 copy transform into Vega 2 formula transforms.
+fn copy(transform: &Map<String, Value>) -> Vec<Value> {
+    let from = param(transform, "from").unwrap_or_default();
+    let from = if from == "data" {
+        ""
+    } else {
+        from.strip_prefix("data.").unwrap_or(from)
+    };
+    let names = |key: &str| {
+        transform
+            .get(key)
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+    };
+
+    let mut outputs = names("as");
+    names("fields")
+        .filter_map(|name| {
+            let output = outputs.next().unwrap_or(name);
+            let path = from
+                .split('.')
+                .filter(|part| !part.is_empty())
+                .chain([name])
+                .fold(String::new(), |mut path, part| {
+                    let _ = write!(path, "[{}]", Value::from(part));
+                    path
+                });
+            (!from.is_empty() || output != name).then(
+                || json!({ "type": "formula", "field": output, "expr": format!("datum{path}") }),
+            )
+        })
+        .collect()
+}
+
+/// Converts a Vega 1 link transform into a Vega 2 link path transform.
+fn link(transform: &Map<String, Value>) -> Value {
+    let source = field(param(transform, "source").unwrap_or("source"));
+    let target = field(param(transform, "target").unwrap_or("target"));
+    let shape = match param(transform, "shape") {
+        Some("curve") => "curve",
+        Some("diagonal" | "diagonal-horizontal") => "diagonalX",
+        Some("diagonal-vertical") => "diagonalY",
+        _ => "line",
+    };
+
+    let mut out = json!({
+        "type": "linkpath",
+        "sourceX": format!("{source}.x"),
+        "sourceY": format!("{source}.y"),
+        "targetX": format!("{target}.x"),
+        "targetY": format!("{target}.y"),
+        "shape": shape,
+        "output": { "path": "path" },
+    });
+    if let Some(tension) = transform.get("tension") {
+        out["tension"] = tension.clone();
+    }
+    out
+}
+
+/// Converts a Vega 1 unique transform into Vega 2 aggregate and formula
+/// transforms.
+fn unique(transform: &Map<String, Value>) -> Vec<Value> {
+    let name = field(param(transform, "field").unwrap_or("data"));
+    let output = param(transform, "as").unwrap_or("unique");
+    vec![
+        json!({ "type": "aggregate", "groupby": [name] }),
+        json!({
+            "type": "formula",
+            "field": output,
+            "expr": format!("datum[{}]", Value::from(name)),
+        }),
+    ]
+}
+
+/// Converts a Vega 1 zip transform into a Vega 2 lookup transform.
+///
+/// Without keys, Vega 1 zips by index, which Vega 2 can only do by adding an
+/// index field first.
+fn zip(transform: &Map<String, Value>) -> Vec<Value> {
+    let mut lookup = json!({
+        "type": "lookup",
+        "on": transform.get("with").cloned().unwrap_or_default(),
+        "as": [param(transform, "as").unwrap_or("zip")],
+        "default": transform.get("default").cloned().unwrap_or_default(),
+    });
+
+    if let Some(key) = param(transform, "key") {
+        lookup["keys"] = json!([field(key)]);
+        if let Some(with_key) = param(transform, "withKey") {
+            lookup["onKey"] = field(with_key).into();
+        }
+        vec![lookup]
+    } else {
+        lookup["keys"] = json!(["index"]);
+        vec![
+            json!({ "type": "rank", "output": { "rank": "index" } }),
+            json!({ "type": "formula", "field": "index", "expr": "datum.index - 1" }),
+            lookup,
+        ]
+    }
+}
+
+/// Rewrites the parameters of a Vega 1 word cloud transform, which may be
+/// either field names or literal values, into Vega 2 field references.
+fn wordcloud(transform: &mut Map<String, Value>) {
+    for key in [
+        "font",
+        "fontSize",
+        "fontStyle",
+        "fontWeight",
+        "padding",
+        "rotate",
+        "text",
+    ] {
+        let Some(value) = transform.get_mut(key) else {
+            continue;
+        };
+
+        match value {
+            Value::String(name) if key == "text" || name.starts_with("data.") => {
+                *value = json!({ "field": field(name) });
+            }
+            Value::String(_) | Value::Number(_) => {
+                *value = json!({ "value": value.take() });
+            }
+            Value::Object(object) if object.contains_key("field") => {
+                rewrite_field_ref(value);
+            }
+            _ => {
+                log::warn!("Vega 1 wordcloud {key} {value} is not supported");
+                transform.remove(key);
+            }
+        }
+    }
+
+    transform.insert(
+        "output".into(),
+        json!({
+            "x": "x",
+            "y": "y",
+            "font": "font",
+            "fontSize": "fontSize",
+            "fontStyle": "fontStyle",
+            "fontWeight": "fontWeight",
+            "rotate": "angle",
+        }),
+    );
+}
+
+/// Rewrites a Vega 1 expression into a Vega 2 expression.
+///
+/// Vega 1 expressions refer to the data object as `d` and wrap data objects in
+/// a `data` property, and allow the `Math.` prefix on maths functions.
+fn rewrite_expr(expr: &str) -> String {
+    /// Returns true if the character can be part of an identifier.
+    fn is_ident(c: char) -> bool {
+        c.is_ascii_alphanumeric() || c == '_' || c == '$'
+    }
+
+    let mut out = String::with_capacity(expr.len());
+    let mut quote = None;
+    let mut index = 0;
+    while let Some(c) = expr[index..].chars().next() {
+        let rest = &expr[index..];
+        if let Some(q) = quote {
+            let len = if c == '\\' {
+                rest.chars().take(2).map(char::len_utf8).sum()
+            } else {
+                if c == q {
+                    quote = None;
+                }
+                c.len_utf8()
+            };
+            out.push_str(&rest[..len]);
+            index += len;
+        } else if c == '"' || c == '\'' {
+            quote = Some(c);
+            out.push(c);
+            index += 1;
+        } else if is_ident(c) && !c.is_ascii_digit() {
+            let len = rest.find(|c: char| !is_ident(c)).unwrap_or(rest.len());
+            let (ident, after) = rest.split_at(len);
+            index += len;
+            if out.ends_with('.') || !after.starts_with('.') {
+                out.push_str(ident);
+            } else if ident == "d" || ident == "datum" {
+                out.push_str("datum.");
+                index += if after.starts_with(".data.") { 6 } else { 1 };
+            } else if ident == "Math" {
+                index += 1;
+            } else {
+                out.push_str(ident);
+            }
+        } else if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !is_ident(c) && c != '.')
+                .unwrap_or(rest.len());
+            out.push_str(&rest[..len]);
+            index += len;
+        } else {
+            out.push(c);
+            index += c.len_utf8();
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Rewrites a Vega 1 transform pipeline given as JSON.
+    fn transforms(transforms: Value) -> Value {
+        let Value::Array(transforms) = transforms else {
+            unreachable!()
+        };
+        rewrite_transforms(transforms).into()
+    }
+
+    #[test]
+    fn detect_field() {
+        assert!(is_vega1(&json!({ "x": { "field": "data.x" } })));
+        assert!(!is_vega1(&json!({ "x": { "field": "x" } })));
+    }
+
+    #[test]
+    fn detect_expr() {
+        assert!(is_vega1(&json!({ "test": "d.data.y > 1" })));
+        assert!(!is_vega1(&json!({ "test": "datum.y > 1" })));
+        assert!(is_vega1(&json!({ "expr": "d.data.y * 2" })));
+        assert!(!is_vega1(&json!({ "expr": "datum.y * 2" })));
+    }
+
+    #[test]
+    fn detect_transform_type() {
+        assert!(is_vega1(&json!({ "transform": [{ "type": "stats" }] })));
+        assert!(!is_vega1(
+            &json!({ "transform": [{ "type": "aggregate" }] })
+        ));
+    }
+
+    #[test]
+    fn detect_facet_keys() {
+        let vega1 = json!({ "transform": [{ "type": "facet", "keys": ["c"] }] });
+        let vega2 = json!({ "transform": [{ "type": "facet", "groupby": ["c"] }] });
+        assert!(is_vega1(&vega1));
+        assert!(!is_vega1(&vega2));
+    }
+
+    #[test]
+    fn detect_value_param() {
+        let vega1 = json!({ "transform": [{ "type": "pie", "value": "v" }] });
+        let vega2 = json!({ "transform": [{ "type": "pie", "field": "v" }] });
+        assert!(is_vega1(&vega1));
+        assert!(!is_vega1(&vega2));
+    }
+
+    #[test]
+    fn detect_stack_params() {
+        let point = json!({ "transform": [{ "type": "stack", "point": "x" }] });
+        let height = json!({ "transform": [{ "type": "stack", "height": "y" }] });
+        let vega2 = json!({ "transform": [{ "type": "stack", "groupby": ["x"], "field": "y" }] });
+        assert!(is_vega1(&point));
+        assert!(is_vega1(&height));
+        assert!(!is_vega1(&vega2));
+    }
+
+    #[test]
+    fn detect_ignores_values() {
+        assert!(!is_vega1(&json!({ "values": [{ "field": "data.x" }] })));
+        assert!(is_vega1(&json!({ "other": [{ "field": "data.x" }] })));
+    }
+
+    #[test]
+    fn normalize_version() {
+        let vega2 = r#"{"version":2,"marks":[{"field":"data.x"}]}"#;
+        assert!(matches!(normalize(vega2), Ok(Cow::Borrowed(spec)) if spec == vega2));
+
+        let unversioned = r#"{"marks":[{"field":"x"}]}"#;
+        assert!(matches!(normalize(unversioned), Ok(Cow::Borrowed(_))));
+
+        let vega1 = r#"{"version":1,"marks":[{"field":"x.y"}]}"#;
+        let Ok(Cow::Owned(spec)) = normalize(vega1) else {
+            panic!("Vega 1 spec was not rewritten");
+        };
+        assert_eq!(
+            serde_json::from_str::<Value>(&spec).unwrap(),
+            json!({ "version": 1, "marks": [{ "field": "x.y" }] })
+        );
+
+        let unversioned = r#"{"marks":[{"field":"data.x"}]}"#;
+        let Ok(Cow::Owned(spec)) = normalize(unversioned) else {
+            panic!("unversioned Vega 1 spec was not rewritten");
+        };
+        assert_eq!(
+            serde_json::from_str::<Value>(&spec).unwrap(),
+            json!({ "version": 1, "marks": [{ "field": "x" }] })
+        );
+
+        assert!(matches!(
+            normalize(r#"{"version":3}"#),
+            Err(Error::Version(3))
+        ));
+        assert!(normalize("{").is_err());
+    }
+
+    #[test]
+    fn field_prefix() {
+        assert_eq!(field("data.x"), "x");
+        assert_eq!(field("-data.x"), "-x");
+        assert_eq!(field("data"), "data");
+        assert_eq!(field("a.data.x"), "a.data.x");
+
+        let mut value = json!({
+            "field": { "group": "data.x", "parent": ["data.y", "z"] },
+            "values": [{ "field": "data.x" }],
+        });
+        rewrite(&mut value);
+        assert_eq!(
+            value,
+            json!({
+                "field": { "group": "x", "parent": ["y", "z"] },
+                "values": [{ "field": "data.x" }],
+            })
+        );
+    }
+
+    #[test]
+    fn template() {
+        let mut value = json!({ "text": { "template": "{{datum.data.a}} {{data.b}}" } });
+        rewrite(&mut value);
+        assert_eq!(
+            value,
+            json!({ "text": { "template": "{{datum.a}} {{datum.b}}" } })
+        );
+    }
+
+    #[test]
+    fn expr() {
+        assert_eq!(
+            rewrite_expr("d.data.y > 20 && Math.round(d.x) == 'd.data.y'"),
+            "datum.y > 20 && round(datum.x) == 'd.data.y'"
+        );
+        assert_eq!(rewrite_expr("datum.data.a + a.d.b"), "datum.a + a.d.b");
+        assert_eq!(rewrite_expr("d.data.a * 1.5e3"), "datum.a * 1.5e3");
+    }
+
+    #[test]
+    fn facet_nesting() {
+        assert_eq!(
+            transforms(json!([
+                { "type": "facet", "keys": ["data.c"] },
+                { "type": "filter", "test": "d.data.y > 1" },
+                { "type": "formula", "field": "z", "expr": "d.data.y * 2" },
+            ])),
+            json!([{
+                "type": "facet",
+                "groupby": ["c"],
+                "transform": [
+                    { "type": "filter", "test": "datum.y > 1" },
+                    { "type": "formula", "field": "z", "expr": "datum.y * 2" },
+                ],
+            }])
+        );
+    }
+
+    #[test]
+    fn stats() {
+        let ops = ["count", "min", "max", "sum", "mean", "variance", "stdev"];
+        assert_eq!(
+            transforms(json!([{ "type": "stats", "value": "data.y" }])),
+            json!([{
+                "type": "aggregate",
+                "summarize": [{ "field": "y", "ops": ops, "as": ops }],
+            }])
+        );
+
+        let ops = [
+            "count", "min", "max", "sum", "mean", "variance", "stdev", "median",
+        ];
+        assert_eq!(
+            transforms(json!([
+                { "type": "facet", "keys": ["data.x"] },
+                { "type": "stats", "value": "data.y", "median": true },
+            ])),
+            json!([{
+                "type": "facet",
+                "groupby": ["x"],
+                "summarize": [{ "field": "y", "ops": ops, "as": ops }],
+            }])
+        );
+    }
+
+    #[test]
+    fn stack() {
+        let output = json!({ "start": "y2", "end": "y", "mid": "cy" });
+        assert_eq!(
+            transforms(json!([
+                { "type": "facet", "keys": ["data.c"] },
+                {
+                    "type": "stack",
+                    "point": "data.x",
+                    "height": "data.y",
+                    "order": "reverse",
+                    "offset": "expand",
+                },
+            ])),
+            json!([
+                {
+                    "type": "stack",
+                    "groupby": ["x"],
+                    "field": "y",
+                    "sortby": ["-c"],
+                    "offset": "normalize",
+                    "output": output,
+                },
+                { "type": "facet", "groupby": ["c"] },
+            ])
+        );
+
+        assert_eq!(
+            transforms(json!([{ "type": "stack", "point": "data.x", "height": "data.y" }])),
+            json!([{
+                "type": "stack",
+                "groupby": ["x"],
+                "field": "y",
+                "sortby": [],
+                "offset": "zero",
+                "output": output,
+            }])
+        );
+    }
+
+    #[test]
+    fn copy() {
+        assert_eq!(
+            transforms(json!([{
+                "type": "copy",
+                "from": "data.a",
+                "fields": ["x", "y"],
+                "as": ["x", "b"],
+            }])),
+            json!([
+                { "type": "formula", "field": "x", "expr": r#"datum["a"]["x"]"# },
+                { "type": "formula", "field": "b", "expr": r#"datum["a"]["y"]"# },
+            ])
+        );
+
+        assert_eq!(
+            transforms(json!([{
+                "type": "copy",
+                "from": "data",
+                "fields": ["x", "y"],
+                "as": ["x", "z"],
+            }])),
+            json!([{ "type": "formula", "field": "z", "expr": r#"datum["y"]"# }])
+        );
+    }
+
+    #[test]
+    fn link() {
+        assert_eq!(
+            transforms(json!([{ "type": "link", "shape": "diagonal", "tension": 0.5 }])),
+            json!([{
+                "type": "linkpath",
+                "sourceX": "source.x",
+                "sourceY": "source.y",
+                "targetX": "target.x",
+                "targetY": "target.y",
+                "shape": "diagonalX",
+                "tension": 0.5,
+                "output": { "path": "path" },
+            }])
+        );
+    }
+
+    #[test]
+    fn unique() {
+        assert_eq!(
+            transforms(json!([{ "type": "unique", "field": "data.k", "as": "u" }])),
+            json!([
+                { "type": "aggregate", "groupby": ["k"] },
+                { "type": "formula", "field": "u", "expr": r#"datum["k"]"# },
+            ])
+        );
+    }
+
+    #[test]
+    fn zip() {
+        assert_eq!(
+            transforms(json!([{
+                "type": "zip",
+                "with": "other",
+                "as": "o",
+                "key": "data.a",
+                "withKey": "data.b",
+            }])),
+            json!([{
+                "type": "lookup",
+                "on": "other",
+                "as": ["o"],
+                "default": null,
+                "keys": ["a"],
+                "onKey": "b",
+            }])
+        );
+
+        assert_eq!(
+            transforms(json!([{ "type": "zip", "with": "other" }])),
+            json!([
+                { "type": "rank", "output": { "rank": "index" } },
+                { "type": "formula", "field": "index", "expr": "datum.index - 1" },
+                {
+                    "type": "lookup",
+                    "on": "other",
+                    "as": ["zip"],
+                    "default": null,
+                    "keys": ["index"],
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn wordcloud() {
+        assert_eq!(
+            transforms(json!([{
+                "type": "wordcloud",
+                "text": "data.t",
+                "font": "Arial",
+                "fontSize": "data.s",
+                "rotate": { "field": "data.r" },
+                "padding": 1,
+                "fontStyle": [],
+            }])),
+            json!([{
+                "type": "wordcloud",
+                "text": { "field": "t" },
+                "font": { "value": "Arial" },
+                "fontSize": { "field": "s" },
+                "rotate": { "field": "r" },
+                "padding": { "value": 1 },
+                "output": {
+                    "x": "x",
+                    "y": "y",
+                    "font": "font",
+                    "fontSize": "fontSize",
+                    "fontStyle": "fontStyle",
+                    "fontWeight": "fontWeight",
+                    "rotate": "angle",
+                },
+            }])
+        );
+    }
+
+    #[test]
+    fn value_params() {
+        assert_eq!(
+            transforms(json!([{ "type": "pie", "value": "data.v" }])),
+            json!([{
+                "type": "pie",
+                "field": "v",
+                "output": { "start": "startAngle", "end": "endAngle", "mid": "midAngle" },
+            }])
+        );
+    }
+}
@@ -1,5 +1,10 @@
-use super::spec_to_svg as render;
-use crate::php::{DateTime, DateTimeZone};
+use super::{Loader, spec_to_svg as render};
+use crate::{
+    db::Article,
+    php::{DateTime, DateTimeZone},
+    title::Title,
+};
+use std::sync::Arc;
 
 const BASE_DIR: &str = "./src/renderer/extension_tags/graph/tests";
 
@@ -15,11 +20,47 @@ macro_rules! run_tests {
     }
 }
 
-#[track_caller]
-fn run_test(test_name: &str, input: &str) {
-    use std::io::Write as _;
+struct TestLoader;
+
+impl TestLoader {
+    fn article(title: &Title, model: &str, body: &str) -> Arc<Article> {
+        Arc::new(Article {
+            id: 1,
+            title: title.key().to_owned(),
+            body: body.to_owned(),
+            date: None,
+            model: model.to_owned(),
+            redirect: None,
+        })
+    }
+}
+
+impl Loader for TestLoader {
+    fn page(&self, title: &Title) -> Option<Arc<Article>> {
+        match title.key() {
+            "Wiki-rs/Population.csv" => Some(Self::article(
+                title,
+                "wikitext",
+                include_str!("./tests/pages/population.csv"),
+            )),
+            _ => None,
+        }
+    }
+
+    fn shared_page(&self, title: &Title) -> Option<Arc<Article>> {
+        match title.key() {
+            "Data:Wiki-rs population.tab" => Some(Self::article(
+                title,
+                "Tabular.JsonConfig",
+                include_str!("./tests/pages/population.tab.json"),
+            )),
+            _ => None,
+        }
+    }
+}
 
-    let now = DateTime::from_parts(
+fn now() -> DateTime {
+    DateTime::from_parts(
         2000,
         Some(1),
         Some(1),
@@ -29,11 +70,16 @@ fn run_test(test_name: &str, input: &str) {
         Some(0),
         Some(&DateTimeZone::UTC),
     )
-    .unwrap();
+    .unwrap()
+}
+
+#[track_caller]
+fn run_test(test_name: &str, input: &str) {
+    use std::io::Write as _;
 
     let mut mint = goldenfile::Mint::new(format!("{BASE_DIR}/goldenfiles"));
     let mut file = mint.new_goldenfile(format!("{test_name}.svg")).unwrap();
-    let result = render(input, now).unwrap();
+    let result = render(input, now(), &TestLoader).unwrap();
     let _ = writeln!(file, "{result}");
 }
 
@@ -45,22 +91,78 @@ run_tests! {
     driving,
     error,
     falkensee,
+    fold,
     force,
     grouped_bar,
+    hierarchy,
     historical_population,
+    hover,
     image,
+    impute,
     jobs,
     lifelines,
     map,
     parallel_coords,
     playfair,
     population,
+    rank,
+    rtl_labels,
     scatter_matrix,
+    sort,
     stacked_area,
     stacked_bar,
+    tabular,
+    treeify,
     treemap,
+    vega1_arc,
+    vega1_bar,
+    vega1_stacked_area,
+    voronoi,
     weather,
     wiki_plastic_by_polymer,
     wiki_plastic_by_sector,
+    wikiraw_csv,
     wordcloud,
 }
+
+#[test]
+fn tooltip() {
+    let spec = r#"{
+        "data": [{ "name": "table", "values": [{ "x": 1, "label": "a<b" }, { "x": 2, "label": "" }] }],
+        "marks": [
+            {
+                "type": "rect",
+                "from": { "data": "table" },
+                "properties": { "enter": {
+                    "x": { "field": "x" },
+                    "width": { "value": 1 },
+                    "height": { "value": 1 },
+                    "tooltip": { "template": "{{datum.x}}: {{datum.label}}" }
+                } }
+            },
+            {
+                "type": "text",
+                "from": { "data": "table" },
+                "properties": { "enter": {
+                    "text": { "value": "t" },
+                    "tooltip": { "field": "label" }
+                } }
+            },
+            {
+                "type": "text",
+                "from": { "data": "table" },
+                "properties": { "enter": { "text": { "value": "u" } } }
+            }
+        ]
+    }"#;
+
+    let result = render(spec, now(), &TestLoader).unwrap();
+    assert!(result.contains("<title>1: a&lt;b</title>"));
+    assert!(result.contains("<title>2: </title>"));
+
+    // Empty tooltips are not drawn, and text with a tooltip has to receive
+    // pointer events for the tooltip to be shown
+    assert_eq!(result.matches("<title>a&lt;b</title>t</text>").count(), 1);
+    assert_eq!(result.matches("<title>").count(), 3);
+    assert_eq!(result.matches("pointer-events").count(), 2);
+}
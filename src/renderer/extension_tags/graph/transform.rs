@@ -49,7 +49,8 @@ pub(super) enum Transform<'s> {
     /// Folds one or more data properties into two: a key property containing
     /// the original data property name, and a value property containing the
     /// data value.
-    Fold(Todo),
+    #[serde(borrow)]
+    Fold(Fold<'s>),
     /// Performs force-directed layout for network data.
     #[serde(borrow)]
     Force(Force<'s>),
@@ -65,9 +66,11 @@ pub(super) enum Transform<'s> {
     #[serde(borrow)]
     Geopath(Geopath<'s>),
     /// Computes tidy, cluster, and partition layouts.
-    Hierarchy(Todo),
+    #[serde(borrow)]
+    Hierarchy(Hierarchy<'s>),
     /// Performs imputation of missing values.
-    Impute(Todo),
+    #[serde(borrow)]
+    Impute(Impute<'s>),
     /// Computes a path definition for connecting nodes within a node-link
     /// network or tree diagram.
     #[serde(borrow)]
@@ -88,21 +91,25 @@ pub(super) enum Transform<'s> {
     Pie(Pie<'s>),
     /// Computes an ascending rank score for data tuples based on their observed
     /// order and any key fields.
-    Rank(Todo),
+    #[serde(borrow)]
+    Rank(Rank<'s>),
     /// Sorts the values of a data set.
-    Sort(Todo),
+    #[serde(borrow)]
+    Sort(Sort<'s>),
     /// Computes layout values for stacked graphs, as in stacked bar charts or
     /// stream graphs.
     #[serde(borrow)]
     Stack(Stack<'s>),
     /// Computes a tree structure over a flat tabular dataset.
-    Treeify(Todo),
+    #[serde(borrow)]
+    Treeify(Treeify<'s>),
     /// Computes a squarified treemap layout.
     #[serde(borrow)]
     Treemap(Treemap<'s>),
     /// Computes a voronoi diagram for a set of input seed points and returns
     /// the computed cell paths.
-    Voronoi(Todo),
+    #[serde(borrow)]
+    Voronoi(Voronoi<'s>),
     /// Computes a word cloud layout, similar to Wordle (not that Wordle, the
     /// other Wordle).
     #[serde(borrow)]
@@ -120,22 +127,22 @@ impl<'s> Transform<'s> {
             Transform::Cross(cross) => cross.transform(node, &data),
             Transform::Facet(facet) => facet.transform(node, &data),
             Transform::Filter(filter) => filter.transform(node, data.into_owned()),
-            Transform::Fold(_) => todo!(),
+            Transform::Fold(fold) => fold.transform(&data),
             Transform::Force(force) => force.transform(node, data.into_owned()),
             Transform::Formula(formula) => formula.transform(node, data.into_owned()),
             Transform::Geo(geo) => geo.transform(data.into_owned()),
             Transform::Geopath(geopath) => geopath.transform(data.into_owned()),
-            Transform::Hierarchy(_) => todo!(),
-            Transform::Impute(_) => todo!(),
+            Transform::Hierarchy(hierarchy) => hierarchy.transform(node, data.into_owned()),
+            Transform::Impute(impute) => impute.transform(data.into_owned()),
             Transform::LinkPath(link_path) => link_path.transform(data.into_owned()),
             Transform::Lookup(lookup) => lookup.transform(node, data.into_owned()),
             Transform::Pie(pie) => pie.transform(data.into_owned()),
-            Transform::Rank(_) => todo!(),
-            Transform::Sort(_) => todo!(),
+            Transform::Rank(rank) => rank.transform(data.into_owned()),
+            Transform::Sort(sort) => sort.transform(data.into_owned()),
             Transform::Stack(stack) => stack.transform(data.into_owned()),
-            Transform::Treeify(_) => todo!(),
+            Transform::Treeify(treeify) => treeify.transform(data.into_owned()),
             Transform::Treemap(treemap) => treemap.transform(node, data.into_owned()),
-            Transform::Voronoi(_) => todo!(),
+            Transform::Voronoi(voronoi) => voronoi.transform(data.into_owned()),
             Transform::Wordcloud(wordcloud) => wordcloud.transform(node, data.into_owned()),
         }
     }
@@ -1281,6 +1288,67 @@ impl<'s> Filter<'s> {
     }
 }
 
+/// A data property folding transformer.
+#[derive(Debug, serde::Deserialize)]
+pub(super) struct Fold<'s> {
+    /// A list of fields to fold into key-value pairs.
+    #[serde(borrow, deserialize_with = "super::data::vec_or_str")]
+    fields: Vec<Cow<'s, str>>,
+    /// The output field names.
+    #[serde(borrow, default)]
+    output: FoldOutput<'s>,
+}
+
+impl<'s> Fold<'s> {
+    /// Transforms the input data into one item for each folded field of each
+    /// original item.
+    fn transform(&self, data: &[Value<'s>]) -> Vec<Value<'s>> {
+        let mut out = Vec::with_capacity(data.len() * self.fields.len());
+        for item in data {
+            for field in &self.fields {
+                let value = get_nested_value(item, field).cloned().unwrap_or_default();
+                let mut item = item.clone();
+                item.insert(self.output.key.clone(), Value::Str(field.clone()));
+                item.insert(self.output.value.clone(), value);
+                out.push(item);
+            }
+        }
+        out
+    }
+}
+
+/// Fold output field names.
+#[derive(Debug, serde::Deserialize)]
+struct FoldOutput<'s> {
+    /// The name of the field containing the original field name.
+    #[serde(borrow, default = "FoldOutput::default_key")]
+    key: Cow<'s, str>,
+    /// The name of the field containing the original field value.
+    #[serde(borrow, default = "FoldOutput::default_value")]
+    value: Cow<'s, str>,
+}
+
+impl FoldOutput<'_> {
+    /// The default value for [`Self::key`].
+    const fn default_key() -> Cow<'static, str> {
+        Cow::Borrowed("key")
+    }
+
+    /// The default value for [`Self::value`].
+    const fn default_value() -> Cow<'static, str> {
+        Cow::Borrowed("value")
+    }
+}
+
+impl Default for FoldOutput<'_> {
+    fn default() -> Self {
+        Self {
+            key: Self::default_key(),
+            value: Self::default_value(),
+        }
+    }
+}
+
 /// A Vega expression transformer.
 #[derive(Debug, serde::Deserialize)]
 pub(super) struct Formula<'s> {
@@ -1372,7 +1440,7 @@ impl<'s> Geopath<'s> {
     }
 }
 
-/// Geopath output field names.
+/// Geopath and voronoi output field names.
 #[derive(Debug, serde::Deserialize)]
 struct GeopathOutput<'s> {
     /// The name of the path field.
@@ -1395,6 +1463,258 @@ impl Default for GeopathOutput<'_> {
     }
 }
 
+/// A node-link tree and adjacency diagram layout transformer.
+#[derive(Debug, serde::Deserialize)]
+pub(super) struct Hierarchy<'s> {
+    /// The name of the field containing the child nodes.
+    #[serde(borrow, default = "Treemap::default_children")]
+    children: Cow<'s, str>,
+    /// The values to use to determine the size of each leaf-level partition
+    /// cell.
+    #[serde(borrow, default)]
+    field: Option<Cow<'s, str>>,
+    /// The layout mode to use.
+    #[serde(default)]
+    mode: HierarchyMode,
+    /// The width and height of each node. If specified, this overrides
+    /// [`Self::size`] and the layout is instead sized to fit the tree.
+    #[serde(default, rename = "nodesize")]
+    node_size: Option<[f64; 2]>,
+    /// The layout orientation.
+    #[serde(default)]
+    orient: HierarchyOrient,
+    /// The output field names.
+    #[serde(borrow, default)]
+    output: TreemapOutput<'s>,
+    /// The name of the field containing the parent node.
+    #[serde(borrow, default = "Treemap::default_parent")]
+    parent: Cow<'s, str>,
+    /// The width and height of the layout. Defaults to the width and height of
+    /// the enclosing data rectangle or group.
+    #[serde(default)]
+    size: Option<[f64; 2]>,
+    /// A list of fields to use as sort criteria for sibling nodes.
+    #[serde(borrow, default, deserialize_with = "super::data::vec_or_str")]
+    sort: Vec<Cow<'s, str>>,
+}
+
+impl<'s> Hierarchy<'s> {
+    /// Transforms the input data to add extra fields.
+    fn transform(&self, node: &Node<'s, '_>, mut data: Vec<Value<'s>>) -> Vec<Value<'s>> {
+        let has_root = data.iter().any(|item| {
+            item.as_object()
+                .and_then(|item| item.get(&self.parent))
+                .is_none_or(Value::is_null)
+        });
+
+        if !has_root {
+            return data;
+        }
+
+        let mut nodes = treemap::hierarchy(
+            &data,
+            &self.children,
+            self.field.as_deref().unwrap_or_default(),
+            &self.sort,
+        );
+
+        let radial = matches!(self.orient, HierarchyOrient::Radial);
+        let (size, fixed) = if let Some(node_size) = self.node_size {
+            (node_size, true)
+        } else {
+            let size = self.size.unwrap_or_else(|| [node.width(), node.height()]);
+            (size, false)
+        };
+
+        match self.mode {
+            HierarchyMode::Cluster => tree::cluster(&mut nodes, size, fixed, radial),
+            HierarchyMode::Partition => tree::partition(&mut nodes, size),
+            HierarchyMode::Tidy => tree::tidy(&mut nodes, size, fixed, radial),
+        }
+
+        let partition = matches!(self.mode, HierarchyMode::Partition);
+        for node in &nodes {
+            let item = &mut data[node.object];
+            item.insert(self.output.depth.clone(), f64::from(node.depth));
+            item.insert(self.output.x.clone(), node.rect.left);
+            item.insert(self.output.y.clone(), node.rect.top);
+            item.insert("depth", f64::from(node.depth));
+            item.insert("x", node.rect.left);
+            item.insert("y", node.rect.top);
+            if partition {
+                item.insert(self.output.height.clone(), node.rect.height());
+                item.insert(self.output.width.clone(), node.rect.width());
+                item.insert("dy", node.rect.height());
+                item.insert("dx", node.rect.width());
+            }
+        }
+
+        data
+    }
+}
+
+/// Hierarchy layout mode.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum HierarchyMode {
+    /// A dendrogram, with all leaf nodes at the same depth.
+    Cluster,
+    /// An adjacency diagram, with nodes sized by their value.
+    Partition,
+    /// A tidy node-link tree.
+    #[default]
+    Tidy,
+}
+
+/// Hierarchy layout orientation.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum HierarchyOrient {
+    /// The x-coordinate is a breadth and the y-coordinate is a depth.
+    #[default]
+    Cartesian,
+    /// The x-coordinate is an angle and the y-coordinate is a radius, so
+    /// sibling separation is scaled down by depth.
+    Radial,
+}
+
+/// A missing value imputation transformer.
+#[derive(Debug, serde::Deserialize)]
+#[serde(bound = "'s: 'de")]
+pub(super) struct Impute<'s> {
+    /// The data field for which missing values should be imputed.
+    #[serde(borrow)]
+    field: Cow<'s, str>,
+    /// A list of fields to partition the data into groups. Missing values are
+    /// imputed separately for each group.
+    #[serde(
+        borrow,
+        rename = "groupby",
+        deserialize_with = "super::data::vec_or_str"
+    )]
+    group_by: Vec<Cow<'s, str>>,
+    /// The imputation method to use.
+    #[serde(default)]
+    method: ImputeMethod,
+    /// A list of fields whose unique value combinations determine the items
+    /// that should exist within each group.
+    #[serde(
+        borrow,
+        rename = "orderby",
+        deserialize_with = "super::data::vec_or_str"
+    )]
+    order_by: Vec<Cow<'s, str>>,
+    /// The value to use when the imputation method is
+    /// [`ImputeMethod::Value`].
+    #[serde(borrow, default = "Impute::default_value")]
+    value: Value<'s>,
+}
+
+impl<'s> Impute<'s> {
+    /// The default value for [`Self::value`].
+    fn default_value() -> Value<'s> {
+        Value::from(0.0)
+    }
+
+    /// Transforms the input data by appending new items for the missing
+    /// values of each group.
+    fn transform(&self, mut data: Vec<Value<'s>>) -> Vec<Value<'s>> {
+        let mut domain = IndexSet::new();
+        let mut groups = IndexMap::<_, IndexMap<_, _>>::new();
+        for item in &data {
+            let order = self
+                .order_by
+                .iter()
+                .map(|key| get_nested_value(item, key).unwrap_or(&Value::Null))
+                .collect::<Vec<_>>();
+            let (index, _) = domain.insert_full(order);
+            let group = self
+                .group_by
+                .iter()
+                .map(|key| get_nested_value(item, key).unwrap_or(&Value::Null))
+                .collect::<Vec<_>>();
+            let value = get_nested_value(item, &self.field).unwrap_or(&Value::Null);
+            groups.entry(group).or_default().insert(index, value);
+        }
+
+        let mut imputed = vec![];
+        for (group, values) in &groups {
+            let value = self.impute_value(values.values().copied());
+            for (index, order) in domain.iter().enumerate() {
+                if values.contains_key(&index) {
+                    continue;
+                }
+
+                let item = [(Cow::Borrowed("_imputed"), Value::Bool(true))]
+                    .into_iter()
+                    .chain(
+                        self.group_by
+                            .iter()
+                            .zip(group)
+                            .chain(self.order_by.iter().zip(order))
+                            .map(|(key, value)| (key.clone(), (*value).clone())),
+                    )
+                    .chain([(self.field.clone(), value.clone())])
+                    .collect::<Value<'s>>();
+                imputed.push(item);
+            }
+        }
+
+        data.extend(imputed);
+        data
+    }
+
+    /// Computes the value to use for missing items in a group with the given
+    /// field `values`.
+    fn impute_value<'b>(&self, values: impl Iterator<Item = &'b Value<'s>>) -> Value<'s>
+    where
+        's: 'b,
+    {
+        if matches!(self.method, ImputeMethod::Value) {
+            return self.value.clone();
+        }
+
+        let values = values
+            .filter(|value| !value.is_null() && !value.to_f64().is_nan())
+            .collect::<Vec<_>>();
+
+        if values.is_empty() {
+            return Value::Null;
+        }
+
+        let numbers = values.iter().map(|value| value.to_f64());
+        #[expect(
+            clippy::cast_precision_loss,
+            reason = "if there are ever ≥2**53 values, something sure happened"
+        )]
+        let value = match self.method {
+            ImputeMethod::Max => numbers.fold(f64::NEG_INFINITY, f64::max),
+            ImputeMethod::Mean => numbers.sum::<f64>() / values.len() as f64,
+            ImputeMethod::Median => quantile(values.iter().copied(), 0.5),
+            ImputeMethod::Min => numbers.fold(f64::INFINITY, f64::min),
+            ImputeMethod::Value => unreachable!("handled by early return"),
+        };
+        value.into()
+    }
+}
+
+/// A missing value imputation method.
+#[derive(Clone, Copy, Debug, Default, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ImputeMethod {
+    /// Use the maximum value of the group.
+    Max,
+    /// Use the mean value of the group.
+    Mean,
+    /// Use the median value of the group.
+    Median,
+    /// Use the minimum value of the group.
+    Min,
+    /// Use [`Impute::value`].
+    #[default]
+    Value,
+}
+
 /// A pie chart layout transformer.
 #[derive(Debug, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -1768,6 +2088,99 @@ impl<'s> Lookup<'s> {
     }
 }
 
+/// A rank score transformer.
+#[derive(Debug, serde::Deserialize)]
+pub(super) struct Rank<'s> {
+    /// A key field to use to rank items. If unspecified, items are ranked in
+    /// their observed order.
+    #[serde(borrow, default)]
+    field: Option<Cow<'s, str>>,
+    /// If true, computes normalized rank scores in the range 0..=1.
+    #[serde(default)]
+    normalize: bool,
+    /// The output field names.
+    #[serde(borrow, default)]
+    output: RankOutput<'s>,
+}
+
+impl<'s> Rank<'s> {
+    /// Transforms the input data to add extra fields.
+    fn transform(&self, mut data: Vec<Value<'s>>) -> Vec<Value<'s>> {
+        // Items with the same key share a rank, and keys are ranked in the
+        // order they are first observed
+        let (ranks, len) = if let Some(field) = &self.field {
+            let mut keys = IndexSet::new();
+            let ranks = data
+                .iter()
+                .map(|item| {
+                    let key = get_nested_value(item, field).map(ValueExt::to_string);
+                    keys.insert_full(key).0 + 1
+                })
+                .collect::<Vec<_>>();
+            (ranks, keys.len())
+        } else {
+            ((1..=data.len()).collect(), data.len())
+        };
+
+        for (item, rank) in data.iter_mut().zip(ranks) {
+            #[expect(
+                clippy::cast_precision_loss,
+                reason = "if there are ever ≥2**53 values, something sure happened"
+            )]
+            let rank = if self.normalize {
+                rank as f64 / len as f64
+            } else {
+                rank as f64
+            };
+            item.insert(self.output.rank.clone(), rank);
+        }
+
+        data
+    }
+}
+
+/// Rank output field names.
+#[derive(Debug, serde::Deserialize)]
+struct RankOutput<'s> {
+    /// The name of the rank field.
+    #[serde(borrow, default = "RankOutput::default_rank")]
+    rank: Cow<'s, str>,
+}
+
+impl RankOutput<'_> {
+    /// The default value for [`Self::rank`].
+    const fn default_rank() -> Cow<'static, str> {
+        Cow::Borrowed("rank")
+    }
+}
+
+impl Default for RankOutput<'_> {
+    fn default() -> Self {
+        Self {
+            rank: Self::default_rank(),
+        }
+    }
+}
+
+/// A data sorting transformer.
+#[derive(Debug, serde::Deserialize)]
+pub(super) struct Sort<'s> {
+    /// A list of fields to use as sort criteria. Fields prefixed with `-` are
+    /// sorted in descending order.
+    #[serde(borrow, deserialize_with = "super::data::vec_or_str")]
+    by: Vec<Cow<'s, str>>,
+}
+
+impl<'s> Sort<'s> {
+    /// Transforms the input data by sorting it.
+    fn transform(&self, mut data: Vec<Value<'s>>) -> Vec<Value<'s>> {
+        // This must be a stable sort since items which compare equal are
+        // expected to stay in their input order
+        data.sort_by(|a, b| comparator(a, b, &self.by));
+        data
+    }
+}
+
 /// A stacked chart layout transformer.
 #[derive(Debug, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -1918,9 +2331,98 @@ enum StackOffset {
     Normalize,
 }
 
-/// Unimplemented transform.
+/// A tree structure transformer for flat tabular data.
 #[derive(Debug, serde::Deserialize)]
-pub(super) struct Todo;
+pub(super) struct Treeify<'s> {
+    /// An ordered list of fields by which to group items into a tree. Each
+    /// field adds one level of internal nodes.
+    #[serde(
+        borrow,
+        rename = "groupby",
+        deserialize_with = "super::data::vec_or_str"
+    )]
+    group_by: Vec<Cow<'s, str>>,
+    /// The output field names.
+    #[serde(borrow, default)]
+    output: TreeifyOutput<'s>,
+}
+
+impl<'s> Treeify<'s> {
+    /// Transforms the input data to a flat list of tree nodes.
+    ///
+    /// Like the `treejson` data format, the output is a pre-order list of
+    /// nodes starting with a new root node, where children and parents are
+    /// referenced by their indexes in the list.
+    fn transform(&self, data: Vec<Value<'s>>) -> Vec<Value<'s>> {
+        let mut out = Vec::with_capacity(data.len() + 1);
+        out.push(Value::from([(self.output.parent.clone(), Value::Null)]));
+        self.level(&mut out, 0, 0, data);
+        out
+    }
+
+    /// Groups `values` by the field at `level` and appends the groups to `out`
+    /// as children of the node at index `parent`. After the last level,
+    /// appends the original items instead.
+    fn level(&self, out: &mut Vec<Value<'s>>, level: usize, parent: usize, values: Vec<Value<'s>>) {
+        let mut children = vec![];
+        if let Some(field) = self.group_by.get(level) {
+            let mut groups = IndexMap::<_, Vec<_>>::new();
+            for item in values {
+                let key = get_nested_value(&item, field).cloned().unwrap_or_default();
+                groups.entry(key).or_default().push(item);
+            }
+
+            for (key, values) in groups {
+                let index = out.len();
+                children.push(Value::from(index as u64));
+                out.push(Value::from([
+                    (field.clone(), key),
+                    (self.output.parent.clone(), Value::from(parent as u64)),
+                ]));
+                self.level(out, level + 1, index, values);
+            }
+        } else {
+            for mut item in values {
+                children.push(Value::from(out.len() as u64));
+                item.insert(self.output.parent.clone(), parent as u64);
+                out.push(item);
+            }
+        }
+        out[parent].insert(self.output.children.clone(), Value::Array(children));
+    }
+}
+
+/// Treeify output field names.
+#[derive(Debug, serde::Deserialize)]
+struct TreeifyOutput<'s> {
+    /// The name of the children field.
+    #[serde(borrow, default = "TreeifyOutput::default_children")]
+    children: Cow<'s, str>,
+    /// The name of the parent field.
+    #[serde(borrow, default = "TreeifyOutput::default_parent")]
+    parent: Cow<'s, str>,
+}
+
+impl TreeifyOutput<'_> {
+    /// The default value for [`Self::children`].
+    const fn default_children() -> Cow<'static, str> {
+        Cow::Borrowed("children")
+    }
+
+    /// The default value for [`Self::parent`].
+    const fn default_parent() -> Cow<'static, str> {
+        Cow::Borrowed("parent")
+    }
+}
+
+impl Default for TreeifyOutput<'_> {
+    fn default() -> Self {
+        Self {
+            children: Self::default_children(),
+            parent: Self::default_parent(),
+        }
+    }
+}
 
 /// A tree map layout transformer.
 #[derive(Debug, serde::Deserialize)]
@@ -2065,7 +2567,7 @@ enum TreemapMode {
     Squarify,
 }
 
-/// Tree map output field names.
+/// Tree map and hierarchy output field names.
 #[derive(Debug, serde::Deserialize)]
 struct TreemapOutput<'s> {
     /// The name of the depth field.
@@ -2124,6 +2626,128 @@ impl Default for TreemapOutput<'_> {
     }
 }
 
+/// A Voronoi diagram transformer.
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct Voronoi<'s> {
+    /// The clip extent of the diagram, as `[[x0, y0], [x1, y1]]`.
+    #[serde(default = "Voronoi::default_clip_extent")]
+    clip_extent: [[f64; 2]; 2],
+    /// The output field names.
+    #[serde(borrow, default)]
+    output: GeopathOutput<'s>,
+    /// The data field for the x-coordinate of each seed point.
+    #[serde(borrow, default = "Voronoi::default_x")]
+    x: Cow<'s, str>,
+    /// The data field for the y-coordinate of each seed point.
+    #[serde(borrow, default = "Voronoi::default_y")]
+    y: Cow<'s, str>,
+}
+
+impl<'s> Voronoi<'s> {
+    /// The distance below which two cell vertices are considered the same.
+    const EPSILON: f64 = 1e-6;
+
+    /// The default value for [`Self::clip_extent`].
+    const fn default_clip_extent() -> [[f64; 2]; 2] {
+        [[-1e5, -1e5], [1e5, 1e5]]
+    }
+
+    /// The default value for [`Self::x`].
+    const fn default_x() -> Cow<'static, str> {
+        Cow::Borrowed("layout_x")
+    }
+
+    /// The default value for [`Self::y`].
+    const fn default_y() -> Cow<'static, str> {
+        Cow::Borrowed("layout_y")
+    }
+
+    /// Computes the cell polygon for the seed point at `index`.
+    ///
+    /// The cell starts as the clip extent and is then clipped to the
+    /// half-plane on the near side of the perpendicular bisector between the
+    /// seed and every other seed. Returns `None` if the seed is invalid, is a
+    /// duplicate of an earlier seed, or lies outside of the clip extent.
+    fn cell(&self, seeds: &[Vec2], index: usize) -> Option<Vec<Vec2>> {
+        let seed = seeds[index];
+        if !seed.is_valid() || seeds[..index].contains(&seed) {
+            return None;
+        }
+
+        let [[x0, y0], [x1, y1]] = self.clip_extent;
+        let mut cell = vec![
+            Vec2::new(x0, y0),
+            Vec2::new(x1, y0),
+            Vec2::new(x1, y1),
+            Vec2::new(x0, y1),
+        ];
+
+        for other in seeds {
+            if !other.is_valid() || *other == seed {
+                continue;
+            }
+
+            // Points p in the cell satisfy normal · p <= offset
+            let normal = *other - seed;
+            let offset = normal.dot((seed + *other) / 2.0);
+
+            let mut clipped = Vec::with_capacity(cell.len() + 1);
+            for (index, a) in cell.iter().enumerate() {
+                let b = cell[(index + 1) % cell.len()];
+                let da = normal.dot(*a) - offset;
+                let db = normal.dot(b) - offset;
+                if da <= 0.0 {
+                    clipped.push(*a);
+                }
+                if (da <= 0.0) != (db <= 0.0) {
+                    clipped.push(*a + (b - *a) * (da / (da - db)));
+                }
+            }
+
+            if clipped.is_empty() {
+                return None;
+            }
+            cell = clipped;
+        }
+
+        // Clipping through an existing vertex duplicates it
+        cell.dedup_by(|b, a| (*a - *b).len() < Self::EPSILON);
+        if let [first, .., last] = cell.as_slice()
+            && (*first - *last).len() < Self::EPSILON
+        {
+            cell.pop();
+        }
+
+        (cell.len() > 2).then_some(cell)
+    }
+
+    /// Transforms the input data to add extra fields.
+    fn transform(&self, mut data: Vec<Value<'s>>) -> Vec<Value<'s>> {
+        let seeds = data
+            .iter()
+            .map(|item| {
+                let x = get_nested_value(item, &self.x).map_or(f64::NAN, ValueExt::to_f64);
+                let y = get_nested_value(item, &self.y).map_or(f64::NAN, ValueExt::to_f64);
+                Vec2::new(x, y)
+            })
+            .collect::<Vec<_>>();
+
+        for (index, item) in data.iter_mut().enumerate() {
+            let path = self.cell(&seeds, index).map_or(Value::Null, |cell| {
+                let points = cell
+                    .into_iter()
+                    .map(|point| format!("{},{}", point.x.v(), point.y.v()))
+                    .collect::<Vec<_>>();
+                Value::from(format!("M{}Z", points.join("L")))
+            });
+            item.insert(self.output.path.clone(), path);
+        }
+
+        data
+    }
+}
+
 /// A word cloud layout transformer.
 #[derive(Debug, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -2948,17 +3572,17 @@ mod treemap {
         /// The computed area of the node.
         area: f64,
         /// The indexes of the children of this node in the flat list.
-        children: Vec<usize>,
+        pub(super) children: Vec<usize>,
         /// The tree depth of this node.
         pub(super) depth: u16,
         /// The index of the associated data object in the data list.
         pub(super) object: usize,
         /// The index of the parent of this node in the flat list.
-        parent: Option<usize>,
+        pub(super) parent: Option<usize>,
         /// The visual bounds of this node.
         pub(super) rect: Rect,
         /// The computed value of this node.
-        value: f64,
+        pub(super) value: f64,
     }
 
     impl TreeNode {
@@ -3197,7 +3821,7 @@ mod treemap {
     /// Creates a flat list of [`TreeNode`] from the given `data`, using
     /// `children_key` and `value_key` to look up children, node weights, and
     /// sort values from the data objects in `data`.
-    fn hierarchy(
+    pub(super) fn hierarchy(
         data: &[Value<'_>],
         children_key: &str,
         value_key: &str,
@@ -3287,6 +3911,388 @@ mod treemap {
     }
 }
 
+/// Types and functions for node-link tree and adjacency diagram layouts.
+mod tree {
+    use super::{Rect, treemap::TreeNode};
+
+    /// The Reingold–Tilford layout state of a tree node.
+    #[derive(Clone, Copy, Default)]
+    struct Wrapped {
+        /// The greatest distinct ancestor of the node.
+        ancestor: usize,
+        /// The change in shift of the node.
+        change: f64,
+        /// The default ancestor for the children of the node.
+        default_ancestor: Option<usize>,
+        /// The modifier of the node.
+        modifier: f64,
+        /// The index of the node within its parent.
+        number: usize,
+        /// The preliminary x-coordinate of the node.
+        prelim: f64,
+        /// The shift of the node.
+        shift: f64,
+        /// The next node on the contour of the tree, if the node is a leaf.
+        thread: Option<usize>,
+    }
+
+    /// A tidy tree layout engine.
+    ///
+    /// Uses the Buchheim et al. linear time version of the Reingold–Tilford
+    /// algorithm.
+    struct Tidy<'a> {
+        /// The flat list of nodes in the tree.
+        nodes: &'a [TreeNode],
+        /// If true, sibling separation is scaled by depth.
+        radial: bool,
+        /// The layout state of each node, plus a synthetic parent of the root
+        /// node at the end.
+        state: Vec<Wrapped>,
+    }
+
+    impl<'a> Tidy<'a> {
+        /// Creates a new `Tidy`.
+        fn new(nodes: &'a [TreeNode], radial: bool) -> Self {
+            let mut state = (0..=nodes.len())
+                .map(|ancestor| Wrapped {
+                    ancestor,
+                    ..<_>::default()
+                })
+                .collect::<Vec<_>>();
+            for node in nodes {
+                for (number, child) in node.children.iter().enumerate() {
+                    state[*child].number = number;
+                }
+            }
+            Self {
+                nodes,
+                radial,
+                state,
+            }
+        }
+
+        /// Computes the x-coordinate of each node.
+        fn layout(mut self) -> Vec<f64> {
+            for index in visit_after(self.nodes, 0) {
+                self.first_walk(index);
+            }
+
+            let root = self.nodes.len();
+            self.state[root].modifier = -self.state[0].prelim;
+
+            // Parents always come before their children in the flat list
+            let mut x = Vec::with_capacity(self.nodes.len());
+            for index in 0..self.nodes.len() {
+                let parent = self.parent(index);
+                x.push(self.state[index].prelim + self.state[parent].modifier);
+                self.state[index].modifier += self.state[parent].modifier;
+            }
+            x
+        }
+
+        /// Returns the greatest distinct ancestor of `inner_left` and `v`.
+        fn ancestor(&self, inner_left: usize, v: usize, ancestor: usize) -> usize {
+            let candidate = self.state[inner_left].ancestor;
+            if self.parent(candidate) == self.parent(v) {
+                candidate
+            } else {
+                ancestor
+            }
+        }
+
+        /// Moves the subtree at `v` away from its left sibling `w` so that
+        /// their contours do not overlap, returning the new default ancestor.
+        fn apportion(&mut self, v: usize, w: Option<usize>, mut ancestor: usize) -> usize {
+            let Some(w) = w else {
+                return ancestor;
+            };
+
+            let mut outer_right = v;
+            let mut outer_left = self.children(self.parent(v))[0];
+            let mut inner_right_mod = self.state[v].modifier;
+            let mut outer_right_mod = self.state[outer_right].modifier;
+            let mut inner_left_mod = self.state[w].modifier;
+            let mut outer_left_mod = self.state[outer_left].modifier;
+
+            let mut next_inner_left = self.right(w);
+            let mut next_inner_right = self.left(v);
+            while let (Some(inner_left), Some(inner_right)) = (next_inner_left, next_inner_right) {
+                outer_left = self.left(outer_left).unwrap();
+                outer_right = self.right(outer_right).unwrap();
+                self.state[outer_right].ancestor = v;
+                let shift = self.state[inner_left].prelim + inner_left_mod
+                    - self.state[inner_right].prelim
+                    - inner_right_mod
+                    + self.separation(inner_left, inner_right);
+                if shift > 0.0 {
+                    let wm = self.ancestor(inner_left, v, ancestor);
+                    self.move_subtree(wm, v, shift);
+                    inner_right_mod += shift;
+                    outer_right_mod += shift;
+                }
+                inner_left_mod += self.state[inner_left].modifier;
+                inner_right_mod += self.state[inner_right].modifier;
+                outer_left_mod += self.state[outer_left].modifier;
+                outer_right_mod += self.state[outer_right].modifier;
+                next_inner_left = self.right(inner_left);
+                next_inner_right = self.left(inner_right);
+            }
+
+            if let Some(inner_left) = next_inner_left
+                && self.right(outer_right).is_none()
+            {
+                self.state[outer_right].thread = Some(inner_left);
+                self.state[outer_right].modifier += inner_left_mod - outer_right_mod;
+            }
+
+            if let Some(inner_right) = next_inner_right
+                && self.left(outer_left).is_none()
+            {
+                self.state[outer_left].thread = Some(inner_right);
+                self.state[outer_left].modifier += inner_right_mod - outer_left_mod;
+                ancestor = v;
+            }
+
+            ancestor
+        }
+
+        /// Returns the children of the node at `index`.
+        fn children(&self, index: usize) -> &'a [usize] {
+            let nodes = self.nodes;
+            if let Some(node) = nodes.get(index) {
+                &node.children
+            } else {
+                &[0]
+            }
+        }
+
+        /// Shifts the children of the node at `v` according to their
+        /// accumulated shifts.
+        fn execute_shifts(&mut self, v: usize) {
+            let mut shift = 0.0;
+            let mut change = 0.0;
+            for child in self.children(v).iter().rev() {
+                let state = &mut self.state[*child];
+                state.prelim += shift;
+                state.modifier += shift;
+                change += state.change;
+                shift += state.shift + change;
+            }
+        }
+
+        /// Computes a preliminary x-coordinate for the node at `v`, whose
+        /// children must already have been walked.
+        fn first_walk(&mut self, v: usize) {
+            let parent = self.parent(v);
+            let siblings = self.children(parent);
+            let number = self.state[v].number;
+            let w = (number != 0).then(|| siblings[number - 1]);
+
+            let children = self.children(v);
+            if let (Some(first), Some(last)) = (children.first(), children.last()) {
+                self.execute_shifts(v);
+                let midpoint = self.state[*first].prelim.midpoint(self.state[*last].prelim);
+                if let Some(w) = w {
+                    self.state[v].prelim = self.state[w].prelim + self.separation(v, w);
+                    self.state[v].modifier = self.state[v].prelim - midpoint;
+                } else {
+                    self.state[v].prelim = midpoint;
+                }
+            } else if let Some(w) = w {
+                self.state[v].prelim = self.state[w].prelim + self.separation(v, w);
+            }
+
+            let ancestor = self.state[parent].default_ancestor.unwrap_or(siblings[0]);
+            self.state[parent].default_ancestor = Some(self.apportion(v, w, ancestor));
+        }
+
+        /// Returns the next node on the left contour of the subtree at `v`.
+        fn left(&self, v: usize) -> Option<usize> {
+            self.children(v).first().copied().or(self.state[v].thread)
+        }
+
+        /// Shifts the subtree at `wp` right by `shift`, spreading the shift
+        /// over the subtrees between `wm` and `wp`.
+        fn move_subtree(&mut self, wm: usize, wp: usize, shift: f64) {
+            #[expect(
+                clippy::cast_precision_loss,
+                reason = "if there are ever ≥2**53 siblings, something sure happened"
+            )]
+            let change = shift / (self.state[wp].number - self.state[wm].number) as f64;
+            self.state[wp].change -= change;
+            self.state[wp].shift += shift;
+            self.state[wm].change += change;
+            self.state[wp].prelim += shift;
+            self.state[wp].modifier += shift;
+        }
+
+        /// Returns the index of the parent of the node at `index`.
+        fn parent(&self, index: usize) -> usize {
+            self.nodes[index].parent.unwrap_or(self.nodes.len())
+        }
+
+        /// Returns the next node on the right contour of the subtree at `v`.
+        fn right(&self, v: usize) -> Option<usize> {
+            self.children(v).last().copied().or(self.state[v].thread)
+        }
+
+        /// Returns the separation between the nodes at `a` and `b`.
+        fn separation(&self, a: usize, b: usize) -> f64 {
+            separation(self.nodes, a, b, self.radial)
+        }
+    }
+
+    /// Computes a dendrogram layout, with all leaf nodes at the same depth.
+    /// If `fixed` is true, `size` is the size of each node instead of the size
+    /// of the whole layout.
+    pub(super) fn cluster(nodes: &mut [TreeNode], size: [f64; 2], fixed: bool, radial: bool) {
+        let mut x = vec![0.0; nodes.len()];
+        let mut y = vec![0.0; nodes.len()];
+        let mut previous = None;
+        let mut offset = 0.0;
+        for index in visit_after(nodes, 0) {
+            let children = &nodes[index].children;
+            if children.is_empty() {
+                if let Some(previous) = previous {
+                    offset += separation(nodes, index, previous, radial);
+                    x[index] = offset;
+                }
+                previous = Some(index);
+            } else {
+                #[expect(
+                    clippy::cast_precision_loss,
+                    reason = "if there are ever ≥2**53 children, something sure happened"
+                )]
+                let len = children.len() as f64;
+                x[index] = children.iter().map(|child| x[*child]).sum::<f64>() / len;
+                y[index] = 1.0 + children.iter().map(|child| y[*child]).fold(0.0, f64::max);
+            }
+        }
+
+        let mut left = 0;
+        while let Some(child) = nodes[left].children.first() {
+            left = *child;
+        }
+        let mut right = 0;
+        while let Some(child) = nodes[right].children.last() {
+            right = *child;
+        }
+        let x0 = x[left] - separation(nodes, left, right, radial) / 2.0;
+        let x1 = x[right] + separation(nodes, right, left, radial) / 2.0;
+        let (root_x, root_y) = (x[0], y[0]);
+
+        for (index, node) in nodes.iter_mut().enumerate() {
+            let (x, y) = if fixed {
+                ((x[index] - root_x) * size[0], (root_y - y[index]) * size[1])
+            } else {
+                let y = if root_y == 0.0 {
+                    1.0
+                } else {
+                    y[index] / root_y
+                };
+                ((x[index] - x0) / (x1 - x0) * size[0], (1.0 - y) * size[1])
+            };
+            node.rect = Rect::new(x, y, x, y);
+        }
+    }
+
+    /// Computes an adjacency diagram layout, with each node sized according to
+    /// its value.
+    pub(super) fn partition(nodes: &mut [TreeNode], size: [f64; 2]) {
+        let depth = nodes.iter().map(|node| node.depth).max().unwrap_or(0) + 1;
+        let dy = size[1] / f64::from(depth);
+
+        let Some(root) = nodes.first_mut() else {
+            return;
+        };
+        root.rect = Rect::from_xywh(0.0, 0.0, size[0], dy);
+
+        let mut pending = vec![0];
+        while let Some(index) = pending.pop() {
+            let node = &nodes[index];
+            let scale = if node.value == 0.0 {
+                0.0
+            } else {
+                node.rect.width() / node.value
+            };
+            let mut x = node.rect.left;
+            let children = core::mem::take(&mut nodes[index].children);
+            for child in &children {
+                let child = &mut nodes[*child];
+                let dx = child.value * scale;
+                child.rect = Rect::from_xywh(x, f64::from(child.depth) * dy, dx, dy);
+                x += dx;
+            }
+            pending.extend(&children);
+            nodes[index].children = children;
+        }
+    }
+
+    /// Computes a tidy node-link tree layout. If `fixed` is true, `size` is
+    /// the size of each node instead of the size of the whole layout.
+    pub(super) fn tidy(nodes: &mut [TreeNode], size: [f64; 2], fixed: bool, radial: bool) {
+        let x = Tidy::new(nodes, radial).layout();
+
+        if fixed {
+            for (node, x) in nodes.iter_mut().zip(x) {
+                let (x, y) = (x * size[0], f64::from(node.depth) * size[1]);
+                node.rect = Rect::new(x, y, x, y);
+            }
+            return;
+        }
+
+        let (mut left, mut right, mut bottom) = (0, 0, 0);
+        for (index, node) in nodes.iter().enumerate() {
+            if x[index] < x[left] {
+                left = index;
+            }
+            if x[index] > x[right] {
+                right = index;
+            }
+            if node.depth > nodes[bottom].depth {
+                bottom = index;
+            }
+        }
+
+        let tx = separation(nodes, left, right, radial) / 2.0 - x[left];
+        let kx = size[0] / (x[right] + separation(nodes, right, left, radial) / 2.0 + tx);
+        let ky = size[1] / f64::from(nodes[bottom].depth.max(1));
+        for (node, x) in nodes.iter_mut().zip(x) {
+            let (x, y) = ((x + tx) * kx, f64::from(node.depth) * ky);
+            node.rect = Rect::new(x, y, x, y);
+        }
+    }
+
+    /// Returns the separation between the nodes at `a` and `b`. Siblings are
+    /// closer together than cousins, and in radial layouts nodes are closer
+    /// together the further they are from the centre.
+    fn separation(nodes: &[TreeNode], a: usize, b: usize, radial: bool) -> f64 {
+        let separation = if nodes[a].parent == nodes[b].parent {
+            1.0
+        } else {
+            2.0
+        };
+        if radial {
+            separation / f64::from(nodes[a].depth.max(1))
+        } else {
+            separation
+        }
+    }
+
+    /// Returns the indexes of all the nodes in the subtree at `index` in
+    /// post-order.
+    fn visit_after(nodes: &[TreeNode], index: usize) -> Vec<usize> {
+        let mut pending = vec![index];
+        let mut processed = vec![];
+        while let Some(index) = pending.pop() {
+            processed.push(index);
+            pending.extend(&nodes[index].children);
+        }
+        processed.reverse();
+        processed
+    }
+}
+
 // SPDX-SnippetEnd
 
 // SPDX-SnippetBegin
@@ -3296,7 +4302,7 @@ mod treemap {
 /// Types and functions for making a word cloud.
 mod word_cloud {
     use super::{
-        super::renderer::{Pixels, buffer_to_path, shape_text},
+        super::{super::font, renderer::Pixels},
         Cow, Either, Rect, SmallRng, Vec2, WordcloudSpiral,
     };
     use rand::Rng as _;
@@ -3444,29 +4450,24 @@ mod word_cloud {
             word.weight == "bold" || word.weight.parse::<u16>().is_ok_and(|weight| weight >= 600);
         let italic = word.style == "italic";
 
-        let (path, mut offset) = shape_text(&word.text, bold, italic, |face, buffer| {
-            let path = buffer_to_path(face, buffer)?;
+        let path = font::shape_text(&word.text, bold, italic).to_path(word.size)?;
 
-            let stroke = Stroke {
-                width: (word.padding * 2.0) as f32,
-                ..Default::default()
-            };
+        let stroke = Stroke {
+            width: (word.padding * 2.0) as f32,
+            ..Default::default()
+        };
 
-            let transform = {
-                let scale = (word.size / f64::from(face.units_per_em())) as f32;
-                SkiaTransform::from_rotate(word.rotate as f32).post_scale(scale, scale)
-            };
+        let transform = SkiaTransform::from_rotate(word.rotate as f32);
 
-            // The word cloud algorithm uses the post-transform bounding box
-            // midpoint for the origin of a word, but the Vega word cloud example
-            // thinks that the output coordinate will be at the alphabetic baseline.
-            // This means it is necessary to calculate the offset from the bounding
-            // box midpoint to the text anchor so it can be translated later.
-            let mut origin = Point::from_xy(path.bounds().width() / 2.0, 0.0);
-            transform.map_point(&mut origin);
+        // The word cloud algorithm uses the post-transform bounding box
+        // midpoint for the origin of a word, but the Vega word cloud example
+        // thinks that the output coordinate will be at the alphabetic baseline.
+        // This means it is necessary to calculate the offset from the bounding
+        // box midpoint to the text anchor so it can be translated later.
+        let mut offset = Point::from_xy(path.bounds().width() / 2.0, 0.0);
+        transform.map_point(&mut offset);
 
-            Some((path.transform(transform)?.stroke(&stroke, 1.0)?, origin))
-        })?;
+        let path = path.transform(transform)?.stroke(&stroke, 1.0)?;
 
         let bounds = path.bounds();
 
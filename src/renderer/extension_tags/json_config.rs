@@ -0,0 +1,404 @@
+//! Helpers for the data pages of the `JsonConfig` extension, which hosts
+//! tabular (`.tab`), map (`.map`), and chart (`.chart`) data in the `Data:`
+//! namespace of the shared media repository.
+
+use crate::{
+    common::format_number,
+    renderer::{Error, language::CONTENT_LANGUAGE},
+};
+use core::fmt::Write as _;
+use serde_json::{Map, Value, json};
+use std::borrow::Cow;
+
+/// Gets the best text from a map of language codes to texts, using the
+/// content language, then English, then whatever comes first. Other values
+/// are converted to plain text.
+pub(super) fn text(value: &Value) -> Cow<'_, str> {
+    match value {
+        Value::Object(texts) => best(texts, CONTENT_LANGUAGE).map_or(Cow::Borrowed(""), cell_text),
+        value => cell_text(value),
+    }
+}
+
+/// Gets the best value from a map of language codes to texts, using `lang`,
+/// then English, then whatever comes first.
+fn best<'a>(texts: &'a Map<String, Value>, lang: &str) -> Option<&'a Value> {
+    texts
+        .get(lang)
+        .or_else(|| texts.get("en"))
+        .or_else(|| texts.values().next())
+}
+
+/// Localises the text of a data page into the content language, like the
+/// `jsondata` API does.
+pub(super) fn localize(data: Value) -> Value {
+    localize_in(data, CONTENT_LANGUAGE)
+}
+
+/// Localises the text of a data page into the language `lang`, like the
+/// `jsondata` API does.
+pub(crate) fn localize_in(mut data: Value, lang: &str) -> Value {
+    let pick = |value: &mut Value| {
+        if let Value::Object(texts) = value {
+            *value = best(texts, lang).cloned().unwrap_or_default();
+        }
+    };
+
+    if let Some(description) = data.get_mut("description") {
+        pick(description);
+    }
+
+    let mut localized_columns = vec![];
+    if let Some(fields) = data
+        .pointer_mut("/schema/fields")
+        .and_then(Value::as_array_mut)
+    {
+        for (index, field) in fields.iter_mut().enumerate() {
+            if let Some(title) = field.get_mut("title") {
+                pick(title);
+            }
+            if field.get("type").and_then(Value::as_str) == Some("localized") {
+                localized_columns.push(index);
+            }
+        }
+    }
+
+    if !localized_columns.is_empty()
+        && let Some(rows) = data.get_mut("data").and_then(Value::as_array_mut)
+    {
+        for row in rows {
+            for &index in &localized_columns {
+                if let Some(value) = row.get_mut(index) {
+                    pick(value);
+                }
+            }
+        }
+    }
+
+    if let Some(license) = data.get_mut("license") {
+        *license = expand_license(license.as_str().unwrap_or_default());
+    }
+
+    data
+}
+
+/// Expands a license code into the license object of the `jsondata` API.
+fn expand_license(code: &str) -> Value {
+    let (text, url) = if code == "CC0-1.0" {
+        (
+            Cow::Borrowed("Creative Commons Zero v1.0 Universal"),
+            Cow::Borrowed("https://creativecommons.org/publicdomain/zero/1.0/"),
+        )
+    } else if code == "ODbL-1.0" {
+        (
+            Cow::Borrowed("Open Database License v1.0"),
+            Cow::Borrowed("https://opendatacommons.org/licenses/odbl/1-0/"),
+        )
+    } else if let Some(version) = code.strip_prefix("CC-BY-SA-") {
+        (
+            Cow::Owned(format!("Creative Commons Attribution-ShareAlike {version}")),
+            Cow::Owned(format!(
+                "https://creativecommons.org/licenses/by-sa/{version}/"
+            )),
+        )
+    } else if let Some(version) = code.strip_prefix("CC-BY-") {
+        (
+            Cow::Owned(format!("Creative Commons Attribution {version}")),
+            Cow::Owned(format!(
+                "https://creativecommons.org/licenses/by/{version}/"
+            )),
+        )
+    } else {
+        (Cow::Borrowed(code), Cow::Borrowed(""))
+    };
+
+    json!({ "code": code, "text": text, "url": url })
+}
+
+/// Converts the rows of a tabular data page into objects keyed by field name.
+///
+/// Missing cells are copied as nulls.
+pub(super) fn rows(data: &Value) -> Vec<Map<String, Value>> {
+    let names = data
+        .pointer("/schema/fields")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|field| {
+            field
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+
+    data.get("data")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|row| {
+            names
+                .iter()
+                .enumerate()
+                .map(|(index, &name)| {
+                    (name.to_owned(), row.get(index).cloned().unwrap_or_default())
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Renders the body of a tabular data page as a localised HTML table, with
+/// its description as the caption and its sources and license below. The
+/// sources are Wikitext, so they are rendered to HTML by `render_wikitext`.
+pub(crate) fn render_tabular<F>(body: &str, render_wikitext: F) -> Result<String, Error>
+where
+    F: FnOnce(&str) -> Result<String, Error>,
+{
+    let data = serde_json::from_str(body).map_err(|err| Error::Extension(Box::new(err)))?;
+    let mut out = String::new();
+    write_tabular(&mut out, &localize(data), render_wikitext)?;
+    Ok(out)
+}
+
+/// Writes a localised tabular data page as an HTML table.
+fn write_tabular<F>(out: &mut String, data: &Value, render_wikitext: F) -> Result<(), Error>
+where
+    F: FnOnce(&str) -> Result<String, Error>,
+{
+    let fields = data
+        .pointer("/schema/fields")
+        .and_then(Value::as_array)
+        .map_or(&[][..], Vec::as_slice);
+
+    write!(out, r#"<table class="wikitable mw-tabular">"#)?;
+
+    let description = text(&data["description"]);
+    if !description.is_empty() {
+        write!(
+            out,
+            "<caption>{}</caption>",
+            html_escape::encode_text(&description)
+        )?;
+    }
+
+    write!(out, "<thead><tr>")?;
+    for field in fields {
+        let name = field["name"].as_str().unwrap_or_default();
+        let title = text(&field["title"]);
+        write!(
+            out,
+            r#"<th title="{}">{}</th>"#,
+            html_escape::encode_double_quoted_attribute(name),
+            html_escape::encode_text(if title.is_empty() { name } else { &title })
+        )?;
+    }
+    write!(out, "</tr></thead><tbody>")?;
+
+    for row in data["data"].as_array().into_iter().flatten() {
+        write!(out, "<tr>")?;
+        for (index, field) in fields.iter().enumerate() {
+            match row.get(index).unwrap_or(&Value::Null) {
+                Value::Null => write!(out, "<td></td>")?,
+                Value::Number(value) if kind(field) == "number" => {
+                    let value = value.as_f64().unwrap_or_default();
+                    write!(
+                        out,
+                        r#"<td style="text-align:end">{}</td>"#,
+                        format_number(value, false)
+                    )?;
+                }
+                value => write!(
+                    out,
+                    "<td>{}</td>",
+                    html_escape::encode_text(&cell_text(value))
+                )?,
+            }
+        }
+        write!(out, "</tr>")?;
+    }
+    write!(out, "</tbody></table>")?;
+
+    let sources = text(&data["sources"]);
+    if !sources.is_empty() {
+        let sources = render_wikitext(&format!("Sources: {sources}"))?;
+        write!(out, r#"<div class="mw-tabular-sources">{sources}</div>"#)?;
+    }
+
+    if let Some(license) = data.get("license") {
+        let name = license["text"].as_str().unwrap_or_default();
+        let url = license["url"].as_str().unwrap_or_default();
+        if url.is_empty() {
+            write!(
+                out,
+                r#"<p class="mw-tabular-license">License: {}</p>"#,
+                html_escape::encode_text(name)
+            )?;
+        } else {
+            write!(
+                out,
+                r#"<p class="mw-tabular-license">License: <a href="{}">{}</a></p>"#,
+                html_escape::encode_double_quoted_attribute(url),
+                html_escape::encode_text(name)
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Gets the schema type of a tabular data field.
+fn kind(field: &Value) -> &str {
+    field["type"].as_str().unwrap_or("string")
+}
+
+/// Converts a tabular data cell into plain text.
+fn cell_text(value: &Value) -> Cow<'_, str> {
+    match value {
+        Value::String(value) => Cow::Borrowed(value),
+        Value::Bool(value) => Cow::Borrowed(if *value { "true" } else { "false" }),
+        Value::Null => Cow::Borrowed(""),
+        value => Cow::Owned(value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn texts() {
+        assert_eq!(text(&json!({ "fr": "Salut", "en": "Hi" })), "Hi");
+        assert_eq!(text(&json!({ "de": "Hallo" })), "Hallo");
+        assert_eq!(text(&json!({})), "");
+        assert_eq!(text(&json!("Plain")), "Plain");
+        assert_eq!(text(&json!(2020)), "2020");
+        assert_eq!(text(&json!(1.5)), "1.5");
+        assert_eq!(text(&json!(true)), "true");
+        assert_eq!(text(&Value::Null), "");
+    }
+
+    #[test]
+    fn localized() {
+        let data = json!({
+            "license": "CC0-1.0",
+            "description": { "en": "Fruit", "fr": "Fruits" },
+            "schema": { "fields": [
+                { "name": "name", "type": "localized", "title": { "fr": "Nom" } },
+                { "name": "label", "type": "string", "title": { "de": "Etikett" } },
+            ] },
+            "data": [
+                [{ "en": "apple", "fr": "pomme" }, { "en": "kept" }],
+                [{ "en": "pear" }],
+            ],
+        });
+
+        assert_eq!(
+            localize_in(data, "fr"),
+            json!({
+                "license": {
+                    "code": "CC0-1.0",
+                    "text": "Creative Commons Zero v1.0 Universal",
+                    "url": "https://creativecommons.org/publicdomain/zero/1.0/",
+                },
+                "description": "Fruits",
+                "schema": { "fields": [
+                    { "name": "name", "type": "localized", "title": "Nom" },
+                    { "name": "label", "type": "string", "title": "Etikett" },
+                ] },
+                "data": [["pomme", { "en": "kept" }], ["pear"]],
+            })
+        );
+    }
+
+    #[test]
+    fn licenses() {
+        assert_eq!(
+            expand_license("ODbL-1.0"),
+            json!({
+                "code": "ODbL-1.0",
+                "text": "Open Database License v1.0",
+                "url": "https://opendatacommons.org/licenses/odbl/1-0/",
+            })
+        );
+        assert_eq!(
+            expand_license("CC-BY-SA-4.0"),
+            json!({
+                "code": "CC-BY-SA-4.0",
+                "text": "Creative Commons Attribution-ShareAlike 4.0",
+                "url": "https://creativecommons.org/licenses/by-sa/4.0/",
+            })
+        );
+        assert_eq!(
+            expand_license("CC-BY-3.0"),
+            json!({
+                "code": "CC-BY-3.0",
+                "text": "Creative Commons Attribution 3.0",
+                "url": "https://creativecommons.org/licenses/by/3.0/",
+            })
+        );
+        assert_eq!(
+            expand_license("Other"),
+            json!({ "code": "Other", "text": "Other", "url": "" })
+        );
+    }
+
+    #[test]
+    fn row_objects() {
+        let data = json!({
+            "schema": { "fields": [{ "name": "year" }, { "name": "count" }] },
+            "data": [[2020, 1], [2021], [2022, 3, "extra"]],
+        });
+
+        let objects = rows(&data)
+            .into_iter()
+            .map(Value::Object)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            objects,
+            [
+                json!({ "year": 2020, "count": 1 }),
+                json!({ "year": 2021, "count": null }),
+                json!({ "year": 2022, "count": 3 }),
+            ]
+        );
+
+        assert!(rows(&json!({})).is_empty());
+    }
+
+    #[test]
+    fn tabular() {
+        let body = r#"{
+            "license": "CC0-1.0",
+            "description": { "en": "Fruit & veg" },
+            "sources": "Market",
+            "schema": { "fields": [
+                { "name": "name", "type": "localized", "title": { "en": "Name" } },
+                { "name": "count", "type": "number" },
+                { "name": "ok", "type": "boolean" }
+            ] },
+            "data": [
+                [{ "en": "<apple>" }, 1234.5, true],
+                [{ "en": "pear" }, null, false]
+            ]
+        }"#;
+
+        let render_wikitext = |text: &str| Ok(format!("<p>{}</p>", html_escape::encode_text(text)));
+        assert_eq!(
+            render_tabular(body, render_wikitext).unwrap(),
+            concat!(
+                r#"<table class="wikitable mw-tabular"><caption>Fruit &amp; veg</caption>"#,
+                r#"<thead><tr><th title="name">Name</th><th title="count">count</th>"#,
+                r#"<th title="ok">ok</th></tr></thead><tbody>"#,
+                r#"<tr><td>&lt;apple&gt;</td><td style="text-align:end">1,234.5</td>"#,
+                r#"<td>true</td></tr><tr><td>pear</td><td></td><td>false</td></tr>"#,
+                r#"</tbody></table>"#,
+                r#"<div class="mw-tabular-sources"><p>Sources: Market</p></div>"#,
+                r#"<p class="mw-tabular-license">License: "#,
+                r#"<a href="https://creativecommons.org/publicdomain/zero/1.0/">"#,
+                r#"Creative Commons Zero v1.0 Universal</a></p>"#,
+            )
+        );
+        assert!(render_tabular("{", render_wikitext).is_err());
+    }
+}
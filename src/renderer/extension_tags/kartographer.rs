@@ -0,0 +1,879 @@
+//! The `<mapframe>` and `<maplink>` extension tags.
+//! <https://www.mediawiki.org/wiki/Help:Extension:Kartographer>
+//!
+//! Kartographer draws interactive slippy maps with tiles from a map server.
+//! Neither scripts nor a map server are available here, so instead the
+//! embedded GeoJSON is projected with the Graph extension’s geo engine and
+//! drawn as a static SVG image, on top of an optional basemap loaded from a
+//! local GeoJSON file.
+
+use super::{
+    Error, ExtensionTag, OutputMode, Result, State, eval_string,
+    graph::WebMercator,
+    preprocess_frame,
+    svg::{NS_SVG, ValueDisplay as _, n},
+};
+use crate::{
+    common::decode_html,
+    title::Title,
+    wikitext::{FileMap, helpers::TextContent, visit::Visitor as _},
+};
+use core::{
+    f64::consts::{FRAC_PI_2, FRAC_PI_4},
+    fmt::Write as _,
+};
+use minidom::Element;
+use serde_json_borrow::Value;
+use std::{borrow::Cow, collections::HashMap, sync::OnceLock};
+
+/// The default zoom level of a map with coordinates but no data.
+const DEFAULT_ZOOM: f64 = 12.0;
+
+/// The maximum zoom level used when zooming a map to fit its data.
+const MAX_FIT_ZOOM: f64 = 16.0;
+
+/// The padding around data when zooming a map to fit its data, in pixels.
+const FIT_PADDING: f64 = 20.0;
+
+/// The nominal width of a full-width map, in pixels.
+const FULL_WIDTH: f64 = 800.0;
+
+/// The default size of a map frame, in pixels.
+const DEFAULT_SIZE: f64 = 300.0;
+
+/// The size of a map link popup, in pixels.
+const POPUP_SIZE: [f64; 2] = [800.0, 600.0];
+
+/// The outline of a marker pin, with its tip at the origin.
+const PIN: &str = "M0,0C-2,-8,-10,-12,-10,-20A10,10,0,1,1,10,-20C10,-12,2,-8,0,0Z";
+
+/// The centre of the head of [`PIN`].
+const PIN_HEAD_Y: f64 = -20.0;
+
+/// The optional basemap drawn under all maps.
+static BASEMAP: OnceLock<Value<'static>> = OnceLock::new();
+
+/// Sets the basemap drawn under all maps from the given GeoJSON. This can only
+/// be done once.
+pub(crate) fn set_basemap(geojson: String) -> Result<(), serde_json::Error> {
+    let geojson = Box::leak(geojson.into_boxed_str());
+    let value = serde_json::from_str::<Value<'static>>(geojson)?;
+    if BASEMAP.set(value).is_err() {
+        log::warn!("Basemap was already set");
+    }
+    Ok(())
+}
+
+/// The `<mapframe>` extension tag.
+pub(super) fn map_frame(
+    out: &mut String,
+    state: &mut State<'_>,
+    arguments: &ExtensionTag<'_, '_, '_>,
+) -> Result {
+    let width = arguments.get(state, "width")?;
+    let (width, is_full) = match width.as_deref().map(str::trim_ascii) {
+        Some("full" | "100%") => (FULL_WIDTH, true),
+        width => (parse_size(width), false),
+    };
+    let height = parse_size(arguments.get(state, "height")?.as_deref());
+    let align = match arguments
+        .get(state, "align")?
+        .as_deref()
+        .map(str::trim_ascii)
+    {
+        Some(align @ ("left" | "center")) => align,
+        _ if is_full => "none",
+        _ => "right",
+    };
+    let frameless = arguments.get(state, "frameless")?.is_some();
+    let alt = arguments.get(state, "alt")?;
+    let caption = match arguments.get(state, "text")? {
+        Some(text) if !frameless && !text.trim_ascii().is_empty() => {
+            let text = eval_string(state, arguments.sp, text.trim_ascii())?;
+            Some(state.strip_markers.unstrip(&text).into_owned())
+        }
+        _ => None,
+    };
+
+    let svg = match render_map(state, arguments, [width, height], alt.as_deref())? {
+        Ok(svg) => svg,
+        Err(err) => {
+            write_error(out, &err)?;
+            return Ok(OutputMode::Inline);
+        }
+    };
+
+    let full = if is_full { " mw-kartographer-full" } else { "" };
+    if frameless {
+        write!(
+            out,
+            r#"<div class="mw-kartographer-frameless mw-halign-{align}{full}">{svg}</div>"#
+        )?;
+    } else {
+        write!(
+            out,
+            r#"<figure class="mw-kartographer-frame mw-halign-{align}{full}">{svg}"#
+        )?;
+        if let Some(caption) = caption {
+            write!(out, "<figcaption>{caption}</figcaption>")?;
+        }
+        write!(out, "</figure>")?;
+    }
+
+    Ok(OutputMode::Block)
+}
+
+/// The `<maplink>` extension tag.
+///
+/// The map is rendered into a popup which is shown when the link is followed.
+pub(super) fn map_link(
+    out: &mut String,
+    state: &mut State<'_>,
+    arguments: &ExtensionTag<'_, '_, '_>,
+) -> Result {
+    let text = match arguments.get(state, "text")? {
+        Some(text) if !text.trim_ascii().is_empty() => {
+            let text = eval_string(state, arguments.sp, text.trim_ascii())?;
+            Some(state.strip_markers.unstrip(&text).into_owned())
+        }
+        _ => None,
+    };
+    let class = arguments.get(state, "class")?.unwrap_or_default();
+    let class = html_escape::encode_double_quoted_attribute(&class);
+
+    let coordinates = coordinates(state, arguments)?;
+    let svg = match render_map(state, arguments, POPUP_SIZE, None)? {
+        Ok(svg) => svg,
+        Err(err) => {
+            write_error(out, &err)?;
+            return Ok(OutputMode::Inline);
+        }
+    };
+
+    let text =
+        text.unwrap_or_else(|| coordinates.map_or_else(|| "Map".to_owned(), format_coordinates));
+
+    state.globals.map_link_ordinal += 1;
+    let id = format!("mw-kartographer-map-{}", state.globals.map_link_ordinal);
+    write!(
+        out,
+        r##"<a class="mw-kartographer-maplink {class}" href="#{id}">{text}</a><span class="mw-kartographer-popup" id="{id}" role="dialog"><a class="mw-kartographer-popup-close" href="#_" title="Close">×</a>{svg}</span>"##
+    )?;
+
+    Ok(OutputMode::Inline)
+}
+
+/// A GeoJSON feature.
+struct Feature<'a> {
+    /// The geometry of the feature.
+    geometry: &'a Value<'a>,
+    /// The simplestyle properties of the feature, in order of precedence.
+    properties: [Option<&'a Value<'a>>; 2],
+}
+
+impl<'a> Feature<'a> {
+    /// Gets a simplestyle property of the feature.
+    fn get(&self, key: &str) -> Option<&'a Value<'a>> {
+        self.properties
+            .iter()
+            .flatten()
+            .find_map(|properties| properties.get(key))
+    }
+
+    /// Gets a simplestyle colour property of the feature.
+    fn color(&self, key: &str, default: &str) -> String {
+        self.get(key)
+            .and_then(Value::as_str)
+            .and_then(parse_color)
+            .unwrap_or_else(|| default.to_owned())
+    }
+
+    /// Gets a simplestyle number property of the feature.
+    fn number(&self, key: &str, default: f64) -> f64 {
+        self.get(key)
+            .and_then(|value| {
+                value
+                    .as_f64()
+                    .or_else(|| value.as_str()?.trim_ascii().parse::<f64>().ok())
+            })
+            .filter(|value| value.is_finite() && *value >= 0.0)
+            .unwrap_or(default)
+    }
+
+    /// Gets a simplestyle string property of the feature.
+    fn string(&self, key: &str) -> Option<&'a str> {
+        self.get(key)
+            .and_then(Value::as_str)
+            .map(str::trim_ascii)
+            .filter(|value| !value.is_empty())
+    }
+}
+
+/// Renders the map for a `<mapframe>` or `<maplink>` into an SVG string.
+///
+/// Returns the inner error if the GeoJSON is invalid.
+fn render_map(
+    state: &mut State<'_>,
+    arguments: &ExtensionTag<'_, '_, '_>,
+    size: [f64; 2],
+    alt: Option<&str>,
+) -> Result<Result<String, String>> {
+    let body = preprocess_frame(state, arguments.sp, arguments.body())?;
+    let body = state.strip_markers.unstrip(&body);
+    let body = body.trim_ascii();
+
+    let root = if body.is_empty() {
+        Value::Null
+    } else {
+        match serde_json::from_str::<Value<'_>>(body) {
+            Ok(root) => root,
+            Err(err) => return Ok(Err(err.to_string())),
+        }
+    };
+    let objects = match &root {
+        Value::Null => &[][..],
+        Value::Array(objects) => &objects[..],
+        object => core::slice::from_ref(object),
+    };
+
+    // External data has to be loaded and kept alive before anything else so
+    // that features can borrow from it
+    let pages = objects
+        .iter()
+        .filter_map(|object| load_external_data(state, object))
+        .collect::<Vec<_>>();
+    let page_data = pages
+        .iter()
+        .filter_map(
+            |(object, body)| match serde_json::from_str::<Value<'_>>(body) {
+                Ok(data) => Some((*object, data)),
+                Err(err) => {
+                    log::warn!("Invalid map data: {err}");
+                    None
+                }
+            },
+        )
+        .collect::<Vec<_>>();
+
+    let mut features = Vec::new();
+    for object in objects {
+        if is_type(object, "ExternalData") {
+            if let Some((_, data)) = page_data
+                .iter()
+                .find(|(external, _)| core::ptr::eq(*external, object))
+                && let Some(data) = data.get("data")
+            {
+                collect_features(&mut features, data, object.get("properties"));
+            }
+        } else {
+            collect_features(&mut features, object, None);
+        }
+    }
+
+    let coordinates = coordinates(state, arguments)?;
+    let zoom = arguments
+        .get(state, "zoom")?
+        .and_then(|zoom| zoom.trim_ascii().parse::<f64>().ok())
+        .map(|zoom| zoom.clamp(0.0, 19.0));
+
+    let bounds = features.iter().fold(None, |bounds, feature| {
+        extend_bounds(bounds, feature.geometry)
+    });
+
+    let (center, zoom) = match (coordinates, zoom, bounds) {
+        (Some(center), Some(zoom), _) => (center, zoom),
+        (center, zoom, Some(bounds)) => {
+            let (fit_center, fit_zoom) = fit_bounds(bounds, size);
+            (center.unwrap_or(fit_center), zoom.unwrap_or(fit_zoom))
+        }
+        (Some(center), None, None) => (center, DEFAULT_ZOOM),
+        (None, zoom, None) => ([0.0, 0.0], zoom.unwrap_or(0.0)),
+    };
+
+    let projection = WebMercator::new(center, zoom, size);
+    let mut renderer = Renderer::new(size, alt);
+
+    if let Some(basemap) = BASEMAP.get() {
+        renderer.add_basemap(&projection, basemap);
+    }
+
+    let mut counters = HashMap::new();
+    for feature in &features {
+        let title = feature_title(state, arguments, feature)?;
+        renderer.add_feature(&projection, feature, title.as_deref(), &mut counters);
+    }
+
+    let mut out = Vec::new();
+    renderer
+        .finish()
+        .write_to(&mut out)
+        .map_err(|err| Error::Extension(Box::new(err)))?;
+    // SAFETY: We just wrote this from strs.
+    Ok(Ok(unsafe { String::from_utf8_unchecked(out) }))
+}
+
+/// An SVG map renderer.
+struct Renderer {
+    /// The marker layer.
+    markers: Element,
+    /// The shape layer.
+    shapes: Element,
+    /// The size of the map, in pixels.
+    size: [f64; 2],
+    /// Accumulator.
+    svg: Element,
+}
+
+impl Renderer {
+    /// Creates a new renderer for a map of the given `size` with the given
+    /// alternative text.
+    fn new(size: [f64; 2], alt: Option<&str>) -> Self {
+        let [width, height] = size;
+        let mut svg = Element::builder("svg", NS_SVG)
+            .attr(n!("viewBox"), format!("0 0 {} {}", width.v(), height.v()))
+            .attr(n!("width"), width.v())
+            .attr(n!("height"), height.v())
+            .attr(n!("class"), "mw-kartographer-map")
+            .attr(n!("role"), "img");
+        if let Some(alt) = alt {
+            svg = svg.attr(n!("aria-label"), alt);
+        }
+
+        let mut svg = svg.build();
+        svg.append_child(
+            Element::builder("rect", NS_SVG)
+                .attr(n!("class"), "mw-kartographer-water")
+                .attr(n!("width"), width.v())
+                .attr(n!("height"), height.v())
+                .build(),
+        );
+
+        Self {
+            markers: Element::builder("g", NS_SVG)
+                .attr(n!("class"), "mw-kartographer-markers")
+                .build(),
+            shapes: Element::builder("g", NS_SVG)
+                .attr(n!("class"), "mw-kartographer-shapes")
+                .build(),
+            size,
+            svg,
+        }
+    }
+
+    /// Adds the basemap.
+    fn add_basemap(&mut self, projection: &WebMercator, basemap: &Value<'_>) {
+        let d = projection.path(basemap);
+        if !d.is_empty() {
+            self.svg.append_child(
+                Element::builder("path", NS_SVG)
+                    .attr(n!("class"), "mw-kartographer-land")
+                    .attr(n!("d"), d)
+                    .build(),
+            );
+        }
+    }
+
+    /// Adds a feature with the given plain text title.
+    ///
+    /// `counters` holds the state of the automatic `-number` and `-letter`
+    /// marker symbols.
+    fn add_feature(
+        &mut self,
+        projection: &WebMercator,
+        feature: &Feature<'_>,
+        title: Option<&str>,
+        counters: &mut HashMap<String, usize>,
+    ) {
+        self.add_geometry(projection, feature, feature.geometry, title, counters);
+    }
+
+    /// Adds a single geometry object of a feature.
+    fn add_geometry(
+        &mut self,
+        projection: &WebMercator,
+        feature: &Feature<'_>,
+        geometry: &Value<'_>,
+        title: Option<&str>,
+        counters: &mut HashMap<String, usize>,
+    ) {
+        match geometry
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+        {
+            "Point" => {
+                if let Some(point) = geometry.get("coordinates").and_then(to_position) {
+                    self.add_marker(projection, feature, point, title, counters);
+                }
+            }
+            "MultiPoint" => {
+                for point in geometry
+                    .get("coordinates")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(to_position)
+                {
+                    self.add_marker(projection, feature, point, title, counters);
+                }
+            }
+            "GeometryCollection" => {
+                for geometry in geometry
+                    .get("geometries")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                {
+                    self.add_geometry(projection, feature, geometry, title, counters);
+                }
+            }
+            kind @ ("LineString" | "MultiLineString" | "Polygon" | "MultiPolygon") => {
+                self.add_shape(
+                    projection,
+                    feature,
+                    geometry,
+                    kind.ends_with("Polygon"),
+                    title,
+                );
+            }
+            _ => {}
+        }
+    }
+
+    /// Adds a marker at the given position, in degrees longitude/latitude.
+    fn add_marker(
+        &mut self,
+        projection: &WebMercator,
+        feature: &Feature<'_>,
+        position: [f64; 2],
+        title: Option<&str>,
+        counters: &mut HashMap<String, usize>,
+    ) {
+        let [x, y] = projection.point(position);
+        let [width, height] = self.size;
+        if !(0.0..=width).contains(&x) || !(0.0..=height).contains(&y) {
+            return;
+        }
+
+        let scale = match feature.string("marker-size") {
+            Some("small") => 0.75,
+            Some("large") => 1.25,
+            _ => 1.0,
+        };
+
+        let mut marker = Element::builder("g", NS_SVG)
+            .attr(n!("class"), "mw-kartographer-marker")
+            .attr(
+                n!("transform"),
+                format!("translate({},{}) scale({scale})", x.v(), y.v()),
+            )
+            .append(
+                Element::builder("path", NS_SVG)
+                    .attr(n!("d"), PIN)
+                    .attr(n!("fill"), feature.color("marker-color", "#7e7e7e"))
+                    .build(),
+            );
+
+        if let Some(symbol) = feature
+            .string("marker-symbol")
+            .and_then(|symbol| marker_symbol(symbol, counters))
+        {
+            marker = marker.append(
+                Element::builder("text", NS_SVG)
+                    .attr(n!("y"), PIN_HEAD_Y.v())
+                    .append(symbol)
+                    .build(),
+            );
+        }
+
+        if let Some(title) = title {
+            marker = marker.append(
+                Element::builder("title", NS_SVG)
+                    .append(title.to_owned())
+                    .build(),
+            );
+        }
+
+        self.markers.append_child(marker.build());
+    }
+
+    /// Adds a line or polygon.
+    fn add_shape(
+        &mut self,
+        projection: &WebMercator,
+        feature: &Feature<'_>,
+        geometry: &Value<'_>,
+        is_polygon: bool,
+        title: Option<&str>,
+    ) {
+        let d = projection.path(geometry);
+        if d.is_empty() {
+            return;
+        }
+
+        let mut shape = Element::builder("path", NS_SVG)
+            .attr(n!("d"), d)
+            .attr(n!("stroke"), feature.color("stroke", "#555555"))
+            .attr(n!("stroke-width"), feature.number("stroke-width", 2.0).v())
+            .attr(
+                n!("stroke-opacity"),
+                feature.number("stroke-opacity", 1.0).min(1.0).v(),
+            );
+
+        shape = if is_polygon {
+            shape
+                .attr(n!("fill"), feature.color("fill", "#555555"))
+                .attr(
+                    n!("fill-opacity"),
+                    feature.number("fill-opacity", 0.6).min(1.0).v(),
+                )
+        } else {
+            shape.attr(n!("fill"), "none")
+        };
+
+        if let Some(title) = title {
+            shape = shape.append(
+                Element::builder("title", NS_SVG)
+                    .append(title.to_owned())
+                    .build(),
+            );
+        }
+
+        self.shapes.append_child(shape.build());
+    }
+
+    /// Returns the finished SVG, consuming the renderer.
+    fn finish(mut self) -> Element {
+        self.svg.append_child(self.shapes);
+        self.svg.append_child(self.markers);
+        self.svg
+    }
+}
+
+/// Collects the features from a GeoJSON object into `features`.
+fn collect_features<'a>(
+    features: &mut Vec<Feature<'a>>,
+    object: &'a Value<'a>,
+    overrides: Option<&'a Value<'a>>,
+) {
+    match object
+        .get("type")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+    {
+        "Feature" => {
+            if let Some(geometry) = object.get("geometry") {
+                features.push(Feature {
+                    geometry,
+                    properties: [overrides, object.get("properties")],
+                });
+            }
+        }
+        "FeatureCollection" => {
+            for feature in object
+                .get("features")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+            {
+                collect_features(features, feature, overrides);
+            }
+        }
+        "Point" | "MultiPoint" | "LineString" | "MultiLineString" | "Polygon" | "MultiPolygon"
+        | "GeometryCollection" => {
+            features.push(Feature {
+                geometry: object,
+                properties: [overrides, None],
+            });
+        }
+        _ => {}
+    }
+}
+
+/// Loads the text of the page referenced by a Kartographer `ExternalData`
+/// object.
+///
+/// Only the `page` service can be used offline; the other services query
+/// Wikidata and OpenStreetMap.
+fn load_external_data<'a>(
+    state: &State<'_>,
+    object: &'a Value<'a>,
+) -> Option<(&'a Value<'a>, String)> {
+    if !is_type(object, "ExternalData") {
+        return None;
+    }
+
+    let service = object.get("service").and_then(Value::as_str);
+    let Some("page") = service else {
+        log::warn!("TODO: Kartographer ExternalData service {service:?}");
+        return None;
+    };
+
+    let title = object.get("title").and_then(Value::as_str)?;
+    let title = Title::new(&format!("Data:{}", title.trim_ascii()), None);
+    match state.statics.shared_db().get(&title) {
+        Ok(article) => Some((object, article.body.clone())),
+        Err(err) => {
+            log::warn!("Could not load map data {}: {err}", title.key());
+            None
+        }
+    }
+}
+
+/// Gets the `latitude` and `longitude` of a map tag as a position, in degrees
+/// longitude/latitude.
+fn coordinates(
+    state: &mut State<'_>,
+    arguments: &ExtensionTag<'_, '_, '_>,
+) -> Result<Option<[f64; 2]>> {
+    let parse = |value: Option<Cow<'_, str>>| {
+        value.and_then(|value| value.trim_ascii().parse::<f64>().ok())
+    };
+    let latitude = parse(arguments.get(state, "latitude")?);
+    let longitude = parse(arguments.get(state, "longitude")?);
+    Ok(latitude
+        .zip(longitude)
+        .filter(|(latitude, longitude)| latitude.abs() <= 90.0 && longitude.abs() <= 180.0)
+        .map(|(latitude, longitude)| [longitude, latitude]))
+}
+
+/// Extends `bounds`, in raw Mercator coordinates `[x0, y0, x1, y1]`, to include
+/// all the positions of `geometry`.
+fn extend_bounds(bounds: Option<[f64; 4]>, geometry: &Value<'_>) -> Option<[f64; 4]> {
+    /// Recursively walks a GeoJSON `coordinates` array.
+    fn walk(bounds: Option<[f64; 4]>, coordinates: &Value<'_>) -> Option<[f64; 4]> {
+        if let Some(position) = to_position(coordinates) {
+            let [x, y] = mercator(position);
+            Some(bounds.map_or([x, y, x, y], |[x0, y0, x1, y1]| {
+                [x0.min(x), y0.min(y), x1.max(x), y1.max(y)]
+            }))
+        } else {
+            coordinates
+                .as_array()
+                .into_iter()
+                .flatten()
+                .fold(bounds, walk)
+        }
+    }
+
+    if let Some(geometries) = geometry.get("geometries").and_then(Value::as_array) {
+        geometries.iter().fold(bounds, extend_bounds)
+    } else if let Some(coordinates) = geometry.get("coordinates") {
+        walk(bounds, coordinates)
+    } else {
+        bounds
+    }
+}
+
+/// Calculates the centre, in degrees longitude/latitude, and zoom level which
+/// fit the given `bounds`, in raw Mercator coordinates, into an image of the
+/// given `size`.
+fn fit_bounds([x0, y0, x1, y1]: [f64; 4], [width, height]: [f64; 2]) -> ([f64; 2], f64) {
+    /// The size of a slippy map tile, in pixels.
+    const TILE_SIZE: f64 = 256.0;
+
+    let center = inverse_mercator([(x0 + x1) / 2.0, (y0 + y1) / 2.0]);
+    let fit = |extent: f64, size: f64| {
+        let size = (size - FIT_PADDING * 2.0).max(1.0);
+        (size * core::f64::consts::TAU / (TILE_SIZE * extent)).log2()
+    };
+    let zoom = fit(x1 - x0, width)
+        .min(fit(y1 - y0, height))
+        .floor()
+        .clamp(0.0, MAX_FIT_ZOOM);
+    (center, zoom)
+}
+
+/// Converts a position, in degrees longitude/latitude, to raw Mercator
+/// coordinates.
+fn mercator([longitude, latitude]: [f64; 2]) -> [f64; 2] {
+    /// The latitude limit of Web Mercator, in degrees.
+    const MAX_LATITUDE: f64 = 85.051_128_78;
+
+    let latitude = latitude.clamp(-MAX_LATITUDE, MAX_LATITUDE).to_radians();
+    [
+        longitude.to_radians(),
+        // This is flipped so that y increases towards the south, like an image
+        -(FRAC_PI_4 + latitude / 2.0).tan().ln(),
+    ]
+}
+
+/// Converts raw Mercator coordinates to a position, in degrees
+/// longitude/latitude.
+fn inverse_mercator([x, y]: [f64; 2]) -> [f64; 2] {
+    [
+        x.to_degrees(),
+        (2.0 * (-y).exp().atan() - FRAC_PI_2).to_degrees(),
+    ]
+}
+
+/// Converts a GeoJSON position into `[longitude, latitude]`.
+fn to_position(value: &Value<'_>) -> Option<[f64; 2]> {
+    match value.as_array()? {
+        [x, y, ..] => Some([x.as_f64()?, y.as_f64()?]),
+        _ => None,
+    }
+}
+
+/// Returns true if the given GeoJSON object has the given type.
+fn is_type(object: &Value<'_>, kind: &str) -> bool {
+    object.get("type").and_then(Value::as_str) == Some(kind)
+}
+
+/// Converts the Wikitext `title` and `description` of a feature to a plain
+/// text tooltip.
+fn feature_title(
+    state: &mut State<'_>,
+    arguments: &ExtensionTag<'_, '_, '_>,
+    feature: &Feature<'_>,
+) -> Result<Option<String>> {
+    let mut out = String::new();
+    for key in ["title", "description"] {
+        if let Some(text) = feature.string(key) {
+            let text = preprocess_frame(state, arguments.sp, text)?;
+            let text = state.strip_markers.unstrip(&text);
+            let sp = arguments.sp.clone_with_source(FileMap::new(&text));
+            let root = state.statics.parser.parse_no_expansion(&sp.source)?;
+            let mut extractor = TextContent::new(&sp.source, String::new());
+            extractor.visit_tokens(&root.root)?;
+            let text = extractor.finish();
+            let text = decode_html(text.trim_ascii());
+            if !text.is_empty() {
+                if !out.is_empty() {
+                    out.push('\n');
+                }
+                out += &text;
+            }
+        }
+    }
+    Ok((!out.is_empty()).then_some(out))
+}
+
+/// Converts a simplestyle `marker-symbol` into the text to draw on a marker.
+///
+/// The automatic `-number` and `-letter` symbols count up separately for each
+/// distinct symbol name. Symbols from the Maki icon set are not drawn.
+fn marker_symbol(symbol: &str, counters: &mut HashMap<String, usize>) -> Option<String> {
+    if symbol.starts_with("-number") || symbol.starts_with("-letter") {
+        let counter = counters.entry(symbol.to_owned()).or_default();
+        *counter += 1;
+        Some(if symbol.starts_with("-number") {
+            counter.to_string()
+        } else {
+            let letter = u8::try_from((*counter - 1) % 26).unwrap_or_default();
+            char::from(b'a' + letter).to_string()
+        })
+    } else if symbol.chars().count() <= 2 {
+        Some(symbol.to_owned())
+    } else {
+        None
+    }
+}
+
+/// Parses a simplestyle colour, which is a hex colour with an optional `#`.
+fn parse_color(value: &str) -> Option<String> {
+    let hex = value.trim_ascii();
+    let hex = hex.strip_prefix('#').unwrap_or(hex);
+    (matches!(hex.len(), 3 | 6) && hex.bytes().all(|c| c.is_ascii_hexdigit()))
+        .then(|| format!("#{hex}"))
+}
+
+/// Parses a map dimension attribute.
+fn parse_size(value: Option<&str>) -> f64 {
+    value
+        .and_then(|value| {
+            value
+                .trim_ascii()
+                .trim_end_matches("px")
+                .trim_ascii_end()
+                .parse::<f64>()
+                .ok()
+        })
+        .filter(|value| value.is_finite() && *value > 0.0)
+        .map_or(DEFAULT_SIZE, |value| value.min(FULL_WIDTH * 2.0))
+}
+
+/// Formats a position, in degrees longitude/latitude, as degrees, minutes, and
+/// seconds.
+fn format_coordinates([longitude, latitude]: [f64; 2]) -> String {
+    /// Formats a single coordinate.
+    fn dms(value: f64, positive: char, negative: char) -> String {
+        let hemisphere = if value < 0.0 { negative } else { positive };
+        let seconds = (value.abs() * 3600.0).round();
+        let degrees = (seconds / 3600.0).floor();
+        let minutes = ((seconds - degrees * 3600.0) / 60.0).floor();
+        let seconds = seconds - degrees * 3600.0 - minutes * 60.0;
+        format!("{degrees}°{minutes}′{seconds}″{hemisphere}")
+    }
+
+    format!("{} {}", dms(latitude, 'N', 'S'), dms(longitude, 'E', 'W'))
+}
+
+/// Writes a map error.
+fn write_error(out: &mut String, err: &str) -> Result<()> {
+    write!(
+        out,
+        r#"<span class="error mw-kartographer-error">Invalid map data: {}</span>"#,
+        html_escape::encode_text(err)
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dms() {
+        assert_eq!(
+            format_coordinates([-0.1275, 51.507_222]),
+            "51°30′26″N 0°7′39″W"
+        );
+        assert_eq!(format_coordinates([151.0, -33.5]), "33°30′0″S 151°0′0″E");
+    }
+
+    #[test]
+    fn fit() {
+        let bounds = [mercator([-10.0, -10.0]), mercator([10.0, 10.0])];
+        let ([x, y], zoom) = fit_bounds(
+            [bounds[0][0], bounds[1][1], bounds[1][0], bounds[0][1]],
+            [300.0, 300.0],
+        );
+        assert!(x.abs() < 1e-9 && y.abs() < 1e-9);
+        assert!((zoom - 4.0).abs() < f64::EPSILON);
+
+        let [x, y] = inverse_mercator(mercator([12.5, 41.9]));
+        assert!((x - 12.5).abs() < 1e-9 && (y - 41.9).abs() < 1e-9);
+    }
+
+    #[test]
+    fn symbols() {
+        let mut counters = HashMap::new();
+        assert_eq!(
+            marker_symbol("-number", &mut counters).as_deref(),
+            Some("1")
+        );
+        assert_eq!(
+            marker_symbol("-number", &mut counters).as_deref(),
+            Some("2")
+        );
+        assert_eq!(
+            marker_symbol("-number-other", &mut counters).as_deref(),
+            Some("1")
+        );
+        assert_eq!(
+            marker_symbol("-letter", &mut counters).as_deref(),
+            Some("a")
+        );
+        assert_eq!(marker_symbol("A", &mut counters).as_deref(), Some("A"));
+        assert_eq!(marker_symbol("museum", &mut counters), None);
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(parse_color("#f00").as_deref(), Some("#f00"));
+        assert_eq!(parse_color("00FF00").as_deref(), Some("#00FF00"));
+        assert_eq!(parse_color("red"), None);
+        assert_eq!(parse_color("#12345"), None);
+    }
+}
@@ -134,33 +134,51 @@
     reason = "implementing an interface invisible to clippy"
 )]
 
+mod cache;
+mod chart;
+mod cite;
+mod disk_cache;
+mod font;
 mod graph;
+mod json_config;
+mod kartographer;
+mod raster;
 mod svg;
+mod template_data;
 mod timeline;
 
+pub(crate) use cache::{PngSources, set_cache as set_ext_cache};
+pub(crate) use chart::render_chart;
+pub(crate) use cite::References;
+pub(crate) use font::set_fallback_fonts;
+pub(crate) use json_config::{localize_in as localize_json_data, render_tabular};
+pub(crate) use kartographer::set_basemap as set_map_basemap;
+pub(crate) use raster::{Error as RenderError, png as render_png, set_cache as set_render_cache};
+pub(crate) use template_data::TemplateData;
+
 use super::{
-    Error, ExpandMode, ExpandTemplates, State, StripMarker,
-    document::Document,
+    Error, ExpandMode, ExpandTemplates, State, Statics, StripMarker,
+    document::{Document, inline_style},
     image,
     stack::{IndexedArgs, KeyCacheKvs, Kv, StackFrame},
     surrogate::Surrogate as _,
+    tags::LinkKind,
     text_run,
 };
 use crate::{
     common::{CowExt as _, anchor_encode, decode_html},
-    db::Database,
+    db::{Article, Database},
     php::strtr,
     title::{Namespace, Title},
     wikitext::{self, Argument, FileMap, Output, Span, Spanned, Token},
 };
 use core::{fmt::Write as _, ops::Range};
 use either::Either;
-use numerals::roman::Roman;
 use regex::{Regex, RegexBuilder};
 use std::{
     borrow::Cow,
     collections::{HashMap, HashSet},
-    sync::LazyLock,
+    sync::{Arc, LazyLock},
 };
 
 /// The result type for an extension tag function.
@@ -214,39 +232,147 @@ impl ExtensionTag<'_, '_, '_> {
 }
 
 /// The `<gallery>` extension tag.
+/// <https://www.mediawiki.org/wiki/Help:Images#Rendering_a_gallery_of_images>
 fn gallery(
     out: &mut String,
     state: &mut State<'_>,
     arguments: &ExtensionTag<'_, '_, '_>,
 ) -> Result {
-    // TODO: params mode, showfilename, showthumbnails (for mode = slideshow),
-    // PLUS any valid HTML attribute that applies to a `<ul>`,
-    // TODO: modes: nolines, packed, packed-hover, packed-overlay, slideshow
+    /// Gallery parameters, which are never emitted as attributes of the list.
+    const PARAMETERS: &[&str] = &[
+        "caption",
+        "class",
+        "heights",
+        "mode",
+        "perrow",
+        "showfilename",
+        "showthumbnails",
+        "widths",
+    ];
+
     let mode = arguments
         .get(state, "mode")?
-        .unwrap_or("traditional".into());
+        .map(|mode| mode.trim_ascii().to_ascii_lowercase());
+    let mode = match mode.as_deref() {
+        Some(mode @ ("nolines" | "packed" | "packed-hover" | "packed-overlay" | "slideshow")) => {
+            mode
+        }
+        _ => "traditional",
+    };
+    let is_grid = matches!(mode, "traditional" | "nolines");
 
     let class = arguments.get(state, "class")?.unwrap_or_default();
-    let heights = arguments.get(state, "heights")?;
+    let heights = gallery_size(arguments.get(state, "heights")?.as_deref());
+    let widths = gallery_size(arguments.get(state, "widths")?.as_deref());
+    let per_row = arguments
+        .get(state, "perrow")?
+        .and_then(|per_row| per_row.trim_ascii().parse::<u32>().ok())
+        .filter(|per_row| *per_row > 0);
+    let show_filename = arguments.get(state, "showfilename")?.is_some();
+    let show_thumbnails = arguments.get(state, "showthumbnails")?.is_some();
+
+    let mut attrs = String::new();
+    let mut style = String::new();
+    for attribute in arguments.iter() {
+        let Some(name) = attribute.name(state, arguments.sp)? else {
+            continue;
+        };
+        let name = name.trim_ascii().to_ascii_lowercase();
+        if PARAMETERS.contains(&name.as_str()) || !is_list_attribute(&name) {
+            continue;
+        }
+
+        let value = attribute.value(state, arguments.sp)?;
+        let value = state.strip_markers.unstrip(&value);
+        match name.as_str() {
+            "id" => write!(attrs, r#" id="{}""#, anchor_encode(&value))?,
+            "style" => style += &inline_style(&decode_html(&value))?,
+            _ => write!(
+                attrs,
+                r#" {name}="{}""#,
+                html_escape::encode_double_quoted_attribute(&decode_html(&value))
+            )?,
+        }
+    }
+
+    if let Some(heights) = heights {
+        write!(style, "--gallery-heights:{heights}px;")?;
+    }
+    if is_grid && (per_row.is_some() || widths.is_some()) {
+        let width = widths.map_or("1fr".into(), |widths| Cow::Owned(format!("{widths}px")));
+        let per_row = per_row.map_or("auto-fill".into(), |per_row| {
+            Cow::Owned(per_row.to_string())
+        });
+        write!(style, "grid-template-columns:repeat({per_row},{width});")?;
+    }
+    if !style.is_empty() {
+        write!(
+            attrs,
+            r#" style="{}""#,
+            html_escape::encode_double_quoted_attribute(&style)
+        )?;
+    }
 
     let mut defaults = image::Options::default();
     defaults.align = Some("none".into());
     defaults.format = Some("thumb".into());
-    if let Some(heights) = &heights {
-        defaults
+
+    let mut items = Vec::new();
+    for line in arguments.body().lines() {
+        let (target, rest) = line.split_once('|').unwrap_or((line, ""));
+
+        let target = percent_encoding::percent_decode_str(target.trim_ascii()).decode_utf8_lossy();
+        if !Title::is_valid(&target) {
+            continue;
+        }
+
+        let title = Title::new(&target, Namespace::find_by_id(Namespace::FILE));
+        if title.namespace().id != Namespace::FILE {
+            continue;
+        }
+
+        let args = preprocess_frame(state, arguments.sp, rest)?;
+        let sp = arguments.sp.clone_with_source(FileMap::new(&args));
+        let args = state.statics.parser.parse_gallery_media(&sp.source)?;
+        let mut options = image::media_options(state, &sp, title.clone(), &args, defaults.clone())?;
+
+        // Sizes given for individual images are ignored by MW, but since there
+        // is no other way to know the dimensions of an image, they are used to
+        // lay out packed galleries
+        let ratio = options
             .attrs
-            .insert("height".into(), Cow::Borrowed(heights));
-    }
+            .get("width")
+            .zip(options.attrs.get("height"))
+            .and_then(|(width, height)| {
+                Some(width.parse::<f64>().ok()? / height.parse::<f64>().ok()?)
+            })
+            .filter(|ratio| ratio.is_finite() && *ratio > 0.0);
+        if ratio.is_none() {
+            options.attrs.remove("width");
+            if let Some(heights) = heights {
+                options
+                    .attrs
+                    .insert("height".into(), Cow::Owned(heights.to_string()));
+            } else {
+                options.attrs.remove("height");
+            }
+        }
 
-    let per_row = arguments.get(state, "perrow")?;
-    let widths = arguments.get(state, "widths")?;
-    let attrs = if per_row.is_some() || widths.is_some() {
-        let width = widths.map_or("1fr".into(), |widths| Cow::Owned(format!("{widths}px")));
-        let per_row = per_row.unwrap_or("auto-fill".into());
-        format!(r#" style="grid-template-columns: repeat({per_row}, {width})""#)
-    } else {
-        <_>::default()
-    };
+        let src = options
+            .attrs
+            .get("src")
+            .map(ToString::to_string)
+            .unwrap_or_default();
+
+        let mut inner = Document::new(true);
+        image::render_media_with_options(&mut inner, state, &sp, &options)?;
+        items.push(GalleryItem {
+            html: inner.finish()?,
+            ratio,
+            src,
+            title,
+        });
+    }
 
     // MW put this *inside* the list, which is obviously stupid and wrong
     if let Some(caption) = arguments.get(state, "caption")? {
@@ -259,42 +385,174 @@ fn gallery(
         out,
         r#"<ul class="gallery mw-gallery-{mode} {class}"{attrs}>"#
     )?;
-    for image in arguments.body().lines() {
-        let Some((target, rest)) = image.split_once('|') else {
-            continue;
-        };
-
-        let target = percent_encoding::percent_decode_str(target).decode_utf8_lossy();
-        if !Title::is_valid(&target) {
-            continue;
+    if mode == "slideshow" {
+        write_slideshow(out, state, &items, show_filename, show_thumbnails)?;
+    } else {
+        for item in &items {
+            write!(out, r#"<li class="gallerybox""#)?;
+            if !is_grid && let Some(ratio) = item.ratio {
+                write!(out, r#" style="--gallery-ratio:{ratio:.4}""#)?;
+            }
+            write!(out, ">{}", item.html)?;
+            if show_filename {
+                write_gallery_filename(out, state, &item.title)?;
+            }
+            write!(out, "</li>")?;
         }
+    }
+    write!(out, "</ul>")?;
 
-        let title = Title::new(&target, Namespace::find_by_id(Namespace::FILE));
+    Ok(OutputMode::Block)
+}
 
-        let args = preprocess_frame(state, arguments.sp, rest)?;
-        let sp = arguments.sp.clone_with_source(FileMap::new(&args));
-        let args = state.statics.parser.parse_gallery_media(&sp.source)?;
-        let options = image::media_options(state, &sp, title, &args, defaults.clone())?;
+/// A rendered `<gallery>` image.
+struct GalleryItem {
+    /// The rendered media.
+    html: String,
+    /// The aspect ratio of the media, if it is known.
+    ratio: Option<f64>,
+    /// The URL of the media.
+    src: String,
+    /// The title of the media file.
+    title: Title,
+}
 
-        let mut inner = Document::new(true);
-        image::render_media_with_options(&mut inner, state, &sp, &options)?;
-        write!(out, r#"<li class="gallerybox">{}</li>"#, inner.finish()?)?;
+/// Parses a `<gallery>` `heights` or `widths` value. Like MW, non-positive
+/// sizes are treated as missing.
+fn gallery_size(value: Option<&str>) -> Option<u32> {
+    value?
+        .trim_ascii()
+        .trim_end_matches("px")
+        .trim_ascii_end()
+        .parse::<u32>()
+        .ok()
+        .filter(|size| *size > 0)
+}
+
+/// Returns true if the given lowercase attribute name is allowed on the `<ul>`
+/// of a `<gallery>`.
+fn is_list_attribute(name: &str) -> bool {
+    let is_valid_suffix = |suffix: &str| {
+        !suffix.is_empty()
+            && suffix
+                .bytes()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, b'-' | b'_' | b'.'))
+    };
+
+    matches!(
+        name,
+        "dir"
+            | "id"
+            | "itemid"
+            | "itemprop"
+            | "itemref"
+            | "itemscope"
+            | "itemtype"
+            | "lang"
+            | "role"
+            | "style"
+            | "tabindex"
+            | "title"
+            | "type"
+    ) || name.strip_prefix("aria-").is_some_and(is_valid_suffix)
+        || name.strip_prefix("data-").is_some_and(|suffix| {
+            is_valid_suffix(suffix) && !suffix.starts_with("mw") && !suffix.starts_with("ooui")
+        })
+}
+
+/// Writes a link to the file page of a `<gallery>` image.
+fn write_gallery_filename(out: &mut String, state: &State<'_>, title: &Title) -> Result<()> {
+    let href = LinkKind::Internal(title.clone()).to_string(&state.statics.base_uri, None);
+    write!(
+        out,
+        r#"<a class="galleryfilename" href="{}" title="{}">{}</a>"#,
+        html_escape::encode_double_quoted_attribute(&href),
+        html_escape::encode_double_quoted_attribute(title.prefixed_text()),
+        html_escape::encode_text(title.text())
+    )?;
+    Ok(())
+}
+
+/// Writes the items of a slideshow `<gallery>`.
+///
+/// Since there are no scripts, each slide has a radio button which is checked
+/// when the slide is visible, and the navigation controls are labels for the
+/// radio buttons of other slides.
+fn write_slideshow(
+    out: &mut String,
+    state: &mut State<'_>,
+    items: &[GalleryItem],
+    show_filename: bool,
+    show_thumbnails: bool,
+) -> Result<()> {
+    state.globals.gallery_ordinal += 1;
+    let group = format!("mw-gallery-slideshow-{}", state.globals.gallery_ordinal);
+
+    for (index, item) in items.iter().enumerate() {
+        let checked = if index == 0 { " checked" } else { "" };
+        write!(
+            out,
+            r#"<li class="gallerybox"><input class="gallery-slideshow-toggle" type="radio" name="{group}" id="{group}-{index}"{checked}>"#
+        )?;
+        if items.len() > 1 {
+            let prev = index.checked_sub(1).unwrap_or(items.len() - 1);
+            let next = (index + 1) % items.len();
+            write!(
+                out,
+                r#"<label class="gallery-slideshow-prev" for="{group}-{prev}" title="Previous">‹</label><label class="gallery-slideshow-next" for="{group}-{next}" title="Next">›</label>"#
+            )?;
+        }
+        write!(out, "{}", item.html)?;
+        if show_filename {
+            write_gallery_filename(out, state, &item.title)?;
+        }
+        write!(out, "</li>")?;
     }
-    write!(out, "</ul>")?;
 
-    Ok(OutputMode::Block)
+    if show_thumbnails && items.len() > 1 {
+        write!(out, r#"<li class="gallerythumbnails">"#)?;
+        for (index, item) in items.iter().enumerate() {
+            write!(
+                out,
+                r#"<label for="{group}-{index}" title="{}"><img src="{}" alt=""></label>"#,
+                html_escape::encode_double_quoted_attribute(item.title.text()),
+                html_escape::encode_double_quoted_attribute(&item.src)
+            )?;
+        }
+        write!(out, "</li>")?;
+    }
+
+    Ok(())
 }
 
 /// The `<graph>` extension tag.
 /// <https://www.mediawiki.org/w/index.php?oldid=7692802>
 fn graph(out: &mut String, state: &mut State<'_>, arguments: &ExtensionTag<'_, '_, '_>) -> Result {
     // Possible attributes: fallback, fallbackWidth
-    let result = graph::spec_to_svg(arguments.body(), state.statics.base_time)
+    let result = graph::spec_to_svg(arguments.body(), state.statics.base_time, state.statics)
         .map_err(|err| Error::Extension(Box::new(err)))?;
-    write!(out, "{result}")?;
+    let png = cache::register_png(state, raster::Kind::Svg, &result);
+    write!(out, "{}", raster::with_png_url(&result, png.as_deref()))?;
     Ok(OutputMode::Block)
 }
 
+impl graph::Loader for Statics {
+    fn page(&self, title: &Title) -> Option<Arc<Article>> {
+        load_graph_page(&self.db, title)
+    }
+
+    fn shared_page(&self, title: &Title) -> Option<Arc<Article>> {
+        load_graph_page(self.shared_db(), title)
+    }
+}
+
+/// Loads a page for a `<graph>` data URL from the given database.
+fn load_graph_page(db: &Database<'static>, title: &Title) -> Option<Arc<Article>> {
+    db.get(title)
+        .inspect_err(|err| log::warn!("Could not load graph data {}: {err}", title.key()))
+        .ok()
+}
+
 /// The `<indicator>` extension tag.
 /// <https://www.mediawiki.org/wiki/Help:Page_status_indicators>
 fn indicator(
@@ -337,28 +595,6 @@ fn indicator(
     Ok(OutputMode::Empty)
 }
 
-/// The `<mapframe>` extension tag.
-/// <https://www.mediawiki.org/wiki/Extension:Kartographer>
-fn map_frame(out: &mut String, _: &mut State<'_>, arguments: &ExtensionTag<'_, '_, '_>) -> Result {
-    // TODO: Params: width (number, "100%", "full"), height (number), zoom
-    // (0-19, default 12), latitude, longitude, align ("left", "center",
-    // "right"), mapstyle ("osm", "osm-intl"), lang (code or "local"), alt,
-    // text, frameless
-
-    // TODO: Actually parse whatever JSON is there and emit a map
-    let body = strtr(
-        arguments.body(),
-        &[
-            ("-{", "-&#123;"),
-            ("}-", "&#125;-"),
-            ("<", "&lt;"),
-            (">", "&gt;"),
-        ],
-    );
-    write!(out, "<pre>{body}</pre>")?;
-    Ok(OutputMode::Block)
-}
-
 /// The `<math>` extension tag.
 /// <https://www.mediawiki.org/wiki/Special:MyLanguage/Extension:Math>
 fn math(out: &mut String, state: &mut State<'_>, arguments: &ExtensionTag<'_, '_, '_>) -> Result {
@@ -389,7 +625,9 @@ fn math(out: &mut String, state: &mut State<'_>, arguments: &ExtensionTag<'_, '_
         .convert_with_local_counter(latex, mode)
     {
         Ok(maths) => {
-            out.write_str(&wikitext::escape(&maths))?;
+            let png = cache::register_png(state, raster::Kind::MathMl, &maths);
+            let maths = wikitext::escape(&maths);
+            out.write_str(&raster::with_png_url(&maths, png.as_deref()))?;
         }
         Err(err) => {
             write!(
@@ -534,281 +772,6 @@ fn pre(out: &mut String, state: &mut State<'_>, arguments: &ExtensionTag<'_, '_,
     Ok(OutputMode::Block)
 }
 
-/// A reference key.
-#[derive(Debug, Eq, Hash, PartialEq)]
-struct RefKey {
-    /// The group name.
-    group: String,
-    /// The reference name.
-    name: String,
-}
-
-/// Stored citation references.
-#[derive(Debug, Default)]
-pub(crate) struct References {
-    /// Bump allocation of reference text.
-    text: String,
-    /// References in a group. Value is a map of ranges into `text`. For
-    /// compatibility, the default group is an empty string.
-    groups: HashMap<String, Vec<Range<usize>>>,
-    /// Named references. Value is an index into `groups[key.group]`.
-    named: HashMap<RefKey, usize>,
-}
-
-impl References {
-    /// Appends text to a named reference, separated by a single space. If the
-    /// reference does not already exist, it is created.
-    fn append_named(&mut self, name: RefKey, value: &str) {
-        let group = self.groups.entry(name.group.clone()).or_default();
-
-        let index = self.named.entry(name).or_insert(group.len());
-
-        if let Some(range) = group.get_mut(*index) {
-            if range.end == self.text.len() {
-                if !Range::is_empty(range) {
-                    self.text.push(' ');
-                }
-                self.text += value;
-                range.end = self.text.len();
-            } else {
-                let old_range = range.clone();
-                range.start = self.text.len();
-                if !old_range.is_empty() {
-                    self.text.extend_from_within(old_range);
-                    self.text.push(' ');
-                }
-                self.text += value;
-                range.end = self.text.len();
-            }
-        } else {
-            let range = self.text.len()..(self.text.len() + value.len());
-            self.text += value;
-            group.push(range);
-        }
-    }
-
-    /// Adds a named reference with the given text. If the reference already
-    /// exists and contains text, this call does nothing. Returns the
-    /// page-unique ID of the reference.
-    fn insert_named(&mut self, name: RefKey, value: &str) -> String {
-        if let Some(index) = self.named.get(&name) {
-            let key = Self::make_id(&name.group, *index);
-            // TODO: 'cite_error_references_duplicate_key'
-            let range = &self.groups[&name.group][*index];
-
-            // Some pages like 'Wikidata' create empty named refs and then
-            // populate the data later
-            if range.is_empty() && !value.is_empty() {
-                self.append_named(name, value);
-            }
-
-            return key;
-        }
-
-        let range = self.text.len()..(self.text.len() + value.len());
-        self.text += value;
-
-        let group = self.groups.entry(name.group.clone()).or_default();
-        let index = group.len();
-        let key = Self::make_id(&name.group, index);
-        self.named.insert(name, index);
-        group.push(range);
-        key
-    }
-
-    /// Adds an named reference with the given text. Returns the page-unique
-    /// numeric ID of the reference.
-    fn insert_unnamed(&mut self, group: String, value: &str) -> String {
-        let range = self.text.len()..(self.text.len() + value.len());
-        self.text += value;
-
-        let name = group.clone();
-        let group = self.groups.entry(group).or_default();
-        let key = Self::make_id(&name, group.len());
-        group.push(range);
-        key
-    }
-
-    /// Returns an iterator over the references in the given group.
-    fn iter_group(&self, group_name: &String) -> Option<impl Iterator<Item = (String, &str)>> {
-        self.groups.get(group_name).map(|group| {
-            group
-                .iter()
-                .enumerate()
-                .map(|(index, range)| (Self::make_id(group_name, index), &self.text[range.clone()]))
-        })
-    }
-
-    /// Encodes forward and backward reference anchors for an ID.
-    fn make_anchors(id: &str) -> (String, String) {
-        (
-            anchor_encode(&format!("cite_ref-{id}")),
-            anchor_encode(&format!("ref_{id}")),
-        )
-    }
-
-    /// Makes a unique key for a grouped reference.
-    fn make_id(group: &str, index: usize) -> String {
-        let index = index + 1;
-        if group.is_empty() {
-            index.to_string()
-        } else {
-            match group {
-                "decimal" => index.to_string(),
-                "lower-alpha" => Self::make_id_alpha(index - 1, 'a', 26),
-                "upper-alpha" => Self::make_id_alpha(index - 1, 'A', 26),
-                "lower-greek" => Self::make_id_alpha(index - 1, 'α', 24),
-                "upper-greek" => Self::make_id_alpha(index - 1, 'Α', 24),
-                "lower-roman" => format!("{:x}", Roman::from(i16::try_from(index).unwrap())),
-                "upper-roman" => format!("{:X}", Roman::from(i16::try_from(index).unwrap())),
-                _ => format!("{group} {index}"),
-            }
-        }
-    }
-
-    /// Makes an alphabetic ordinal key.
-    fn make_id_alpha(mut n: usize, base: char, alphabet_size: usize) -> String {
-        let mut buf = ['\0'; 8];
-        let mut index = 0;
-        #[expect(
-            clippy::cast_possible_truncation,
-            reason = "`alphabet_size` is always ≤u32::MAX"
-        )]
-        loop {
-            buf[index] = char::from_u32(u32::from(base) + (n % alphabet_size) as u32).unwrap();
-            n /= alphabet_size;
-            index += 1;
-            if n == 0 {
-                break;
-            }
-        }
-        buf[..index].iter().rev().collect()
-    }
-}
-
-/// The `<ref>` extension tag.
-/// <https://www.mediawiki.org/wiki/Special:MyLanguage/Extension:Cite>
-fn r#ref(out: &mut String, state: &mut State<'_>, arguments: &ExtensionTag<'_, '_, '_>) -> Result {
-    // Due to transclusion it is necessary to render immediately instead of
-    // storing the node list for later, since rendering later would require
-    // retaining the stack frame too
-    let reference = eval_string(state, arguments.sp, arguments.body().trim_ascii())?;
-
-    let group = arguments
-        .get(state, "group")?
-        .as_deref()
-        .map_or(<_>::default(), ToString::to_string);
-
-    if let Some(follow) = arguments.get(state, "follow")? {
-        state.globals.references.append_named(
-            RefKey {
-                group,
-                name: follow.to_string(),
-            },
-            &reference,
-        );
-        return Ok(OutputMode::Empty);
-    }
-
-    let id = if let Some(name) = arguments.get(state, "name")? {
-        Some(state.globals.references.insert_named(
-            RefKey {
-                group: group.clone(),
-                name: name.to_string(),
-            },
-            &reference,
-        ))
-    } else if !reference.is_empty() {
-        Some(
-            state
-                .globals
-                .references
-                .insert_unnamed(group.clone(), &reference),
-        )
-    } else {
-        None
-    };
-
-    Ok(if let Some(id) = id {
-        let (from, to) = References::make_anchors(&id);
-        write!(
-            out,
-            r##"<span class="reference" id="{from}"><a href="#{to}">{id}</a></span>"##
-        )?;
-        OutputMode::Inline
-    } else {
-        OutputMode::Empty
-    })
-}
-
-/// The `<references>` extension tag.
-/// <https://www.mediawiki.org/wiki/Special:MyLanguage/Extension:Cite>
-fn references(
-    out: &mut String,
-    state: &mut State<'_>,
-    arguments: &ExtensionTag<'_, '_, '_>,
-) -> Result {
-    // Here, someone vibed the idea that the references tag -- which is supposed
-    // to be an output -- should also accept content, which we must now evaluate
-    // purely for the side effect that someone shoved a `<ref>` in there. This
-    // is codified behaviour by 'Template:Reflist' having a `refs` property, and
-    // is used on the page 'Donkey' (probably among thousands of others).
-    // The Cite extension actually maintains a stack, which feels concerning to
-    // me, since it means someone thought about what happens when you have refs
-    // inside refs, and that sounds like a cursed thing to have to think about.
-    if arguments.body.is_some() {
-        // TODO: Any refs inside here are supposed to be added to the group
-        // matching the group attribute of the `<references>` tag, not the
-        // empty group.
-        arguments.eval_body(state)?;
-    }
-
-    // If some template expansion emits a `<ref>` tag, the content of that
-    // reference will be lost if `<references>` is also processed during
-    // template expansion. Because wiki.rs converts `#tag` calls from scripts
-    // into XML tags to be processed during the final pass, scripts that call
-    // that also will break if `<references>` processing is not deferred.
-    if !arguments.in_document {
-        return render_raw(
-            state,
-            arguments.sp,
-            arguments.callee,
-            // The body was processed, only the output remains.
-            None,
-            // buffalo buffalo buffalo 😵‍💫
-            &arguments.arguments.arguments,
-            out,
-        );
-    }
-
-    let group = arguments
-        .get(state, "group")?
-        .as_deref()
-        .map_or(<_>::default(), ToString::to_string);
-
-    // TODO: For multiple references to the same name, there should be backrefs
-    // to all of them, not just the first one.
-    Ok(
-        if let Some(refs) = state.globals.references.iter_group(&group) {
-            write!(out, r#"<ol class="references">"#)?;
-            for (index, (id, text)) in refs.enumerate() {
-                if !text.is_empty() {
-                    let index = index + 1;
-                    let (from, to) = References::make_anchors(&id);
-                    write!(
-                        out,
-                        r##"<li value="{index}" id="{to}" class="mw-cite-backlink"><a href="#{from}">^</a> {text}</li>"##
-                    )?;
-                }
-            }
-            write!(out, "</ol>")?;
-            OutputMode::Block
-        } else {
-            OutputMode::Empty
-        },
-    )
-}
-
 /// Stored ranges for labelled section transclusion.
 ///
 /// These are not currently used for anything; Lua modules which perform
@@ -919,27 +882,6 @@ fn syntax_highlight(
     Ok(mode)
 }
 
-/// The `<templatedata>` extension tag.
-/// <https://www.mediawiki.org/wiki/Special:MyLanguage/Extension:TemplateData>
-fn template_data(
-    out: &mut String,
-    _: &mut State<'_>,
-    arguments: &ExtensionTag<'_, '_, '_>,
-) -> Result {
-    // TODO: Actually parse the JSON and emit a table
-    let body = strtr(
-        arguments.body(),
-        &[
-            ("-{", "-&#123;"),
-            ("}-", "&#125;-"),
-            ("<", "&lt;"),
-            (">", "&gt;"),
-        ],
-    );
-    write!(out, "<pre>{body}</pre>")?;
-    Ok(OutputMode::Block)
-}
-
 /// The `<timeline>` extension tag.
 /// <https://www.mediawiki.org/wiki/Special:MyLanguage/Extension:EasyTimeline>
 fn timeline(
@@ -948,9 +890,20 @@ fn timeline(
     arguments: &ExtensionTag<'_, '_, '_>,
 ) -> Result {
     if let Some(body) = arguments.body {
-        let result = timeline::timeline_to_svg(body, &state.statics.base_uri)
-            .map_err(|err| Error::Extension(Box::new(err)))?;
-        write!(out, r#"<figure class="wiki-rs-timeline">{result}</figure>"#)?;
+        match timeline::timeline_to_svg(body, &state.statics.base_uri) {
+            Ok(result) => {
+                let png = cache::register_png(state, raster::Kind::Svg, &result);
+                let result = raster::with_png_url(&result, png.as_deref());
+                write!(out, r#"<figure class="wiki-rs-timeline">{result}</figure>"#)?;
+            }
+            Err(err) => {
+                write!(
+                    out,
+                    r#"<div class="error wiki-rs-timeline-error">{}</div>"#,
+                    wikitext::escape_no_wiki(&err.to_string())
+                )?;
+            }
+        }
     }
     Ok(OutputMode::Block)
 }
@@ -1026,17 +979,18 @@ static EXTENSION_TAGS: phf::Map<&'static str, ExtensionTagFn> = phf::phf_map! {
     "gallery" => gallery,
     "graph" => graph,
     "indicator" => indicator,
-    "mapframe" => map_frame,
+    "mapframe" => kartographer::map_frame,
+    "maplink" => kartographer::map_link,
     "math" => math,
     "nowiki" => no_wiki,
     "poem" => poem,
     "pre" => pre,
-    "ref" => r#ref,
-    "references" => references,
+    "ref" => cite::r#ref,
+    "references" => cite::references,
     "section" => section,
     "source" => syntax_highlight,
     "syntaxhighlight" => syntax_highlight,
-    "templatedata" => template_data,
+    "templatedata" => template_data::template_data,
     "templatestyles" => template_styles,
     "timeline" => timeline,
 };
@@ -1088,7 +1042,7 @@ pub(super) fn render_extension_tag(
     let mut out = String::new();
     let mode = if let Some(extension_tag) = EXTENSION_TAGS.get(callee) {
         if let Some(span) = span {
-            extension_tag(
+            cache::render(
                 &mut out,
                 state,
                 &ExtensionTag {
@@ -1102,6 +1056,7 @@ pub(super) fn render_extension_tag(
                     from_parser_fn,
                     in_document,
                 },
+                *extension_tag,
             )
             .map_err(|err| Error::Node {
                 frame: sp.name.to_string() + "$<" + callee + ">",
@@ -0,0 +1,843 @@
+//! A rasteriser for the subset of SVG generated by the extension tags.
+//!
+//! Only presentation attributes are used for styling. Stylesheets in `<style>`
+//! elements are ignored, except for a `background-color` declaration, which is
+//! how timelines set their canvas colour. Images, gradients, patterns, and
+//! markers are not supported.
+
+use super::{super::font, Error, MAX_SIZE};
+use core::f32::consts::{FRAC_PI_2, TAU};
+use minidom::{Element, Node};
+use tiny_skia::{
+    Color, FillRule, Mask, Paint, Path, PathBuilder, Pixmap, Rect, Stroke, StrokeDash, Transform,
+};
+
+/// Draws an SVG image to a new pixmap.
+pub(super) fn rasterize(svg: &Element) -> Result<Pixmap, Error> {
+    let view_box = svg.attr("viewBox").and_then(|value| match *numbers(value) {
+        [x, y, width, height] if width > 0.0 && height > 0.0 => Some((x, y, width, height)),
+        _ => None,
+    });
+
+    let width = svg
+        .attr("width")
+        .and_then(length)
+        .or(view_box.map(|(_, _, width, _)| width))
+        .unwrap_or(300.0);
+    let height = svg
+        .attr("height")
+        .and_then(length)
+        .or(view_box.map(|(_, _, _, height)| height))
+        .unwrap_or(150.0);
+
+    if !(1.0..=MAX_SIZE).contains(&width) || !(1.0..=MAX_SIZE).contains(&height) {
+        return Err(Error::Size(width, height));
+    }
+
+    #[expect(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "values are checked to be in range"
+    )]
+    let mut pixmap =
+        Pixmap::new(width.ceil() as u32, height.ceil() as u32).ok_or(Error::Size(width, height))?;
+
+    if let Some(color) = background(svg) {
+        pixmap.fill(color);
+    }
+
+    let transform = view_box.map_or(Transform::identity(), |(x, y, vb_width, vb_height)| {
+        Transform::from_scale(width / vb_width, height / vb_height).pre_translate(-x, -y)
+    });
+
+    let mut style = Style::default();
+    style.apply(svg);
+    let mut canvas = Canvas { pixmap };
+    canvas.draw_children(svg, &style, transform, None);
+    Ok(canvas.pixmap)
+}
+
+/// Gets the background colour of the image from either the non-standard
+/// `background-color` attribute or a `background-color` declaration in a
+/// stylesheet.
+fn background(svg: &Element) -> Option<Color> {
+    let from_style = || {
+        svg.children()
+            .filter(|child| child.name() == "style")
+            .find_map(|style| {
+                let text = style.text();
+                let (_, rest) = text.split_once("background-color:")?;
+                let end = rest.find([';', '}'])?;
+                Some(rest[..end].trim().to_owned())
+            })
+    };
+
+    svg.attr("background-color")
+        .map(str::to_owned)
+        .or_else(from_style)
+        .and_then(|value| parse_color(&value))
+}
+
+/// A drawing surface.
+struct Canvas {
+    /// The output image.
+    pixmap: Pixmap,
+}
+
+impl Canvas {
+    /// Draws all the child elements of `parent`.
+    fn draw_children(
+        &mut self,
+        parent: &Element,
+        style: &Style,
+        transform: Transform,
+        mask: Option<&Mask>,
+    ) {
+        for child in parent.children() {
+            self.draw(child, style, transform, mask);
+        }
+    }
+
+    /// Draws an element.
+    fn draw(
+        &mut self,
+        element: &Element,
+        style: &Style,
+        transform: Transform,
+        mask: Option<&Mask>,
+    ) {
+        let mut style = style.clone();
+        style.apply(element);
+
+        let transform = element.attr("transform").map_or(transform, |value| {
+            transform.pre_concat(parse_transform(value))
+        });
+
+        match element.name() {
+            "a" | "g" | "svg" => {
+                let clip = element
+                    .attr("clip-path")
+                    .and_then(|value| self.clip(value, transform, mask));
+                self.draw_children(element, &style, transform, clip.as_ref().or(mask));
+            }
+            "line" => {
+                let attr = |name| element.attr(name).and_then(length).unwrap_or(0.0);
+                let mut path = PathBuilder::new();
+                path.move_to(attr("x1"), attr("y1"));
+                path.line_to(attr("x2"), attr("y2"));
+                if let Some(path) = path.finish() {
+                    self.draw_path(&path, &style, transform, mask);
+                }
+            }
+            "path" => {
+                if let Some(path) = element.attr("d").and_then(parse_path) {
+                    self.draw_path(&path, &style, transform, mask);
+                }
+            }
+            "rect" => {
+                let attr = |name| element.attr(name).and_then(length).unwrap_or(0.0);
+                if let Some(rect) =
+                    Rect::from_xywh(attr("x"), attr("y"), attr("width"), attr("height"))
+                {
+                    let path = PathBuilder::from_rect(rect);
+                    self.draw_path(&path, &style, transform, mask);
+                }
+            }
+            "text" => self.draw_text(element, &style, transform, mask),
+            _ => {}
+        }
+    }
+
+    /// Fills and strokes a path.
+    fn draw_path(&mut self, path: &Path, style: &Style, transform: Transform, mask: Option<&Mask>) {
+        if let Some(paint) = style
+            .fill
+            .and_then(|color| paint(color, style.fill_opacity * style.opacity))
+        {
+            self.pixmap
+                .fill_path(path, &paint, FillRule::Winding, transform, mask);
+        }
+
+        if style.stroke_width > 0.0
+            && let Some(paint) = style
+                .stroke
+                .and_then(|color| paint(color, style.stroke_opacity * style.opacity))
+        {
+            let stroke = Stroke {
+                width: style.stroke_width,
+                dash: style
+                    .stroke_dash
+                    .clone()
+                    .and_then(|dash| StrokeDash::new(dash, style.stroke_dash_offset)),
+                ..Stroke::default()
+            };
+            self.pixmap
+                .stroke_path(path, &paint, &stroke, transform, mask);
+        }
+    }
+
+    /// Draws a text element as a single line of text.
+    fn draw_text(
+        &mut self,
+        element: &Element,
+        style: &Style,
+        transform: Transform,
+        mask: Option<&Mask>,
+    ) {
+        let mut text = String::new();
+        text_content(element, &mut text);
+        let text = text.split_ascii_whitespace().collect::<Vec<_>>().join(" ");
+        if text.is_empty() {
+            return;
+        }
+
+        let shaped = font::shape_text(&text, style.bold, style.italic);
+        let font_size = f64::from(style.font_size);
+        #[expect(
+            clippy::cast_possible_truncation,
+            reason = "any value out of range of f32 is malformed and UB is fine"
+        )]
+        let width = shaped.width(font_size) as f32;
+
+        // In right-to-left text, the start of the text is on the right
+        let dx = match (style.anchor, style.rtl) {
+            (Anchor::Start, false) | (Anchor::End, true) => 0.0,
+            (Anchor::Middle, _) => -width / 2.0,
+            (Anchor::End, false) | (Anchor::Start, true) => -width,
+        };
+        let dy = style.baseline * style.font_size;
+
+        let attr = |name| element.attr(name).and_then(length).unwrap_or(0.0);
+        let transform = transform.pre_translate(attr("x") + dx, attr("y") + dy);
+        if let Some(path) = shaped.to_path(font_size) {
+            self.draw_path(&path, style, transform, mask);
+        }
+    }
+
+    /// Creates a clipping mask from a `clip-path` value. Only the `xywh()`
+    /// basic shape is supported.
+    fn clip(&self, value: &str, transform: Transform, mask: Option<&Mask>) -> Option<Mask> {
+        let args = value.trim().strip_prefix("xywh(")?.strip_suffix(')')?;
+        let &[x, y, width, height] = numbers(args).as_slice() else {
+            return None;
+        };
+
+        let path = PathBuilder::from_rect(Rect::from_xywh(x, y, width, height)?);
+        if let Some(mask) = mask {
+            let mut clip = mask.clone();
+            clip.intersect_path(&path, FillRule::Winding, true, transform);
+            Some(clip)
+        } else {
+            let mut clip = Mask::new(self.pixmap.width(), self.pixmap.height())?;
+            clip.fill_path(&path, FillRule::Winding, true, transform);
+            Some(clip)
+        }
+    }
+}
+
+/// Text anchor positions.
+#[derive(Clone, Copy, Debug, Default)]
+enum Anchor {
+    /// The text starts at the origin.
+    #[default]
+    Start,
+    /// The text is centred on the origin.
+    Middle,
+    /// The text ends at the origin.
+    End,
+}
+
+/// Inherited presentation attributes.
+#[derive(Clone, Debug)]
+struct Style {
+    /// The text anchor.
+    anchor: Anchor,
+    /// The offset of the alphabetic baseline from the dominant baseline, in
+    /// ems.
+    baseline: f32,
+    /// Whether text is bold.
+    bold: bool,
+    /// The fill colour.
+    fill: Option<Color>,
+    /// The fill opacity.
+    fill_opacity: f32,
+    /// The font size, in pixels.
+    font_size: f32,
+    /// Whether text is italic.
+    italic: bool,
+    /// The combined opacity of the element and its ancestors.
+    opacity: f32,
+    /// Whether text is right-to-left.
+    rtl: bool,
+    /// The stroke colour.
+    stroke: Option<Color>,
+    /// The stroke dash pattern.
+    stroke_dash: Option<Vec<f32>>,
+    /// The stroke dash offset.
+    stroke_dash_offset: f32,
+    /// The stroke opacity.
+    stroke_opacity: f32,
+    /// The stroke width.
+    stroke_width: f32,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            anchor: Anchor::Start,
+            baseline: 0.0,
+            bold: false,
+            fill: Some(Color::BLACK),
+            fill_opacity: 1.0,
+            font_size: 16.0,
+            italic: false,
+            opacity: 1.0,
+            rtl: false,
+            stroke: None,
+            stroke_dash: None,
+            stroke_dash_offset: 0.0,
+            stroke_opacity: 1.0,
+            stroke_width: 1.0,
+        }
+    }
+}
+
+impl Style {
+    /// Applies the presentation attributes of `element` to this style.
+    fn apply(&mut self, element: &Element) {
+        let attr = |name| {
+            element
+                .attr(name)
+                .map(str::trim)
+                .filter(|value| *value != "inherit")
+        };
+
+        if let Some(value) = attr("direction") {
+            self.rtl = value == "rtl";
+        }
+        if let Some(value) = attr("dominant-baseline") {
+            self.baseline = match value {
+                "central" => 0.35,
+                "middle" => 0.3,
+                "hanging" | "text-before-edge" => 0.8,
+                "ideographic" | "text-after-edge" => -0.2,
+                _ => 0.0,
+            };
+        }
+        if let Some(value) = attr("fill") {
+            self.fill = parse_color(value);
+        }
+        if let Some(value) = attr("fill-opacity") {
+            self.fill_opacity = opacity(value);
+        }
+        if let Some(size) = attr("font-size").and_then(length) {
+            self.font_size = size;
+        }
+        if let Some(value) = attr("font-style") {
+            self.italic = matches!(value, "italic" | "oblique");
+        }
+        if let Some(value) = attr("font-weight") {
+            self.bold = match value {
+                "bold" | "bolder" => true,
+                value => value.parse::<u16>().is_ok_and(|weight| weight >= 600),
+            };
+        }
+        if let Some(value) = attr("opacity") {
+            self.opacity *= opacity(value);
+        }
+        if let Some(value) = attr("stroke") {
+            self.stroke = parse_color(value);
+        }
+        if let Some(value) = attr("stroke-dasharray") {
+            let mut dash = numbers(value);
+            if dash.len() % 2 == 1 {
+                dash.extend_from_within(..);
+            }
+            self.stroke_dash = (!dash.is_empty()).then_some(dash);
+        }
+        if let Some(value) = attr("stroke-dashoffset") {
+            self.stroke_dash_offset = length(value).unwrap_or(0.0);
+        }
+        if let Some(value) = attr("stroke-opacity") {
+            self.stroke_opacity = opacity(value);
+        }
+        if let Some(value) = attr("stroke-width") {
+            self.stroke_width = length(value).unwrap_or(1.0);
+        }
+        if let Some(value) = attr("text-anchor") {
+            self.anchor = match value {
+                "middle" => Anchor::Middle,
+                "end" => Anchor::End,
+                _ => Anchor::Start,
+            };
+        }
+    }
+}
+
+/// Appends the text content of an element to `out`, excluding tooltips.
+fn text_content(element: &Element, out: &mut String) {
+    for node in element.nodes() {
+        match node {
+            Node::Element(child) if child.name() != "title" => text_content(child, out),
+            Node::Element(_) => {}
+            Node::Text(text) => out.push_str(text),
+        }
+    }
+}
+
+/// Creates a solid colour paint.
+fn paint(mut color: Color, opacity: f32) -> Option<Paint<'static>> {
+    color.apply_opacity(opacity);
+    (color.alpha() > 0.0).then(|| {
+        let mut paint = Paint::default();
+        paint.set_color(color);
+        paint.anti_alias = true;
+        paint
+    })
+}
+
+/// Parses a CSS colour value. The `none` keyword, and anything else which is
+/// not a colour, returns `None`.
+fn parse_color(value: &str) -> Option<Color> {
+    if value.eq_ignore_ascii_case("currentColor") {
+        return Some(Color::BLACK);
+    }
+
+    let [r, g, b, a] = csscolorparser::parse(value).ok()?.to_rgba8();
+    Some(Color::from_rgba8(r, g, b, a))
+}
+
+/// Parses an opacity value, which may be a number or a percentage.
+fn opacity(value: &str) -> f32 {
+    if let Some(percent) = value.strip_suffix('%') {
+        percent
+            .trim()
+            .parse::<f32>()
+            .map_or(1.0, |value| value / 100.0)
+    } else {
+        value.parse::<f32>().unwrap_or(1.0)
+    }
+    .clamp(0.0, 1.0)
+}
+
+/// Parses a length in user units. Lengths in other units, including
+/// percentages, return `None`.
+fn length(value: &str) -> Option<f32> {
+    let value = value.trim();
+    value
+        .strip_suffix("px")
+        .unwrap_or(value)
+        .trim_end()
+        .parse()
+        .ok()
+}
+
+/// Parses a list of numbers separated by whitespace and/or commas, skipping
+/// anything which is not a number.
+fn numbers(value: &str) -> Vec<f32> {
+    value
+        .split(|c: char| c.is_ascii_whitespace() || c == ',')
+        .filter_map(|number| number.parse().ok())
+        .collect()
+}
+
+/// Parses an SVG transform list.
+fn parse_transform(value: &str) -> Transform {
+    let mut transform = Transform::identity();
+    let mut rest = value;
+    while let Some((name, tail)) = rest.split_once('(')
+        && let Some((args, tail)) = tail.split_once(')')
+    {
+        let name = name.trim_matches(|c: char| c.is_ascii_whitespace() || c == ',');
+        let next = match (name, numbers(args).as_slice()) {
+            ("matrix", &[sx, ky, kx, sy, tx, ty]) => Transform::from_row(sx, ky, kx, sy, tx, ty),
+            ("rotate", &[angle]) => Transform::from_rotate(angle),
+            ("rotate", &[angle, x, y]) => Transform::from_rotate_at(angle, x, y),
+            ("scale", &[scale]) => Transform::from_scale(scale, scale),
+            ("scale", &[x, y]) => Transform::from_scale(x, y),
+            ("skewX", &[angle]) => Transform::from_skew(angle.to_radians().tan(), 0.0),
+            ("skewY", &[angle]) => Transform::from_skew(0.0, angle.to_radians().tan()),
+            ("translate", &[x]) => Transform::from_translate(x, 0.0),
+            ("translate", &[x, y]) => Transform::from_translate(x, y),
+            _ => Transform::identity(),
+        };
+        transform = transform.pre_concat(next);
+        rest = tail;
+    }
+    transform
+}
+
+/// Parses SVG path data. As in browsers, if there is an error in the data,
+/// everything up to the error is still drawn.
+fn parse_path(data: &str) -> Option<Path> {
+    let mut parser = PathParser {
+        data: data.as_bytes(),
+        pos: 0,
+    };
+    let mut pen = Pen::default();
+    let mut last = None;
+    loop {
+        parser.skip();
+        let Some(next) = parser.peek() else {
+            break;
+        };
+
+        // Extra coordinates after a command repeat the command, except for
+        // move, which turns into line
+        let command = if next.is_ascii_alphabetic() {
+            parser.pos += 1;
+            next
+        } else {
+            match last {
+                Some(b'M') => b'L',
+                Some(b'm') => b'l',
+                Some(command) if !command.eq_ignore_ascii_case(&b'z') => command,
+                _ => break,
+            }
+        };
+
+        if pen.segment(&mut parser, command).is_none() {
+            break;
+        }
+        last = Some(command);
+    }
+    pen.builder.finish()
+}
+
+/// A path data tokeniser.
+struct PathParser<'a> {
+    /// The path data.
+    data: &'a [u8],
+    /// The current position in the data.
+    pos: usize,
+}
+
+impl PathParser<'_> {
+    /// Reads a flag.
+    fn flag(&mut self) -> Option<bool> {
+        self.skip();
+        let flag = match self.peek()? {
+            b'0' => false,
+            b'1' => true,
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(flag)
+    }
+
+    /// Reads a number.
+    fn number(&mut self) -> Option<f32> {
+        self.skip();
+        let is_digit = |pos: usize| self.data.get(pos).is_some_and(u8::is_ascii_digit);
+
+        let start = self.pos;
+        let mut end = start;
+        if matches!(self.data.get(end), Some(b'+' | b'-')) {
+            end += 1;
+        }
+        let digits = end;
+        while is_digit(end) {
+            end += 1;
+        }
+        if self.data.get(end) == Some(&b'.') && is_digit(end + 1) {
+            end += 1;
+            while is_digit(end) {
+                end += 1;
+            }
+        }
+        if end == digits {
+            return None;
+        }
+        if matches!(self.data.get(end), Some(b'e' | b'E')) {
+            let mut exp = end + 1;
+            if matches!(self.data.get(exp), Some(b'+' | b'-')) {
+                exp += 1;
+            }
+            if is_digit(exp) {
+                end = exp;
+                while is_digit(end) {
+                    end += 1;
+                }
+            }
+        }
+
+        self.pos = end;
+        core::str::from_utf8(&self.data[start..end])
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    /// Returns the next byte without consuming it.
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    /// Reads a coordinate pair.
+    fn point(&mut self) -> Option<(f32, f32)> {
+        Some((self.number()?, self.number()?))
+    }
+
+    /// Skips whitespace and separators.
+    fn skip(&mut self) {
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_whitespace() || c == b',')
+        {
+            self.pos += 1;
+        }
+    }
+}
+
+/// The state of a path being drawn.
+#[derive(Default)]
+struct Pen {
+    /// The path.
+    builder: PathBuilder,
+    /// The second control point of the last cubic Bézier curve, for smooth
+    /// curves.
+    cubic: Option<(f32, f32)>,
+    /// The control point of the last quadratic Bézier curve, for smooth
+    /// curves.
+    quad: Option<(f32, f32)>,
+    /// The current point.
+    point: (f32, f32),
+    /// The start point of the current subpath.
+    start: (f32, f32),
+}
+
+impl Pen {
+    /// Reads and draws one segment of the path.
+    fn segment(&mut self, parser: &mut PathParser<'_>, command: u8) -> Option<()> {
+        let (x0, y0) = self.point;
+        let origin = if command.is_ascii_lowercase() {
+            self.point
+        } else {
+            (0.0, 0.0)
+        };
+        let abs = |(x, y): (f32, f32)| (x + origin.0, y + origin.1);
+        let reflect = |control: Option<(f32, f32)>| {
+            control.map_or((x0, y0), |(x, y)| (2.0 * x0 - x, 2.0 * y0 - y))
+        };
+
+        let mut cubic = None;
+        let mut quad = None;
+        self.point = match command.to_ascii_uppercase() {
+            b'M' => {
+                let (x, y) = abs(parser.point()?);
+                self.builder.move_to(x, y);
+                self.start = (x, y);
+                (x, y)
+            }
+            b'L' => {
+                let (x, y) = abs(parser.point()?);
+                self.builder.line_to(x, y);
+                (x, y)
+            }
+            b'H' => {
+                let x = parser.number()? + origin.0;
+                self.builder.line_to(x, y0);
+                (x, y0)
+            }
+            b'V' => {
+                let y = parser.number()? + origin.1;
+                self.builder.line_to(x0, y);
+                (x0, y)
+            }
+            b'C' | b'S' => {
+                let (x1, y1) = if command.eq_ignore_ascii_case(&b'c') {
+                    abs(parser.point()?)
+                } else {
+                    reflect(self.cubic)
+                };
+                let (x2, y2) = abs(parser.point()?);
+                let (x, y) = abs(parser.point()?);
+                self.builder.cubic_to(x1, y1, x2, y2, x, y);
+                cubic = Some((x2, y2));
+                (x, y)
+            }
+            b'Q' | b'T' => {
+                let (x1, y1) = if command.eq_ignore_ascii_case(&b'q') {
+                    abs(parser.point()?)
+                } else {
+                    reflect(self.quad)
+                };
+                let (x, y) = abs(parser.point()?);
+                self.builder.quad_to(x1, y1, x, y);
+                quad = Some((x1, y1));
+                (x, y)
+            }
+            b'A' => {
+                let rx = parser.number()?;
+                let ry = parser.number()?;
+                let angle = parser.number()?;
+                let large = parser.flag()?;
+                let sweep = parser.flag()?;
+                let to = abs(parser.point()?);
+                self.arc(rx, ry, angle, large, sweep, to);
+                to
+            }
+            b'Z' => {
+                self.builder.close();
+                self.start
+            }
+            _ => return None,
+        };
+        self.cubic = cubic;
+        self.quad = quad;
+        Some(())
+    }
+
+    /// Draws an elliptical arc from the current point to `to` as a series of
+    /// cubic Bézier curves.
+    /// <https://www.w3.org/TR/SVG2/implnote.html#ArcConversionEndpointToCenter>
+    fn arc(&mut self, rx: f32, ry: f32, angle: f32, large: bool, sweep: bool, to: (f32, f32)) {
+        /// The signed angle between two vectors.
+        fn angle_between((ux, uy): (f32, f32), (vx, vy): (f32, f32)) -> f32 {
+            (ux * vy - uy * vx).atan2(ux * vx + uy * vy)
+        }
+
+        let (x0, y0) = self.point;
+        let (x, y) = to;
+        if (x0 - x).abs() < f32::EPSILON && (y0 - y).abs() < f32::EPSILON {
+            return;
+        }
+
+        let mut rx = rx.abs();
+        let mut ry = ry.abs();
+        if rx == 0.0 || ry == 0.0 {
+            self.builder.line_to(x, y);
+            return;
+        }
+
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let dx = (x0 - x) / 2.0;
+        let dy = (y0 - y) / 2.0;
+        let x1 = cos * dx + sin * dy;
+        let y1 = -sin * dx + cos * dy;
+
+        // Out of range radii are scaled up until the arc fits
+        let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+        if lambda > 1.0 {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+
+        let numerator = (rx * ry).powi(2) - (rx * y1).powi(2) - (ry * x1).powi(2);
+        let denominator = (rx * y1).powi(2) + (ry * x1).powi(2);
+        let direction = if large == sweep { -1.0 } else { 1.0 };
+        let coef = direction * (numerator / denominator).max(0.0).sqrt();
+        let cx1 = coef * rx * y1 / ry;
+        let cy1 = -coef * ry * x1 / rx;
+        let cx = cos * cx1 - sin * cy1 + x0.midpoint(x);
+        let cy = sin * cx1 + cos * cy1 + y0.midpoint(y);
+
+        let start = ((x1 - cx1) / rx, (y1 - cy1) / ry);
+        let end = ((-x1 - cx1) / rx, (-y1 - cy1) / ry);
+        let theta = angle_between((1.0, 0.0), start);
+        let mut delta = angle_between(start, end);
+        if sweep && delta < 0.0 {
+            delta += TAU;
+        } else if !sweep && delta > 0.0 {
+            delta -= TAU;
+        }
+
+        // Each segment is at most a quarter turn so that the approximation is
+        // good enough
+        #[expect(
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss,
+            reason = "value is a small positive integer"
+        )]
+        let segments = (delta.abs() / FRAC_PI_2).ceil().max(1.0) as u8;
+        let step = delta / f32::from(segments);
+        let t = 4.0 / 3.0 * (step / 4.0).tan();
+        let map = |(px, py): (f32, f32)| {
+            (
+                cx + rx * cos * px - ry * sin * py,
+                cy + rx * sin * px + ry * cos * py,
+            )
+        };
+
+        for index in 0..segments {
+            let a1 = theta + step * f32::from(index);
+            let a2 = a1 + step;
+            let (sin1, cos1) = a1.sin_cos();
+            let (sin2, cos2) = a2.sin_cos();
+            let (c1x, c1y) = map((cos1 - t * sin1, sin1 + t * cos1));
+            let (c2x, c2y) = map((cos2 + t * sin2, sin2 - t * cos2));
+            let (px, py) = map((cos2, sin2));
+            self.builder.cubic_to(c1x, c1y, c2x, c2y, px, py);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Gets the bounding box of a path as `[left, top, right, bottom]`.
+    fn bounds(path: &Path) -> [f32; 4] {
+        let bounds = path.bounds();
+        [bounds.left(), bounds.top(), bounds.right(), bounds.bottom()]
+    }
+
+    #[test]
+    fn transforms() {
+        assert_eq!(parse_transform(""), Transform::identity());
+        assert_eq!(
+            parse_transform("translate(10 20) scale(2)"),
+            Transform::from_row(2.0, 0.0, 0.0, 2.0, 10.0, 20.0)
+        );
+        assert_eq!(
+            parse_transform("translate(5),scale(2, 3)"),
+            Transform::from_row(2.0, 0.0, 0.0, 3.0, 5.0, 0.0)
+        );
+        assert_eq!(
+            parse_transform("matrix(1,2,3,4,5,6)"),
+            Transform::from_row(1.0, 2.0, 3.0, 4.0, 5.0, 6.0)
+        );
+        let mut point = tiny_skia::Point::from_xy(20.0, 10.0);
+        parse_transform("rotate(90 10 10)").map_point(&mut point);
+        assert!((point.x - 10.0).abs() < 1e-4 && (point.y - 20.0).abs() < 1e-4);
+        assert_eq!(
+            parse_transform("bogus(1) translate(1 2) rotate(1 2)"),
+            Transform::from_translate(1.0, 2.0)
+        );
+    }
+
+    #[test]
+    #[expect(clippy::float_cmp, reason = "the bounds of straight paths are exact")]
+    fn paths() {
+        assert!(parse_path("").is_none());
+        assert!(parse_path("L10 10").is_none());
+
+        let path = parse_path("M0 0L10 0L10 10Z").unwrap();
+        assert_eq!(bounds(&path), [0.0, 0.0, 10.0, 10.0]);
+        assert_eq!(path.len(), 4);
+
+        // Implicit line to after move to, and relative commands
+        let path = parse_path("m1,1 10,0 v5 h-10z").unwrap();
+        assert_eq!(bounds(&path), [1.0, 1.0, 11.0, 6.0]);
+        assert_eq!(path.len(), 5);
+
+        // Numbers without separators
+        let path = parse_path("M1e1-2.5L.5.5").unwrap();
+        assert_eq!(bounds(&path), [0.5, -2.5, 10.0, 0.5]);
+
+        // Everything before an error is kept
+        let path = parse_path("M0 0 L10 10 L20").unwrap();
+        assert_eq!(bounds(&path), [0.0, 0.0, 10.0, 10.0]);
+        assert_eq!(path.len(), 2);
+
+        // Half circle above the x-axis
+        let path = parse_path("M0 0A5 5 0 0 1 10 0").unwrap();
+        let [left, top, right, bottom] = bounds(&path);
+        assert!(left.abs() < 1e-4 && right == 10.0);
+        assert!((top + 5.0).abs() < 1e-4 && bottom.abs() < 1e-4);
+
+        // Smooth curves reflect the previous control point
+        let path = parse_path("M0 0C0 10 10 10 10 0S20 -10 20 0").unwrap();
+        assert_eq!(bounds(&path), [0.0, -10.0, 20.0, 10.0]);
+    }
+}
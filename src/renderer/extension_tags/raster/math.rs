@@ -0,0 +1,439 @@
+//! A simple box layout for MathML formulas.
+//!
+//! This is nowhere close to a real math typesetter. Each element is laid out
+//! into a box with a width, ascent, and descent, using fixed proportions
+//! instead of font metrics, and then the boxes are converted into SVG text
+//! and shapes which can be drawn by the rasteriser. Stretchy operators are not
+//! stretched, and characters which are not in the built-in font only render
+//! if a fallback font which has them was given at startup.
+
+use super::{
+    super::{
+        font,
+        svg::{NS_SVG, ValueDisplay as _, n},
+    },
+    Error,
+};
+use minidom::Element;
+
+/// The MathML namespace.
+const NS_MATHML: &str = "http://www.w3.org/1998/Math/MathML";
+
+/// The base font size, in pixels.
+const FONT_SIZE: f64 = 16.0;
+
+/// The height of text above the baseline, in ems.
+const ASCENT: f64 = 0.8;
+
+/// The depth of text below the baseline, in ems.
+const DESCENT: f64 = 0.2;
+
+/// The height of the math axis (where fraction bars go) above the baseline,
+/// in ems.
+const AXIS: f64 = 0.25;
+
+/// The thickness of fraction bars and radical lines, in ems.
+const RULE: f64 = 0.06;
+
+/// The scale of scripts relative to their base.
+const SCRIPT_SCALE: f64 = 0.7;
+
+/// The padding around the formula, in pixels.
+const PADDING: f64 = 2.0;
+
+/// Parses a MathML formula, adding the MathML namespace if it is missing.
+pub(super) fn parse(markup: &str) -> Result<Element, Error> {
+    let markup = markup.trim();
+    if let Some(rest) = markup.strip_prefix("<math")
+        && !markup[..markup.find('>').unwrap_or(markup.len())].contains("xmlns")
+    {
+        Ok(format!(r#"<math xmlns="{NS_MATHML}"{rest}"#).parse()?)
+    } else {
+        Ok(markup.parse()?)
+    }
+}
+
+/// Lays out a MathML formula as an SVG image.
+pub(super) fn to_svg(math: &Element) -> Element {
+    let layout = layout(math, FONT_SIZE);
+    let width = (layout.width + PADDING * 2.0).ceil();
+    let height = (layout.ascent + layout.descent + PADDING * 2.0).ceil();
+    let baseline = PADDING + layout.ascent;
+    Element::builder("svg", NS_SVG)
+        .attr(n!("viewBox"), format!("0 0 {} {}", width.v(), height.v()))
+        .attr(n!("width"), width.v())
+        .attr(n!("height"), height.v())
+        .append(layout.place(PADDING, baseline))
+        .build()
+}
+
+/// A laid out box.
+#[derive(Default)]
+struct Layout {
+    /// The height of the box above its baseline.
+    ascent: f64,
+    /// The depth of the box below its baseline.
+    descent: f64,
+    /// The drawn items, relative to the left edge of the baseline.
+    items: Vec<Element>,
+    /// The width of the box.
+    width: f64,
+}
+
+impl Layout {
+    /// Creates a box for a run of text.
+    fn text(text: &str, size: f64, italic: bool) -> Self {
+        let width = font::shape_text(text, false, italic).width(size);
+        let mut element = Element::builder("text", NS_SVG).attr(n!("font-size"), size.v());
+        if italic {
+            element = element.attr(n!("font-style"), "italic");
+        }
+        Self {
+            ascent: ASCENT * size,
+            descent: DESCENT * size,
+            items: vec![element.append(text.to_owned()).build()],
+            width,
+        }
+    }
+
+    /// Creates an empty box with the given width.
+    fn space(width: f64) -> Self {
+        Self {
+            width,
+            ..Self::default()
+        }
+    }
+
+    /// Appends `other` to the right of this box, with its baseline moved up by
+    /// `shift`.
+    fn push(&mut self, other: Layout, shift: f64) {
+        self.push_at(other, self.width, shift);
+    }
+
+    /// Adds `other` to this box with its left edge at `x` and its baseline
+    /// moved up by `shift`, and extends this box to fit.
+    fn push_at(&mut self, other: Layout, x: f64, shift: f64) {
+        self.ascent = self.ascent.max(other.ascent + shift);
+        self.descent = self.descent.max(other.descent - shift);
+        self.width = self.width.max(x + other.width);
+        if !other.items.is_empty() {
+            self.items.push(other.place(x, -shift));
+        }
+    }
+
+    /// Converts the box into a group at the given position.
+    fn place(self, x: f64, y: f64) -> Element {
+        Element::builder("g", NS_SVG)
+            .attr(n!("transform"), format!("translate({} {})", x.v(), y.v()))
+            .append_all(self.items)
+            .build()
+    }
+
+    /// Adds a horizontal rule to this box.
+    fn rule(&mut self, x: f64, y: f64, width: f64, thickness: f64) {
+        self.items.push(
+            Element::builder("rect", NS_SVG)
+                .attr(n!("x"), x.v())
+                .attr(n!("y"), (y - thickness / 2.0).v())
+                .attr(n!("width"), width.v())
+                .attr(n!("height"), thickness.v())
+                .build(),
+        );
+    }
+}
+
+/// Lays out a MathML element at the given font size.
+fn layout(element: &Element, size: f64) -> Layout {
+    let mut children = element.children();
+    match element.name() {
+        "mi" => {
+            let text = element.text();
+            let text = text.trim();
+            let italic = text.chars().count() == 1
+                && element.attr("mathvariant").is_none_or(|v| v == "italic");
+            Layout::text(text, size, italic)
+        }
+        "mn" | "ms" | "mtext" => Layout::text(element.text().trim(), size, false),
+        "mo" => operator(element, size),
+        "mspace" => Layout::space(
+            element
+                .attr("width")
+                .and_then(|width| em(width, size))
+                .unwrap_or(0.0),
+        ),
+        "mfrac" => {
+            let num = children.next().map(|child| layout(child, size * 0.9));
+            let den = children.next().map(|child| layout(child, size * 0.9));
+            fraction(
+                num.unwrap_or_default(),
+                den.unwrap_or_default(),
+                element.attr("linethickness").map(str::trim) != Some("0"),
+                size,
+            )
+        }
+        "msqrt" => radical(row(children, size), None, size),
+        "mroot" => {
+            let base = children.next().map(|child| layout(child, size));
+            let index = children
+                .next()
+                .map(|child| layout(child, size * SCRIPT_SCALE * SCRIPT_SCALE));
+            radical(base.unwrap_or_default(), index, size)
+        }
+        "msub" | "msup" | "msubsup" => {
+            let base = children
+                .next()
+                .map_or_else(Layout::default, |child| layout(child, size));
+            let script =
+                |child: Option<&Element>| child.map(|child| layout(child, size * SCRIPT_SCALE));
+            let (sub, sup) = match element.name() {
+                "msub" => (script(children.next()), None),
+                "msup" => (None, script(children.next())),
+                _ => (script(children.next()), script(children.next())),
+            };
+            scripts(base, sub, sup, size)
+        }
+        "munder" | "mover" | "munderover" => {
+            let base = children
+                .next()
+                .map_or_else(Layout::default, |child| layout(child, size));
+            let script =
+                |child: Option<&Element>| child.map(|child| layout(child, size * SCRIPT_SCALE));
+            let (under, over) = match element.name() {
+                "munder" => (script(children.next()), None),
+                "mover" => (None, script(children.next())),
+                _ => (script(children.next()), script(children.next())),
+            };
+            limits(base, under, over, size)
+        }
+        "mtable" => table(element, size),
+        "mphantom" => {
+            let mut layout = row(children, size);
+            layout.items.clear();
+            layout
+        }
+        "semantics" => children
+            .next()
+            .map_or_else(Layout::default, |child| layout(child, size)),
+        "annotation" | "annotation-xml" | "mprescripts" | "none" => Layout::default(),
+        _ => row(children, size),
+    }
+}
+
+/// Lays out a sequence of elements horizontally.
+fn row<'a>(children: impl Iterator<Item = &'a Element>, size: f64) -> Layout {
+    let mut row = Layout::default();
+    for child in children {
+        row.push(layout(child, size), 0.0);
+    }
+    row
+}
+
+/// Lays out an operator with space around it.
+fn operator(element: &Element, size: f64) -> Layout {
+    let text = element.text();
+    let text = text.trim();
+
+    // Fences and separators are tight, and everything else gets a thick space
+    // unless the formula says otherwise
+    let default_space = if matches!(
+        text,
+        "(" | ")" | "[" | "]" | "{" | "}" | "|" | "‖" | "⟨" | "⟩" | "," | ";" | "." | "!" | "′"
+    ) {
+        0.0
+    } else {
+        0.2 * size
+    };
+    let space = |name| {
+        element
+            .attr(name)
+            .and_then(|value| em(value, size))
+            .unwrap_or(default_space)
+    };
+
+    let mut layout = Layout::space(space("lspace"));
+    layout.push(Layout::text(text, size, false), 0.0);
+    layout.push(Layout::space(space("rspace")), 0.0);
+    layout
+}
+
+/// Lays out a fraction.
+fn fraction(num: Layout, den: Layout, bar: bool, size: f64) -> Layout {
+    let gap = 0.15 * size;
+    let axis = AXIS * size;
+    let padding = 0.1 * size;
+    let width = num.width.max(den.width) + padding * 2.0;
+
+    let mut layout = Layout::default();
+    let num_shift = axis + gap + num.descent;
+    let den_shift = axis - gap - den.ascent;
+    let num_x = (width - num.width) / 2.0;
+    let den_x = (width - den.width) / 2.0;
+    layout.push_at(num, num_x, num_shift);
+    layout.push_at(den, den_x, den_shift);
+    if bar {
+        layout.rule(0.0, -axis, width, RULE * size);
+    }
+    layout.width = width;
+    layout
+}
+
+/// Lays out a square root, or an n-th root if there is an `index`.
+fn radical(base: Layout, index: Option<Layout>, size: f64) -> Layout {
+    let gap = 0.15 * size;
+    let thickness = RULE * size;
+    let sign_width = 0.6 * size;
+    let top = -(base.ascent + gap);
+    let bottom = base.descent;
+
+    let mut layout = Layout::default();
+    let offset = if let Some(index) = index {
+        let offset = (index.width - 0.3 * size).max(0.0);
+        let shift = -top / 2.0 + index.descent;
+        layout.push_at(index, 0.0, shift);
+        offset
+    } else {
+        0.0
+    };
+
+    let x = |value: f64| (offset + value).v();
+    let end = offset + sign_width + base.width + 0.1 * size;
+    layout.items.push(
+        Element::builder("path", NS_SVG)
+            .attr(
+                n!("d"),
+                format!(
+                    "M{},{}L{},{}L{},{}L{},{}H{}",
+                    x(0.0),
+                    (-0.3 * size).v(),
+                    x(0.15 * size),
+                    (-0.4 * size).v(),
+                    x(0.35 * size),
+                    bottom.v(),
+                    x(0.55 * size),
+                    top.v(),
+                    end.v()
+                ),
+            )
+            .attr(n!("fill"), "none")
+            .attr(n!("stroke"), "black")
+            .attr(n!("stroke-width"), thickness.v())
+            .build(),
+    );
+    layout.ascent = layout.ascent.max(-top + thickness);
+    layout.descent = layout.descent.max(bottom);
+    layout.push_at(base, offset + sign_width, 0.0);
+    layout.width = end;
+    layout
+}
+
+/// Lays out a base with subscripts and superscripts to its right.
+fn scripts(base: Layout, sub: Option<Layout>, sup: Option<Layout>, size: f64) -> Layout {
+    let raise = (base.ascent * 0.6).max(0.4 * size);
+    let lower = (base.descent * 0.8).max(0.2 * size);
+    let base_ascent = base.ascent;
+
+    let mut layout = Layout::default();
+    layout.push(base, 0.0);
+    let x = layout.width;
+    if let Some(sup) = sup {
+        layout.push_at(sup, x, raise.max(base_ascent - raise / 2.0));
+    }
+    if let Some(sub) = sub {
+        layout.push_at(sub, x, -lower);
+    }
+    layout
+}
+
+/// Lays out a base with scripts centred above and/or below it.
+fn limits(base: Layout, under: Option<Layout>, over: Option<Layout>, size: f64) -> Layout {
+    let gap = 0.1 * size;
+    let width = [Some(&base), under.as_ref(), over.as_ref()]
+        .into_iter()
+        .flatten()
+        .fold(0.0_f64, |width, layout| width.max(layout.width));
+
+    let mut layout = Layout::default();
+    let (base_ascent, base_descent) = (base.ascent, base.descent);
+    let x = (width - base.width) / 2.0;
+    layout.push_at(base, x, 0.0);
+    if let Some(over) = over {
+        let x = (width - over.width) / 2.0;
+        let shift = base_ascent + gap + over.descent;
+        layout.push_at(over, x, shift);
+    }
+    if let Some(under) = under {
+        let x = (width - under.width) / 2.0;
+        let shift = -(base_descent + gap + under.ascent);
+        layout.push_at(under, x, shift);
+    }
+    layout.width = width;
+    layout
+}
+
+/// Lays out a table with centred cells, centred on the math axis.
+fn table(element: &Element, size: f64) -> Layout {
+    let column_gap = 0.8 * size;
+    let row_gap = 0.3 * size;
+
+    let rows = element
+        .children()
+        .filter(|row| matches!(row.name(), "mtr" | "mlabeledtr"))
+        .map(|tr| {
+            tr.children()
+                .filter(|cell| cell.name() == "mtd")
+                .map(|cell| row(cell.children(), size))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut widths = Vec::<f64>::new();
+    for row in &rows {
+        if widths.len() < row.len() {
+            widths.resize(row.len(), 0.0);
+        }
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = width.max(cell.width);
+        }
+    }
+
+    let mut layout = Layout::default();
+    let mut y = 0.0;
+    for row in rows {
+        let ascent = row
+            .iter()
+            .fold(0.0_f64, |ascent, cell| ascent.max(cell.ascent));
+        let descent = row
+            .iter()
+            .fold(0.0_f64, |descent, cell| descent.max(cell.descent));
+        y += ascent;
+        let mut x = 0.0;
+        for (cell, width) in row.into_iter().zip(&widths) {
+            let cell_x = x + (width - cell.width) / 2.0;
+            layout.push_at(cell, cell_x, -y);
+            x += width + column_gap;
+        }
+        y += descent + row_gap;
+    }
+
+    // Move the whole table so that it is centred on the axis
+    let height = (y - row_gap).max(0.0);
+    let shift = height / 2.0 + AXIS * size;
+    let mut centred = Layout::default();
+    centred.push_at(layout, 0.0, shift);
+    centred
+}
+
+/// Parses a MathML length in ems or pixels. Named spaces are not supported.
+fn em(value: &str, size: f64) -> Option<f64> {
+    let value = value.trim();
+    if let Some(value) = value.strip_suffix("em") {
+        value.trim().parse::<f64>().ok().map(|value| value * size)
+    } else {
+        value
+            .strip_suffix("px")
+            .unwrap_or(value)
+            .trim()
+            .parse()
+            .ok()
+    }
+}
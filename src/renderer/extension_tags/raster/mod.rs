@@ -0,0 +1,351 @@
+//! Server-side PNG rendering of extension tag images.
+//!
+//! Graphs, timelines, charts, and formulas are normally sent to the browser as
+//! inline SVG or MathML. When PNG rendering is enabled, their markup is also
+//! stored in a cache keyed by a hash of the markup, and the root element of the
+//! output gets a `data-png` attribute pointing to `/render/{hash}.png`. The
+//! PNG is only drawn the first time it is requested, and then replaces the
+//! markup in the cache.
+//!
+//! Pages which link to a PNG may be cached by the browser for much longer than
+//! the markup stays in memory, so if a cache directory is given, the markup and
+//! the drawn PNG are also written there and are used when the memory cache
+//! misses.
+//!
+//! The rasteriser only understands the subset of SVG which is generated by
+//! the extension tags in this crate, and formulas are drawn using a simple box
+//! layout of the MathML instead of a real math typesetter, so the output is
+//! intended for things like exports and link previews where an approximation
+//! is better than nothing.
+
+mod canvas;
+mod math;
+
+use super::disk_cache::{DiskCache, StableHasher};
+use crate::lru_limiter::{ByMemoryUsage, HeapUsageCalculator};
+use axum::http::Uri;
+use core::hash::{Hash as _, Hasher as _};
+use minidom::Element;
+use parking_lot::Mutex;
+use schnellru::LruMap;
+use std::{
+    borrow::Cow,
+    path::PathBuf,
+    sync::{Arc, OnceLock},
+};
+
+/// The maximum width or height of a rendered image, in pixels. At this size, a
+/// pixmap takes 64MiB.
+const MAX_SIZE: f32 = 4096.0;
+
+/// A PNG rendering error.
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    /// The image was drawn but could not be encoded.
+    #[error("PNG encoding failed: {0}")]
+    Encode(String),
+    /// The requested image is not in the cache, either because it was never
+    /// registered, was evicted, or PNG rendering is disabled.
+    #[error("no such image")]
+    NotFound,
+    /// The image has no size or is too big.
+    #[error("invalid image size {0}×{1}")]
+    Size(f32, f32),
+    /// The markup could not be parsed.
+    #[error(transparent)]
+    Xml(#[from] minidom::Error),
+}
+
+/// The kind of markup being rendered.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub(super) enum Kind {
+    /// A MathML formula.
+    MathMl,
+    /// An SVG image.
+    Svg,
+}
+
+impl Kind {
+    /// All kinds of markup.
+    const ALL: [Kind; 2] = [Kind::MathMl, Kind::Svg];
+
+    /// The file extension used when markup of this kind is persisted.
+    fn extension(self) -> &'static str {
+        match self {
+            Kind::MathMl => "mml",
+            Kind::Svg => "svg",
+        }
+    }
+}
+
+/// A render cache entry.
+enum Entry {
+    /// Markup which has not been rendered yet.
+    Source(Kind, Arc<str>),
+    /// A rendered PNG.
+    Png(Arc<[u8]>),
+}
+
+impl HeapUsageCalculator for Entry {
+    #[inline]
+    fn size_of(&self) -> usize {
+        match self {
+            Entry::Source(_, markup) => markup.len(),
+            Entry::Png(png) => png.len(),
+        }
+    }
+}
+
+/// The render cache.
+struct Cache {
+    /// The in-memory entries.
+    memory: Mutex<LruMap<u64, Entry, ByMemoryUsage>>,
+    /// The directory where entries are persisted, if any.
+    disk: Option<DiskCache>,
+}
+
+impl Cache {
+    /// Creates a new cache limited to `max_bytes` of memory which persists
+    /// entries to `disk`, if given.
+    fn new(max_bytes: usize, disk: Option<DiskCache>) -> Self {
+        Self {
+            memory: Mutex::new(LruMap::new(ByMemoryUsage::new(max_bytes))),
+            disk,
+        }
+    }
+
+    /// Adds markup to the cache and returns its hash.
+    fn register(&self, kind: Kind, markup: &str) -> u64 {
+        // The hash is persisted in URLs, so it needs to be stable
+        let mut hasher = StableHasher::default();
+        kind.extension().hash(&mut hasher);
+        markup.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let mut memory = self.memory.lock();
+        if memory.get(&hash).is_none() {
+            memory.insert(hash, Entry::Source(kind, markup.into()));
+            drop(memory);
+
+            if let Some(disk) = &self.disk {
+                let name = format!("{hash:016x}.{}", kind.extension());
+                if let Err(err) = disk.insert(&name, markup.as_bytes()) {
+                    log::warn!("Could not persist render source {name}: {err}");
+                }
+            }
+        }
+
+        hash
+    }
+
+    /// Gets the PNG for the given hash, rendering it if this is the first time
+    /// it was requested.
+    fn png(&self, hash: u64) -> Result<Arc<[u8]>, Error> {
+        // The lock is not held while drawing because that can take a while and
+        // would block every other page which is trying to register an image
+        let source = match self.memory.lock().get(&hash) {
+            Some(Entry::Source(kind, markup)) => Some((*kind, Arc::clone(markup))),
+            Some(Entry::Png(png)) => return Ok(Arc::clone(png)),
+            None => None,
+        };
+
+        let name = format!("{hash:016x}.png");
+        let disk = self.disk.as_ref();
+        if let Some(png) = disk.and_then(|disk| disk.get(&name)) {
+            let png = Arc::<[u8]>::from(png);
+            self.memory
+                .lock()
+                .insert(hash, Entry::Png(Arc::clone(&png)));
+            return Ok(png);
+        }
+
+        let (kind, markup) = source
+            .or_else(|| disk.and_then(|disk| load_source(disk, hash)))
+            .ok_or(Error::NotFound)?;
+
+        let png = Arc::<[u8]>::from(render(kind, &markup)?);
+        if let Some(disk) = disk
+            && let Err(err) = disk.insert(&name, &png)
+        {
+            log::warn!("Could not persist render {name}: {err}");
+        }
+        self.memory
+            .lock()
+            .insert(hash, Entry::Png(Arc::clone(&png)));
+        Ok(png)
+    }
+}
+
+/// The render cache. If this is not set, PNG rendering is disabled.
+static CACHE: OnceLock<Cache> = OnceLock::new();
+
+/// Enables PNG rendering using a cache limited to `max_bytes` of memory. If
+/// `dir` is given, entries are also persisted to that directory, up to
+/// `max_disk_bytes`. This can only be done once.
+pub(crate) fn set_cache(
+    max_bytes: usize,
+    dir: Option<PathBuf>,
+    max_disk_bytes: u64,
+) -> std::io::Result<()> {
+    let disk = dir
+        .map(|dir| DiskCache::new(dir, max_disk_bytes))
+        .transpose()?;
+    if CACHE.set(Cache::new(max_bytes, disk)).is_err() {
+        log::warn!("Render cache was already set");
+    }
+    Ok(())
+}
+
+/// Returns true if PNG rendering is enabled.
+pub(super) fn is_enabled() -> bool {
+    CACHE.get().is_some()
+}
+
+/// Adds markup to the render cache and returns the URL of its PNG rendering,
+/// or `None` if PNG rendering is disabled.
+pub(super) fn register(base_uri: &Uri, kind: Kind, markup: &str) -> Option<String> {
+    let hash = CACHE.get()?.register(kind, markup);
+    Some(format!("{}/render/{hash:016x}.png", base_uri.path()))
+}
+
+/// Adds a `data-png` attribute with the given URL to the root element of
+/// `markup`.
+pub(super) fn with_png_url<'a>(markup: &'a str, url: Option<&str>) -> Cow<'a, str> {
+    let Some(url) = url else {
+        return Cow::Borrowed(markup);
+    };
+
+    let Some(start) = markup.find('<') else {
+        return Cow::Borrowed(markup);
+    };
+
+    let end = markup[start + 1..]
+        .find(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+        .map_or(markup.len(), |index| start + 1 + index);
+    let (head, tail) = markup.split_at(end);
+    Cow::Owned(format!(
+        r#"{head} data-png="{}"{tail}"#,
+        html_escape::encode_double_quoted_attribute(url)
+    ))
+}
+
+/// Gets the PNG for the given file name (`{hash}.png`) from the render cache,
+/// rendering it if this is the first time it was requested.
+pub(crate) fn png(name: &str) -> Result<Arc<[u8]>, Error> {
+    let cache = CACHE.get().ok_or(Error::NotFound)?;
+    let hash = name
+        .strip_suffix(".png")
+        .and_then(|hash| u64::from_str_radix(hash, 16).ok())
+        .ok_or(Error::NotFound)?;
+    cache.png(hash)
+}
+
+/// Loads persisted markup with the given hash.
+fn load_source(disk: &DiskCache, hash: u64) -> Option<(Kind, Arc<str>)> {
+    Kind::ALL.into_iter().find_map(|kind| {
+        let markup = disk.get(&format!("{hash:016x}.{}", kind.extension()))?;
+        Some((kind, String::from_utf8(markup).ok()?.into()))
+    })
+}
+
+/// Draws markup of the given kind to a PNG.
+fn render(kind: Kind, markup: &str) -> Result<Vec<u8>, Error> {
+    let svg = match kind {
+        Kind::MathMl => math::to_svg(&math::parse(markup)?),
+        Kind::Svg => markup.parse::<Element>()?,
+    };
+
+    canvas::rasterize(&svg)?
+        .encode_png()
+        .map_err(|err| Error::Encode(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn png_url() {
+        let markup = r#"<svg width="1"/>"#;
+        assert!(matches!(with_png_url(markup, None), Cow::Borrowed(m) if m == markup));
+        assert_eq!(
+            with_png_url(markup, Some("/render/1.png")),
+            r#"<svg data-png="/render/1.png" width="1"/>"#
+        );
+        assert_eq!(
+            with_png_url("\n<math>x</math>", Some(r#"/a"b.png"#)),
+            "\n<math data-png=\"/a&quot;b.png\">x</math>"
+        );
+        assert_eq!(
+            with_png_url("<svg/>", Some("/1.png")),
+            r#"<svg data-png="/1.png"/>"#
+        );
+        assert!(matches!(
+            with_png_url("no markup", Some("/1.png")),
+            Cow::Borrowed("no markup")
+        ));
+    }
+
+    #[test]
+    fn rasterize() {
+        let markup = concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="20" viewBox="0 0 80 40">"#,
+            "<style>svg { background-color: #ffffff; }</style>",
+            r##"<rect x="0" y="0" width="30" height="40" fill="#ff0000"/>"##,
+            r#"<path d="M50 0h30v40h-30z" fill="blue"/>"#,
+            r#"<g transform="translate(65 20)"><rect x="-5" y="-5" width="10" height="10" fill="lime"/></g>"#,
+            "</svg>"
+        );
+
+        let png = render(Kind::Svg, markup).unwrap();
+        let pixmap = tiny_skia::Pixmap::decode_png(&png).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (40, 20));
+
+        let pixel = |x, y| {
+            let pixel = pixmap.pixel(x, y).unwrap();
+            [pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()]
+        };
+        assert_eq!(pixel(5, 10), [255, 0, 0, 255]);
+        assert_eq!(pixel(20, 10), [255, 255, 255, 255]);
+        assert_eq!(pixel(27, 3), [0, 0, 255, 255]);
+        assert_eq!(pixel(32, 10), [0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn invalid_size() {
+        let markup = r#"<svg xmlns="http://www.w3.org/2000/svg" width="0" height="10"/>"#;
+        assert!(matches!(render(Kind::Svg, markup), Err(Error::Size(..))));
+        let markup = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="4097"/>"#;
+        assert!(matches!(render(Kind::Svg, markup), Err(Error::Size(..))));
+        assert!(matches!(render(Kind::Svg, "<svg"), Err(Error::Xml(_))));
+    }
+
+    #[test]
+    fn persisted() {
+        let dir = std::env::temp_dir().join(format!("wiki-rs-render-cache-{}", std::process::id()));
+        let disk = || Some(DiskCache::new(dir.clone(), 1 << 20).unwrap());
+        let markup = r#"<svg xmlns="http://www.w3.org/2000/svg" width="2" height="2"/>"#;
+
+        let cache = Cache::new(1 << 20, disk());
+        let hash = cache.register(Kind::Svg, markup);
+        assert!(matches!(cache.png(hash ^ 1), Err(Error::NotFound)));
+        let png = cache.png(hash).unwrap();
+
+        // A new process loads the drawn PNG
+        let cache = Cache::new(1 << 20, disk());
+        assert_eq!(cache.png(hash).unwrap(), png);
+
+        // Or draws it again from the markup, if the PNG was pruned
+        std::fs::remove_file(dir.join(format!("{hash:016x}.png"))).unwrap();
+        let cache = Cache::new(1 << 20, disk());
+        assert_eq!(cache.png(hash).unwrap(), png);
+
+        // Without a directory, nothing survives
+        assert!(matches!(
+            Cache::new(1 << 20, None).png(hash),
+            Err(Error::NotFound)
+        ));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
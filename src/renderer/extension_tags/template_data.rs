@@ -0,0 +1,512 @@
+//! The `<templatedata>` extension tag.
+//! <https://www.mediawiki.org/wiki/Special:MyLanguage/Extension:TemplateData>
+//!
+//! The parsed TemplateData of a page is kept so that it can be returned by the
+//! `templatedata` article action, which is the equivalent of the MediaWiki
+//! `action=templatedata` API.
+
+use super::{ExtensionTag, OutputMode, Result, State};
+use crate::{
+    common::{anchor_encode, format_message},
+    renderer::language::CONTENT_LANGUAGE,
+};
+use core::{convert::Infallible, fmt::Write as _};
+use html_escape::encode_text;
+use indexmap::IndexMap;
+use serde::{Deserialize, Deserializer, de::Error as _};
+use std::borrow::Cow;
+
+/// The known parameter types.
+const TYPES: &[&str] = &[
+    "boolean",
+    "content",
+    "date",
+    "line",
+    "number",
+    "string",
+    "unbalanced-wikitext",
+    "unknown",
+    "url",
+    "wiki-file-name",
+    "wiki-page-name",
+    "wiki-template-name",
+    "wiki-user-name",
+];
+
+/// The TemplateData of a template.
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub(crate) struct TemplateData {
+    /// The description of the template.
+    #[serde(default)]
+    description: Option<InterfaceText>,
+    /// The preferred wikitext formatting of a call to the template. This is
+    /// either `inline`, `block`, or a custom format string.
+    #[serde(default)]
+    format: Option<String>,
+    /// Mappings from the parameters of other consumers to the parameters of
+    /// the template.
+    #[serde(default)]
+    maps: IndexMap<String, serde_json::Value>,
+    /// The parameters of the template, in display order.
+    #[serde(default, deserialize_with = "deserialize_params")]
+    params: IndexMap<String, Param>,
+    /// The order of the parameters when calling the template.
+    #[serde(default)]
+    param_order: Vec<String>,
+    /// Groups of parameters which should be used together.
+    #[serde(default)]
+    sets: Vec<Set>,
+}
+
+impl TemplateData {
+    /// Parses and normalises TemplateData from JSON.
+    fn new(json: &str) -> Result<Self, serde_json::Error> {
+        let mut data = serde_json::from_str::<Self>(json)?;
+
+        if data.param_order.is_empty() {
+            data.param_order = data.params.keys().cloned().collect();
+        } else if let Some(name) = data
+            .param_order
+            .iter()
+            .find(|name| !data.params.contains_key(*name))
+        {
+            return Err(serde_json::Error::custom(invalid_value(&format!(
+                "paramOrder[\"{name}\"]"
+            ))));
+        } else {
+            for name in data.params.keys() {
+                if !data.param_order.contains(name) {
+                    data.param_order.push(name.clone());
+                }
+            }
+        }
+
+        for set in &data.sets {
+            if let Some(name) = set
+                .params
+                .iter()
+                .find(|name| !data.params.contains_key(*name))
+            {
+                return Err(serde_json::Error::custom(invalid_value(&format!(
+                    "sets.params[\"{name}\"]"
+                ))));
+            }
+        }
+
+        Ok(data)
+    }
+}
+
+/// A single template parameter.
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+struct Param {
+    /// Parameter aliases.
+    #[serde(default)]
+    aliases: Vec<String>,
+    /// The value automatically given to the parameter by editors.
+    #[serde(default)]
+    autovalue: Option<String>,
+    /// The default value of the parameter.
+    #[serde(default)]
+    default: Option<InterfaceText>,
+    /// Whether the parameter is deprecated, and optionally what to use
+    /// instead.
+    #[serde(default)]
+    deprecated: Deprecated,
+    /// The description of the parameter.
+    #[serde(default)]
+    description: Option<InterfaceText>,
+    /// An example value of the parameter.
+    #[serde(default)]
+    example: Option<InterfaceText>,
+    /// The human-readable name of the parameter.
+    #[serde(default)]
+    label: Option<InterfaceText>,
+    /// Whether the parameter is required.
+    #[serde(default)]
+    required: bool,
+    /// Whether the parameter is suggested.
+    #[serde(default)]
+    suggested: bool,
+    /// Suggested values for the parameter.
+    #[serde(default)]
+    suggestedvalues: Vec<String>,
+    /// The type of the parameter.
+    #[serde(default = "Param::default_type", rename = "type")]
+    kind: String,
+}
+
+impl Param {
+    /// The default parameter type.
+    fn default_type() -> String {
+        "unknown".to_owned()
+    }
+
+    /// Returns the message key for the status of the parameter.
+    fn status(&self) -> &'static str {
+        if self.deprecated.is_deprecated() {
+            "deprecated"
+        } else if self.required {
+            "required"
+        } else if self.suggested {
+            "suggested"
+        } else {
+            "optional"
+        }
+    }
+}
+
+/// A group of parameters which should be used together.
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+struct Set {
+    /// The name of the set.
+    label: InterfaceText,
+    /// The parameters in the set.
+    params: Vec<String>,
+}
+
+/// The deprecation status of a parameter.
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+enum Deprecated {
+    /// The parameter is, or is not, deprecated.
+    Flag(bool),
+    /// The parameter is deprecated for the given reason.
+    Reason(String),
+}
+
+impl Default for Deprecated {
+    fn default() -> Self {
+        Self::Flag(false)
+    }
+}
+
+impl Deprecated {
+    /// Returns true if the parameter is deprecated.
+    fn is_deprecated(&self) -> bool {
+        !matches!(self, Self::Flag(false))
+    }
+}
+
+/// Text which is either a plain string or a map of strings by language code.
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+enum InterfaceText {
+    /// Text in the content language.
+    Text(String),
+    /// Text by language code.
+    Localized(IndexMap<String, String>),
+}
+
+impl InterfaceText {
+    /// Returns the text in the content language, falling back to English and
+    /// then to whatever language comes first.
+    fn get(&self) -> Option<&str> {
+        match self {
+            Self::Text(text) => Some(text.as_str()),
+            Self::Localized(texts) => texts
+                .get(CONTENT_LANGUAGE)
+                .or_else(|| texts.get("en"))
+                .or_else(|| texts.values().next())
+                .map(String::as_str),
+        }
+    }
+}
+
+/// Deserialises the `params` object of a TemplateData object, resolving
+/// `inherits` properties.
+fn deserialize_params<'de, D>(deserializer: D) -> Result<IndexMap<String, Param>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw =
+        IndexMap::<String, serde_json::Map<String, serde_json::Value>>::deserialize(deserializer)?;
+    raw.iter()
+        .map(|(name, param)| {
+            let mut param = param.clone();
+            if let Some(base) = param.remove("inherits") {
+                let base = base
+                    .as_str()
+                    .and_then(|base| raw.get(base))
+                    .ok_or_else(|| {
+                        D::Error::custom(invalid_value(&format!("params.{name}.inherits")))
+                    })?;
+                let mut inherited = base.clone();
+                inherited.remove("inherits");
+                inherited.extend(param);
+                param = inherited;
+            }
+
+            let mut param = Param::deserialize(serde_json::Value::Object(param))
+                .map_err(|err| D::Error::custom(format!("params.{name}: {err}")))?;
+            if let Some(kind) = param.kind.strip_prefix("string/") {
+                param.kind = kind.to_owned();
+            }
+            if !TYPES.contains(&param.kind.as_str()) {
+                return Err(D::Error::custom(invalid_value(&format!(
+                    "params.{name}.type"
+                ))));
+            }
+            Ok((name.clone(), param))
+        })
+        .collect()
+}
+
+/// The `<templatedata>` extension tag.
+pub(super) fn template_data(
+    out: &mut String,
+    state: &mut State<'_>,
+    arguments: &ExtensionTag<'_, '_, '_>,
+) -> Result {
+    let data = match TemplateData::new(arguments.body().trim_ascii()) {
+        Ok(data) => data,
+        Err(err) => {
+            let message = if err.is_data() {
+                Cow::Owned(encode_text(&err.to_string()).into_owned())
+            } else {
+                message("templatedata-invalid-parse", &[])
+            };
+            write!(out, r#"<div class="errorbox">{message}</div>"#)?;
+            return Ok(OutputMode::Block);
+        }
+    };
+
+    write_table(out, &data)?;
+
+    if state.globals.template_data.is_none() {
+        state.globals.template_data = Some(data);
+    }
+
+    Ok(OutputMode::Block)
+}
+
+/// Writes the parameter documentation table for some TemplateData.
+fn write_table(out: &mut String, data: &TemplateData) -> Result<()> {
+    write!(out, r#"<div class="mw-templatedata-doc-wrap">"#)?;
+
+    if let Some(description) = data.description.as_ref().and_then(InterfaceText::get) {
+        write!(
+            out,
+            r#"<p class="mw-templatedata-doc-desc">{}</p>"#,
+            encode_text(description)
+        )?;
+    } else {
+        write!(
+            out,
+            r#"<p class="mw-templatedata-doc-desc mw-templatedata-doc-muted">{}</p>"#,
+            message("templatedata-doc-desc-empty", &[])
+        )?;
+    }
+
+    write!(
+        out,
+        r#"<table class="wikitable mw-templatedata-doc-params"><caption><p>{}</p>"#,
+        message("templatedata-doc-params", &[])
+    )?;
+    if let Some(format @ ("block" | "inline")) = data.format.as_deref() {
+        write!(
+            out,
+            "<p>{}</p>",
+            message(&format!("templatedata-doc-format-{format}"), &[])
+        )?;
+    }
+    write!(
+        out,
+        r#"</caption><thead><tr><th colspan="2">{}</th><th>{}</th><th>{}</th><th>{}</th></tr></thead><tbody>"#,
+        message("templatedata-doc-param-name", &[]),
+        message("templatedata-doc-param-desc", &[]),
+        message("templatedata-doc-param-type", &[]),
+        message("templatedata-doc-param-status", &[]),
+    )?;
+
+    if data.params.is_empty() {
+        write!(
+            out,
+            r#"<tr><td class="mw-templatedata-doc-muted" colspan="5">{}</td></tr>"#,
+            message("templatedata-doc-no-params-set", &[])
+        )?;
+    }
+
+    for name in &data.param_order {
+        if let Some(param) = data.params.get(name) {
+            write_param(out, name, param)?;
+        }
+    }
+
+    write!(out, "</tbody></table></div>")?;
+    Ok(())
+}
+
+/// Writes a single parameter row of the documentation table.
+fn write_param(out: &mut String, name: &str, param: &Param) -> Result<()> {
+    let status = param.status();
+    let label = param
+        .label
+        .as_ref()
+        .and_then(InterfaceText::get)
+        .unwrap_or(name);
+
+    write!(
+        out,
+        r#"<tr class="mw-templatedata-doc-param-status-{status}" id="templatedata:{}"><th>{}</th><td class="mw-templatedata-doc-param-name"><code>{}</code>"#,
+        anchor_encode(name),
+        encode_text(label),
+        encode_text(name)
+    )?;
+    for alias in &param.aliases {
+        write!(
+            out,
+            r#" <code class="mw-templatedata-doc-param-alias">{}</code>"#,
+            encode_text(alias)
+        )?;
+    }
+    write!(out, "</td>")?;
+
+    if let Some(description) = param.description.as_ref().and_then(InterfaceText::get) {
+        write!(out, "<td><p>{}</p>", encode_text(description))?;
+    } else {
+        write!(
+            out,
+            r#"<td><p class="mw-templatedata-doc-muted">{}</p>"#,
+            message("templatedata-doc-param-desc-empty", &[])
+        )?;
+    }
+
+    if let Deprecated::Reason(reason) = &param.deprecated {
+        write!(out, "<p>{}</p>", encode_text(reason))?;
+    }
+
+    let details = [
+        ("suggestedvalues", None),
+        (
+            "default",
+            param.default.as_ref().and_then(InterfaceText::get),
+        ),
+        (
+            "example",
+            param.example.as_ref().and_then(InterfaceText::get),
+        ),
+        ("autovalue", param.autovalue.as_deref()),
+    ];
+    let has_details =
+        !param.suggestedvalues.is_empty() || details.iter().any(|(_, value)| value.is_some());
+    if has_details {
+        write!(out, "<dl>")?;
+        for (key, value) in details {
+            let label = message(&format!("templatedata-doc-param-{key}"), &[]);
+            if key == "suggestedvalues" {
+                if !param.suggestedvalues.is_empty() {
+                    write!(out, "<dt>{label}</dt><dd>")?;
+                    for (index, value) in param.suggestedvalues.iter().enumerate() {
+                        if index != 0 {
+                            out.push(' ');
+                        }
+                        write!(out, "<code>{}</code>", encode_text(value))?;
+                    }
+                    write!(out, "</dd>")?;
+                }
+            } else if let Some(value) = value {
+                write!(out, "<dt>{label}</dt><dd>{}</dd>", encode_text(value))?;
+            }
+        }
+        write!(out, "</dl>")?;
+    }
+    write!(out, "</td>")?;
+
+    write!(
+        out,
+        r#"<td class="mw-templatedata-doc-param-type">{}</td><td class="mw-templatedata-doc-param-status">{}</td></tr>"#,
+        message(&format!("templatedata-doc-param-type-{}", param.kind), &[]),
+        message(&format!("templatedata-doc-param-status-{status}"), &[])
+    )?;
+
+    Ok(())
+}
+
+/// Formats an invalid property value error message.
+fn invalid_value(property: &str) -> String {
+    message("templatedata-invalid-value", &[property]).into_owned()
+}
+
+/// Formats an interface message with the given parameters.
+fn message<'a>(key: &str, params: &[&'a str]) -> Cow<'static, str> {
+    let Ok(message) = format_message([key], |index| {
+        Ok::<_, Infallible>(
+            index
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| params.get(index.checked_sub(1)?))
+                .map(|param| Cow::<'a, str>::Borrowed(param)),
+        )
+    });
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalise() {
+        let data = TemplateData::new(
+            r#"{
+                "description": { "de": "Beschreibung", "en": "Description" },
+                "params": {
+                    "1": { "label": "First", "type": "string/wiki-page-name", "required": true },
+                    "2": { "inherits": "1", "required": false, "deprecated": "Use 1" }
+                },
+                "paramOrder": ["2"],
+                "format": "inline"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(data.param_order, ["2", "1"]);
+        assert_eq!(
+            data.description.as_ref().and_then(InterfaceText::get),
+            Some("Description")
+        );
+        let first = &data.params["1"];
+        assert_eq!(first.kind, "wiki-page-name");
+        assert_eq!(first.status(), "required");
+        let second = &data.params["2"];
+        assert_eq!(second.kind, "wiki-page-name");
+        assert_eq!(
+            second.label.as_ref().and_then(InterfaceText::get),
+            Some("First")
+        );
+        assert_eq!(second.status(), "deprecated");
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(!TemplateData::new("{").unwrap_err().is_data());
+        assert!(TemplateData::new(r#"{ "params": {}, "bogus": 1 }"#).is_err());
+        assert!(TemplateData::new(r#"{ "params": { "a": { "type": "bogus" } } }"#).is_err());
+        assert!(TemplateData::new(r#"{ "params": { "a": {} }, "paramOrder": ["b"] }"#).is_err());
+        assert!(
+            TemplateData::new(
+                r#"{ "params": { "a": {} }, "sets": [{ "label": "x", "params": ["b"] }] }"#
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn table() {
+        let data = TemplateData::new(
+            r#"{ "params": { "name": { "aliases": ["n"], "default": "<x>", "suggested": true } } }"#,
+        )
+        .unwrap();
+        let mut out = String::new();
+        write_table(&mut out, &data).unwrap();
+        assert!(out.contains(
+            r#"<p class="mw-templatedata-doc-desc mw-templatedata-doc-muted">No description.</p>"#
+        ));
+        assert!(out.contains(r#"<tr class="mw-templatedata-doc-param-status-suggested" id="templatedata:name"><th>name</th><td class="mw-templatedata-doc-param-name"><code>name</code> <code class="mw-templatedata-doc-param-alias">n</code></td>"#));
+        assert!(out.contains("<dl><dt>Default</dt><dd>&lt;x&gt;</dd></dl>"));
+        assert!(out.contains(r#"<td class="mw-templatedata-doc-param-type">Unknown</td><td class="mw-templatedata-doc-param-status">suggested</td>"#));
+    }
+}
@@ -360,7 +360,8 @@ peg::parser! {pub(super) grammar easy_timeline() for str {
   { Command::LineData(v) }
 
   rule line_data_values(date_format: DateFormat) -> LineData<'input>
-  = parts:line_data_value(date_format)**space()
+  = offset:position!()
+    parts:line_data_value(date_format)**space()
   {?
     let mut at = None;
     let mut at_pos = None;
@@ -405,7 +406,7 @@ peg::parser! {pub(super) grammar easy_timeline() for str {
         }
     };
 
-    Ok(LineData { color, instr, layer, width })
+    Ok(LineData { color, instr, layer, offset, width })
   }
 
   rule line_data_value(date_format: DateFormat) -> LineDataPart<'input>
@@ -536,7 +537,8 @@ peg::parser! {pub(super) grammar easy_timeline() for str {
   { Command::PlotData(v) }
 
   rule plot_data_values(date_format: DateFormat) -> PlotData<'input>
-  = parts:plot_data_value(date_format)**space()
+  = offset:position!()
+    parts:plot_data_value(date_format)**space()
   {?
     let mut align = None;
     let mut anchor = None;
@@ -593,7 +595,7 @@ peg::parser! {pub(super) grammar easy_timeline() for str {
 
     Ok(PlotData {
         align, anchor, at, bar, color, font_size, link,
-        mark, shift, text_color, text, width
+        mark, offset, shift, text_color, text, width
     })
   }
 
@@ -660,18 +662,19 @@ peg::parser! {pub(super) grammar easy_timeline() for str {
   {
     let mut grid_color = None;
     let mut interval = 1;
-    let mut start = None;
+    // The scale starts at the start of the period unless told otherwise
+    let mut start = Time::Start;
     let mut unit = ScaleUnit::Year;
     for part in parts {
         match part {
             ScalePart::GridColor(c) => grid_color = Some(c),
             ScalePart::Increment(i) => interval = i,
-            ScalePart::Start(s) => start = Some(s),
+            ScalePart::Start(s) => start = s,
             ScalePart::Unit(u) => unit = u,
         }
     }
 
-    Scale { grid_color, interval, start, unit }
+    Scale { grid_color, interval, start: Some(start), unit }
   }
 
   rule scale_value(date_format: DateFormat) -> ScalePart<'input>
@@ -698,7 +701,8 @@ peg::parser! {pub(super) grammar easy_timeline() for str {
   { Command::TextData(v) }
 
   rule text_data_values() -> TextData<'input>
-  = parts:text_data_value()**space()
+  = offset:position!()
+    parts:text_data_value()**space()
   {
     let mut font_size = None;
     let mut line_height = None;
@@ -719,7 +723,7 @@ peg::parser! {pub(super) grammar easy_timeline() for str {
         }
     }
 
-    TextData { font_size, line_height, link, pos, tabs, text, text_color }
+    TextData { font_size, line_height, link, offset, pos, tabs, text, text_color }
   }
 
   rule text_data_value() -> TextDataPart<'input>
@@ -862,9 +866,10 @@ peg::parser! {pub(super) grammar easy_timeline() for str {
   / t:integer()
   {? (6..=30).contains(&t).then_some(FontSize::Absolute(t)).ok_or("integer 6..=30") }
 
+  // Either coordinate may be omitted, in which case it is zero
   rule point() -> Point
-  = "(" space()? x:abs_size() space()? "," space()? y:abs_size() space()? ")"
-  { Point(x, y) }
+  = "(" space()? x:abs_size()? space()? "," space()? y:abs_size()? space()? ")"
+  { Point(x.unwrap_or(0.0), y.unwrap_or(0.0)) }
 
   rule rel_size() -> Unit
   = value:decimal()
@@ -11,13 +11,17 @@ mod renderer;
 mod tests;
 
 /// Converts an EasyTimeline script into a serialised SVG image.
-pub fn timeline_to_svg(input: &str, base_uri: &Uri) -> Result<String> {
-    let (expanded, deltas) = parser::expand(input.trim_ascii_end())?;
-    let pen = parser::parse(&expanded, input, &deltas)?;
-    let svg = renderer::render(pen, base_uri)?;
+pub fn timeline_to_svg(input: &str, base_uri: &Uri) -> Result<String, ScriptError> {
+    let input = input.trim_ascii_end();
+    let (expanded, deltas) =
+        parser::expand(input).map_err(|err| ScriptError::new(input, &[], err))?;
 
     let mut out = Vec::new();
-    svg.write_to(&mut out)?;
+    parser::parse(&expanded, input, &deltas)
+        .and_then(|pen| renderer::render(pen, base_uri))
+        .and_then(|svg| Ok(svg.write_to(&mut out)?))
+        .map_err(|err| ScriptError::new(input, &deltas, err))?;
+
     // SAFETY: We just wrote this from strs.
     Ok(unsafe { String::from_utf8_unchecked(out) })
 }
@@ -25,29 +29,97 @@ pub fn timeline_to_svg(input: &str, base_uri: &Uri) -> Result<String> {
 /// An EasyTimeline error.
 #[derive(Debug, thiserror::Error)]
 pub(crate) enum Error {
+    /// An error caused by the script at the given offset of the expanded
+    /// input.
+    #[error("{1}")]
+    At(usize, Box<Error>),
     /// Use of undefined colour.
-    #[error("missing color '{0}'")]
+    #[error("Unknown color '{0}'.\n  Specify command 'Colors' before this command.")]
     Color(String),
     /// Missing required `ImageSize` command.
-    #[error("missing ImageSize")]
+    #[error("Command 'ImageSize' missing.")]
     ImageSize,
     /// Use of `barset` without corresponding `BarData`.
-    #[error("barset must be defined in BarData")]
+    #[error("Attribute 'barset' invalid.\n  Barsets must be defined in command 'BarData'.")]
     ImplicitBarset,
     /// Script parsing error.
-    #[error(transparent)]
+    #[error("Invalid syntax at column {}: expected {}.", .0.location.column, .0.expected)]
     Parse(#[from] peg::error::ParseError<peg::str::LineCol>),
     /// Missing one or more plot area dimensions.
-    #[error("incomplete PlotArea")]
+    #[error(
+        "Command 'PlotArea' incomplete.\n  Specify left, bottom, and either right and top or width and height."
+    )]
     PlotArea,
     /// Out-of-range time.
-    #[error("time {0:?} not in range {1:?}..={2:?}")]
+    #[error("Date '{0}' not within range of command 'Period' ({1} - {2}).")]
     Time(Time, Time, Time),
     /// XML library error.
     #[error(transparent)]
     Xml(#[from] minidom::Error),
 }
 
+impl Error {
+    /// Associates the error with the given offset in the expanded input, if
+    /// it is not already associated with an offset.
+    fn at(self, offset: usize) -> Self {
+        if matches!(self, Self::At(..) | Self::Parse(_)) {
+            self
+        } else {
+            Self::At(offset, Box::new(self))
+        }
+    }
+}
+
+/// An EasyTimeline error, formatted in the same way as the error report from
+/// EasyTimeline.
+#[derive(Debug)]
+pub(crate) struct ScriptError {
+    /// The error.
+    err: Error,
+    /// The line number and text of the line of the script which caused the
+    /// error, if known.
+    line: Option<(usize, String)>,
+}
+
+impl ScriptError {
+    /// Creates a new script error, using `deltas` from [`parser::expand`] to
+    /// find the line of the original `input` which caused it.
+    fn new(input: &str, deltas: &[(usize, isize)], err: Error) -> Self {
+        let (offset, err) = match err {
+            Error::At(offset, err) => (Some(parser::unexpand(offset, deltas)), *err),
+            Error::Parse(ref parse) => (Some(parse.location.offset), err),
+            err => (None, err),
+        };
+
+        let line = offset.map(|offset| {
+            let input = input.as_bytes();
+            let offset = offset.min(input.len());
+            let start = memchr::memrchr(b'\n', &input[..offset]).map_or(0, |index| index + 1);
+            let end =
+                memchr::memchr(b'\n', &input[offset..]).map_or(input.len(), |index| offset + index);
+            let number = memchr::memchr_iter(b'\n', &input[..start]).count() + 1;
+            let text = String::from_utf8_lossy(&input[start..end])
+                .trim()
+                .to_owned();
+            (number, text)
+        });
+
+        Self { err, line }
+    }
+}
+
+impl core::fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        writeln!(f, "Timeline generation failed: 1 error found")?;
+        if let Some((number, text)) = &self.line {
+            write!(f, "Line {number}: {text}\n\n")?;
+        }
+        write!(f, "- {}", self.err)
+    }
+}
+
+impl core::error::Error for ScriptError {}
+
 /// A colour identifier.
 type ColorId<'input> = &'input str;
 /// The EasyTimeline result type.
@@ -292,6 +364,8 @@ struct Line<'input> {
     color: ColorId<'input>,
     /// The draw instruction.
     instr: LineDataInstr,
+    /// The offset of the line in the expanded input.
+    offset: usize,
     /// The stroke width.
     width: f64,
 }
@@ -447,6 +521,8 @@ struct Plot<'input> {
     at: PlotDataPos,
     /// The index of the corresponding series.
     index: usize,
+    /// The offset of the segment in the expanded input.
+    offset: usize,
     /// Drawing properties.
     pen: parser::PlotPen<'input>,
     /// Text label.
@@ -663,7 +739,8 @@ struct Scale<'input> {
     grid_color: Option<ColorId<'input>>,
     /// The scale grid line interval.
     interval: i32,
-    /// The scale origin.
+    /// The scale origin. If `None`, the scale was never defined and is not
+    /// drawn.
     start: Option<Time>,
     /// The time unit for the grid lines.
     unit: ScaleUnit,
@@ -707,6 +784,8 @@ struct TabStop {
 struct Text<'input> {
     /// Label URL.
     link: Option<Url<'input>>,
+    /// The offset of the label in the expanded input.
+    offset: usize,
     /// Drawing properties.
     pen: parser::TextPen<'input>,
     /// Text.
@@ -769,6 +848,16 @@ pub(crate) enum Time {
     Start,
 }
 
+impl core::fmt::Display for Time {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Time::Decimal(time) => write!(f, "{time}"),
+            Time::End => f.write_str("end"),
+            Time::Start => f.write_str("start"),
+        }
+    }
+}
+
 impl core::ops::Sub for Time {
     type Output = Self;
 
@@ -20,6 +20,10 @@ use std::collections::{BTreeMap, HashMap};
 /// Because variable replacement is arbitrary text in any position, it is
 /// simpler to do this separately than it is to try to try to make the grammar
 /// support it.
+///
+/// Returns the expanded text and a list of `(offset, delta)` pairs, where
+/// `offset` is the position in the expanded text after which every position is
+/// `delta` bytes longer than in the original input.
 pub(super) fn expand(input: &str) -> Result<(String, Vec<(usize, isize)>)> {
     let mut expanded = String::new();
     let mut defines = Defines::new();
@@ -40,7 +44,16 @@ pub(super) fn expand(input: &str) -> Result<(String, Vec<(usize, isize)>)> {
                 len
             }
             grammar::Chunk::Define(len, Define { key, value }) => {
-                defines.insert(key.to_ascii_lowercase(), value);
+                // Values can use earlier variables, which are replaced now
+                // since a later redefinition should not change them
+                let mut expanded_value = String::new();
+                replace_idents(
+                    &mut expanded_value,
+                    &mut Vec::new(),
+                    &defines,
+                    value.trim_end(),
+                );
+                defines.insert(key.to_ascii_lowercase(), expanded_value);
                 deltas.push((expanded.len(), -isize::try_from(len).unwrap()));
                 len
             }
@@ -51,7 +64,7 @@ pub(super) fn expand(input: &str) -> Result<(String, Vec<(usize, isize)>)> {
 }
 
 /// A collection of defined variables.
-type Defines<'input> = HashMap<String, &'input str>;
+type Defines = HashMap<String, String>;
 
 /// A defined variable.
 #[derive(Debug)]
@@ -73,24 +86,35 @@ pub(super) fn parse<'a>(
     while cursor != input.len() {
         let (len, command) = grammar::easy_timeline::timeline(&input[cursor..], pen.date_format)
             .map_err(|mut err| {
-                let mut offset = cursor + err.location.offset;
-                for delta in deltas
-                    .iter()
-                    .map_while(move |&(at, delta)| (at < offset).then_some(delta))
-                {
-                    offset = offset.strict_sub_signed(delta);
-                }
+                let offset = unexpand(cursor + err.location.offset, deltas);
                 err.location = peg::Parse::position_repr(original, offset);
                 err
             })?;
         // eprintln!("command: {command:#?}");
-        pen.update_state(command)?;
+        let start = input[cursor..]
+            .find(|c: char| !c.is_ascii_whitespace())
+            .map_or(cursor, |start| cursor + start);
+        pen.update_state(command, cursor)
+            .map_err(|err| err.at(start))?;
         cursor += len;
     }
 
     Ok(pen)
 }
 
+/// Converts an offset in the text returned by [`expand`] into an offset in the
+/// original input.
+///
+/// Offsets inside replaced variables are approximate.
+pub(super) fn unexpand(offset: usize, deltas: &[(usize, isize)]) -> usize {
+    deltas
+        .iter()
+        .take_while(|&&(at, _)| at <= offset)
+        .fold(offset, |offset, &(_, delta)| {
+            offset.strict_sub_signed(delta)
+        })
+}
+
 /// A timeline series identifier.
 pub(super) type BarId<'input> = &'input str;
 /// A collection of timeline series.
@@ -236,8 +260,9 @@ impl<'input> Timeline<'input> {
         }
     }
 
-    /// Adds new line drawings.
-    fn update_line_data(&mut self, lines: Vec<LineData<'input>>) {
+    /// Adds new line drawings from a command which starts at the given offset
+    /// of the expanded input.
+    fn update_line_data(&mut self, lines: Vec<LineData<'input>>, offset: usize) {
         for line in lines {
             if let Some(instr) = line.instr {
                 let mut line_pen = self.line_pen;
@@ -249,6 +274,7 @@ impl<'input> Timeline<'input> {
                 layer.push(Line {
                     color: line_pen.color,
                     instr,
+                    offset: offset + line.offset,
                     width: line_pen.width,
                 });
             } else {
@@ -257,8 +283,9 @@ impl<'input> Timeline<'input> {
         }
     }
 
-    /// Adds new timeline segments.
-    fn update_plot_data(&mut self, data: Vec<PlotData<'input>>) -> Result {
+    /// Adds new timeline segments from a command which starts at the given
+    /// offset of the expanded input.
+    fn update_plot_data(&mut self, data: Vec<PlotData<'input>>, offset: usize) -> Result {
         if self.bars.is_empty() {
             let mut index = 0;
             for plot in &data {
@@ -275,7 +302,7 @@ impl<'input> Timeline<'input> {
                         index += 1;
                     }
                     Some(PlotDataTarget::BarSet(_)) => {
-                        return Err(Error::ImplicitBarset);
+                        return Err(Error::ImplicitBarset.at(offset + plot.offset));
                     }
                     None => {}
                 }
@@ -285,6 +312,7 @@ impl<'input> Timeline<'input> {
         let mut bar_set = None;
         let mut index = 0;
         for plot in data {
+            let offset = offset + plot.offset;
             match plot.bar {
                 Some(PlotDataTarget::Bar(id)) => {
                     index = self.bars[&id.to_ascii_lowercase()].index;
@@ -295,7 +323,7 @@ impl<'input> Timeline<'input> {
                         .bar_sets
                         .get(&id.to_ascii_lowercase())
                         .cloned()
-                        .unwrap();
+                        .ok_or_else(|| Error::ImplicitBarset.at(offset))?;
                     index = range.start;
                     bar_set = Some(range);
                 }
@@ -304,14 +332,14 @@ impl<'input> Timeline<'input> {
                         self.reindex(&mut bar_set, index);
                         index += 1;
                     } else {
-                        return Err(Error::ImplicitBarset);
+                        return Err(Error::ImplicitBarset.at(offset));
                     }
                 }
                 Some(PlotDataTarget::BarSet(BarsetId::Reset)) => {
                     if let Some(bar_set) = &bar_set {
                         index = bar_set.start;
                     } else {
-                        return Err(Error::ImplicitBarset);
+                        return Err(Error::ImplicitBarset.at(offset));
                     }
                 }
                 None => {}
@@ -326,6 +354,7 @@ impl<'input> Timeline<'input> {
                 self.bar_layer.push(Plot {
                     at,
                     index,
+                    offset,
                     pen: plot_pen,
                     text: plot.text,
                     link: plot.link,
@@ -440,8 +469,9 @@ impl<'input> Timeline<'input> {
         }
     }
 
-    /// Updates the parser state from a command.
-    fn update_state(&mut self, command: Command<'input>) -> Result {
+    /// Updates the parser state from a command which starts at the given
+    /// offset of the expanded input.
+    fn update_state(&mut self, command: Command<'input>, offset: usize) -> Result {
         match command {
             Command::AlignBars(align_bars) => self.align_bars = align_bars,
             Command::BackgroundColors(colors) => {
@@ -457,7 +487,7 @@ impl<'input> Timeline<'input> {
             Command::DateFormat(date_format) => self.date_format = date_format,
             Command::Legend(legend) => self.legend = legend,
             Command::LineData(lines) => {
-                self.update_line_data(lines);
+                self.update_line_data(lines, offset);
             }
             Command::ImageSize(image_size) => self.image_size = Some(image_size),
             Command::Period(period) => self.period = period,
@@ -468,7 +498,7 @@ impl<'input> Timeline<'input> {
                 replace(&mut self.plot_area.bottom, plot_area.bottom);
             }
             Command::PlotData(data) => {
-                self.update_plot_data(data)?;
+                self.update_plot_data(data, offset)?;
             }
             Command::Preset(preset) => self.update_preset(preset),
             Command::ScaleMajor(scale) => {
@@ -478,7 +508,7 @@ impl<'input> Timeline<'input> {
                 self.scale_minor = scale;
             }
             Command::TextData(data) => {
-                self.update_text_data(data);
+                self.update_text_data(data, offset);
             }
             Command::TimeAxis(time_axis) => self.time_axis = time_axis,
         }
@@ -486,8 +516,9 @@ impl<'input> Timeline<'input> {
         Ok(())
     }
 
-    /// Adds text labels.
-    fn update_text_data(&mut self, data: Vec<TextData<'input>>) {
+    /// Adds text labels from a command which starts at the given offset of the
+    /// expanded input.
+    fn update_text_data(&mut self, data: Vec<TextData<'input>>, offset: usize) {
         for mut line in data {
             // It seems like this should only advance when there
             // is actually text, but no, it does it *every* line, and
@@ -511,6 +542,7 @@ impl<'input> Timeline<'input> {
                 text_pen.update(&line);
                 self.text_layer.push(Text {
                     link: line.link,
+                    offset: offset + line.offset,
                     pen: text_pen,
                     spans: text,
                 });
@@ -622,12 +654,12 @@ pub(super) struct LineData<'input> {
     pub instr: Option<LineDataInstr>,
     /// The target layer.
     pub layer: Option<Layer>,
+    /// The offset of the line in the command.
+    pub offset: usize,
     /// The stroke width.
     pub width: Option<f64>,
 }
 
-// TODO: It seems like lines use colours immediately but everything else does
-// not?
 /// A line drawing pen.
 #[derive(Clone, Copy, Debug)]
 pub(super) struct LinePen<'input> {
@@ -651,7 +683,7 @@ impl<'input> LinePen<'input> {
 impl Default for LinePen<'_> {
     fn default() -> Self {
         Self {
-            color: <_>::default(),
+            color: "black",
             layer: <_>::default(),
             width: 1.0,
         }
@@ -677,6 +709,8 @@ pub(super) struct PlotData<'input> {
     pub link: Option<&'input str>,
     /// Mark colour.
     pub mark: Option<&'input str>,
+    /// The offset of the segment in the command.
+    pub offset: usize,
     /// Text label position offset.
     pub shift: Option<Point>,
     /// Text label colour.
@@ -783,6 +817,8 @@ pub(super) struct TextData<'input> {
     pub line_height: Option<f64>,
     /// The link URL.
     pub link: Option<&'input str>,
+    /// The offset of the label in the command.
+    pub offset: usize,
     /// The position relative to the bottom-left corner of the image.
     pub pos: Option<Point>,
     /// Tab stops.
@@ -855,7 +891,7 @@ where
 fn replace_idents(
     out: &mut String,
     deltas: &mut Vec<(usize, isize)>,
-    defines: &Defines<'_>,
+    defines: &Defines,
     input: &str,
 ) {
     let mut flushed = 0;
@@ -875,8 +911,8 @@ fn replace_idents(
             if let Some(value) = defines.get(&key.to_lowercase()) {
                 let old_len = key.len() + "$".len();
                 let new_len = value.len();
-                deltas.push((out.len(), new_len.checked_signed_diff(old_len).unwrap()));
                 *out += value;
+                deltas.push((out.len(), new_len.checked_signed_diff(old_len).unwrap()));
                 flushed = end;
             } else {
                 flushed = at;
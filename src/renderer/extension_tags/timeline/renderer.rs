@@ -14,15 +14,36 @@
 )]
 
 use super::{
-    super::svg::{NS_SVG, ValueDisplay, n},
+    super::{
+        font,
+        svg::{NS_SVG, ValueDisplay, n},
+    },
     AlignBars, Alignment, ColorId, ColorValue, Dims, Either, Error, FontSize, ImageSize,
-    LegendPosition, Line, LineDataInstr, Orientation, PREDEFINED_COLORS, PlotDataPos, Rect, Result,
-    ScaleUnit, TabStop, TextSpan, Uri, Url,
+    LegendPosition, Line, LineDataInstr, Orientation, PREDEFINED_COLORS, PlotAreaEnd, PlotDataPos,
+    Rect, Result, ScaleUnit, TabStop, TextSpan, Unit, Uri, Url,
     parser::Timeline,
 };
 use core::ops::Deref;
 use minidom::{Element, ElementBuilder};
 
+/// The gap between the plot area and the labels below it on the cross axis,
+/// in CSS pixels.
+const CROSS_LABEL_GAP: f64 = 2.0;
+
+/// The gap between the plot area and the labels to the left of it, in CSS
+/// pixels.
+const LEFT_LABEL_GAP: f64 = 10.0;
+
+/// The gap between the plot area and the labels below it on the main axis,
+/// in CSS pixels.
+const MAIN_LABEL_GAP: f64 = 5.0;
+
+/// The length of major scale ticks, in CSS pixels.
+const MAJOR_TICK_LEN: f64 = 5.0;
+
+/// The length of minor scale ticks, in CSS pixels.
+const MINOR_TICK_LEN: f64 = 2.5;
+
 /// Renders a timeline into an SVG element.
 pub(super) fn render(pen: Timeline<'_>, base_uri: &Uri) -> Result<Element> {
     let mut renderer = Renderer::new(pen, base_uri)?;
@@ -67,14 +88,25 @@ impl<'input> Renderer<'input> {
     /// Creates a new renderer for the given `timeline`, using the given
     /// `base_uri` for generating URLs.
     fn new(timeline: Timeline<'input>, base_uri: &'input Uri) -> Result<Self> {
+        // Automatic sizes give each bar `bar` pixels inside the plot area, so
+        // only absolute insets can be added to the size
+        let inset = |start: Option<Unit>, end: Option<PlotAreaEnd>| {
+            start.map_or(0.0, |start| start.into_abs(0.0))
+                + match end {
+                    Some(PlotAreaEnd::Inset(end)) => end.into_abs(0.0),
+                    Some(PlotAreaEnd::Size(_)) | None => 0.0,
+                }
+        };
         let image_dims = match timeline.image_size {
             Some(ImageSize::AutoHeight { bar, width }) => Dims(
                 width,
-                bar * timeline.bar_count as f64,
+                bar * timeline.bar_count as f64
+                    + inset(timeline.plot_area.bottom, timeline.plot_area.top),
                 timeline.time_axis.orientation,
             ),
             Some(ImageSize::AutoWidth { bar, height }) => Dims(
-                bar * timeline.bar_count as f64,
+                bar * timeline.bar_count as f64
+                    + inset(timeline.plot_area.left, timeline.plot_area.right),
                 height,
                 timeline.time_axis.orientation,
             ),
@@ -205,7 +237,7 @@ impl<'input> Renderer<'input> {
 
     /// Adds global styles to the SVG output.
     fn add_global_styles(&mut self) -> Result<&mut Self> {
-        // TODO: Should default to 'transparent'?
+        // EasyTimeline images are never transparent
         let canvas_bg = self.color(self.canvas_color.unwrap_or("white"))?;
         let css = format!(
             ".wiki-rs-timeline{{background-color:{};text a{{fill:#00f}}line{{transform:translate(.5px,.5px)}}}}",
@@ -221,7 +253,9 @@ impl<'input> Renderer<'input> {
         for text in &self.timeline.text_layer {
             let y = text.pen.pos.y(self.image_dims.height());
             let x = text.pen.pos.x();
-            let color = self.color(text.pen.text_color)?;
+            let color = self
+                .color(text.pen.text_color)
+                .map_err(|err| err.at(text.offset))?;
             self.top_layer.append_child(make_text(&MakeText {
                 alignment: Alignment::Start,
                 base_uri: self.base_uri,
@@ -344,12 +378,18 @@ impl<'input> Renderer<'input> {
         match (self.plot_area.orientation, len) {
             (Orientation::Horizontal, Either::Left(x))
             | (Orientation::Vertical, Either::Right(x)) => {
-                let offset = if len.is_left() { 5.0 } else { 2.0 };
+                let offset = if len.is_left() {
+                    MAIN_LABEL_GAP
+                } else {
+                    CROSS_LABEL_GAP
+                };
 
                 (x, self.plot_area.bottom() + offset)
             }
             (Orientation::Horizontal, Either::Right(y))
-            | (Orientation::Vertical, Either::Left(y)) => (self.plot_area.left() - 10.0, y),
+            | (Orientation::Vertical, Either::Left(y)) => {
+                (self.plot_area.left() - LEFT_LABEL_GAP, y)
+            }
         }
     }
 }
@@ -430,19 +470,26 @@ fn draw_axes(r: &mut Renderer<'_>) -> Result {
 }
 
 /// Draws the timeline legend.
+///
+/// Vertically oriented legends are split into columns, and horizontally
+/// oriented legends are drawn in rows which wrap at the edge of the image.
 fn draw_legend(r: &mut Renderer<'_>) -> Result<()> {
+    /// The size of a colour swatch.
     const SWATCH_SIZE: f64 = 12.0;
+    /// The space between a swatch and its label, and between entries.
     const MARGIN: f64 = SWATCH_SIZE + 8.0;
+    /// The offset from the top of a swatch to the baseline of its label.
+    const BASELINE: f64 = 10.0;
+    /// The space between rows of entries.
+    const ROW_GAP: f64 = 4.0;
 
     let font_size = FontSize::Small;
     let line_height = font_size.line_height();
+    let horizontal = r.legend.orientation == Orientation::Horizontal;
 
     let columns = if let Some(columns) = r.legend.columns {
         usize::from(columns)
-    } else if r.legend.orientation == Orientation::Horizontal
-        || r.legend.position == LegendPosition::Right
-        || r.legends.len() <= 5
-    {
+    } else if horizontal || r.legend.position == LegendPosition::Right || r.legends.len() <= 5 {
         1
     } else if r.legends.len() <= 10 {
         2
@@ -463,9 +510,9 @@ fn draw_legend(r: &mut Renderer<'_>) -> Result<()> {
 
     let y = r.legend.top.map_or_else(
         || match r.legend.position {
-            LegendPosition::Bottom => r.plot_area.bottom() + MARGIN * -2.0,
-            LegendPosition::Top => r.image_dims.height() - MARGIN,
-            LegendPosition::Right => r.plot_area.top() - MARGIN,
+            LegendPosition::Bottom => r.plot_area.bottom() + MARGIN * 2.0,
+            LegendPosition::Top => MARGIN,
+            LegendPosition::Right => r.plot_area.top() + MARGIN,
         },
         |top| r.image_dims.height() - top.into_abs(r.image_dims.height()),
     );
@@ -488,6 +535,19 @@ fn draw_legend(r: &mut Renderer<'_>) -> Result<()> {
     let mut index = 0;
     let text_color = r.color("black")?;
     for (color, text) in &r.timeline.legends {
+        // Horizontal entries are only as wide as they need to be, unless the
+        // column width is given explicitly
+        let entry_width = if horizontal && r.legend.column_width.is_none() {
+            MARGIN + text_width(text, font_size) + MARGIN
+        } else {
+            column_width
+        };
+
+        if horizontal && dx != 0.0 && x + dx + entry_width > r.image_dims.width() {
+            dx = 0.0;
+            dy += line_height + ROW_GAP;
+        }
+
         let swatch = Rect::new(
             x + dx,
             y + dy,
@@ -507,16 +567,20 @@ fn draw_legend(r: &mut Renderer<'_>) -> Result<()> {
             tabs: &[],
             text,
             x: x + dx + MARGIN,
-            y: y + dy + /* TODO: No magic numbers */ 10.0,
+            y: y + dy + BASELINE,
         })?);
 
-        index += 1;
-        if index == per_column {
-            dx += column_width;
-            dy = 0.0;
-            index = 0;
+        if horizontal {
+            dx += entry_width;
         } else {
-            dy += line_height + 4.0;
+            index += 1;
+            if index == per_column {
+                dx += column_width;
+                dy = 0.0;
+                index = 0;
+            } else {
+                dy += line_height + ROW_GAP;
+            }
         }
     }
 
@@ -528,67 +592,72 @@ fn draw_lines(r: &Renderer<'_>, lines: &[Line<'_>]) -> Result<Element> {
     let mut group = Element::builder("g", NS_SVG);
     let reverse = r.time_axis.reverse_order();
     for line in lines {
-        let color = r.color(line.color)?;
-        let (x1, y1, x2, y2) = match line.instr {
-            LineDataInstr::Independent(p0, p1) => {
-                let x1 = p0.x();
-                let y1 = p0.y(r.image_dims.height());
-                let x2 = p1.x();
-                let y2 = p1.y(r.image_dims.height());
-                (x1, y1, x2, y2)
-            }
-            LineDataInstr::Orthogonal { at, from, till } => {
-                let main =
-                    r.plot_area.main_start() + r.period.norm(at, reverse)? * r.plot_area.main_len();
-                let cross1 = from.map_or(r.plot_area.cross_end(), |from| {
-                    let from = from.into_abs(r.plot_area.cross_len());
-                    match r.plot_area.orientation {
-                        Orientation::Horizontal => r.image_dims.height() - from,
-                        Orientation::Vertical => from,
-                    }
-                });
-                let cross2 = till.map_or(r.plot_area.cross_start(), |till| {
-                    let till = till.into_abs(r.plot_area.cross_len());
-                    match r.plot_area.orientation {
-                        Orientation::Horizontal => r.image_dims.height() - till,
-                        Orientation::Vertical => till,
-                    }
-                });
+        group = group.append(draw_line(r, line, reverse).map_err(|err| err.at(line.offset))?);
+    }
+    Ok(group.build())
+}
 
+/// Draws a single line.
+fn draw_line(r: &Renderer<'_>, line: &Line<'_>, reverse: bool) -> Result<Element> {
+    let color = r.color(line.color)?;
+    let (x1, y1, x2, y2) = match line.instr {
+        LineDataInstr::Independent(p0, p1) => {
+            let x1 = p0.x();
+            let y1 = p0.y(r.image_dims.height());
+            let x2 = p1.x();
+            let y2 = p1.y(r.image_dims.height());
+            (x1, y1, x2, y2)
+        }
+        LineDataInstr::Orthogonal { at, from, till } => {
+            let main =
+                r.plot_area.main_start() + r.period.norm(at, reverse)? * r.plot_area.main_len();
+            let cross1 = from.map_or(r.plot_area.cross_end(), |from| {
+                let from = from.into_abs(r.plot_area.cross_len());
                 match r.plot_area.orientation {
-                    Orientation::Horizontal => (main, cross1, main, cross2),
-                    Orientation::Vertical => (cross1, main, cross2, main),
+                    Orientation::Horizontal => r.image_dims.height() - from,
+                    Orientation::Vertical => from,
                 }
-            }
-            LineDataInstr::Parallel { at, from, till } => {
-                let cross = at.into_abs(r.plot_area.cross_len());
-                let main1 = r.plot_area.main_start()
-                    + from.map_or(Ok(0.0), |from| r.period.norm(from, reverse))?
-                        * r.plot_area.main_len();
-                let main2 = r.plot_area.main_start()
-                    + till.map_or(Ok(1.0), |till| r.period.norm(till, reverse))?
-                        * r.plot_area.main_len();
-
+            });
+            let cross2 = till.map_or(r.plot_area.cross_start(), |till| {
+                let till = till.into_abs(r.plot_area.cross_len());
                 match r.plot_area.orientation {
-                    Orientation::Horizontal => {
-                        let y = r.image_dims.height() - cross;
-                        (main1, y, main2, y)
-                    }
-                    Orientation::Vertical => {
-                        let x = r.plot_area.cross_start() + cross;
-                        (x, main1, x, main2)
-                    }
+                    Orientation::Horizontal => r.image_dims.height() - till,
+                    Orientation::Vertical => till,
                 }
+            });
+
+            match r.plot_area.orientation {
+                Orientation::Horizontal => (main, cross1, main, cross2),
+                Orientation::Vertical => (cross1, main, cross2, main),
             }
-        };
-        // The sequence for whole integers 1..=10 is 2, 3, 4, 5, 6, 7, 8, 8, 8, 8
-        // This is probably an artefact of rounding in a small coordinate space
-        // and using some modified Bresenham algorithm. The only care here is
-        // approximating the correct visual appearance.
-        let width = (line.width + 1.0).clamp(1.0, 8.0);
-        group = group.append(make_line(x1, y1, x2, y2, color, width));
-    }
-    Ok(group.build())
+        }
+        LineDataInstr::Parallel { at, from, till } => {
+            let cross = at.into_abs(r.plot_area.cross_len());
+            let main1 = r.plot_area.main_start()
+                + from.map_or(Ok(0.0), |from| r.period.norm(from, reverse))?
+                    * r.plot_area.main_len();
+            let main2 = r.plot_area.main_start()
+                + till.map_or(Ok(1.0), |till| r.period.norm(till, reverse))?
+                    * r.plot_area.main_len();
+
+            match r.plot_area.orientation {
+                Orientation::Horizontal => {
+                    let y = r.image_dims.height() - cross;
+                    (main1, y, main2, y)
+                }
+                Orientation::Vertical => {
+                    let x = r.plot_area.cross_start() + cross;
+                    (x, main1, x, main2)
+                }
+            }
+        }
+    };
+    // The sequence for whole integers 1..=10 is 2, 3, 4, 5, 6, 7, 8, 8, 8, 8
+    // This is probably an artefact of rounding in a small coordinate space
+    // and using some modified Bresenham algorithm. The only care here is
+    // approximating the correct visual appearance.
+    let width = (line.width + 1.0).clamp(1.0, 8.0);
+    Ok(make_line(x1, y1, x2, y2, color, width))
 }
 
 /// Draws all time series segments.
@@ -599,14 +668,15 @@ fn draw_plots(r: &mut Renderer<'_>) -> Result<Element> {
     let reverse = r.time_axis.reverse_order();
 
     for plot in &r.timeline.bar_layer {
+        let at = |err: Error| err.at(plot.offset);
         let (start, end) = match plot.at {
             PlotDataPos::At(time) => {
-                let at = r.period.norm(time, reverse)? * r.plot_area.main_len();
+                let at = r.period.norm(time, reverse).map_err(at)? * r.plot_area.main_len();
                 (at, at)
             }
             PlotDataPos::Range(from, till) => {
-                let start = r.period.norm(from, reverse)? * r.plot_area.main_len();
-                let end = r.period.norm(till, reverse)? * r.plot_area.main_len();
+                let start = r.period.norm(from, reverse).map_err(at)? * r.plot_area.main_len();
+                let end = r.period.norm(till, reverse).map_err(at)? * r.plot_area.main_len();
                 (start.min(end), end.max(start))
             }
         };
@@ -614,19 +684,19 @@ fn draw_plots(r: &mut Renderer<'_>) -> Result<Element> {
         let width = plot.pen.width.into_abs(r.image_dims.cross_axis());
 
         if matches!(plot.at, PlotDataPos::Range(..)) {
-            let color = r.color(plot.pen.color)?;
+            let color = r.color(plot.pen.color).map_err(at)?;
             let rect = r.bar_dims(plot.index, start, end - start, width);
             group.append_child(make_rect(rect, Some(color)));
         }
 
         if let Some(mark) = plot.pen.mark {
-            let color = r.color(mark)?;
+            let color = r.color(mark).map_err(at)?;
             let rect = r.bar_dims(plot.index, if reverse { start } else { end }, 1.0, width);
             marks.push(make_rect(rect, Some(color)));
         }
 
         if let Some(text) = &plot.text {
-            let color = r.color(plot.pen.text_color)?;
+            let color = r.color(plot.pen.text_color).map_err(at)?;
             let main = r.plot_area.main_start()
                 + match plot.pen.anchor {
                     Alignment::Start => start,
@@ -695,7 +765,11 @@ fn draw_scale(r: &mut Renderer<'_>, color: ColorValue, is_major: bool) -> Result
     let px_per_interval = sign * interval * r.plot_area.main_len() / units;
     let mut main =
         r.period.norm(start, reverse)? * r.plot_area.main_len() + r.plot_area.main_start();
-    let cross = if is_major { 5.0 } else { 2.5 };
+    let cross = if is_major {
+        MAJOR_TICK_LEN
+    } else {
+        MINOR_TICK_LEN
+    };
 
     let (baseline, alignment) = r.label_align(true);
     let mut labels = Element::builder("g", NS_SVG)
@@ -782,6 +856,23 @@ fn make_rect(rect: Rect, color: Option<ColorValue>) -> Element {
     .build()
 }
 
+/// Calculates the width of the widest line of the given text.
+fn text_width(text: &[TextSpan<'_>], font_size: FontSize) -> f64 {
+    let text = text
+        .iter()
+        .map(|span| match span {
+            TextSpan::ExternalLink { text, .. }
+            | TextSpan::Link { text, .. }
+            | TextSpan::Text(text) => *text,
+        })
+        .collect::<String>()
+        .replace(['_', '^'], " ");
+
+    text.split('~')
+        .map(|line| font::shape_text(line, false, false).width(font_size.value()))
+        .fold(0.0, f64::max)
+}
+
 /// Text properties.
 struct MakeText<'input> {
     /// The text alignment relative to the text origin.
@@ -811,8 +902,7 @@ fn make_text(mt: &MakeText<'_>) -> Result<Element> {
     let mut group = Element::builder("g", NS_SVG);
     let mut x = mt.x;
     let mut y = mt.y;
-    let mut align = mt.alignment;
-    let mut line = next_line(x, y, mt.font_size, mt.color, align);
+    let mut line = TextLine::new(x, y, mt, mt.alignment);
     let mut tabs = mt.tabs.iter();
     let line_height = mt.line_height.unwrap_or_else(|| mt.font_size.line_height());
     for span in mt.text {
@@ -821,10 +911,9 @@ fn make_text(mt: &MakeText<'_>) -> Result<Element> {
             if index != 0 {
                 tabs = mt.tabs.iter();
                 x = mt.x;
-                align = mt.alignment;
                 y += line_height;
-                group = group.append(line.build());
-                line = next_line(x, y, mt.font_size, mt.color, align);
+                group = group.append(line.finish());
+                line = TextLine::new(x, y, mt, mt.alignment);
             }
 
             for (index, text_span) in line_text.split('^').enumerate() {
@@ -832,30 +921,17 @@ fn make_text(mt: &MakeText<'_>) -> Result<Element> {
                     && let Some(tab) = tabs.next()
                 {
                     x = mt.x + tab.displacement;
-                    align = tab.alignment;
-                    group = group.append(line.build());
-                    line = next_line(x, y, mt.font_size, mt.color, align);
+                    group = group.append(line.finish());
+                    line = TextLine::new(x, y, mt, tab.alignment);
                 }
 
-                let text_span = text_span.replace('_', " ");
-
-                if let Some(target) = &target {
-                    line = line.append(
-                        Element::builder("a", NS_SVG)
-                            .attr(n!("href"), target)
-                            .append(text_span)
-                            .build(),
-                    );
-                } else {
-                    line = line.append(text_span);
-                }
+                line.push(text_span.replace('_', " "), target.as_deref());
             }
         }
     }
 
-    let line = line.build();
-    if line.nodes().next().is_some() {
-        group = group.append(line);
+    if !line.is_empty {
+        group = group.append(line.finish());
     }
 
     let mut group = group.build();
@@ -866,20 +942,71 @@ fn make_text(mt: &MakeText<'_>) -> Result<Element> {
     })
 }
 
-/// Creates a new line of text with the given properties.
-fn next_line(
-    x: f64,
-    y: f64,
-    font_size: FontSize,
-    color: ColorValue,
+/// A line of text being built by [`make_text`].
+struct TextLine {
+    /// The text alignment relative to the text origin.
     align: Alignment,
-) -> ElementBuilder {
-    Element::builder("text", NS_SVG)
-        .attr(n!("x"), x.v())
-        .attr(n!("y"), y.v())
-        .attr(n!("font-size"), font_size.value().v())
-        .attr(n!("fill"), color.v())
-        .attr(n!("text-anchor"), align.to_svg())
+    /// The line element.
+    element: ElementBuilder,
+    /// Whether nothing has been appended to the line.
+    is_empty: bool,
+    /// The plain text of the line, for detecting its direction.
+    text: String,
+}
+
+impl TextLine {
+    /// Creates a new line of text with the given properties.
+    fn new(x: f64, y: f64, mt: &MakeText<'_>, align: Alignment) -> Self {
+        Self {
+            align,
+            element: Element::builder("text", NS_SVG)
+                .attr(n!("x"), x.v())
+                .attr(n!("y"), y.v())
+                .attr(n!("font-size"), mt.font_size.value().v())
+                .attr(n!("fill"), mt.color.v())
+                .attr(n!("text-anchor"), align.to_svg()),
+            is_empty: true,
+            text: String::new(),
+        }
+    }
+
+    /// Appends some text to the line, linked to `target` if given.
+    fn push(&mut self, text: String, target: Option<&str>) {
+        self.text += &text;
+        self.is_empty = false;
+        let element = core::mem::replace(&mut self.element, Element::builder("text", NS_SVG));
+        self.element = if let Some(target) = target {
+            element.append(
+                Element::builder("a", NS_SVG)
+                    .attr(n!("href"), target)
+                    .append(text)
+                    .build(),
+            )
+        } else {
+            element.append(text)
+        };
+    }
+
+    /// Finishes the line.
+    ///
+    /// Right-to-left text gets an explicit direction so that the browser
+    /// orders mixed-direction text correctly, which also flips the meaning of
+    /// the text anchor, so the anchor is flipped too.
+    fn finish(self) -> Element {
+        if font::is_rtl(&self.text) {
+            let align = match self.align {
+                Alignment::Start => Alignment::End,
+                Alignment::Middle => Alignment::Middle,
+                Alignment::End => Alignment::Start,
+            };
+            self.element
+                .attr(n!("direction"), "rtl")
+                .attr(n!("text-anchor"), align.to_svg())
+                .build()
+        } else {
+            self.element.build()
+        }
+    }
 }
 
 impl ValueDisplay for ColorValue {
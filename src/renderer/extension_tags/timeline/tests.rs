@@ -26,15 +26,72 @@ fn run_test(test_name: &str, input: &str) {
 }
 
 run_tests! {
+    bar_links,
     basic,
+    define,
     eras,
     font_sizes,
     fractional_major_scale,
     history_of_computing,
+    image_size_auto,
+    legend_horizontal,
+    line_layers,
     mcdonnell_douglas_md_11,
+    plot_shift,
+    scale_minor,
     secretary_of_state_for_defence,
     tabs,
     timeline_of_lighting_technology,
     vertical,
     wikimedia_growth,
 }
+
+#[test]
+fn script_error() {
+    let input = "Define $start = 1900
+Define $end = 2000
+
+ImageSize = width:200 height:60
+PlotArea  = left:10 right:10 top:10 bottom:20
+TimeAxis  = orientation:horizontal
+Period    = from:$start till:$end
+PlotData  =
+  bar:A from:$start till:$end color:nosuchcolor
+";
+    let err = render(input, &Uri::from_static("http://example.com")).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Timeline generation failed: 1 error found
+Line 9: bar:A from:$start till:$end color:nosuchcolor
+
+- Unknown color 'nosuchcolor'.
+  Specify command 'Colors' before this command."
+    );
+}
+
+#[test]
+fn script_parse_error_after_define() {
+    // The error is right after a variable which is longer than its value, so
+    // its column is only right if the variable’s length change is applied
+    let input = "Define $start = 1900
+Define $end = 2000
+
+ImageSize = width:200 height:60
+PlotArea  = left:10 right:10 top:10 bottom:20
+TimeAxis  = orientation:horizontal
+Period    = from:$start till:$end
+PlotData  =
+  bar:A till:$end from:$start%
+";
+    let err = render(input, &Uri::from_static("http://example.com")).unwrap_err();
+    let err = err.to_string();
+    assert!(
+        err.starts_with(
+            "Timeline generation failed: 1 error found
+Line 9: bar:A till:$end from:$start%
+
+- Invalid syntax at column 30: "
+        ),
+        "{err}"
+    );
+}
@@ -3,7 +3,13 @@
 use super::{Result, text_run};
 use crate::{
     common::anchor_encode,
-    wikitext::{HeadingLevel, Span, Spanned, Token, helpers::TextContent, visit::Visitor as _},
+    db::Database,
+    title::{Namespace, Title},
+    wikitext::{
+        HeadingLevel, Parser, Span, Spanned, Token,
+        helpers::{BehaviorSwitches, TextContent},
+        visit::Visitor as _,
+    },
 };
 use core::fmt;
 use std::collections::{BTreeSet, HashMap};
@@ -19,27 +25,53 @@ impl Categories {
     }
 
     /// Emits the categories as an HTML list of links, consuming this object.
+    /// Categories for which `is_hidden` returns true are emitted separately in
+    /// a collapsed list.
     pub fn finish<W: fmt::Write + ?Sized>(
         self,
         f: &mut W,
         base_path: &str,
+        is_hidden: impl Fn(&str) -> bool,
     ) -> Result<(), fmt::Error> {
-        if !self.0.is_empty() {
-            f.write_str(r#"<ul class="wiki-rs-categories">"#)?;
-            for category in self.0 {
-                let target = category.trim_start_matches(':');
-                let name = target.trim_start_matches("Category:");
-                write!(
-                    f,
-                    r#"<li><a href="{base_path}/article/{target}">{name}</a></li>"#,
-                )?;
-            }
-            f.write_str("</ul>")?;
+        let (hidden, visible) = self
+            .0
+            .into_iter()
+            .partition::<Vec<_>, _>(|category| is_hidden(category));
+
+        if !visible.is_empty() {
+            write_categories(f, base_path, &visible)?;
+        }
+
+        if !hidden.is_empty() {
+            f.write_str(
+                r#"<details class="wiki-rs-hidden-categories"><summary>Hidden categories</summary>"#,
+            )?;
+            write_categories(f, base_path, &hidden)?;
+            f.write_str("</details>")?;
         }
+
         Ok(())
     }
 }
 
+/// Emits a list of categories as an HTML list of links.
+fn write_categories<W: fmt::Write + ?Sized>(
+    f: &mut W,
+    base_path: &str,
+    categories: &[String],
+) -> Result<(), fmt::Error> {
+    f.write_str(r#"<ul class="wiki-rs-categories">"#)?;
+    for category in categories {
+        let target = category.trim_start_matches(':');
+        let name = target.trim_start_matches("Category:");
+        write!(
+            f,
+            r#"<li><a href="{base_path}/article/{target}">{name}</a></li>"#,
+        )?;
+    }
+    f.write_str("</ul>")
+}
+
 /// A collection of indicator badges.
 #[derive(Debug, Default)]
 pub(crate) struct Indicators(HashMap<String, String>);
@@ -67,9 +99,18 @@ impl core::fmt::Display for Indicators {
 
 /// An article outline (table of contents).
 #[derive(Debug, Default)]
-pub(crate) struct Outline(Vec<(HeadingLevel, String)>);
+pub(crate) struct Outline {
+    /// The headings of the article.
+    entries: Vec<(HeadingLevel, String)>,
+    /// If true, the outline was suppressed by behavior switches.
+    hidden: bool,
+}
 
 impl Outline {
+    /// The placeholder which is replaced by the outline when it is placed
+    /// inside the article content using `__TOC__`.
+    pub(super) const PLACEHOLDER: &str = "<mw:tocplace></mw:tocplace>";
+
     /// Push a new entry to the outline at the given heading level.
     pub(super) fn push(
         &mut self,
@@ -88,20 +129,43 @@ impl Outline {
         };
 
         let id = name.clone();
-        self.0.push((level, name));
+        self.entries.push((level, name));
         Ok(id)
     }
+
+    /// Applies the table of contents behavior switches of an article to the
+    /// outline, then replaces the placeholder in the article content with the
+    /// outline, if it was placed there.
+    pub(super) fn finish(&mut self, content: &mut String, properties: &PageProperties) {
+        // The TOC is shown when there are at least 4 headings, unless it is
+        // suppressed by `__NOTOC__`. `__FORCETOC__` and `__TOC__` show it with
+        // fewer headings, and `__TOC__` also overrides `__NOTOC__`.
+        let toc = properties.contains("toc");
+        self.hidden = (!toc && properties.contains("notoc"))
+            || (self.entries.len() < 4 && !toc && !properties.contains("forcetoc"));
+
+        if let Some(start) = content.find(Self::PLACEHOLDER) {
+            let toc = if self.hidden || self.entries.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    r#"<div id="toc" class="toc" role="navigation"><div class="toctitle"><h2>Contents</h2></div>{self}</div>"#
+                )
+            };
+            content.replace_range(start..start + Self::PLACEHOLDER.len(), &toc);
+        }
+    }
 }
 
 impl core::fmt::Display for Outline {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        if self.0.is_empty() {
+        if self.hidden || self.entries.is_empty() {
             return Ok(());
         }
 
         write!(f, r##"<ul><li><a href="#">(Top)</a></li>"##)?;
         let mut current = 2;
-        for (level, name) in &self.0 {
+        for (level, name) in &self.entries {
             while current > u8::from(*level) {
                 write!(f, "</ul>")?;
                 current -= 1;
@@ -124,3 +188,67 @@ impl core::fmt::Display for Outline {
         Ok(())
     }
 }
+
+/// The behavior switches used by an article, which MediaWiki records as page
+/// properties.
+#[derive(Debug, Default)]
+pub(crate) struct PageProperties(BTreeSet<String>);
+
+impl PageProperties {
+    /// Reads the page properties of an article from the database without
+    /// rendering it.
+    ///
+    /// Only switches which are written in the article itself, or directly in
+    /// one of the templates that it calls, are found, since anything else
+    /// requires a full expansion to discover.
+    pub fn from_article(db: &Database<'static>, parser: &Parser<'_>, title: &Title) -> Self {
+        let mut properties = Self::default();
+        let Ok(article) = db.get(title) else {
+            return properties;
+        };
+
+        let templates = properties.extend_from_source(parser, &article.body, false);
+        for target in templates {
+            let title = Title::new(&target, Namespace::find_by_id(Namespace::TEMPLATE));
+            if let Ok(template) = db.get(&title) {
+                properties.extend_from_source(parser, &template.body, true);
+            }
+        }
+
+        properties
+    }
+
+    /// Returns true if the article has the given property.
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains(name)
+    }
+
+    /// Adds a property to the set. Returns true if the property was not
+    /// already set.
+    pub(super) fn insert(&mut self, name: &str) -> bool {
+        !self.0.contains(name) && self.0.insert(name.to_owned())
+    }
+
+    /// Returns an iterator over the names of the properties.
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+
+    /// Adds the switches which are written directly in some Wikitext to the
+    /// set, returning the names of the templates which it calls directly.
+    fn extend_from_source(
+        &mut self,
+        parser: &Parser<'_>,
+        source: &str,
+        including: bool,
+    ) -> Vec<String> {
+        let Ok(root) = parser.parse(source, including) else {
+            return Vec::new();
+        };
+        let mut collector = BehaviorSwitches::new(source);
+        let Ok(()) = collector.visit_output(&root);
+        let (switches, templates) = collector.finish();
+        self.0.extend(switches.into_iter().map(str::to_owned));
+        templates.into_iter().map(str::to_owned).collect()
+    }
+}
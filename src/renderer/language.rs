@@ -0,0 +1,328 @@
+//! Language metadata for the language parser functions.
+//!
+//! MediaWiki ships names for hundreds of languages from CLDR. This is a much
+//! smaller table covering the languages which show up most often in
+//! `{{#language}}` and `{{#dir}}` calls; anything else falls back to the
+//! normalised language code, which is what MediaWiki does too when it has no
+//! name for a language.
+
+use std::borrow::Cow;
+
+/// Text direction.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum Direction {
+    /// Left-to-right.
+    Ltr,
+    /// Right-to-left.
+    Rtl,
+}
+
+impl Direction {
+    /// The value of the HTML `dir` attribute for this direction.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Ltr => "ltr",
+            Self::Rtl => "rtl",
+        }
+    }
+}
+
+/// The wiki content language code.
+pub(super) const CONTENT_LANGUAGE: &str = "en";
+
+/// Internal codes which are not valid BCP 47 codes, and their replacements.
+static NON_STANDARD_CODES: phf::Map<&str, &str> = phf::phf_map! {
+    "als" => "gsw",
+    "bat-smg" => "sgs",
+    "be-x-old" => "be-tarask",
+    "cbk-zam" => "cbk",
+    "de-formal" => "de-x-formal",
+    "eml" => "egl",
+    "en-rtl" => "en-x-rtl",
+    "es-formal" => "es-x-formal",
+    "fiu-vro" => "vro",
+    "hu-formal" => "hu-x-formal",
+    "map-bms" => "jv-x-bms",
+    "mo" => "ro-Cyrl-MD",
+    "nl-informal" => "nl-x-informal",
+    "nrm" => "nrf",
+    "roa-rup" => "rup",
+    "roa-tara" => "nap-x-tara",
+    "simple" => "en-simple",
+    "sr-ec" => "sr-Cyrl",
+    "sr-el" => "sr-Latn",
+    "zh-classical" => "lzh",
+    "zh-min-nan" => "nan",
+    "zh-yue" => "yue",
+};
+
+/// Languages written right-to-left.
+static RTL_LANGUAGES: phf::Set<&str> = phf::phf_set! {
+    "aeb-arab", "ar", "arc", "arq", "ary", "arz", "azb", "bcc", "bgn", "bqi",
+    "ckb", "dv", "fa", "glk", "he", "khw", "kk-arab", "kk-cn", "ks-arab",
+    "ku-arab", "lki", "lrc", "luz", "mzn", "nqo", "ota", "pnb", "ps", "sd",
+    "sdh", "skr-arab", "syc", "ug-arab", "ur", "yi",
+};
+
+/// Known languages, as `code => (autonym, English name)`.
+static LANGUAGES: phf::Map<&str, (&str, &str)> = phf::phf_map! {
+    "af" => ("Afrikaans", "Afrikaans"),
+    "als" => ("Alemannisch", "Swiss German"),
+    "am" => ("አማርኛ", "Amharic"),
+    "ar" => ("العربية", "Arabic"),
+    "arz" => ("مصرى", "Egyptian Arabic"),
+    "az" => ("azərbaycanca", "Azerbaijani"),
+    "be" => ("беларуская", "Belarusian"),
+    "be-tarask" => ("беларуская (тарашкевіца)", "Belarusian (Taraškievica orthography)"),
+    "bg" => ("български", "Bulgarian"),
+    "bn" => ("বাংলা", "Bangla"),
+    "bs" => ("bosanski", "Bosnian"),
+    "ca" => ("català", "Catalan"),
+    "ckb" => ("کوردی", "Central Kurdish"),
+    "cs" => ("čeština", "Czech"),
+    "cy" => ("Cymraeg", "Welsh"),
+    "da" => ("dansk", "Danish"),
+    "de" => ("Deutsch", "German"),
+    "de-formal" => ("Deutsch (Sie-Form)", "German (formal address)"),
+    "el" => ("Ελληνικά", "Greek"),
+    "eml" => ("emiliàn e rumagnòl", "Emiliano-Romagnolo"),
+    "en" => ("English", "English"),
+    "eo" => ("Esperanto", "Esperanto"),
+    "es" => ("español", "Spanish"),
+    "et" => ("eesti", "Estonian"),
+    "eu" => ("euskara", "Basque"),
+    "fa" => ("فارسی", "Persian"),
+    "fi" => ("suomi", "Finnish"),
+    "fiu-vro" => ("võro", "Võro"),
+    "fr" => ("français", "French"),
+    "ga" => ("Gaeilge", "Irish"),
+    "gl" => ("galego", "Galician"),
+    "gsw" => ("Alemannisch", "Swiss German"),
+    "gu" => ("ગુજરાતી", "Gujarati"),
+    "he" => ("עברית", "Hebrew"),
+    "hi" => ("हिन्दी", "Hindi"),
+    "hr" => ("hrvatski", "Croatian"),
+    "hu" => ("magyar", "Hungarian"),
+    "hy" => ("հայերեն", "Armenian"),
+    "id" => ("Bahasa Indonesia", "Indonesian"),
+    "is" => ("íslenska", "Icelandic"),
+    "it" => ("italiano", "Italian"),
+    "ja" => ("日本語", "Japanese"),
+    "ka" => ("ქართული", "Georgian"),
+    "kk" => ("қазақша", "Kazakh"),
+    "km" => ("ភាសាខ្មែរ", "Khmer"),
+    "kn" => ("ಕನ್ನಡ", "Kannada"),
+    "ko" => ("한국어", "Korean"),
+    "la" => ("Latina", "Latin"),
+    "lt" => ("lietuvių", "Lithuanian"),
+    "lv" => ("latviešu", "Latvian"),
+    "lzh" => ("文言", "Literary Chinese"),
+    "mk" => ("македонски", "Macedonian"),
+    "ml" => ("മലയാളം", "Malayalam"),
+    "mn" => ("монгол", "Mongolian"),
+    "mr" => ("मराठी", "Marathi"),
+    "ms" => ("Bahasa Melayu", "Malay"),
+    "my" => ("မြန်မာဘာသာ", "Burmese"),
+    "nan" => ("閩南語 / Bân-lâm-gí", "Min Nan Chinese"),
+    "ne" => ("नेपाली", "Nepali"),
+    "nl" => ("Nederlands", "Dutch"),
+    "nn" => ("norsk nynorsk", "Norwegian Nynorsk"),
+    "nb" => ("norsk bokmål", "Norwegian Bokmål"),
+    "no" => ("norsk", "Norwegian"),
+    "pa" => ("ਪੰਜਾਬੀ", "Punjabi"),
+    "pl" => ("polski", "Polish"),
+    "ps" => ("پښتو", "Pashto"),
+    "pt" => ("português", "Portuguese"),
+    "pt-br" => ("português do Brasil", "Brazilian Portuguese"),
+    "ro" => ("română", "Romanian"),
+    "roa-rup" => ("armãneashti", "Aromanian"),
+    "ru" => ("русский", "Russian"),
+    "rup" => ("armãneashti", "Aromanian"),
+    "sh" => ("srpskohrvatski / српскохрватски", "Serbo-Croatian"),
+    "si" => ("සිංහල", "Sinhala"),
+    "simple" => ("Simple English", "Simple English"),
+    "sk" => ("slovenčina", "Slovak"),
+    "sl" => ("slovenščina", "Slovenian"),
+    "sq" => ("shqip", "Albanian"),
+    "sr" => ("српски / srpski", "Serbian"),
+    "sr-ec" => ("српски (ћирилица)", "Serbian (Cyrillic script)"),
+    "sr-el" => ("srpski (latinica)", "Serbian (Latin script)"),
+    "sv" => ("svenska", "Swedish"),
+    "sw" => ("Kiswahili", "Swahili"),
+    "ta" => ("தமிழ்", "Tamil"),
+    "te" => ("తెలుగు", "Telugu"),
+    "tg" => ("тоҷикӣ", "Tajik"),
+    "th" => ("ไทย", "Thai"),
+    "tl" => ("Tagalog", "Tagalog"),
+    "tr" => ("Türkçe", "Turkish"),
+    "uk" => ("українська", "Ukrainian"),
+    "ur" => ("اردو", "Urdu"),
+    "uz" => ("oʻzbekcha / ўзбекча", "Uzbek"),
+    "vi" => ("Tiếng Việt", "Vietnamese"),
+    "vro" => ("võro", "Võro"),
+    "yi" => ("ייִדיש", "Yiddish"),
+    "yue" => ("粵語", "Cantonese"),
+    "zh" => ("中文", "Chinese"),
+    "zh-classical" => ("文言", "Literary Chinese"),
+    "zh-hans" => ("中文（简体）", "Simplified Chinese"),
+    "zh-hant" => ("中文（繁體）", "Traditional Chinese"),
+    "zh-min-nan" => ("閩南語 / Bân-lâm-gí", "Min Nan Chinese"),
+    "zh-yue" => ("粵語", "Cantonese"),
+};
+
+/// Converts an internal language code to a BCP 47 language tag.
+///
+/// This is `LanguageCode::bcp47`.
+pub(super) fn to_bcp47(code: &str) -> String {
+    let code = code.to_lowercase();
+    let code = NON_STANDARD_CODES
+        .get(code.as_str())
+        .copied()
+        .unwrap_or(code.as_str());
+    let mut out = String::with_capacity(code.len());
+    let mut prev_is_private = false;
+    for (index, segment) in code.split('-').enumerate() {
+        if index != 0 {
+            out.push('-');
+        }
+        if prev_is_private {
+            out += &segment.to_lowercase();
+        } else if index != 0 && segment.len() == 2 {
+            // ISO 3166 country code
+            out += &segment.to_uppercase();
+        } else if index != 0 && segment.len() == 4 {
+            // ISO 15924 script code
+            let mut chars = segment.chars();
+            if let Some(first) = chars.next() {
+                out.extend(first.to_uppercase());
+                out += &chars.as_str().to_lowercase();
+            }
+        } else {
+            out += &segment.to_lowercase();
+        }
+        prev_is_private = segment.eq_ignore_ascii_case("x");
+    }
+    out
+}
+
+/// Converts a BCP 47 language tag to an internal language code.
+///
+/// This is `LanguageCode::bcp47ToInternal`.
+pub(super) fn from_bcp47(code: &str) -> Cow<'static, str> {
+    let code = code.to_lowercase();
+    NON_STANDARD_CODES
+        .entries()
+        .find_map(|(internal, bcp47)| bcp47.eq_ignore_ascii_case(&code).then_some(*internal))
+        .map_or(Cow::Owned(code), Cow::Borrowed)
+}
+
+/// Returns the text direction of the language with the given internal code.
+pub(super) fn direction(code: &str) -> Direction {
+    if RTL_LANGUAGES.contains(code.to_lowercase().as_str()) {
+        Direction::Rtl
+    } else {
+        Direction::Ltr
+    }
+}
+
+/// Returns the name of the language with the given internal `code`, as written
+/// in the language with the internal code `in_language`, or in the language
+/// itself if `in_language` is `None`.
+///
+/// Only English names are known, so any other language gets the autonym.
+pub(super) fn name(code: &str, in_language: Option<&str>) -> Option<&'static str> {
+    let (autonym, english) = LANGUAGES.get(code.to_lowercase().as_str())?;
+    let in_english = in_language.is_some_and(|in_language| {
+        let in_language = in_language.to_lowercase();
+        in_language == "simple"
+            || in_language
+                .split('-')
+                .next()
+                .is_some_and(|primary| primary == CONTENT_LANGUAGE)
+    });
+    Some(if in_english { *english } else { *autonym })
+}
+
+/// Returns the direction of the first strongly directional character in
+/// `text`, if there is one.
+///
+/// This approximates the Unicode bidi classes using the blocks of the
+/// right-to-left scripts.
+pub(super) fn strong_direction(text: &str) -> Option<Direction> {
+    text.chars().find_map(|c| {
+        if matches!(
+            u32::from(c),
+            0x0590..=0x08ff | 0xfb1d..=0xfdff | 0xfe70..=0xfeff | 0x10800..=0x10fff | 0x1e800..=0x1efff
+        ) {
+            Some(Direction::Rtl)
+        } else if c.is_alphabetic() {
+            Some(Direction::Ltr)
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bcp47() {
+        for (code, expected) in [
+            ("en", "en"),
+            ("sr-ec", "sr-Cyrl"),
+            ("zh-yue", "yue"),
+            ("de-formal", "de-x-formal"),
+            ("simple", "en-simple"),
+            ("eml", "egl"),
+            ("egl", "egl"),
+            ("als", "gsw"),
+            ("fiu-vro", "vro"),
+            ("zh-classical", "lzh"),
+            ("roa-rup", "rup"),
+            ("nrm", "nrf"),
+            ("roa-tara", "nap-x-tara"),
+            ("be-x-old", "be-tarask"),
+            ("EN-gb", "en-GB"),
+            ("sr-LATN-x-FOO", "sr-Latn-x-foo"),
+        ] {
+            assert_eq!(to_bcp47(code), expected, "{code}");
+        }
+    }
+
+    #[test]
+    fn bcp47_internal() {
+        assert_eq!(from_bcp47("de-x-formal"), "de-formal");
+        assert_eq!(from_bcp47("en-simple"), "simple");
+        assert_eq!(from_bcp47("EGL"), "eml");
+        assert_eq!(from_bcp47("fr"), "fr");
+    }
+
+    #[test]
+    fn dir() {
+        assert_eq!(direction("fa"), Direction::Rtl);
+        assert_eq!(direction("en"), Direction::Ltr);
+        assert_eq!(direction("tg"), Direction::Ltr);
+        assert_eq!(direction(&from_bcp47("egl")), Direction::Ltr);
+        assert_eq!(direction("<invalid>"), Direction::Ltr);
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(name("ar", None), Some("العربية"));
+        assert_eq!(name("aR", Some("En")), Some("Arabic"));
+        assert_eq!(
+            name(&from_bcp47("de-x-formal"), Some(&from_bcp47("en-simple"))),
+            Some("German (formal address)")
+        );
+        assert_eq!(name("qqq", None), None);
+    }
+
+    #[test]
+    fn strong() {
+        assert_eq!(strong_direction("123 abc"), Some(Direction::Ltr));
+        assert_eq!(strong_direction("123 עברית abc"), Some(Direction::Rtl));
+        assert_eq!(strong_direction("123 ..."), None);
+    }
+}
@@ -0,0 +1,416 @@
+//! Labelled section transclusion parser functions.
+//! <https://www.mediawiki.org/wiki/Special:MyLanguage/Extension:Labeled_Section_Transclusion>
+//!
+//! There are two flavours of section here. Labelled sections are delimited by
+//! `<section begin="name" />` and `<section end="name" />` tags, and are used
+//! by `#lst` and `#lstx`. Those are found by walking the top-level tokens of
+//! the transcluded page, after inclusion control has been applied, since this
+//! is the same thing that the preprocessor DOM walk in MediaWiki does. Heading
+//! sections are delimited by headings, and are used by `#lsth`. Those are found
+//! using the raw text of the page, since that is also what MediaWiki does.
+
+use super::{
+    Result, State, Surrogate as _,
+    expand_templates::{ExpandMode, ExpandTemplates, should_adopt},
+    resolve_redirects,
+    stack::{IndexedArgs, StackFrame},
+};
+use crate::{
+    db::Article,
+    title::Title,
+    wikitext::{Argument, FileMap, InclusionMode, Output, Spanned, Token},
+};
+use core::{fmt::Write as _, ops::Range};
+use std::sync::Arc;
+
+/// `{{#lst: title | section [| end section] }}`
+pub(super) fn transclude_section(
+    out: &mut String,
+    state: &mut State<'_>,
+    arguments: &IndexedArgs<'_, '_, '_>,
+) -> Result {
+    let Some(article) = load(out, state, arguments)? else {
+        return Ok(());
+    };
+    let Some(begin) = eval_trimmed(state, arguments, 1)? else {
+        return Ok(());
+    };
+    let end = eval_trimmed(state, arguments, 2)?.unwrap_or_else(|| begin.clone());
+
+    let root = parse(state, &article)?;
+    let sp = arguments.sp.chain(
+        Title::new(&article.title, None),
+        FileMap::new(&article.body),
+        &[],
+    )?;
+    for (inside, tokens) in split_sections(&article.body, &root, &begin, &end) {
+        if inside {
+            expand(out, state, &sp, tokens)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// `{{#lstx: title | section [| replacement text [| end section]] }}`
+pub(super) fn transclude_except(
+    out: &mut String,
+    state: &mut State<'_>,
+    arguments: &IndexedArgs<'_, '_, '_>,
+) -> Result {
+    let Some(article) = load(out, state, arguments)? else {
+        return Ok(());
+    };
+    let begin = eval_trimmed(state, arguments, 1)?.unwrap_or_default();
+    let replacement = arguments.eval(state, 2)?.unwrap_or_default().into_owned();
+    let end = eval_trimmed(state, arguments, 3)?.unwrap_or_else(|| begin.clone());
+
+    let root = parse(state, &article)?;
+    let sp = arguments.sp.chain(
+        Title::new(&article.title, None),
+        FileMap::new(&article.body),
+        &[],
+    )?;
+    for (inside, tokens) in split_sections(&article.body, &root, &begin, &end) {
+        if inside {
+            out.push_str(&replacement);
+        } else {
+            expand(out, state, &sp, tokens)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// `{{#lsth: title [| heading [| end heading]] }}`
+pub(super) fn transclude_heading(
+    out: &mut String,
+    state: &mut State<'_>,
+    arguments: &IndexedArgs<'_, '_, '_>,
+) -> Result {
+    let Some(article) = load(out, state, arguments)? else {
+        return Ok(());
+    };
+    let begin = eval_trimmed(state, arguments, 1)?.unwrap_or_default();
+    let end = eval_trimmed(state, arguments, 2)?.unwrap_or_default();
+
+    let Some(range) = heading_range(&article.body, &begin, &end) else {
+        return Ok(());
+    };
+
+    // The extracted text is preprocessed on its own, so any inclusion control
+    // tags which are cut in half by the extraction are the problem of whoever
+    // wrote the page
+    let text = &article.body[range];
+    let root = state.statics.parser.parse(text, true)?;
+    let sp = arguments
+        .sp
+        .chain(Title::new(&article.title, None), FileMap::new(text), &[])?;
+    expand(out, state, &sp, root.root)
+}
+
+/// Loads the page named by the first argument. If the page does not exist, a
+/// link to the page is written to `out` instead, which is a red link by
+/// definition.
+fn load(
+    out: &mut String,
+    state: &mut State<'_>,
+    arguments: &IndexedArgs<'_, '_, '_>,
+) -> Result<Option<Arc<Article>>> {
+    let Some(name) = eval_trimmed(state, arguments, 0)? else {
+        return Ok(None);
+    };
+
+    if !Title::is_valid(&name) {
+        return Ok(None);
+    }
+
+    let title = Title::new(&name, None);
+    let Ok(article) = state.statics.db.get(&title) else {
+        log::warn!("No page found for '{title}'");
+        write!(out, "[[{title}]]")?;
+        return Ok(None);
+    };
+
+    Ok(resolve_redirects(&state.statics.db, article).ok())
+}
+
+/// Parses an article in include mode, using the template cache since that is
+/// the mode that templates are parsed in too.
+fn parse(state: &mut State<'_>, article: &Article) -> Result<Arc<Output>> {
+    // For now, just assume that the cache will always be big enough and unwrap
+    Ok(Arc::clone(
+        state
+            .statics
+            .template_cache
+            .write()?
+            .get_or_insert_fallible(article.id, || {
+                state
+                    .statics
+                    .parser
+                    .parse(&article.body, true)
+                    .map(Arc::new)
+            })?
+            .unwrap(),
+    ))
+}
+
+/// Evaluates an argument, returning its trimmed value, or `None` if the
+/// argument does not exist or is empty.
+fn eval_trimmed(
+    state: &mut State<'_>,
+    arguments: &IndexedArgs<'_, '_, '_>,
+    index: usize,
+) -> Result<Option<String>> {
+    Ok(arguments
+        .eval(state, index)?
+        .map(|value| value.trim_ascii().to_owned())
+        .filter(|value| !value.is_empty()))
+}
+
+/// Expands a list of tokens from a transcluded page.
+fn expand(
+    out: &mut String,
+    state: &mut State<'_>,
+    sp: &StackFrame<'_>,
+    root: Vec<Spanned<Token>>,
+) -> Result {
+    let mut expansion = ExpandTemplates::new(ExpandMode::Include);
+    expansion.adopt_output(
+        state,
+        sp,
+        &Output {
+            has_onlyinclude: false,
+            root,
+        },
+    )?;
+    out.push_str(&expansion.finish());
+    Ok(())
+}
+
+/// Splits the top-level tokens of a page into alternating runs of tokens which
+/// are outside (`false`) and inside (`true`) of labelled sections which start
+/// with `begin` and end with `end`. A page may contain many sections with the
+/// same name, and each one gets its own run.
+///
+/// Inclusion control is applied to the tokens as they are split, and the
+/// `<section>` tags themselves are omitted from the output, since they render
+/// to nothing anyway.
+fn split_sections(
+    source: &str,
+    output: &Output,
+    begin: &str,
+    end: &str,
+) -> Vec<(bool, Vec<Spanned<Token>>)> {
+    let mut inclusion_mode = Vec::new();
+    if output.has_onlyinclude {
+        inclusion_mode.push(InclusionMode::NoInclude);
+    }
+
+    let mut inside = false;
+    let mut runs = vec![(inside, Vec::new())];
+    for token in &output.root {
+        match &token.node {
+            Token::StartInclude(mode) => {
+                inclusion_mode.push(*mode);
+                continue;
+            }
+            Token::EndInclude(mode) => {
+                inclusion_mode.pop_if(|current| *current == *mode);
+                continue;
+            }
+            _ => {}
+        }
+
+        if !should_adopt(token, true, inclusion_mode.last()) {
+            continue;
+        }
+
+        if let Some((tag_begin, tag_end)) = section_tag(source, token) {
+            if !inside && tag_begin.is_some_and(|name| name.eq_ignore_ascii_case(begin)) {
+                inside = true;
+                runs.push((inside, Vec::new()));
+            } else if inside && tag_end.is_some_and(|name| name.eq_ignore_ascii_case(end)) {
+                inside = false;
+                runs.push((inside, Vec::new()));
+            }
+            continue;
+        }
+
+        runs.last_mut().unwrap().1.push(token.clone());
+    }
+
+    runs
+}
+
+/// If `token` is a `<section>` tag, returns the raw values of its `begin` and
+/// `end` attributes.
+fn section_tag<'a>(
+    source: &'a str,
+    token: &Spanned<Token>,
+) -> Option<(Option<&'a str>, Option<&'a str>)> {
+    let Token::Extension {
+        name, attributes, ..
+    } = &token.node
+    else {
+        return None;
+    };
+
+    if !source[name.into_range()].eq_ignore_ascii_case("section") {
+        return None;
+    }
+
+    let (mut begin, mut end) = (None, None);
+    for attribute in attributes {
+        let Some((name, value)) = attribute_text(source, attribute) else {
+            continue;
+        };
+        if name.eq_ignore_ascii_case("begin") {
+            begin = Some(value);
+        } else if name.eq_ignore_ascii_case("end") {
+            end = Some(value);
+        }
+    }
+    Some((begin, end))
+}
+
+/// Returns the raw text of the name and unquoted value of an attribute.
+fn attribute_text<'a>(
+    source: &'a str,
+    attribute: &Spanned<Argument>,
+) -> Option<(&'a str, &'a str)> {
+    let text = |tokens: &[Spanned<Token>]| {
+        let (first, last) = (tokens.first()?, tokens.last()?);
+        source.get(first.span.start..last.span.end)
+    };
+
+    let name = text(attribute.name()?)?.trim_ascii();
+    let value = text(attribute.value()).unwrap_or_default().trim_ascii();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .or_else(|| {
+            value
+                .strip_prefix('\'')
+                .and_then(|value| value.strip_suffix('\''))
+        })
+        .unwrap_or(value)
+        .trim_ascii();
+    Some((name, value))
+}
+
+/// Finds the range of text under the heading `begin`, ending at the next
+/// heading of the same or higher level, or at the heading `end`, if one is
+/// given and exists. If `begin` is empty, the lead section before the first
+/// heading is returned instead.
+///
+/// The returned range starts at the end of the `begin` heading line and ends
+/// before the line terminator preceding the next heading.
+fn heading_range(text: &str, begin: &str, end: &str) -> Option<Range<usize>> {
+    let (start, max_level) = if begin.is_empty() {
+        (0, 6)
+    } else {
+        lines(text).find_map(|(offset, line)| {
+            heading_level(line, Some(begin), 6).map(|level| (offset + line.len(), level))
+        })?
+    };
+
+    let find_end = |name, max_level| {
+        lines(text)
+            .skip_while(|(offset, _)| *offset < start)
+            .find_map(|(offset, line)| {
+                heading_level(line, name, max_level).map(|_| offset.saturating_sub(1))
+            })
+    };
+
+    let end = (!end.is_empty())
+        .then(|| find_end(Some(end), 6))
+        .flatten()
+        .or_else(|| find_end(None, max_level))
+        .unwrap_or(text.len());
+
+    Some(start..end.max(start))
+}
+
+/// Returns an iterator over the lines of `text`, and their offsets, without
+/// line terminators.
+fn lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split_inclusive('\n').scan(0, |offset, line| {
+        let start = *offset;
+        *offset += line.len();
+        Some((start, line.strip_suffix('\n').unwrap_or(line)))
+    })
+}
+
+/// Returns the level of the heading on `line`.
+///
+/// If `name` is given, the heading content must match it case-insensitively,
+/// and the heading marker may be shorter than the run of `=` at the start of
+/// the line, just like it would be with a backtracking regular expression.
+/// Otherwise, any heading at or above `max_level` matches.
+fn heading_level(line: &str, name: Option<&str>, max_level: usize) -> Option<usize> {
+    let run = line.bytes().take_while(|b| *b == b'=').count();
+    let levels = if name.is_some() {
+        1..=run.min(max_level)
+    } else if run <= max_level {
+        run..=run
+    } else {
+        return None;
+    };
+
+    levels.rev().filter(|level| *level != 0).find(|level| {
+        let rest = line[*level..].trim_ascii_end();
+        rest.len() >= *level
+            && rest.ends_with(&"=".repeat(*level))
+            && name.is_none_or(|name| {
+                rest[..rest.len() - level]
+                    .trim_ascii()
+                    .eq_ignore_ascii_case(name)
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = "Lead\n== One ==\nFirst\n=== Sub ===\nNested\n== Two ==\nSecond";
+
+    #[test]
+    fn heading_lead() {
+        let range = heading_range(PAGE, "", "").unwrap();
+        assert_eq!(&PAGE[range], "Lead");
+    }
+
+    #[test]
+    fn heading_section() {
+        let range = heading_range(PAGE, "one", "").unwrap();
+        assert_eq!(&PAGE[range], "\nFirst\n=== Sub ===\nNested");
+
+        let range = heading_range(PAGE, "Sub", "").unwrap();
+        assert_eq!(&PAGE[range], "\nNested");
+
+        let range = heading_range(PAGE, "Two", "").unwrap();
+        assert_eq!(&PAGE[range], "\nSecond");
+    }
+
+    #[test]
+    fn heading_end() {
+        let range = heading_range(PAGE, "One", "Sub").unwrap();
+        assert_eq!(&PAGE[range], "\nFirst");
+    }
+
+    #[test]
+    fn heading_missing() {
+        assert_eq!(heading_range(PAGE, "Three", ""), None);
+    }
+
+    #[test]
+    fn heading_levels() {
+        assert_eq!(heading_level("== A ==", Some("a"), 6), Some(2));
+        assert_eq!(heading_level("=== A ===  ", None, 3), Some(3));
+        assert_eq!(heading_level("=== A ===", None, 2), None);
+        assert_eq!(heading_level("====", None, 6), None);
+        assert_eq!(heading_level("not a heading", None, 6), None);
+    }
+}
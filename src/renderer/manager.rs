@@ -1,9 +1,11 @@
 //! Types and functions for communicating with article renderers.
 
 use super::{
-    Error, ExpandMode, ExpandTemplates, Result, State, Statics, TemplateCache,
+    Error, ExpandMode, ExpandTemplates, HiddenCategoryCache, Result, State, Statics, TemplateCache,
+    Trace,
     document::Document,
-    globals::{Indicators, Outline},
+    extension_tags::{TemplateData, render_tabular},
+    globals::{Indicators, Outline, PageProperties},
     resolve_redirects,
     stack::StackFrame,
     surrogate::Surrogate as _,
@@ -29,10 +31,17 @@ pub(crate) enum Command {
     Article {
         /// The article to render.
         article: Arc<Article>,
+        /// The time to render the article as of, instead of the default base
+        /// time.
+        as_of: Option<DateTime>,
         /// The load mode to use when rendering the article.
         load_mode: LoadMode,
+        /// If true, annotate the output like Parsoid does.
+        parsoid: bool,
         /// If true, follow the article’s redirect before rendering.
         redirect: bool,
+        /// If true, record the template expansion tree.
+        trace: bool,
     },
     /// Render some arbitrary Wikitext.
     Eval {
@@ -67,10 +76,16 @@ pub type Out = Result<RenderOutput, Error>;
 
 /// Manager for renderer connections.
 pub(crate) struct RenderManager {
+    /// The default base time to provide to spawned renderers.
+    base_time: DateTime,
     /// The base URI to provide to spawned renderers.
     base_uri: Uri,
+    /// The shared media repository database to provide to spawned renderers.
+    commons_database: Option<Arc<Database<'static>>>,
     /// The article database to provide to spawned renderers.
     database: Arc<Database<'static>>,
+    /// Hidden category cache.
+    hidden_categories: HiddenCategoryCache,
     /// Time and memory limits.
     limits: Limits,
     /// Template cache.
@@ -78,11 +93,22 @@ pub(crate) struct RenderManager {
 }
 
 impl RenderManager {
-    /// Creates a new render manager.
-    pub fn new(base_uri: &Uri, database: &Arc<Database<'static>>, limits: Limits) -> Self {
+    /// Creates a new render manager. The `base_time` is the current time used
+    /// by renders which do not request a different time. The optional
+    /// `commons_database` is used for pages from the shared media repository.
+    pub fn new(
+        base_uri: &Uri,
+        database: &Arc<Database<'static>>,
+        commons_database: Option<&Arc<Database<'static>>>,
+        base_time: DateTime,
+        limits: Limits,
+    ) -> Self {
         Self {
+            base_time,
             base_uri: base_uri.clone(),
+            commons_database: commons_database.cloned(),
             database: Arc::clone(database),
+            hidden_categories: <_>::default(),
             limits,
             template_cache: Arc::new(RwLock::new(LruMap::new(ByMemoryUsage::new(
                 limits.template_cache,
@@ -100,19 +126,21 @@ impl r2d2::ManageConnection for RenderManager {
         let (tx, rx) = mpsc::channel::<In>();
         let base_uri = self.base_uri.clone();
         let limits = self.limits;
+        let hidden_categories = Arc::clone(&self.hidden_categories);
         let template_cache = Arc::clone(&self.template_cache);
-        // TODO: This date should be calculated from the database file.
         let db = Arc::clone(&self.database);
-        let base_time = db.creation_date().map_or_else(DateTime::now, |date| {
-            DateTime::from_unix_timestamp(date.unix_timestamp())
-        })?;
+        let commons_db = self.commons_database.clone();
+        let base_time = self.base_time;
         let parser = Parser::new(&CONFIG);
         std::thread::spawn(move || {
-            let vm = new_vm(&base_uri, &db, &parser).unwrap();
+            let shared_db = commons_db.as_ref().unwrap_or(&db);
+            let vm = new_vm(&base_uri, &db, shared_db, &parser).unwrap();
             let mut statics = Statics {
                 base_time,
                 base_uri,
+                commons_db,
                 db,
+                hidden_categories,
                 limits,
                 parser,
                 template_cache,
@@ -124,23 +152,32 @@ impl r2d2::ManageConnection for RenderManager {
                 let output = match command {
                     Command::Article {
                         article,
+                        as_of,
                         load_mode,
+                        parsoid,
                         redirect,
-                    } => render_article(&mut statics, &article, load_mode, redirect),
+                        trace,
+                    } => {
+                        set_base_time(&mut statics, as_of.unwrap_or(base_time));
+                        render_article(&mut statics, &article, load_mode, redirect, trace, parsoid)
+                    }
                     Command::Eval {
                         args,
                         code,
                         markers,
                         mode,
                         page_name,
-                    } => render_string(
-                        &mut statics,
-                        &page_name,
-                        &code,
-                        args.as_deref(),
-                        mode,
-                        markers,
-                    ),
+                    } => {
+                        set_base_time(&mut statics, base_time);
+                        render_string(
+                            &mut statics,
+                            &page_name,
+                            &code,
+                            args.as_deref(),
+                            mode,
+                            markers,
+                        )
+                    }
                     Command::Redirect { article } => statics
                         .parser
                         .parse_redirect(&article.body)
@@ -148,7 +185,10 @@ impl r2d2::ManageConnection for RenderManager {
                             content: redirect.to_owned(),
                             indicators: <_>::default(),
                             outline: <_>::default(),
+                            properties: <_>::default(),
                             styles: <_>::default(),
+                            template_data: None,
+                            trace: None,
                         })
                         .map_err(Error::from),
                 };
@@ -178,8 +218,25 @@ pub(crate) struct RenderOutput {
     /// The article outline (table of contents). [`Display`](core::fmt::Display)
     /// formats as HTML.
     pub outline: Outline,
+    /// Page properties set by behavior switches.
+    pub properties: PageProperties,
     /// Extra CSS required for correct article styling.
     pub styles: String,
+    /// The TemplateData of the article, if it has any.
+    pub template_data: Option<TemplateData>,
+    /// The template expansion tree, if one was requested.
+    pub trace: Option<Trace>,
+}
+
+/// Changes the base time used by the next render.
+///
+/// Cached Lua modules are discarded if the time changes, since they may have
+/// captured the old time when they were loaded.
+fn set_base_time(statics: &mut Statics, base_time: DateTime) {
+    if statics.base_time != base_time {
+        statics.base_time = base_time;
+        statics.vm_cache.clear();
+    }
 }
 
 /// Main renderer entrypoint for articles.
@@ -188,6 +245,8 @@ fn render_article(
     article: &Arc<Article>,
     load_mode: LoadMode,
     redirect: bool,
+    trace: bool,
+    parsoid: bool,
 ) -> Result<RenderOutput> {
     let article = Arc::clone(article);
     let article = if redirect {
@@ -196,12 +255,29 @@ fn render_article(
         article
     };
 
+    // Tabular data pages are JSON, not Wikitext, except for their sources
+    if article.model == "Tabular.JsonConfig" {
+        let content = render_tabular(&article.body, |sources| {
+            let sp = StackFrame::new(Title::new(&article.title, None), FileMap::new(sources));
+            Ok(render(statics, load_mode, &sp, false, false)?.content)
+        })?;
+        return Ok(RenderOutput {
+            content,
+            indicators: <_>::default(),
+            outline: <_>::default(),
+            properties: <_>::default(),
+            styles: <_>::default(),
+            template_data: None,
+            trace: None,
+        });
+    }
+
     let sp = StackFrame::new(
         Title::new(&article.title, None),
         FileMap::new(&article.body),
     );
 
-    render(statics, load_mode, &sp)
+    render(statics, load_mode, &sp, trace, parsoid)
 }
 
 /// Main renderer entrypoint for eval.
@@ -232,9 +308,9 @@ fn render_string(
 
     let load_mode = LoadMode::Module;
     match mode {
-        EvalPp::Post => render(statics, load_mode, &sp),
+        EvalPp::Post => render(statics, load_mode, &sp, false, false),
         EvalPp::Pre | EvalPp::PreTree | EvalPp::Tree => {
-            let (state, source) = preprocess(statics, &sp, load_mode)?;
+            let (state, source) = preprocess(statics, &sp, load_mode, false, false)?;
             let mut content = if mode == EvalPp::Pre {
                 source
             } else if mode == EvalPp::PreTree {
@@ -256,15 +332,24 @@ fn render_string(
                 content,
                 indicators: <_>::default(),
                 outline: <_>::default(),
+                properties: <_>::default(),
                 styles: <_>::default(),
+                template_data: None,
+                trace: None,
             })
         }
     }
 }
 
 /// Main renderer entrypoint.
-fn render(statics: &mut Statics, load_mode: LoadMode, sp: &StackFrame<'_>) -> Result<RenderOutput> {
-    let (mut state, source) = preprocess(statics, sp, load_mode)?;
+fn render(
+    statics: &mut Statics,
+    load_mode: LoadMode,
+    sp: &StackFrame<'_>,
+    trace: bool,
+    parsoid: bool,
+) -> Result<RenderOutput> {
+    let (mut state, source) = preprocess(statics, sp, load_mode, trace, parsoid)?;
 
     let sp = sp.clone_with_source(FileMap::new(&source));
     let root = state.statics.parser.parse_no_expansion(&sp.source)?;
@@ -276,6 +361,7 @@ fn render(statics: &mut Statics, load_mode: LoadMode, sp: &StackFrame<'_>) -> Re
     let mut renderer = Document::new(false);
     renderer.adopt_output(&mut state, &sp, &root)?;
     let mut content = renderer.finish()?;
+    core::mem::take(&mut state.globals.references).finish(&mut content)?;
 
     let mut timings = state.timing.into_iter().collect::<Vec<_>>();
     timings.sort_by(|(_, (_, a)), (_, (_, b))| b.cmp(a));
@@ -302,26 +388,40 @@ fn render(statics: &mut Statics, load_mode: LoadMode, sp: &StackFrame<'_>) -> Re
         );
     }
 
+    state
+        .globals
+        .outline
+        .finish(&mut content, &state.globals.properties);
+
+    let statics = &*state.statics;
     state
         .globals
         .categories
-        .finish(&mut content, state.statics.base_uri.path())?;
+        .finish(&mut content, statics.base_uri.path(), |category| {
+            statics.is_hidden_category(category)
+        })?;
 
     Ok(RenderOutput {
         content,
         indicators: state.globals.indicators,
         outline: state.globals.outline,
+        properties: state.globals.properties,
         styles: state.globals.styles.text,
+        template_data: state.globals.template_data,
+        trace: state.trace,
     })
 }
 
 /// Expands all templates for the given root frame, collecting out-of-band
 /// information and returning the incomplete state and the final pre-processed
-/// Wikitext.
+/// Wikitext. If `trace` is true, the expansion tree is also recorded. If
+/// `parsoid` is true, top-level expansions are marked for Parsoid annotation.
 fn preprocess<'a>(
     statics: &'a mut Statics,
     sp: &StackFrame<'_>,
     load_mode: LoadMode,
+    trace: bool,
+    parsoid: bool,
 ) -> Result<(State<'a>, String)> {
     let root = statics.parser.parse(&sp.source, false)?;
 
@@ -330,9 +430,11 @@ fn preprocess<'a>(
     let mut state = State {
         globals: <_>::default(),
         load_mode,
+        parsoid: parsoid.then(<_>::default),
         statics,
         strip_markers: <_>::default(),
         timing: <_>::default(),
+        trace: trace.then(Trace::default),
     };
 
     // TODO: Rewrite the PEG so that it does the expansions instead of
@@ -319,6 +319,11 @@ use crate::{
 use axum::http::Uri;
 use core::{fmt, time::Duration};
 pub(crate) use expand_templates::{ExpandMode, ExpandTemplates};
+pub(crate) use extension_tags::{
+    RenderError, TemplateData, localize_json_data, render_png, set_ext_cache, set_fallback_fonts,
+    set_map_basemap, set_render_cache,
+};
+pub(crate) use globals::PageProperties;
 pub(crate) use manager::{Command, In, RenderManager as Manager, RenderOutput};
 use memchr::memmem;
 pub(crate) use parser_fns::call_parser_fn;
@@ -333,20 +338,26 @@ use std::{
 pub(crate) use surrogate::Surrogate;
 use tags::LinkKind;
 pub(crate) use template::{call_template, resolve_callee};
+pub(crate) use trace::{Trace, TraceKind};
 
+mod date_formatter;
 mod document;
 mod emitters;
 mod expand_templates;
 mod extension_tags;
 mod globals;
 mod image;
+mod language;
 mod lru_limiter;
+mod lst;
 mod manager;
 mod parser_fns;
+mod parsoid;
 mod stack;
 mod surrogate;
 mod tags;
 mod template;
+mod trace;
 mod trim;
 
 /// An article rendering error.
@@ -450,6 +461,10 @@ type ArticleId = u64;
 /// A template cache.
 type TemplateCache = Arc<RwLock<LruMap<ArticleId, Arc<Output>, ByMemoryUsage>>>;
 
+/// A cache of whether each category is a hidden category, keyed by category
+/// name.
+type HiddenCategoryCache = Arc<parking_lot::RwLock<HashMap<String, bool>>>;
+
 /// Global variables which are used for the entire lifetime of a renderer
 /// thread.
 pub(crate) struct Statics {
@@ -457,8 +472,12 @@ pub(crate) struct Statics {
     pub base_time: DateTime,
     /// The server’s base URI.
     pub base_uri: Uri,
+    /// The shared media repository database, for `Data:` pages.
+    pub commons_db: Option<Arc<Database<'static>>>,
     /// The article database.
     pub db: Arc<Database<'static>>,
+    /// Hidden category cache.
+    hidden_categories: HiddenCategoryCache,
     /// Time and memory limits.
     pub limits: Limits,
     /// The parser.
@@ -467,8 +486,35 @@ pub(crate) struct Statics {
     template_cache: TemplateCache,
     /// The Lua interpreter.
     pub vm: Lua,
-    /// VM module cache.
-    pub vm_cache: LruMap<ArticleId, VmCacheEntry, schnellru::UnlimitedCompact>,
+    /// VM module cache, keyed by whether the module came from the shared
+    /// media repository and its ID, since IDs from different databases can
+    /// collide.
+    pub vm_cache: LruMap<(bool, ArticleId), VmCacheEntry, schnellru::UnlimitedCompact>,
+}
+
+impl Statics {
+    /// The database which holds pages from the shared media repository. If no
+    /// shared repository was mounted, this is the article database.
+    pub fn shared_db(&self) -> &Arc<Database<'static>> {
+        self.commons_db.as_ref().unwrap_or(&self.db)
+    }
+
+    /// Returns true if the category with the given name is a hidden category.
+    /// Category pages change only when the database does, so the answer is
+    /// shared by all renderers spawned by the same manager.
+    fn is_hidden_category(&self, category: &str) -> bool {
+        if let Some(hidden) = self.hidden_categories.read().get(category) {
+            return *hidden;
+        }
+
+        let hidden =
+            PageProperties::from_article(&self.db, &self.parser, &Title::new(category, None))
+                .contains("hiddencat");
+        self.hidden_categories
+            .write()
+            .insert(category.to_owned(), hidden);
+        hidden
+    }
 }
 
 /// A list of stripped extension tags.
@@ -619,6 +665,12 @@ pub(crate) enum StripMarker {
     WikiRsSourceStart(String),
     /// A strip marker containing a wiki.rs-specific template source end marker.
     WikiRsSourceEnd(String),
+    /// A strip marker containing the start of a top-level transclusion, for
+    /// Parsoid annotations.
+    TransclusionStart(parsoid::Transclusion),
+    /// A strip marker containing the end of a top-level transclusion with the
+    /// given `about` ID.
+    TransclusionEnd(usize),
 }
 
 impl fmt::Display for StripMarker {
@@ -632,7 +684,10 @@ impl core::ops::Deref for StripMarker {
 
     fn deref(&self) -> &Self::Target {
         match self {
-            StripMarker::WikiRsSourceStart(_) | StripMarker::WikiRsSourceEnd(_) => "",
+            StripMarker::WikiRsSourceStart(_)
+            | StripMarker::WikiRsSourceEnd(_)
+            | StripMarker::TransclusionStart(_)
+            | StripMarker::TransclusionEnd(_) => "",
             StripMarker::Block(s) | StripMarker::Inline(s) | StripMarker::NoWiki(s) => s,
         }
     }
@@ -644,12 +699,16 @@ pub(crate) struct State<'s> {
     pub globals: ArticleState,
     /// The page load strategy.
     pub load_mode: LoadMode,
+    /// Parsoid annotation state, if Parsoid output was requested.
+    parsoid: Option<parsoid::Parsoid>,
     /// Thread static global variables.
     pub statics: &'s mut Statics,
     /// Stripped extension tag substitutions.
     pub strip_markers: StripMarkers,
     /// Page performance timing data.
     timing: HashMap<String, (usize, Duration)>,
+    /// The expansion tree, if tracing was requested.
+    trace: Option<Trace>,
 }
 
 /// A convenience trait alias combining [`fmt::Write`] and [`Surrogate`].
@@ -663,16 +722,26 @@ pub(crate) struct ArticleState {
     categories: globals::Categories,
     /// The last ordinal used by an unlabelled external link.
     external_link_ordinal: u32,
+    /// The last ordinal used by a slideshow gallery.
+    gallery_ordinal: u32,
     /// Indicator icons for the `<indicator>` extension tag.
     indicators: globals::Indicators,
+    /// The last ordinal used by a `<maplink>` popup.
+    map_link_ordinal: u32,
     /// Table of contents.
     outline: globals::Outline,
+    /// Markup added to the render cache by the extension tag being cached.
+    png_sources: extension_tags::PngSources,
+    /// Page properties set by behavior switches.
+    properties: globals::PageProperties,
     /// Collected references for the `<ref>` and `<references>` extension tags.
     references: extension_tags::References,
     /// Labelled section transclusion sections.
     sections: extension_tags::LabelledSections,
     /// Collected CSS for the `<templatestyles>` extension tag.
     styles: extension_tags::Styles,
+    /// The TemplateData from the `<templatedata>` extension tag.
+    template_data: Option<extension_tags::TemplateData>,
     /// Sometimes settable magic variables, e.g. `{{SHORTDESC}}`.
     pub variables: HashMap<String, String>,
 }
@@ -8,27 +8,30 @@
 )]
 
 use super::{
-    Error, Result, State, StripMarkers, extension_tags,
+    Error, Result, State, StripMarker, StripMarkers, date_formatter, extension_tags,
+    language::{self, Direction},
+    lst,
     stack::{IndexedArgs, KeyCacheKvs, Kv, StackFrame},
     template::call_module,
 };
 use crate::{
     common::{
-        CowExt as _, anchor_encode, decode_html, format_date_mediawiki, format_message,
+        CowExt as _, MESSAGES, anchor_encode, decode_html, format_date_mediawiki, format_message,
         format_number, make_url, parse_formatted_number, url_encode,
     },
     config::CONFIG,
     expr,
-    php::{floatval, fuzzy_cmp},
+    php::{DateTime, DateTimeError, DateTimeZone, floatval, fuzzy_cmp, intval, strtr},
     title::{Namespace, Title},
     wikitext::Span,
 };
 use core::{
+    convert::Infallible,
     fmt::{self, Write as _},
-    iter,
     str::FromStr,
 };
 use either::Either;
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC};
 use regex::Regex;
 use std::{borrow::Cow, sync::LazyLock};
 
@@ -230,6 +233,37 @@ mod ext {
 
     use super::*;
 
+    /// `{{#chart: definition [| data = tabular data][| arg:name = value ...] }}`
+    pub fn chart(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        let Some(name) = arguments.eval(state, 0)?.map(trim) else {
+            return Ok(());
+        };
+
+        let mut data = None;
+        let mut args = serde_json::Map::new();
+        for arg in arguments.iter().skip(1) {
+            let Some(key) = arg.name(state, arguments.sp)?.map(trim) else {
+                continue;
+            };
+            let value = arg.value(state, arguments.sp).map(trim)?.into_owned();
+            if key == "data" {
+                data = Some(value);
+            } else if let Some(key) = key.strip_prefix("arg:") {
+                args.insert(key.trim().to_owned(), value.into());
+            }
+        }
+
+        let html = extension_tags::render_chart(state, arguments.sp, &name, data.as_deref(), args)?;
+        state
+            .strip_markers
+            .push(out, "chart", StripMarker::Block(html));
+        Ok(())
+    }
+
     /// `{{#tag: tag_name | content [| attribute [= value] ...] }}`
     pub fn extension_tag(
         out: &mut String,
@@ -273,6 +307,28 @@ mod ext {
         Ok(())
     }
 
+    /// `{{#interwikilink: prefix | title [| link text] }}`
+    pub fn interwiki_link(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        let prefix = arguments.eval(state, 0)?.map(trim).unwrap_or_default();
+        if !prefix.is_empty()
+            && CONFIG
+                .interwiki_map
+                .contains_key(&prefix.to_ascii_lowercase())
+        {
+            let title = arguments.eval(state, 1)?.map(trim).unwrap_or_default();
+            write!(out, "[[:{prefix}:{title}")?;
+            if let Some(text) = arguments.eval(state, 2)?.map(trim) {
+                write!(out, "|{text}")?;
+            }
+            out.write_str("]]")?;
+        }
+        Ok(())
+    }
+
     /// `{{#invoke: module | function [| argument [= value] ...] }}`
     pub fn invoke(
         out: &mut String,
@@ -282,6 +338,19 @@ mod ext {
         call_module(out, state, arguments.sp, &arguments.arguments)
     }
 
+    /// `{{#assessment}}`, `{{#babel}}`, `{{#categorytree}}`,
+    /// `{{#interlanguagelink}}`, `{{#mentor}}`, `{{#related}}`,
+    /// `{{#statements}}`, `{{#target}}`, or `{{NOEXTERNALLANGLINKS}}`
+    pub fn no_output(_: &mut String, _: &mut State<'_>, _: &IndexedArgs<'_, '_, '_>) -> Result {
+        // These either record page metadata which is only ever shown outside
+        // of the article body (interlanguage links, related articles, page
+        // assessments, mass message targets), or render data from some other
+        // database which is not part of a dump (user languages, category
+        // members, Wikidata statements, mentors). Either way, there is nothing
+        // to render.
+        Ok(())
+    }
+
     /// `{{#property: name [| from = Qid] }}`
     pub fn wikibase_property(
         _: &mut String,
@@ -294,6 +363,173 @@ mod ext {
         }
         Ok(())
     }
+
+    /// `{{WBREPONAME}}`
+    pub fn wikibase_repo_name(
+        out: &mut String,
+        _: &mut State<'_>,
+        _: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        out.write_str("Wikidata")?;
+        Ok(())
+    }
+}
+
+mod i18n {
+    //! Localisation functions.
+    //!
+    //! The content language is always English, and there is never a logged-in
+    //! user, so the user language is always the content language too.
+
+    use super::*;
+
+    /// `{{#bcp47[: language code] }}`
+    pub fn bcp47(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        let code = language_code(state, arguments, 0)?;
+        write!(out, "{}", language::to_bcp47(&code))?;
+        Ok(())
+    }
+
+    /// `{{#bidi: text }}`
+    pub fn bidi(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        if let Some(text) = arguments.eval(state, 0)?.map(trim) {
+            // U+202A LEFT-TO-RIGHT EMBEDDING or U+202B RIGHT-TO-LEFT EMBEDDING,
+            // then U+202C POP DIRECTIONAL FORMATTING
+            match language::strong_direction(&text) {
+                Some(Direction::Ltr) => write!(out, "\u{202a}{text}\u{202c}")?,
+                Some(Direction::Rtl) => write!(out, "\u{202b}{text}\u{202c}")?,
+                None => write!(out, "{text}")?,
+            }
+        }
+        Ok(())
+    }
+
+    /// `{{CONTENTLANGUAGE}}` or `{{PAGELANGUAGE}}` or `{{USERLANGUAGE}}`
+    pub fn content_language(
+        out: &mut String,
+        _: &mut State<'_>,
+        _: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        out.write_str(language::CONTENT_LANGUAGE)?;
+        Ok(())
+    }
+
+    /// `{{#dir[: language code [| 'bcp47']] }}`
+    pub fn dir(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        let code = language_code(state, arguments, 0)?;
+        let is_bcp47 = arguments
+            .eval(state, 1)?
+            .map(trim)
+            .is_some_and(|arg| arg.eq_ignore_ascii_case("bcp47"));
+        let code = if is_bcp47 {
+            language::from_bcp47(&code)
+        } else {
+            Cow::Owned(code.to_lowercase())
+        };
+        write!(out, "{}", language::direction(&code).as_str())?;
+        Ok(())
+    }
+
+    /// `{{DIRECTIONMARK}}`
+    pub fn direction_mark(
+        out: &mut String,
+        _: &mut State<'_>,
+        _: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        // U+200E LEFT-TO-RIGHT MARK, since the content language is LTR
+        out.write_char('\u{200e}')?;
+        Ok(())
+    }
+
+    /// `{{#formal: informal form [| formal form] }}`
+    pub fn formal(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        // English does not distinguish formality, so it uses the first form
+        if let Some(form) = arguments.eval(state, 0)?.map(trim) {
+            write!(out, "{form}")?;
+        }
+        Ok(())
+    }
+
+    /// `{{GENDER: user name | masculine | feminine [| neutral] }}`
+    pub fn gender(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        // There are no user preferences, so every gender is unknown, which
+        // uses the neutral form if there is one and the first form otherwise
+        let form = match arguments.eval(state, 3)? {
+            form @ Some(_) => form,
+            None => arguments.eval(state, 1)?,
+        };
+        if let Some(form) = form.map(trim) {
+            write!(out, "{form}")?;
+        }
+        Ok(())
+    }
+
+    /// `{{GRAMMAR: case | word }}`
+    pub fn grammar(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        // English has no grammatical case transformations
+        if let Some(word) = arguments.eval(state, 1)?.map(trim) {
+            write!(out, "{word}")?;
+        }
+        Ok(())
+    }
+
+    /// `{{#language[: language code [| in language code]] }}`
+    pub fn language_name(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        let code = language_code(state, arguments, 0)?;
+        let in_language = arguments
+            .eval(state, 1)?
+            .map(trim)
+            .filter(|code| !code.is_empty());
+        let in_language = in_language.as_deref().map(language::from_bcp47);
+        if let Some(name) = language::name(&language::from_bcp47(&code), in_language.as_deref()) {
+            write!(out, "{name}")?;
+        } else {
+            write!(out, "{}", language::to_bcp47(&code))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the trimmed language code at `index`, or the content language
+    /// code if there is no code.
+    fn language_code<'a>(
+        state: &mut State<'_>,
+        arguments: &'a IndexedArgs<'_, '_, '_>,
+        index: usize,
+    ) -> Result<Cow<'a, str>> {
+        Ok(arguments
+            .eval(state, index)?
+            .map(trim)
+            .filter(|code| !code.is_empty())
+            .unwrap_or(Cow::Borrowed(language::CONTENT_LANGUAGE)))
+    }
 }
 
 mod page {
@@ -311,6 +547,43 @@ mod page {
         Ok(())
     }
 
+    /// `{{#contentmodel[: 'canonical'|'local' [| title]] }}`
+    pub fn content_model(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        let canonical = arguments.eval(state, 0)?.map(trim).as_deref() == Some("canonical");
+        let Some(title) = title_or_current(state, arguments, 1)? else {
+            return Ok(());
+        };
+
+        let model = state.statics.db.get(&title).map_or_else(
+            |_| {
+                Cow::Borrowed(
+                    title
+                        .namespace()
+                        .default_content_model
+                        .unwrap_or("wikitext"),
+                )
+            },
+            |article| Cow::Owned(article.model.clone()),
+        );
+
+        let model = if canonical {
+            model
+        } else {
+            let key = format!("content-model-{}", model.to_lowercase());
+            MESSAGES
+                .get(&key)
+                .and_then(serde_json::Value::as_str)
+                .map_or(model, Cow::Borrowed)
+        };
+
+        write!(out, "{}", escape_wiki_text(&model))?;
+        Ok(())
+    }
+
     /// `{{FULLPAGENAME}}`
     pub fn full_page_name(
         out: &mut String,
@@ -331,6 +604,60 @@ mod page {
         Ok(())
     }
 
+    /// `{{PAGEID[: title] }}`
+    pub fn page_id(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        if let Some(title) = title_or_current(state, arguments, 0)?
+            && let Ok(article) = state.statics.db.get(&title)
+        {
+            write!(out, "{}", article.id)?;
+        }
+        Ok(())
+    }
+
+    /// `{{PROTECTIONLEVEL: action [| title] }}` or
+    /// `{{PENDINGCHANGELEVEL[: title] }}` or `{{CASCADINGSOURCES[: title] }}`
+    /// or `{{REVISIONUSER[: title] }}`
+    pub fn page_metadata(_: &mut String, _: &mut State<'_>, _: &IndexedArgs<'_, '_, '_>) -> Result {
+        // Database dumps contain neither page protection data nor revision
+        // authors, so the answer is always the one for an unprotected page
+        // with an unknown author, which is nothing.
+        Ok(())
+    }
+
+    /// `{{PAGESIZE: title [| R] }}` or `{{REVISIONSIZE}}`
+    #[expect(
+        clippy::cast_precision_loss,
+        reason = "no article is going to be 2**53 bytes long"
+    )]
+    pub fn page_size(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        let (title, no_separators) = if arguments.callee == "revisionsize" {
+            (Some(arguments.sp.root().name.clone()), true)
+        } else {
+            (
+                arguments
+                    .eval(state, 0)?
+                    .map(trim)
+                    .filter(|title| !title.is_empty())
+                    .map(|title| Title::new(&title, None)),
+                arguments.eval(state, 1)?.map(trim).as_deref() == Some("R"),
+            )
+        };
+
+        let size = title
+            .and_then(|title| state.statics.db.get(&title).ok())
+            .map_or(0, |article| article.body.len());
+        write!(out, "{}", format_number(size as f64, no_separators))?;
+        Ok(())
+    }
+
     /// `{{PROTECTIONEXPIRY[: action [| pagename]] }}`
     pub fn protection_expiry(
         out: &mut String,
@@ -449,6 +776,22 @@ mod page {
         }
         Ok(())
     }
+
+    /// Returns the title given in the argument at `index`, the title of the
+    /// current page if there is no such argument, or `None` if the argument is
+    /// not a valid title.
+    fn title_or_current(
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+        index: usize,
+    ) -> Result<Option<Title>> {
+        Ok(match arguments.eval(state, index)?.map(trim) {
+            Some(text) => {
+                (!text.is_empty() && Title::is_valid(&text)).then(|| Title::new(&text, None))
+            }
+            None => Some(arguments.sp.root().name.clone()),
+        })
+    }
 }
 
 mod site {
@@ -456,22 +799,23 @@ mod site {
 
     use super::*;
 
-    /// `{{NUMBEROFPAGES[:flag] }}`
-    #[expect(
-        clippy::cast_precision_loss,
-        reason = "if there are ever ≥2**53 articles, the singularity will have occurred and our new AI overlords can adjust this to fix the slight statistical inaccuracy"
-    )]
-    pub fn number_of_pages(
+    /// `{{ARTICLEPATH}}`
+    pub fn article_path(
         out: &mut String,
         state: &mut State<'_>,
-        arguments: &IndexedArgs<'_, '_, '_>,
+        _: &IndexedArgs<'_, '_, '_>,
     ) -> Result {
-        let no_separators = arguments.eval(state, 0)?.map(trim).as_deref() == Some("R");
-        write!(
-            out,
-            "{}",
-            format_number(state.statics.db.len() as f64, no_separators)
-        )?;
+        write!(out, "{}/article/$1", base_path(state))?;
+        Ok(())
+    }
+
+    /// `{{CURRENTVERSION}}`
+    pub fn current_version(
+        out: &mut String,
+        _: &mut State<'_>,
+        _: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        out.write_str(env!("CARGO_PKG_VERSION"))?;
         Ok(())
     }
 
@@ -489,6 +833,17 @@ mod site {
         Ok(())
     }
 
+    /// `{{SCRIPTPATH}}` or `{{STYLEPATH}}`
+    pub fn script_path(
+        out: &mut String,
+        state: &mut State<'_>,
+        _: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        // Scripts and styles are all served from the root of the wiki
+        write!(out, "{}", base_path(state))?;
+        Ok(())
+    }
+
     /// `{{SERVER}}`
     pub fn server(out: &mut String, state: &mut State<'_>, _: &IndexedArgs<'_, '_, '_>) -> Result {
         if let Some(authority) = state.statics.base_uri.authority() {
@@ -496,6 +851,62 @@ mod site {
         }
         Ok(())
     }
+
+    /// `{{SERVERNAME}}`
+    pub fn server_name(
+        out: &mut String,
+        state: &mut State<'_>,
+        _: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        if let Some(host) = state.statics.base_uri.host() {
+            write!(out, "{host}")?;
+        }
+        Ok(())
+    }
+
+    /// `{{SITENAME}}`
+    pub fn site_name(
+        out: &mut String,
+        state: &mut State<'_>,
+        _: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        write!(out, "{}", state.statics.db.name())?;
+        Ok(())
+    }
+
+    /// `{{NUMBEROFPAGES[:flag] }}` or `{{NUMBEROFARTICLES[:flag] }}` or
+    /// `{{NUMBEROFEDITS[:flag] }}` or `{{NUMBEROFFILES[:flag] }}` or
+    /// `{{NUMBEROFUSERS[:flag] }}` or `{{NUMBEROFACTIVEUSERS[:flag] }}` or
+    /// `{{NUMBEROFADMINS[:flag] }}` or `{{NUMBEROFWIKIS[:flag] }}` or
+    /// `{{NUMBERINGROUP: group [|flag] }}`
+    #[expect(
+        clippy::cast_precision_loss,
+        reason = "if there are ever ≥2**53 articles, the singularity will have occurred and our new AI overlords can adjust this to fix the slight statistical inaccuracy"
+    )]
+    pub fn statistic(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        // Database dumps contain nothing about users or files, so those use
+        // the same values as `mw.site.stats`.
+        let (value, flag_index) = match arguments.callee {
+            "numberofarticles" | "numberofedits" | "numberofpages" => {
+                (state.statics.db.len() as f64, 0)
+            }
+            "numberoffiles" => (0.0, 0),
+            "numberingroup" => (0.0, 1),
+            _ => (1.0, 0),
+        };
+        let no_separators = arguments.eval(state, flag_index)?.map(trim).as_deref() == Some("R");
+        write!(out, "{}", format_number(value, no_separators))?;
+        Ok(())
+    }
+
+    /// Returns the path to the root of the wiki, without a trailing slash.
+    fn base_path<'a>(state: &'a State<'_>) -> &'a str {
+        state.statics.base_uri.path().trim_end_matches('/')
+    }
 }
 
 mod string {
@@ -517,6 +928,23 @@ mod string {
         Ok(())
     }
 
+    /// `{{#commaseparatedlist: item [| item ...] }}`
+    pub fn comma_separated_list(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        for index in 0..arguments.len() {
+            if index != 0 {
+                out.write_str(", ")?;
+            }
+            if let Some(item) = arguments.eval(state, index)?.map(trim) {
+                write!(out, "{item}")?;
+            }
+        }
+        Ok(())
+    }
+
     /// `{{formatnum: number [|flag [|flag]] }}`
     pub fn format_number(
         out: &mut String,
@@ -668,26 +1096,16 @@ mod string {
         state: &mut State<'_>,
         arguments: &IndexedArgs<'_, '_, '_>,
     ) -> Result {
-        if let (Some(value), Some(len)) = (
-            arguments.eval(state, 0)?.map(trim),
-            arguments.eval(state, 1)?.map(trim),
-        ) {
-            let len = len.parse::<usize>().unwrap_or(0);
-            if value.len() < len {
-                let pad = arguments.eval(state, 2)?.map_or(Cow::Borrowed("0"), trim);
-                // log::trace!("padleft({value}, {len}, {pad})");
-                if !pad.is_empty() {
-                    for c in iter::repeat(&pad)
-                        .flat_map(|pad| pad.chars())
-                        .take(len - value.len())
-                    {
-                        out.write_char(c)?;
-                    }
-                }
-            }
-            write!(out, "{value}")?;
-        }
-        Ok(())
+        pad(out, state, arguments, true)
+    }
+
+    /// `{{padright: string | length [| padding value] }}`
+    pub fn pad_right(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        pad(out, state, arguments, false)
     }
 
     /// `{{plural: number [| [number = ] variant ...] }}`
@@ -811,10 +1229,26 @@ mod string {
         }
         Ok(())
     }
+
+    /// Pads a string to the left or right.
+    fn pad(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+        left: bool,
+    ) -> Result {
+        let value = arguments.eval(state, 0)?.map(trim).unwrap_or_default();
+        let len = arguments.eval(state, 1)?.map_or(0, |len| {
+            intval(len.trim_ascii(), Some(10)).map_or(0, |(len, _)| len)
+        });
+        let padding = arguments.eval(state, 2)?.map_or(Cow::Borrowed("0"), trim);
+        let padding = StripMarkers::kill(&padding);
+        // log::trace!("pad({value}, {len}, {padding}, {left})");
+        write!(out, "{}", pad_string(&value, len, &padding, left))?;
+        Ok(())
+    }
 }
 
-// TODO: All the 'current' times should be UTC, and 'local' in the local time,
-// and they should be relative to the database dump time.
 mod time {
     //! Date and time functions.
 
@@ -824,22 +1258,30 @@ mod time {
     pub fn clock_time(
         out: &mut String,
         state: &mut State<'_>,
-        _: &IndexedArgs<'_, '_, '_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
     ) -> Result {
-        let time = &state.statics.base_time;
+        let time = base_time(state, arguments)?;
         write!(out, "{:02}:{:02}", time.hour(), time.minute())?;
         Ok(())
     }
 
-    /// `{{LOCALDAY}}` or `{{CURRENTDAY}}`
-    pub fn day(out: &mut String, state: &mut State<'_>, _: &IndexedArgs<'_, '_, '_>) -> Result {
-        write!(out, "{}", state.statics.base_time.day())?;
+    /// `{{LOCALDAY}}` or `{{CURRENTDAY}}` or `{{REVISIONDAY}}`
+    pub fn day(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        write!(out, "{}", base_time(state, arguments)?.day())?;
         Ok(())
     }
 
-    /// `{{LOCALDAY2}}` or `{{CURRENTDAY2}}`
-    pub fn day_lz(out: &mut String, state: &mut State<'_>, _: &IndexedArgs<'_, '_, '_>) -> Result {
-        write!(out, "{:02}", state.statics.base_time.day())?;
+    /// `{{LOCALDAY2}}` or `{{CURRENTDAY2}}` or `{{REVISIONDAY2}}`
+    pub fn day_lz(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        write!(out, "{:02}", base_time(state, arguments)?.day())?;
         Ok(())
     }
 
@@ -847,9 +1289,9 @@ mod time {
     pub fn day_name(
         out: &mut String,
         state: &mut State<'_>,
-        _: &IndexedArgs<'_, '_, '_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
     ) -> Result {
-        write!(out, "{}", state.statics.base_time.weekday())?;
+        write!(out, "{}", base_time(state, arguments)?.weekday())?;
         Ok(())
     }
 
@@ -857,25 +1299,35 @@ mod time {
     pub fn day_of_week(
         out: &mut String,
         state: &mut State<'_>,
-        _: &IndexedArgs<'_, '_, '_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
     ) -> Result {
         write!(
             out,
             "{}",
-            state.statics.base_time.weekday().number_days_from_sunday()
+            base_time(state, arguments)?
+                .weekday()
+                .number_days_from_sunday()
         )?;
         Ok(())
     }
 
     /// `{{LOCALHOUR}}` or `{{CURRENTHOUR}}`
-    pub fn hour(out: &mut String, state: &mut State<'_>, _: &IndexedArgs<'_, '_, '_>) -> Result {
-        write!(out, "{:02}", state.statics.base_time.hour())?;
+    pub fn hour(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        write!(out, "{:02}", base_time(state, arguments)?.hour())?;
         Ok(())
     }
 
-    /// `{{LOCALMONTH1}}` or `{{CURRENTMONTH1}}`
-    pub fn month(out: &mut String, state: &mut State<'_>, _: &IndexedArgs<'_, '_, '_>) -> Result {
-        write!(out, "{}", u8::from(state.statics.base_time.month()))?;
+    /// `{{LOCALMONTH1}}` or `{{CURRENTMONTH1}}` or `{{REVISIONMONTH1}}`
+    pub fn month(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        write!(out, "{}", u8::from(base_time(state, arguments)?.month()))?;
         Ok(())
     }
 
@@ -883,32 +1335,59 @@ mod time {
     pub fn month_abbr(
         out: &mut String,
         state: &mut State<'_>,
-        _: &IndexedArgs<'_, '_, '_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
     ) -> Result {
-        write!(out, "{:.3}", state.statics.base_time.month())?;
+        write!(out, "{:.3}", base_time(state, arguments)?.month())?;
         Ok(())
     }
 
-    /// `{{LOCALMONTH}}` or `{{LOCALMONTH2}}}}` or `{{CURRENTMONTH}}` or
-    /// `{{CURRENTMONTH2}}`
-    // "localmonth" | "localmonth2" | "currentmonth" | "currentmonth2" => {
+    /// `{{LOCALMONTH}}` or `{{LOCALMONTH2}}` or `{{CURRENTMONTH}}` or
+    /// `{{CURRENTMONTH2}}` or `{{REVISIONMONTH}}`
     pub fn month_lz(
         out: &mut String,
         state: &mut State<'_>,
-        _: &IndexedArgs<'_, '_, '_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
     ) -> Result {
-        write!(out, "{:02}", u8::from(state.statics.base_time.month()))?;
+        write!(out, "{:02}", u8::from(base_time(state, arguments)?.month()))?;
         Ok(())
     }
 
-    /// `{{LOCALMONTHNAME}}` or `{{LOCALMONTHNAMEGEN}}}}` or
-    /// `{{CURRENTMONTHNAME}}` or `{{CURRENTMONTHNAMEGEN}}`
+    /// `{{LOCALMONTHNAME}}` or `{{CURRENTMONTHNAME}}`
     pub fn month_name(
         out: &mut String,
         state: &mut State<'_>,
-        _: &IndexedArgs<'_, '_, '_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
     ) -> Result {
-        write!(out, "{}", state.statics.base_time.month())?;
+        write!(out, "{}", base_time(state, arguments)?.month())?;
+        Ok(())
+    }
+
+    /// `{{LOCALMONTHNAMEGEN}}` or `{{CURRENTMONTHNAMEGEN}}`
+    pub fn month_name_gen(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        let month = base_time(state, arguments)?
+            .format("xg")
+            .map_err(DateTimeError::from)?;
+        write!(out, "{month}")?;
+        Ok(())
+    }
+
+    /// `{{#formatdate: date [| default format] }}`
+    pub fn format_date(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        if let Some(date) = arguments.eval(state, 0)?.map(trim) {
+            // There is no user, so the date preference is always 'default',
+            // which means the default format from the second argument wins.
+            let preference = arguments.eval(state, 1)?.map(trim);
+            let date = date_formatter::reformat(preference.as_deref().unwrap_or_default(), &date);
+            write!(out, "{date}")?;
+        }
         Ok(())
     }
 
@@ -924,31 +1403,57 @@ mod time {
                 .eval(state, 3)?
                 .map(trim)
                 .is_some_and(|local| local.trim_ascii() == "local");
+            write_time(out, state, &format, date.as_deref(), local)?;
+        }
+        Ok(())
+    }
 
-            // 'Template:Date' sends garbage values to `#time` without an
-            // `#iferror` guard to capture the errors.
-            match on_error_resume_next(format_date_mediawiki(
-                &state.statics.base_time,
-                &format,
-                date.as_deref(),
-                local,
-            )) {
-                Ok(result) => {
-                    write!(out, "{result}")?;
-                }
-                Err(err) => write!(out, "{err}")?,
-            }
+    /// `{{#timel: format [| time [| language code ]] }}`
+    pub fn time_local(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        if let Some(format) = arguments.eval(state, 0)?.map(trim) {
+            let date = arguments.eval(state, 1)?.map(trim);
+            write_time(out, state, &format, date.as_deref(), true)?;
         }
         Ok(())
     }
 
-    /// `{{LOCALTIMESTAMP}}` or `{{CURRENTTIMESTAMP}}`
+    /// `{{#timef: time [| 'date'|'time'|'both'|'pretty' [| language code ]] }}`
+    /// or `{{#timefl: time [| 'date'|'time'|'both'|'pretty' [| language code ]] }}`
+    pub fn time_preset(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        let date = arguments.eval(state, 0)?.map(trim);
+        // These are the English `dmy` formats, which are the default ones
+        let format = match arguments.eval(state, 1)?.map(trim).as_deref() {
+            None | Some("both") => "H:i, j F Y",
+            Some("date") => "j F Y",
+            Some("pretty") => "j F",
+            Some("time") => "H:i",
+            Some(_) => return write_error(out, "pfunc_timef_bad_format", &[]),
+        };
+        write_time(
+            out,
+            state,
+            format,
+            date.as_deref(),
+            arguments.callee == "timefl",
+        )
+    }
+
+    /// `{{LOCALTIMESTAMP}}` or `{{CURRENTTIMESTAMP}}` or
+    /// `{{REVISIONTIMESTAMP}}`
     pub fn timestamp(
         out: &mut String,
         state: &mut State<'_>,
-        _: &IndexedArgs<'_, '_, '_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
     ) -> Result {
-        let time = &state.statics.base_time;
+        let time = base_time(state, arguments)?;
         write!(
             out,
             "{}{:02}{:02}{:02}{:02}{:02}",
@@ -963,14 +1468,82 @@ mod time {
     }
 
     /// `{{LOCALWEEK}}` or `{{CURRENTWEEK}}`
-    pub fn week(out: &mut String, state: &mut State<'_>, _: &IndexedArgs<'_, '_, '_>) -> Result {
-        write!(out, "{}", state.statics.base_time.iso_week())?;
+    pub fn week(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        write!(out, "{}", base_time(state, arguments)?.iso_week())?;
         Ok(())
     }
 
-    /// `{{LOCALYEAR}}` or `{{CURRENTYEAR}}`
-    pub fn year(out: &mut String, state: &mut State<'_>, _: &IndexedArgs<'_, '_, '_>) -> Result {
-        write!(out, "{}", state.statics.base_time.year())?;
+    /// `{{LOCALYEAR}}` or `{{CURRENTYEAR}}` or `{{REVISIONYEAR}}`
+    pub fn year(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        write!(out, "{}", base_time(state, arguments)?.year())?;
+        Ok(())
+    }
+
+    /// Returns the base time in the time zone used by the variable being
+    /// called. `LOCAL*` and `REVISION*` variables use the wiki time zone, and
+    /// `CURRENT*` variables use UTC.
+    ///
+    /// `REVISION*` variables use the revision date of the root article. Like
+    /// an unsaved revision in MediaWiki, a page without one uses the current
+    /// time instead.
+    fn base_time(state: &State<'_>, arguments: &IndexedArgs<'_, '_, '_>) -> Result<DateTime> {
+        let tz = time_zone(arguments.callee, DateTimeZone::local());
+
+        let revision_date = if arguments.callee.starts_with("revision") {
+            state
+                .statics
+                .db
+                .get(&arguments.sp.root().name)
+                .ok()
+                .and_then(|article| article.date)
+        } else {
+            None
+        };
+
+        Ok(revision_date
+            .map_or(state.statics.base_time, DateTime::from)
+            .into_offset(tz)?)
+    }
+
+    /// Returns the time zone used by the variable `callee` when the wiki time
+    /// zone is `local`.
+    pub(super) fn time_zone(callee: &str, local: DateTimeZone) -> DateTimeZone {
+        if callee.starts_with("current") {
+            DateTimeZone::UTC
+        } else {
+            local
+        }
+    }
+
+    /// Writes a formatted date for the `#time` family of parser functions.
+    fn write_time(
+        out: &mut String,
+        state: &State<'_>,
+        format: &str,
+        date: Option<&str>,
+        local: bool,
+    ) -> Result {
+        // 'Template:Date' sends garbage values to `#time` without an
+        // `#iferror` guard to capture the errors.
+        match on_error_resume_next(format_date_mediawiki(
+            &state.statics.base_time,
+            format,
+            date,
+            local,
+        )) {
+            Ok(result) => {
+                write!(out, "{result}")?;
+            }
+            Err(err) => write!(out, "{err}")?,
+        }
         Ok(())
     }
 }
@@ -980,6 +1553,24 @@ mod title {
 
     use super::*;
 
+    /// `{{canonicalurl: title [| query string] }}`
+    pub fn canonical_url(
+        out: &mut String,
+        state: &mut State<'_>,
+        arguments: &IndexedArgs<'_, '_, '_>,
+    ) -> Result {
+        if let Some(value) = arguments.eval(state, 0)?.map(trim) {
+            let query = arguments.eval(state, 1)?.map(trim);
+            let title = Title::new(&value, None);
+            let base_uri = &state.statics.base_uri;
+            let proto = format!("{}:", base_uri.scheme_str().unwrap_or("http"));
+            let url = make_url(Some(&proto), base_uri, &title, query.as_deref(), false)?;
+            write!(out, "{url}")?;
+        }
+
+        Ok(())
+    }
+
     /// `{{filepath: title [| 'nowiki'/size [| size/'nowiki']] }}`
     pub fn file_path(_: &mut String, _: &mut State<'_>, _: &IndexedArgs<'_, '_, '_>) -> Result {
         // Normally this would look up a file, optionally picking one based on
@@ -1103,45 +1694,33 @@ mod title {
         Ok(())
     }
 
-    /// `{{#lst:title | section [| replacement text] }}`
-    pub fn transclude_except(
-        _: &mut String,
-        state: &mut State<'_>,
-        arguments: &IndexedArgs<'_, '_, '_>,
-    ) -> Result {
-        log::warn!(
-            "TODO: #lst({:?}, {:?}",
-            arguments.eval(state, 0)?,
-            arguments.eval(state, 1)?
-        );
-        Ok(())
-    }
-
-    /// `{{#lsth:title | section [| replacement text] }}`
-    pub fn transclude_heading(
-        _: &mut String,
+    /// `{{#rel2abs: path [| base path] }}`
+    pub fn relative_to_absolute(
+        out: &mut String,
         state: &mut State<'_>,
         arguments: &IndexedArgs<'_, '_, '_>,
     ) -> Result {
-        log::warn!(
-            "TODO: #lsth({:?}, {:?}",
-            arguments.eval(state, 0)?,
-            arguments.eval(state, 1)?
-        );
+        let to = arguments.eval(state, 0)?.map(trim).unwrap_or_default();
+        let from = arguments
+            .eval(state, 1)?
+            .map(trim)
+            .filter(|from| !from.is_empty())
+            .unwrap_or_else(|| Cow::Borrowed(arguments.sp.root().name.prefixed_text()));
+        match rel2abs(&to, &from) {
+            Ok(path) => write!(out, "{path}")?,
+            Err(path) => write_error(out, "pfunc_rel2abs_invalid_depth", &[&path])?,
+        }
         Ok(())
     }
 
-    /// `{{#lst:title | section [| end section] }}`
-    pub fn transclude_section(
-        _: &mut String,
+    /// `{{#special: special page name }}`
+    pub fn special(
+        out: &mut String,
         state: &mut State<'_>,
         arguments: &IndexedArgs<'_, '_, '_>,
     ) -> Result {
-        log::warn!(
-            "TODO: #lstx({:?}, {:?}",
-            arguments.eval(state, 0)?,
-            arguments.eval(state, 1)?
-        );
+        let name = arguments.eval(state, 0)?.map(trim).unwrap_or_default();
+        write!(out, "{}", special_page_name(&name))?;
         Ok(())
     }
 }
@@ -1158,36 +1737,84 @@ static PARSER_FUNCTIONS: phf::Map<&'static str, ParserFn> = phf::phf_map! {
     "ifexpr" => cond::if_expr,
     "switch" => cond::switch,
 
+    "assessment" => ext::no_output,
+    "babel" => ext::no_output,
+    "categorytree" => ext::no_output,
+    "chart" => ext::chart,
     "coordinates" => ext::geodata_coordinates,
+    "interlanguagelink" => ext::no_output,
+    "interwikilink" => ext::interwiki_link,
     "invoke" => ext::invoke,
+    "mentor" => ext::no_output,
+    "noexternallanglinks" => ext::no_output,
     "property" => ext::wikibase_property,
+    "related" => ext::no_output,
+    "statements" => ext::no_output,
     "tag" => ext::extension_tag,
+    "target" => ext::no_output,
+    "wbreponame" => ext::wikibase_repo_name,
+
+    "bcp47" => i18n::bcp47,
+    "bidi" => i18n::bidi,
+    "contentlanguage" => i18n::content_language,
+    "dir" => i18n::dir,
+    "directionmark" => i18n::direction_mark,
+    "formal" => i18n::formal,
+    "gender" => i18n::gender,
+    "grammar" => i18n::grammar,
+    "language" => i18n::language_name,
+    "pagelanguage" => i18n::content_language,
+    "userlanguage" => i18n::content_language,
 
     "articlepagename" => page::subject_page_name,
     "basepagename" => page::base_page_name,
+    "cascadingsources" => page::page_metadata,
+    "contentmodel" => page::content_model,
     "defaultsort" => page::set_page_var,
     "displaytitle" => page::set_page_var,
     "fullpagename" => page::full_page_name,
     "getshortdesc" => page::page_var,
+    "pageid" => page::page_id,
     "pagename" => page::page_name,
+    "pagesize" => page::page_size,
+    "pendingchangelevel" => page::page_metadata,
     "protectionexpiry" => page::protection_expiry,
+    "protectionlevel" => page::page_metadata,
     "revisionid" => page::revision_id,
+    "revisionsize" => page::page_size,
+    "revisionuser" => page::page_metadata,
     "rootpagename" => page::root_page_name,
     "shortdesc" => page::set_page_var,
     "subjectpagename" => page::subject_page_name,
     "subpagename" => page::sub_page_name,
     "talkpagename" => page::talk_page_name,
 
-    "numberofpages" => site::number_of_pages,
+    "articlepath" => site::article_path,
+    "currentversion" => site::current_version,
+    "numberingroup" => site::statistic,
+    "numberofactiveusers" => site::statistic,
+    "numberofadmins" => site::statistic,
+    "numberofarticles" => site::statistic,
+    "numberofedits" => site::statistic,
+    "numberoffiles" => site::statistic,
+    "numberofpages" => site::statistic,
+    "numberofusers" => site::statistic,
+    "numberofwikis" => site::statistic,
     "pagesincategory" => site::pages_in_category,
+    "scriptpath" => site::script_path,
     "server" => site::server,
+    "servername" => site::server_name,
+    "sitename" => site::site_name,
+    "stylepath" => site::script_path,
 
     "anchorencode" => string::anchor_encode,
+    "commaseparatedlist" => string::comma_separated_list,
     "formatnum" => string::format_number,
     "int" => string::interface_message,
     "lc" => string::lc,
     "lcfirst" => string::lc_first,
     "padleft" => string::pad_left,
+    "padright" => string::pad_right,
     "plural" => string::plural,
     "titleparts" => string::title_parts,
     "uc" => string::uc,
@@ -1204,10 +1831,12 @@ static PARSER_FUNCTIONS: phf::Map<&'static str, ParserFn> = phf::phf_map! {
     "currentmonth2" => time::month_lz,
     "currentmonthabbrev" => time::month_abbr,
     "currentmonthname" => time::month_name,
+    "currentmonthnamegen" => time::month_name_gen,
     "currenttime" => time::clock_time,
     "currenttimestamp" => time::timestamp,
     "currentweek" => time::week,
     "currentyear" => time::year,
+    "formatdate" => time::format_date,
     "localday" => time::day,
     "localday2" => time::day_lz,
     "localdayname" => time::day_name,
@@ -1218,27 +1847,118 @@ static PARSER_FUNCTIONS: phf::Map<&'static str, ParserFn> = phf::phf_map! {
     "localmonth2" => time::month_lz,
     "localmonthabbrev" => time::month_abbr,
     "localmonthname" => time::month_name,
+    "localmonthnamegen" => time::month_name_gen,
     "localtime" => time::clock_time,
     "localtimestamp" => time::timestamp,
     "localweek" => time::week,
     "localyear" => time::year,
+    "revisionday" => time::day,
+    "revisionday2" => time::day_lz,
+    "revisionmonth" => time::month_lz,
+    "revisionmonth1" => time::month,
+    "revisiontimestamp" => time::timestamp,
+    "revisionyear" => time::year,
     "time" => time::time,
+    "timef" => time::time_preset,
+    "timefl" => time::time_preset,
+    "timel" => time::time_local,
 
     "articlespace" => title::namespace,
+    "canonicalurl" => title::canonical_url,
     "filepath" => title::file_path,
     "fullurl" => title::full_url,
     "ifexist" => title::if_exist,
     "localurl" => title::local_url,
-    "lst" => title::transclude_section,
-    "lsth" => title::transclude_heading,
-    "lstx" => title::transclude_except,
+    "lst" => lst::transclude_section,
+    "lsth" => lst::transclude_heading,
+    "lstx" => lst::transclude_except,
     "namespace" => title::namespace,
     "namespacenumber" => title::namespace,
     "ns" => title::namespace_by_name_or_id,
+    "rel2abs" => title::relative_to_absolute,
+    "special" => title::special,
     "subjectspace" => title::namespace,
     "talkspace" => title::namespace,
+
+    "basepagenamee" => encoded,
+    "canonicalurle" => encoded,
+    "fullpagenamee" => encoded,
+    "fullurle" => encoded,
+    "localurle" => encoded,
+    "namespacee" => encoded,
+    "nse" => encoded,
+    "pagenamee" => encoded,
+    "rootpagenamee" => encoded,
+    "speciale" => encoded,
+    "subjectpagenamee" => encoded,
+    "subjectspacee" => encoded,
+    "subpagenamee" => encoded,
+    "talkpagenamee" => encoded,
+    "talkspacee" => encoded,
+};
+
+/// Canonical names of special pages, keyed by case-folded alias.
+static SPECIAL_PAGES: phf::Map<&str, &str> = phf::phf_map! {
+    "allpages" => "AllPages",
+    "block" => "Block",
+    "blockip" => "Block",
+    "booksources" => "BookSources",
+    "categories" => "Categories",
+    "citethispage" => "CiteThisPage",
+    "contribs" => "Contributions",
+    "contributions" => "Contributions",
+    "diff" => "Diff",
+    "email" => "EmailUser",
+    "emailuser" => "EmailUser",
+    "export" => "Export",
+    "filepath" => "FilePath",
+    "listfiles" => "ListFiles",
+    "listusers" => "ListUsers",
+    "log" => "Log",
+    "login" => "UserLogin",
+    "logs" => "Log",
+    "movepage" => "MovePage",
+    "mycontributions" => "MyContributions",
+    "mypage" => "MyPage",
+    "mytalk" => "MyTalk",
+    "newpages" => "NewPages",
+    "permalink" => "PermanentLink",
+    "permanentlink" => "PermanentLink",
+    "preferences" => "Preferences",
+    "prefixindex" => "PrefixIndex",
+    "random" => "Random",
+    "randompage" => "Random",
+    "recentchanges" => "RecentChanges",
+    "recentchangeslinked" => "RecentChangesLinked",
+    "redirect" => "Redirect",
+    "relatedchanges" => "RecentChangesLinked",
+    "search" => "Search",
+    "specialpages" => "SpecialPages",
+    "statistics" => "Statistics",
+    "upload" => "Upload",
+    "userlogin" => "UserLogin",
+    "version" => "Version",
+    "watchlist" => "Watchlist",
+    "whatlinkshere" => "WhatLinksHere",
 };
 
+/// The characters which are not percent-encoded by `wfUrlencode`.
+const WF_URL_ENCODE: AsciiSet = NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'!')
+    .remove(b'$')
+    .remove(b'(')
+    .remove(b')')
+    .remove(b'*')
+    .remove(b',')
+    .remove(b'/')
+    .remove(b':')
+    .remove(b';')
+    .remove(b'@')
+    .remove(b'~');
+
 /// Renders a parser function.
 pub fn call_parser_fn(
     out: &mut String,
@@ -1283,6 +2003,87 @@ pub fn call_parser_fn(
     }
 }
 
+/// `{{[variable]E}}`, the URL-encoded form of a page name, namespace, or URL
+/// variable.
+fn encoded(out: &mut String, state: &mut State<'_>, arguments: &IndexedArgs<'_, '_, '_>) -> Result {
+    let Some((callee, parser_fn)) = arguments
+        .callee
+        .strip_suffix('e')
+        .and_then(|callee| Some((callee, PARSER_FUNCTIONS.get(callee)?)))
+    else {
+        return Ok(());
+    };
+
+    let mut value = String::new();
+    parser_fn(
+        &mut value,
+        state,
+        &IndexedArgs {
+            arguments: arguments.arguments.clone(),
+            callee,
+            sp: arguments.sp,
+            span: arguments.span,
+        },
+    )?;
+
+    out.push_str(&encode_value(callee, &value));
+    Ok(())
+}
+
+/// Encodes the output of the `callee` variable for its `{{[variable]E}}`
+/// form.
+fn encode_value(callee: &str, value: &str) -> String {
+    if callee.ends_with("url") {
+        html_escape::encode_quoted_attribute(value).into_owned()
+    } else {
+        let value = percent_encoding::utf8_percent_encode(&value.replace(' ', "_"), &WF_URL_ENCODE)
+            .to_string();
+        if callee.ends_with("pagename") {
+            escape_wiki_text(&value)
+        } else {
+            value
+        }
+    }
+}
+
+/// Escapes Wikitext control characters like `wfEscapeWikiText`.
+fn escape_wiki_text(text: &str) -> String {
+    // The leading newline makes the line-start rules also apply to the start
+    // of the text
+    let text = format!("\n{text}");
+    strtr(
+        &text,
+        &[
+            ("ISBN ", "ISBN&#32;"),
+            ("PMID ", "PMID&#32;"),
+            ("RFC ", "RFC&#32;"),
+            ("://", "&#58;//"),
+            ("~~~", "&#126;~~"),
+            ("\n\n", "\n&#10;"),
+            ("\n ", "\n&#32;"),
+            ("\n!", "\n&#33;"),
+            ("\n#", "\n&#35;"),
+            ("\n*", "\n&#42;"),
+            ("\n:", "\n&#58;"),
+            ("!!", "&#33;!"),
+            ("__", "&#95;_"),
+            ("\"", "&#34;"),
+            ("&", "&#38;"),
+            ("'", "&#39;"),
+            (";", "&#59;"),
+            ("<", "&#60;"),
+            ("=", "&#61;"),
+            (">", "&#62;"),
+            ("[", "&#91;"),
+            ("]", "&#93;"),
+            ("{", "&#123;"),
+            ("|", "&#124;"),
+            ("}", "&#125;"),
+        ],
+    )[1..]
+        .to_owned()
+}
+
 /// Converts a `Result<T, E>` into a `Result<T, String>` to ignore errors like
 /// it’s 1995.
 fn on_error_resume_next<T, E: fmt::Display>(value: Result<T, E>) -> Result<T, String> {
@@ -1294,6 +2095,99 @@ fn on_error_resume_next<T, E: fmt::Display>(value: Result<T, E>) -> Result<T, St
     })
 }
 
+/// Pads `value` to `len` characters on the left or right with repetitions of
+/// `padding`, like `CoreParserFunctions::pad`.
+fn pad_string<'a>(value: &'a str, len: i64, padding: &str, left: bool) -> Cow<'a, str> {
+    const MAX_LEN: i64 = 500;
+
+    let len = usize::try_from(len.min(MAX_LEN))
+        .unwrap_or(0)
+        .saturating_sub(value.chars().count());
+    if len == 0 || padding.is_empty() {
+        return Cow::Borrowed(value);
+    }
+
+    let padding = padding.chars().cycle().take(len);
+    Cow::Owned(if left {
+        padding.chain(value.chars()).collect()
+    } else {
+        value.chars().chain(padding).collect()
+    })
+}
+
+/// Resolves the relative page path `to` against the page path `from`, like
+/// `ExtParserFunctions::rel2abs`.
+///
+/// If the path tries to go above the root, returns the normalised combined
+/// path as an error.
+fn rel2abs(to: &str, from: &str) -> Result<String, String> {
+    let to = to.trim_end_matches([' ', '/']);
+    if to.is_empty() || to == "." {
+        return Ok(from.to_owned());
+    }
+
+    let is_relative =
+        to.starts_with('/') || to.starts_with("./") || to.starts_with("../") || to == "..";
+    let full_path = format!("{}/{to}", if is_relative { from } else { "" });
+    let parts = full_path
+        .split('/')
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect::<Vec<_>>();
+
+    let mut resolved = Vec::with_capacity(parts.len());
+    for part in &parts {
+        if *part == ".." {
+            if resolved.pop().is_none() {
+                return Err(parts.join("/"));
+            }
+        } else {
+            resolved.push(*part);
+        }
+    }
+
+    Ok(resolved.join("/"))
+}
+
+/// Returns the localised title of the special page with the given name.
+fn special_page_name(text: &str) -> String {
+    let ns = Namespace::find_by_id(Namespace::SPECIAL).map_or("Special", |ns| ns.name);
+    let (name, sub_page) = text
+        .split_once('/')
+        .map_or((text, None), |(name, sub_page)| (name, Some(sub_page)));
+    let key = name.to_lowercase().replace(' ', "_");
+    match (SPECIAL_PAGES.get(key.as_str()), sub_page) {
+        (Some(name), Some(sub_page)) => format!("{ns}:{name}/{sub_page}"),
+        (Some(name), None) => format!("{ns}:{name}"),
+        (None, _) => {
+            let mut text = text.chars();
+            if let Some(first) = text.next() {
+                format!("{ns}:{}{}", first.to_uppercase(), text.as_str())
+            } else {
+                format!("{ns}:")
+            }
+        }
+    }
+}
+
+/// Writes an error message from the ParserFunctions extension.
+fn write_error(out: &mut String, key: &str, params: &[&str]) -> Result {
+    let Ok(message) = format_message([key], |index| {
+        Ok::<_, Infallible>(
+            index
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| params.get(index.checked_sub(1)?))
+                .map(|param| Cow::Borrowed(*param)),
+        )
+    });
+    write!(
+        out,
+        r#"<strong class="error">{}</strong>"#,
+        html_escape::encode_text(&message)
+    )?;
+    Ok(())
+}
+
 /// Decodes HTML entities and trims ASCII whitespace from the value.
 fn decode_trim(value: Cow<'_, str>) -> Cow<'_, str> {
     trim(value.map(decode_html))
@@ -1308,3 +2202,136 @@ fn decode_trim(value: Cow<'_, str>) -> Cow<'_, str> {
 fn trim(value: Cow<'_, str>) -> Cow<'_, str> {
     value.map_ref(|value| value.trim_ascii())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pad() {
+        assert_eq!(pad_string("xyz", 5, "0", true), "00xyz");
+        assert_eq!(pad_string("xyz", 5, "_", true), "__xyz");
+        assert_eq!(pad_string("xyz", 5, "abcdef", true), "abxyz");
+        assert_eq!(pad_string("xyz", 9, "abcdef", true), "abcdefxyz");
+        assert_eq!(pad_string("xyz", 2, "0", true), "xyz");
+        assert_eq!(pad_string("", 1, "xyz", true), "x");
+        assert_eq!(pad_string("xyz", 5, "", true), "xyz");
+        assert_eq!(pad_string("xyz", -5, "0", true), "xyz");
+        assert_eq!(pad_string("Hello-", 7, "Æ", false), "Hello-Æ");
+        assert_eq!(pad_string("", 2, "xy", false), "xy");
+        assert_eq!(pad_string("Æ", 3, "é", true), "ééÆ");
+        assert_eq!(pad_string("", 1000, "x", false).len(), 500);
+    }
+
+    #[test]
+    fn variable_time_zones() {
+        let local = DateTimeZone::named("Asia/Kolkata").unwrap();
+        let hour_minute = |callee| {
+            let time = DateTime::UNIX_EPOCH
+                .into_offset(time::time_zone(callee, local))
+                .unwrap();
+            (time.hour(), time.minute())
+        };
+        assert_eq!(hour_minute("localhour"), (5, 30));
+        assert_eq!(hour_minute("revisionday"), (5, 30));
+        assert_eq!(hour_minute("currenthour"), (0, 0));
+    }
+
+    #[test]
+    fn relative_paths() {
+        for (to, from, expected) in [
+            (".", "Main Page", "Main Page"),
+            ("/quok", "Help:Foo/bar/baz", "Help:Foo/bar/baz/quok"),
+            ("./quok", "Help:Foo/bar/baz", "Help:Foo/bar/baz/quok"),
+            ("../quok", "Help:Foo/bar/baz", "Help:Foo/bar/quok"),
+            ("../. ", "Help:Foo/bar/baz", "Help:Foo/bar"),
+            ("../quok/.", "Help:Foo/bar/baz", "Help:Foo/bar/quok"),
+            ("../../quok", "Help:Foo/bar/baz", "Help:Foo/quok"),
+            ("../../../quok", "Help:Foo/bar/baz", "quok"),
+            ("abc", "foo", "abc"),
+            ("/abc", "foo", "foo/abc"),
+            ("/abc/", "foo", "foo/abc"),
+        ] {
+            assert_eq!(
+                rel2abs(to, from).as_deref(),
+                Ok(expected),
+                "{to} from {from}"
+            );
+        }
+
+        assert_eq!(
+            rel2abs("../../../../quok", "Help:Foo/bar/baz"),
+            Err("Help:Foo/bar/baz/../../../../quok".to_owned())
+        );
+    }
+
+    #[test]
+    fn url_encoded_names() {
+        let encode = |text: &str| encode_value("pagename", text);
+        assert_eq!(encode("Parser test"), "Parser_test");
+        assert_eq!(encode("Help:A/B (c), d!"), "Help:A/B_(c),_d!");
+        assert_eq!(encode("Foo?bar&baz=1"), "Foo%3Fbar%26baz%3D1");
+        assert_eq!(encode("Café"), "Caf%C3%A9");
+        assert_eq!(encode("A__B;C"), "A&#95;_B&#59;C");
+        // From 'Magic Word: {{PAGENAMEE}}' in magicWords.txt
+        assert_eq!(
+            encode("Ævar Arnfjörð Bjarmason"),
+            "%C3%86var_Arnfj%C3%B6r%C3%B0_Bjarmason"
+        );
+        // From 'Magic Word: {{PAGENAMEE}} with metacharacters (T28781)'
+        assert_eq!(
+            encode("*RFC 1234 http://example.com/"),
+            "&#42;RFC_1234_http&#58;//example.com/"
+        );
+        // From '{{#speciale:}} page name, unknown' in parserTests.txt
+        assert_eq!(
+            encode_value("special", &special_page_name("foobar nonexistent")),
+            "Special:Foobar_nonexistent"
+        );
+        assert_eq!(encode_value("namespace", "User talk"), "User_talk");
+        assert_eq!(
+            encode_value("fullurl", "//a.example/w?x=\"y\"&z"),
+            "//a.example/w?x=&quot;y&quot;&amp;z"
+        );
+    }
+
+    #[test]
+    fn wiki_text_escapes() {
+        assert_eq!(escape_wiki_text("plain"), "plain");
+        assert_eq!(escape_wiki_text("*list"), "&#42;list");
+        assert_eq!(escape_wiki_text("a\n#b"), "a\n&#35;b");
+        assert_eq!(escape_wiki_text("[[x|y]]"), "&#91;&#91;x&#124;y&#93;&#93;");
+        assert_eq!(escape_wiki_text("http://x"), "http&#58;//x");
+        // From 'Magic Word: {{PAGENAME}} with metacharacters' in
+        // magicWords.txt
+        assert_eq!(
+            escape_wiki_text("'foo & bar = baz'"),
+            "&#39;foo &#38; bar &#61; baz&#39;"
+        );
+        // From 'Magic Word: {{PAGENAME}} with metacharacters (T28781)'
+        assert_eq!(
+            escape_wiki_text("*RFC 1234 http://example.com/"),
+            "&#42;RFC&#32;1234 http&#58;//example.com/"
+        );
+    }
+
+    #[test]
+    fn special_pages() {
+        assert_eq!(special_page_name("recentchanges"), "Special:RecentChanges");
+        assert_eq!(special_page_name("Random"), "Special:Random");
+        assert_eq!(
+            special_page_name("Contributions/Foo"),
+            "Special:Contributions/Foo"
+        );
+        assert_eq!(special_page_name("Nonexistent"), "Special:Nonexistent");
+        // From the '{{#special:}}' tests in parserTests.txt
+        assert_eq!(
+            special_page_name("Recentchanges/param"),
+            "Special:RecentChanges/param"
+        );
+        assert_eq!(
+            special_page_name("foobar nonexistent"),
+            "Special:Foobar nonexistent"
+        );
+    }
+}
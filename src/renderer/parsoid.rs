@@ -0,0 +1,302 @@
+//! Parsoid-compatible annotations for the `?action=parsoid` article view.
+//!
+//! Parsoid marks up every piece of rendered output which came from a
+//! transclusion or an extension tag with the information required to get back
+//! to the original Wikitext:
+//!
+//! * `typeof="mw:Transclusion"` or `typeof="mw:Extension/{name}"` on the first
+//!   top-level node of the output;
+//! * `about="#mwt{n}"` on every top-level node of the output, so that the nodes
+//!   which came from the same source can be grouped back together;
+//! * `data-mw`, a JSON object describing the template call or extension tag,
+//!   on the same node as the `typeof`.
+//!
+//! Only top-level expansions are annotated, since anything nested inside of
+//! them is already described by the source of the outer expansion. Text which
+//! is not inside of any element is wrapped in a `<span>`, and an expansion
+//! which produces no elements at all is represented by a `<meta>`, just like
+//! Parsoid does.
+
+use super::{Kv, StackFrame, State, StripMarker};
+use crate::{
+    title::Title,
+    wikitext::{Argument, Spanned, Token},
+};
+use core::fmt::{self, Write as _};
+use serde_json::{Map, json};
+
+/// Parsoid annotation state.
+#[derive(Debug, Default)]
+pub(crate) struct Parsoid {
+    /// The last used `about` ID.
+    about: usize,
+    /// The number of expansions currently in progress.
+    depth: usize,
+}
+
+/// The source of a top-level transclusion.
+#[derive(Debug)]
+pub(crate) struct Transclusion {
+    /// The `about` ID of the transclusion.
+    pub about: usize,
+    /// The serialised `data-mw` JSON of the transclusion.
+    pub data_mw: String,
+}
+
+/// The kind of thing which was transcluded.
+pub(crate) enum TransclusionKind<'a> {
+    /// A parser function or variable with the given name.
+    ParserFn(&'a str),
+    /// A template with the given title.
+    Template(&'a Title),
+}
+
+impl State<'_> {
+    /// Starts a new expansion. Returns true if Parsoid annotations are enabled
+    /// and this is a top-level expansion.
+    ///
+    /// This is also used to suppress annotations for expansions in places
+    /// where the output is not content, like link targets and attributes.
+    pub(crate) fn parsoid_enter(&mut self) -> bool {
+        self.parsoid.as_mut().is_some_and(|parsoid| {
+            parsoid.depth += 1;
+            parsoid.depth == 1
+        })
+    }
+
+    /// Finishes an expansion.
+    pub(crate) fn parsoid_exit(&mut self) {
+        if let Some(parsoid) = &mut self.parsoid {
+            parsoid.depth -= 1;
+        }
+    }
+
+    /// Returns the next `about` ID.
+    fn parsoid_about(&mut self) -> usize {
+        let parsoid = self
+            .parsoid
+            .as_mut()
+            .expect("Parsoid annotations are enabled");
+        parsoid.about += 1;
+        parsoid.about
+    }
+}
+
+/// Creates the `data-mw` for a template transclusion.
+pub(crate) fn transclusion(
+    state: &mut State<'_>,
+    sp: &StackFrame<'_>,
+    kind: &TransclusionKind<'_>,
+    target: &[Spanned<Token>],
+    arguments: &[Kv<'_>],
+) -> Transclusion {
+    let mut target = json!({ "wt": source_of(sp, target) });
+    match kind {
+        TransclusionKind::ParserFn(callee) => {
+            target["function"] = callee.trim_start_matches('#').to_lowercase().into();
+        }
+        TransclusionKind::Template(callee) => {
+            target["href"] = format!("./{}", callee.partial_url()).into();
+        }
+    }
+
+    let mut params = Map::new();
+    let mut index = 0;
+    for argument in arguments {
+        // The first argument to a parser function is part of the target, and
+        // arguments from Lua never come from a top-level expansion.
+        let Kv::Argument(argument) = argument else {
+            continue;
+        };
+
+        let (name, value) = name_value(sp, argument);
+        let name = name.map_or_else(
+            || {
+                index += 1;
+                index.to_string()
+            },
+            str::to_owned,
+        );
+        params.insert(name, json!({ "wt": value }));
+    }
+
+    Transclusion {
+        about: state.parsoid_about(),
+        data_mw: json!({
+            "parts": [{
+                "template": {
+                    "target": target,
+                    "params": params,
+                    "i": 0
+                }
+            }]
+        })
+        .to_string(),
+    }
+}
+
+/// Annotates the output of a top-level extension tag.
+pub(crate) fn extension(
+    state: &mut State<'_>,
+    sp: &StackFrame<'_>,
+    name: &str,
+    attributes: &[Spanned<Argument>],
+    body: Option<&str>,
+    marker: Option<StripMarker>,
+) -> Option<StripMarker> {
+    let mut attrs = Map::new();
+    for attribute in attributes {
+        let (name, value) = name_value(sp, attribute);
+        if let Some(name) = name {
+            let value = value.trim_matches(['"', '\'']);
+            attrs.insert(name.to_owned(), value.into());
+        } else if !value.is_empty() {
+            attrs.insert(value.to_owned(), "".into());
+        }
+    }
+
+    let mut data_mw = json!({ "name": name, "attrs": attrs });
+    if let Some(body) = body {
+        data_mw["body"] = json!({ "extsrc": body });
+    }
+
+    let transclusion = Transclusion {
+        about: state.parsoid_about(),
+        data_mw: data_mw.to_string(),
+    };
+    let type_of = format!("mw:Extension/{name}");
+
+    Some(match marker {
+        Some(StripMarker::Block(html)) => {
+            StripMarker::Block(annotate(&html, &type_of, &transclusion))
+        }
+        Some(StripMarker::Inline(html)) => {
+            StripMarker::Inline(annotate(&html, &type_of, &transclusion))
+        }
+        None => StripMarker::Inline(placeholder(
+            transclusion.about,
+            &type_of,
+            &transclusion.data_mw,
+        )),
+        // Parsoid does not treat `<nowiki>` as an extension, and the
+        // other kinds of markers are not extension output
+        marker => return marker,
+    })
+}
+
+/// Writes the annotation attributes for a top-level node of an expansion. The
+/// `typeof` and `data-mw` attributes are only written if `source` is given,
+/// which should only happen for the first node.
+pub(crate) fn write_attributes<W: fmt::Write + ?Sized>(
+    out: &mut W,
+    about: usize,
+    source: Option<(&str, &str)>,
+) -> fmt::Result {
+    write!(out, r##" about="#mwt{about}""##)?;
+    if let Some((type_of, data_mw)) = source {
+        write!(
+            out,
+            r#" typeof="{type_of}" data-mw="{}""#,
+            html_escape::encode_double_quoted_attribute(data_mw)
+        )?;
+    }
+    Ok(())
+}
+
+/// Returns a placeholder element for an expansion which generated no
+/// elements.
+pub(crate) fn placeholder(about: usize, type_of: &str, data_mw: &str) -> String {
+    let mut out = String::from("<meta");
+    let _ = write_attributes(&mut out, about, Some((type_of, data_mw)));
+    out.push('>');
+    out
+}
+
+/// Adds annotation attributes to the first element of some rendered HTML,
+/// wrapping it in a `<span>` if it does not start with an element.
+fn annotate(html: &str, type_of: &str, transclusion: &Transclusion) -> String {
+    let mut out = String::with_capacity(html.len() + transclusion.data_mw.len() + 64);
+    let source = Some((type_of, transclusion.data_mw.as_str()));
+    let trimmed = html.trim_ascii_start();
+    let ws = &html[..html.len() - trimmed.len()];
+    let name_len = trimmed
+        .strip_prefix('<')
+        .filter(|rest| rest.starts_with(|c: char| c.is_ascii_alphabetic()))
+        .map(|rest| {
+            rest.find(|c: char| c.is_ascii_whitespace() || c == '>' || c == '/')
+                .unwrap_or(rest.len())
+        });
+
+    if let Some(name_len) = name_len {
+        let (start, rest) = trimmed.split_at(name_len + 1);
+        out += ws;
+        out += start;
+        let _ = write_attributes(&mut out, transclusion.about, source);
+        out += rest;
+    } else {
+        out += "<span";
+        let _ = write_attributes(&mut out, transclusion.about, source);
+        out.push('>');
+        out += html;
+        out += "</span>";
+    }
+    out
+}
+
+/// Returns the raw Wikitext name and value of an argument.
+fn name_value<'a>(
+    sp: &'a StackFrame<'_>,
+    argument: &Spanned<Argument>,
+) -> (Option<&'a str>, &'a str) {
+    if let Some(name) = argument.name() {
+        (
+            Some(source_of(sp, name).trim_ascii()),
+            source_of(sp, argument.value()).trim_ascii(),
+        )
+    } else {
+        (None, source_of(sp, argument.value()))
+    }
+}
+
+/// Returns the raw Wikitext of a list of tokens.
+fn source_of<'a>(sp: &'a StackFrame<'_>, tokens: &[Spanned<Token>]) -> &'a str {
+    let (Some(first), Some(last)) = (tokens.first(), tokens.last()) else {
+        return "";
+    };
+    sp.source.get(first.span.start..last.span.end).unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn annotate_element() {
+        let transclusion = Transclusion {
+            about: 3,
+            data_mw: r#"{"name":"ref"}"#.to_owned(),
+        };
+        assert_eq!(
+            annotate(
+                "\n<sup id=\"cite_ref-1\">[1]</sup>",
+                "mw:Extension/ref",
+                &transclusion
+            ),
+            "\n<sup about=\"#mwt3\" typeof=\"mw:Extension/ref\" \
+             data-mw=\"{&quot;name&quot;:&quot;ref&quot;}\" id=\"cite_ref-1\">[1]</sup>"
+        );
+    }
+
+    #[test]
+    fn annotate_text() {
+        let transclusion = Transclusion {
+            about: 1,
+            data_mw: "{}".to_owned(),
+        };
+        assert_eq!(
+            annotate("plain <b>text</b>", "mw:Extension/poem", &transclusion),
+            "<span about=\"#mwt1\" typeof=\"mw:Extension/poem\" data-mw=\"{}\">\
+             plain <b>text</b></span>"
+        );
+    }
+}
@@ -1,9 +1,10 @@
 //! Template rendering types and functions.
 
 use super::{
-    Error, Result, State, StripMarker, StripMarkers,
+    Error, Result, State, StripMarker, StripMarkers, TraceKind,
     expand_templates::{ExpandMode, ExpandTemplates},
     parser_fns::call_parser_fn,
+    parsoid::{self, Transclusion, TransclusionKind},
     resolve_redirects,
     stack::{KeyCacheKvs, Kv, StackFrame},
     surrogate::Surrogate,
@@ -217,13 +218,13 @@ pub(super) fn render_parameter(
 ///    stack recursion limits.
 /// 6. Query a database.
 /// 7. Emit as text.
-pub(super) fn render_template<'tt>(
+pub(super) fn render_template(
     out: &mut String,
     state: &mut State<'_>,
-    sp: &'tt StackFrame<'_>,
+    sp: &StackFrame<'_>,
     bounds: Span,
-    target: &'tt [Spanned<Token>],
-    arguments: &'tt [Spanned<Argument>],
+    target: &[Spanned<Token>],
+    arguments: &[Spanned<Argument>],
     line_start: bool,
 ) -> Result<bool> {
     // eprintln!("render_template {sp:?} {:?}", inspect(&sp.source, target));
@@ -244,20 +245,22 @@ pub(super) fn render_template<'tt>(
     // necessary.
     let mut partial = String::new();
 
-    let mut first = None;
-    let wrapper_key = match split_target(state, sp, &mut first, target, arguments)? {
-        Target::ParserFn { callee, arguments } => {
-            call_parser_fn(&mut partial, state, sp, Some(bounds), &callee, &arguments)?;
-            None
-        }
-
-        Target::Template { arguments, callee } => {
-            call_template(&mut partial, state, sp, &callee, &arguments)?
-        }
-
-        Target::Text => {
-            return Ok(false);
-        }
+    // Anything expanded while evaluating the target or calling the callee is
+    // part of this transclusion, so the Parsoid expansion depth has to cover
+    // all of it
+    let top_level = state.parsoid_enter();
+    let result = call_target(
+        &mut partial,
+        state,
+        sp,
+        bounds,
+        target,
+        arguments,
+        top_level,
+    );
+    state.parsoid_exit();
+    let Some((wrapper_key, transclusion)) = result? else {
+        return Ok(false);
     };
 
     // “T2529: if the template begins with a table or block-level
@@ -266,6 +269,13 @@ pub(super) fn render_template<'tt>(
     let needs_newline =
         !line_start && (partial.starts_with("{|") || partial.starts_with([':', ';', '#', '*']));
 
+    let about = transclusion.as_ref().map(|transclusion| transclusion.about);
+    if let Some(transclusion) = transclusion {
+        state
+            .strip_markers
+            .push(out, "wiki-rs", StripMarker::TransclusionStart(transclusion));
+    }
+
     if let Some(key) = wrapper_key {
         // It is necessary to inject strip markers rather than extension tags
         // or else the start-of-line rules break
@@ -286,9 +296,69 @@ pub(super) fn render_template<'tt>(
         write!(out, "{partial}")?;
     }
 
+    if let Some(about) = about {
+        state
+            .strip_markers
+            .push(out, "wiki-rs", StripMarker::TransclusionEnd(about));
+    }
+
     Ok(true)
 }
 
+/// Calls the parser function or template for a template expression, writing
+/// the result to `out`. Returns `None` if the expression turned out to be plain
+/// text; otherwise, returns the template wrapper key and, if `top_level` is
+/// true, the Parsoid source of the transclusion.
+fn call_target(
+    out: &mut String,
+    state: &mut State<'_>,
+    sp: &StackFrame<'_>,
+    bounds: Span,
+    target: &[Spanned<Token>],
+    arguments: &[Spanned<Argument>],
+    top_level: bool,
+) -> Result<Option<(Option<String>, Option<Transclusion>)>> {
+    let mut first = None;
+    Ok(Some(
+        match split_target(state, sp, &mut first, target, arguments)? {
+            Target::ParserFn { callee, arguments } => {
+                let kind = if callee == "#invoke" {
+                    TraceKind::Invoke
+                } else {
+                    TraceKind::ParserFn
+                };
+                state.trace_enter(kind, &callee, sp, Some(bounds), &arguments);
+                let result = call_parser_fn(out, state, sp, Some(bounds), &callee, &arguments);
+                state.trace_exit(out);
+                result?;
+                let transcluded = TransclusionKind::ParserFn(&callee);
+                let transclusion = top_level
+                    .then(|| parsoid::transclusion(state, sp, &transcluded, target, &arguments));
+                (None, transclusion)
+            }
+
+            Target::Template { arguments, callee } => {
+                state.trace_enter(
+                    TraceKind::Template,
+                    &callee.to_string(),
+                    sp,
+                    Some(bounds),
+                    &arguments,
+                );
+                let result = call_template(out, state, sp, &callee, &arguments);
+                state.trace_exit(out);
+                let wrapper_key = result?;
+                let transcluded = TransclusionKind::Template(&callee);
+                let transclusion = top_level
+                    .then(|| parsoid::transclusion(state, sp, &transcluded, target, &arguments));
+                (wrapper_key, transclusion)
+            }
+
+            Target::Text => return Ok(None),
+        },
+    ))
+}
+
 /// Template target information.
 enum Target<'tt> {
     /// The target is a parser function (or a variable, which is implemented in
@@ -850,7 +920,10 @@ pub(super) fn left_trim_category(
                         }
                         c.is_ascii_whitespace()
                     }),
-                    StripMarker::WikiRsSourceStart(_) | StripMarker::WikiRsSourceEnd(_) => true,
+                    StripMarker::WikiRsSourceStart(_)
+                    | StripMarker::WikiRsSourceEnd(_)
+                    | StripMarker::TransclusionStart(_)
+                    | StripMarker::TransclusionEnd(_) => true,
                     _ => false,
                 };
 
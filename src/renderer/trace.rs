@@ -0,0 +1,437 @@
+//! Template expansion tracing for the `?action=trace` article view.
+
+use super::{Kv, StackFrame, State};
+use crate::{
+    title::Title,
+    wikitext::{Span, Token},
+};
+use core::{fmt, time::Duration};
+use std::time::Instant;
+
+/// The maximum number of bytes of argument or result text retained per node.
+/// Without a limit, every level of a deep call tree would hold its own copy of
+/// everything below it.
+const TEXT_LIMIT: usize = 2048;
+
+/// The maximum number of expansions recorded per trace. Large articles can
+/// make hundreds of thousands of expansions, and since every node retains its
+/// arguments and result, recording all of them would use too much memory.
+const NODE_LIMIT: usize = 10_000;
+
+/// The kind of expansion which was traced.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum TraceKind {
+    /// An `#invoke` call.
+    Invoke,
+    /// A parser function or variable.
+    ParserFn,
+    /// A `frame:preprocess` call from a module.
+    Preprocess,
+    /// A template transclusion.
+    Template,
+}
+
+impl TraceKind {
+    /// The short name of the kind, used as a CSS class and label.
+    fn as_str(self) -> &'static str {
+        match self {
+            TraceKind::Invoke => "invoke",
+            TraceKind::ParserFn => "parser-fn",
+            TraceKind::Preprocess => "preprocess",
+            TraceKind::Template => "template",
+        }
+    }
+}
+
+/// A single traced expansion.
+#[derive(Debug)]
+pub(crate) struct TraceNode {
+    /// The raw, unexpanded arguments.
+    arguments: Vec<String>,
+    /// The name of the called thing.
+    callee: String,
+    /// Expansions which occurred during this expansion.
+    children: Vec<TraceNode>,
+    /// The total time spent in this expansion, including children.
+    duration: Duration,
+    /// The frame which made the call.
+    frame: Title,
+    /// The kind of expansion.
+    kind: TraceKind,
+    /// The 1-indexed line of the call in the calling frame’s source, if known.
+    line: Option<usize>,
+    /// The (possibly truncated) output of the expansion.
+    result: String,
+}
+
+/// A recorded expansion tree.
+#[derive(Debug, Default)]
+pub(crate) struct Trace {
+    /// Completed top-level expansions.
+    nodes: Vec<TraceNode>,
+    /// The number of expansions which were not recorded because the trace
+    /// reached [`NODE_LIMIT`].
+    omitted: usize,
+    /// The number of expansions which were recorded.
+    recorded: usize,
+    /// Expansions which are in progress, or `None` for expansions which are
+    /// not being recorded.
+    stack: Vec<Option<(TraceNode, Instant)>>,
+}
+
+impl Trace {
+    /// Returns true if no more expansions will be recorded.
+    fn is_full(&self) -> bool {
+        self.recorded >= NODE_LIMIT
+    }
+
+    /// Starts recording a new expansion as a child of the current expansion.
+    fn enter(
+        &mut self,
+        kind: TraceKind,
+        callee: &str,
+        sp: &StackFrame<'_>,
+        span: Option<Span>,
+        arguments: Vec<String>,
+    ) {
+        if self.is_full() {
+            self.stack.push(None);
+            return;
+        }
+        self.recorded += 1;
+
+        // Text given to `frame:preprocess` is not the source of the frame, so
+        // any line number inside of it would point at nonsense.
+        let in_preprocess = self
+            .stack
+            .last()
+            .and_then(Option::as_ref)
+            .is_some_and(|(node, _)| node.kind == TraceKind::Preprocess);
+        let line = span
+            .filter(|span| !in_preprocess && span.start <= sp.source.len())
+            .map(|span| sp.source.find_line_col(span.start).line);
+
+        self.stack.push(Some((
+            TraceNode {
+                arguments,
+                callee: callee.to_owned(),
+                children: Vec::new(),
+                duration: Duration::ZERO,
+                frame: sp.name.clone(),
+                kind,
+                line,
+                result: String::new(),
+            },
+            Instant::now(),
+        )));
+    }
+
+    /// Finishes recording the current expansion.
+    fn exit(&mut self, result: &str) {
+        match self.stack.pop() {
+            Some(Some((mut node, start))) => {
+                node.duration = start.elapsed();
+                node.result = truncate(result);
+                if let Some(Some((parent, _))) = self.stack.last_mut() {
+                    parent.children.push(node);
+                } else {
+                    self.nodes.push(node);
+                }
+            }
+            Some(None) => self.omitted += 1,
+            None => {}
+        }
+    }
+
+    /// Writes the trace as a tree of collapsible HTML elements.
+    pub fn write_html<W: fmt::Write + ?Sized>(&self, f: &mut W, base_path: &str) -> fmt::Result {
+        if self.nodes.is_empty() {
+            return f.write_str(r#"<p class="wiki-rs-trace-empty">Nothing was expanded.</p>"#);
+        }
+
+        f.write_str(r#"<ul class="wiki-rs-trace">"#)?;
+        for node in &self.nodes {
+            write_node(f, base_path, node)?;
+        }
+        f.write_str("</ul>")?;
+
+        if self.omitted != 0 {
+            write!(
+                f,
+                r#"<p class="wiki-rs-trace-omitted">{} more expansions were not recorded.</p>"#,
+                self.omitted
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl State<'_> {
+    /// Starts recording an expansion, if tracing is enabled.
+    pub(crate) fn trace_enter(
+        &mut self,
+        kind: TraceKind,
+        callee: &str,
+        sp: &StackFrame<'_>,
+        span: Option<Span>,
+        arguments: &[Kv<'_>],
+    ) {
+        if let Some(trace) = &self.trace {
+            let arguments = if trace.is_full() {
+                Vec::new()
+            } else {
+                raw_arguments(self, sp, arguments)
+            };
+            if let Some(trace) = &mut self.trace {
+                trace.enter(kind, callee, sp, span, arguments);
+            }
+        }
+    }
+
+    /// Finishes recording an expansion, if tracing is enabled.
+    pub(crate) fn trace_exit(&mut self, result: &str) {
+        if let Some(trace) = &mut self.trace {
+            trace.exit(result);
+        }
+    }
+}
+
+/// Returns the unexpanded source text of each argument.
+fn raw_arguments(state: &mut State<'_>, sp: &StackFrame<'_>, arguments: &[Kv<'_>]) -> Vec<String> {
+    arguments
+        .iter()
+        .map(|argument| match argument {
+            Kv::Argument(argument) => {
+                truncate(sp.source.get(argument.span.into_range()).unwrap_or(""))
+            }
+            Kv::Partial(nodes) => {
+                let mut text = String::new();
+                for node in nodes {
+                    if let Token::Generated(generated) = &node.node {
+                        text += generated;
+                    } else {
+                        text += sp.source.get(node.span.into_range()).unwrap_or("");
+                    }
+                }
+                truncate(&text)
+            }
+            Kv::String(name, value) => state
+                .statics
+                .vm
+                .try_enter(|ctx| {
+                    let name = ctx.fetch(name).to_str()?;
+                    let value = ctx.fetch(value).to_str()?;
+                    Ok(if name.is_empty() {
+                        truncate(value)
+                    } else {
+                        truncate(&format!("{name}={value}"))
+                    })
+                })
+                .unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Copies at most [`TEXT_LIMIT`] bytes of `text`.
+fn truncate(text: &str) -> String {
+    if text.len() > TEXT_LIMIT {
+        let end = text.floor_char_boundary(TEXT_LIMIT);
+        format!("{}…", &text[..end])
+    } else {
+        text.to_owned()
+    }
+}
+
+/// Writes a single trace node and its children.
+fn write_node<W: fmt::Write + ?Sized>(f: &mut W, base_path: &str, node: &TraceNode) -> fmt::Result {
+    let kind = node.kind.as_str();
+    write!(
+        f,
+        r#"<li class="wiki-rs-trace-{kind}"><details><summary><span class="wiki-rs-trace-kind">{kind}</span> <code>{}</code> <span class="wiki-rs-trace-time">{:.2?}</span> <a href="{base_path}/source/{}"#,
+        html_escape::encode_text_minimal(&node.callee),
+        node.duration,
+        node.frame.partial_url(),
+    )?;
+    if let Some(line) = node.line {
+        write!(f, "#L{line}")?;
+    }
+    write!(
+        f,
+        r#"">{}"#,
+        html_escape::encode_text_minimal(&node.frame.to_string())
+    )?;
+    if let Some(line) = node.line {
+        write!(f, ":{line}")?;
+    }
+    f.write_str("</a></summary>")?;
+
+    if !node.arguments.is_empty() {
+        f.write_str(r#"<ol class="wiki-rs-trace-args">"#)?;
+        for argument in &node.arguments {
+            write!(
+                f,
+                "<li><pre>{}</pre></li>",
+                html_escape::encode_text_minimal(argument)
+            )?;
+        }
+        f.write_str("</ol>")?;
+    }
+
+    write!(
+        f,
+        r#"<pre class="wiki-rs-trace-result">{}</pre>"#,
+        html_escape::encode_text_minimal(&node.result)
+    )?;
+
+    if !node.children.is_empty() {
+        f.write_str(r#"<ul class="wiki-rs-trace">"#)?;
+        for child in &node.children {
+            write_node(f, base_path, child)?;
+        }
+        f.write_str("</ul>")?;
+    }
+
+    f.write_str("</details></li>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wikitext::FileMap;
+
+    /// Renders a trace to HTML.
+    fn html(trace: &Trace) -> String {
+        let mut out = String::new();
+        trace.write_html(&mut out, "/wiki").unwrap();
+        out
+    }
+
+    #[test]
+    fn nesting() {
+        let sp = StackFrame::new(Title::new("Page", None), FileMap::new("a\n{{b}}\n{{c}}"));
+        let mut trace = Trace::default();
+        trace.enter(
+            TraceKind::Template,
+            "B",
+            &sp,
+            Some(Span::new(2, 7)),
+            vec!["x".into()],
+        );
+        trace.enter(TraceKind::ParserFn, "#if", &sp, None, vec![]);
+        trace.exit("inner");
+        trace.exit("outer");
+        trace.enter(
+            TraceKind::Template,
+            "C",
+            &sp,
+            Some(Span::new(8, 13)),
+            vec![],
+        );
+        trace.exit("");
+        // Unbalanced exits are ignored
+        trace.exit("");
+
+        assert!(trace.stack.is_empty());
+        let [b, c] = &trace.nodes[..] else {
+            panic!("expected two top-level nodes");
+        };
+        assert_eq!(
+            (b.callee.as_str(), b.line, b.result.as_str()),
+            ("B", Some(2), "outer")
+        );
+        assert_eq!(b.arguments, ["x"]);
+        assert_eq!(b.children.len(), 1);
+        assert_eq!(b.children[0].callee, "#if");
+        assert_eq!(b.children[0].result, "inner");
+        assert_eq!((c.callee.as_str(), c.line), ("C", Some(3)));
+    }
+
+    #[test]
+    fn preprocess_lines() {
+        let sp = StackFrame::new(Title::new("Module:M", None), FileMap::new("local p = {}"));
+        let mut trace = Trace::default();
+        trace.enter(
+            TraceKind::Preprocess,
+            "preprocess",
+            &sp,
+            Some(Span::new(0, 5)),
+            vec![],
+        );
+        trace.enter(TraceKind::Template, "T", &sp, Some(Span::new(0, 5)), vec![]);
+        trace.exit("");
+        trace.exit("");
+
+        assert_eq!(trace.nodes[0].line, Some(1));
+        assert_eq!(trace.nodes[0].children[0].line, None);
+
+        // Spans outside of the source are not given a line
+        trace.enter(
+            TraceKind::Template,
+            "T",
+            &sp,
+            Some(Span::new(100, 105)),
+            vec![],
+        );
+        trace.exit("");
+        assert_eq!(trace.nodes[1].line, None);
+    }
+
+    #[test]
+    fn node_limit() {
+        let sp = StackFrame::new(Title::new("Page", None), FileMap::new(""));
+        let mut trace = Trace::default();
+        trace.enter(TraceKind::Template, "Root", &sp, None, vec![]);
+        for _ in 0..NODE_LIMIT + 5 {
+            trace.enter(TraceKind::ParserFn, "#if", &sp, None, vec![]);
+            trace.exit("");
+        }
+        trace.exit("root");
+
+        assert!(trace.stack.is_empty());
+        assert_eq!(trace.nodes[0].result, "root");
+        assert_eq!(trace.nodes[0].children.len(), NODE_LIMIT - 1);
+        assert_eq!(trace.omitted, 6);
+        assert!(html(&trace).ends_with(
+            r#"<p class="wiki-rs-trace-omitted">6 more expansions were not recorded.</p>"#
+        ));
+    }
+
+    #[test]
+    fn truncation() {
+        assert_eq!(truncate("short"), "short");
+
+        let text = "a".repeat(TEXT_LIMIT - 1) + "é";
+        let result = truncate(&text);
+        assert_eq!(result, "a".repeat(TEXT_LIMIT - 1) + "…");
+        assert_eq!(truncate(&"b".repeat(TEXT_LIMIT)), "b".repeat(TEXT_LIMIT));
+    }
+
+    #[test]
+    fn html_escaping() {
+        assert_eq!(
+            html(&Trace::default()),
+            r#"<p class="wiki-rs-trace-empty">Nothing was expanded.</p>"#
+        );
+
+        let sp = StackFrame::new(Title::new("A&B", None), FileMap::new("{{x}}"));
+        let mut trace = Trace::default();
+        trace.enter(
+            TraceKind::Template,
+            "<T>",
+            &sp,
+            Some(Span::new(0, 5)),
+            vec!["a<b>&c".into()],
+        );
+        trace.exit("<script>");
+
+        let out = html(&trace);
+        assert!(
+            out.starts_with(r#"<ul class="wiki-rs-trace"><li class="wiki-rs-trace-template">"#)
+        );
+        assert!(out.contains("<code>&lt;T></code>"));
+        assert!(out.contains(r#"<a href="/wiki/source/A%26B#L1">A&amp;B:1</a>"#));
+        assert!(out.contains("<li><pre>a&lt;b>&amp;c</pre></li>"));
+        assert!(out.contains(r#"<pre class="wiki-rs-trace-result">&lt;script></pre>"#));
+        assert!(!out.contains("<script>"));
+    }
+}
@@ -64,6 +64,9 @@ pub(crate) struct ConfigurationSource {
     /// Magic words that can be used for redirects, lowercased.
     pub redirect_magic_words: Set<&'static str>,
 
+    /// The IANA name of the local time zone of the wiki.
+    pub time_zone: &'static str,
+
     /// The list of allowable bytes in an article title, in a format suitable
     /// for interpolation into a PHP PCRE character set pattern.
     pub valid_title_bytes: &'static str,
@@ -5,7 +5,8 @@ use super::{
     visit::{Visitor, visit_link},
 };
 use crate::title::{Namespace, Title};
-use core::fmt;
+use core::{convert::Infallible, fmt};
+use std::collections::BTreeSet;
 
 /// Extracts all text from a token tree.
 pub(crate) struct TextContent<'tt, W>
@@ -79,3 +80,61 @@ where
         todo!("inclusion control in text extractor")
     }
 }
+
+/// Collects the behavior switches which are written directly in a token tree,
+/// without expanding anything.
+///
+/// Since most pages get their switches from a template, the targets of
+/// templates which are called directly (i.e. whose names are plain text) are
+/// also collected so that callers can look inside those too.
+pub(crate) struct BehaviorSwitches<'tt> {
+    /// The token tree source.
+    source: &'tt str,
+    /// The canonical names of the collected switches.
+    switches: BTreeSet<&'tt str>,
+    /// The targets of directly called templates.
+    templates: BTreeSet<&'tt str>,
+}
+
+impl<'tt> BehaviorSwitches<'tt> {
+    /// Creates a new behavior switch collector with the given source.
+    pub fn new(source: &'tt str) -> Self {
+        Self {
+            source,
+            switches: <_>::default(),
+            templates: <_>::default(),
+        }
+    }
+
+    /// Returns the collected switches and template targets, consuming the
+    /// collector.
+    pub fn finish(self) -> (BTreeSet<&'tt str>, BTreeSet<&'tt str>) {
+        (self.switches, self.templates)
+    }
+}
+
+impl<'tt> Visitor<'tt, Infallible> for BehaviorSwitches<'tt> {
+    fn source(&self) -> &'tt str {
+        self.source
+    }
+    fn visit_behavior_switch(&mut self, _span: Span, name: &'tt str) -> Result<(), Infallible> {
+        self.switches.insert(name);
+        Ok(())
+    }
+    fn visit_template(
+        &mut self,
+        _span: Span,
+        target: &'tt [Spanned<Token>],
+        _arguments: &'tt [Spanned<Argument>],
+    ) -> Result<(), Infallible> {
+        #[rustfmt::skip]
+        if let [Spanned { span, node: Token::Text }] = target
+            && let target = self.source[span.into_range()].trim_ascii()
+            // Parser functions are not templates
+            && !target.starts_with('#')
+        {
+            self.templates.insert(target);
+        };
+        Ok(())
+    }
+}
@@ -0,0 +1,449 @@
+//! Stable JSON serialisation of parser output.
+//!
+//! Unlike the [inspectors](super::inspect), which are for humans and can change
+//! whenever, this format is versioned and meant for other programs to consume.
+//! Any change which is not strictly additive must increment [`VERSION`].
+//!
+//! # Schema
+//!
+//! The document is an object:
+//!
+//! ```json
+//! { "version": 1, "has_onlyinclude": false, "root": [Node, …] }
+//! ```
+//!
+//! Every `Node` is an object with at least these properties:
+//!
+//! * `type`: The kind of node, in `snake_case` (e.g. `"template"`).
+//! * `span`: `{ "start": Position, "end": Position }`, where `Position` is
+//!   `{ "offset": bytes, "line": 1-indexed, "column": 1-indexed chars }`. The
+//!   end position is exclusive.
+//! * `source`: The source text covered by `span`.
+//!
+//! Other properties depend on the type. Properties ending in `content`,
+//! `target`, `name`, `value`, `default`, `from`, and `to` are lists of token
+//! nodes unless otherwise noted:
+//!
+//! | `type`             | Additional properties                                |
+//! |--------------------|------------------------------------------------------|
+//! | `argument`         | `name` (list or null), `value`                       |
+//! | `autolink`         | `target`, `content`                                  |
+//! | `behavior_switch`  | `name` (string)                                      |
+//! | `comment`          | `content` (string), `unclosed` (bool)                |
+//! | `end_annotation`   | `name` (string)                                      |
+//! | `end_include`      | `mode` (`includeonly`, `noinclude`, `onlyinclude`)   |
+//! | `end_tag`          | `name` (string)                                      |
+//! | `entity`           | `value` (string)                                     |
+//! | `extension`        | `name` (string), `attributes`, `content` (string or null) |
+//! | `external_link`    | `target`, `content`                                  |
+//! | `generated`        | `value` (string)                                     |
+//! | `heading`          | `level` (1–6), `content`                             |
+//! | `horizontal_rule`  | `line_content` (bool)                                |
+//! | `lang_variant`     | `flags`, `variants`, `raw` (bool)                    |
+//! | `link`             | `target`, `arguments`, `trail` (string or null)      |
+//! | `list_item`        | `bullets` (string), `content`                        |
+//! | `new_line`         |                                                      |
+//! | `parameter`        | `name`, `default` (list or null)                     |
+//! | `redirect`         | `link` (node)                                        |
+//! | `start_annotation` | `name` (string), `attributes`                        |
+//! | `start_include`    | `mode`                                               |
+//! | `start_tag`        | `name` (string), `attributes`, `self_closing` (bool) |
+//! | `strip_marker`     | `marker` (string)                                    |
+//! | `table_*`          | `attributes` (except `table_end`)                    |
+//! | `template`         | `target`, `arguments`                                |
+//! | `text`             |                                                      |
+//! | `text_style`       | `style` (`bold`, `bold_italic`, `italic`)            |
+//!
+//! `attributes` and `arguments` are lists of `argument` nodes. Annotation
+//! attributes are `annotation_attribute` nodes with a string `name` and a
+//! nullable string `value`.
+//!
+//! `lang_variant` `flags` is null, `{ "combined": [string, …] }`, or
+//! `{ "common": [string, …] }`, sorted. `variants` is a list of nodes with a
+//! type of `lang_text` (`content`), `lang_one_way` (`from`, `lang` (node),
+//! `to`), `lang_two_way` (`lang` (node), `content`), or `lang_empty`.
+
+use super::{
+    AnnoAttribute, Argument, FileMap, InclusionMode, LangFlags, LangVariant, Output, Span, Spanned,
+    TextStyle, Token,
+};
+use serde::ser::{Serialize, SerializeMap as _, SerializeSeq as _, Serializer};
+
+/// The current schema version.
+pub(crate) const VERSION: u32 = 1;
+
+/// Returns a serialisable view of the given parser output.
+pub(crate) fn to_json<'a>(source: &'a FileMap<'a>, output: &'a Output) -> Document<'a> {
+    Document { output, source }
+}
+
+/// A serialisable parser output document.
+pub(crate) struct Document<'a> {
+    /// The parser output.
+    output: &'a Output,
+    /// The source text.
+    source: &'a FileMap<'a>,
+}
+
+impl Serialize for Document<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(3))?;
+        map.serialize_entry("version", &VERSION)?;
+        map.serialize_entry("has_onlyinclude", &self.output.has_onlyinclude)?;
+        map.serialize_entry("root", &Nodes(self.source, &self.output.root))?;
+        map.end()
+    }
+}
+
+/// A serialisable list of nodes.
+struct Nodes<'a, T>(&'a FileMap<'a>, &'a [Spanned<T>]);
+
+impl<'a, T> Serialize for Nodes<'a, T>
+where
+    Node<'a, T>: Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.1.len()))?;
+        for node in self.1 {
+            seq.serialize_element(&Node(self.0, node))?;
+        }
+        seq.end()
+    }
+}
+
+/// A serialisable node.
+struct Node<'a, T>(&'a FileMap<'a>, &'a Spanned<T>);
+
+impl<T> Node<'_, T> {
+    /// Starts serialising a node of the given type, writing the common
+    /// properties.
+    fn begin<S: Serializer>(&self, serializer: S, kind: &str) -> Result<S::SerializeMap, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("type", kind)?;
+        map.serialize_entry("span", &SpanJson(self.0, self.1.span))?;
+        map.serialize_entry("source", self.text(self.1.span))?;
+        Ok(map)
+    }
+
+    /// Returns the source text for the given span.
+    fn text(&self, span: Span) -> &str {
+        &self.0[span.into_range()]
+    }
+
+    /// Returns a serialisable list of child tokens.
+    fn tokens<'b>(&'b self, tokens: &'b [Spanned<Token>]) -> Nodes<'b, Token> {
+        Nodes(self.0, tokens)
+    }
+
+    /// Returns a serialisable list of child arguments.
+    fn arguments<'b>(&'b self, arguments: &'b [Spanned<Argument>]) -> Nodes<'b, Argument> {
+        Nodes(self.0, arguments)
+    }
+}
+
+impl Serialize for Node<'_, Argument> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = self.begin(serializer, "argument")?;
+        map.serialize_entry("name", &self.1.name().map(|name| self.tokens(name)))?;
+        map.serialize_entry("value", &self.tokens(self.1.value()))?;
+        map.end()
+    }
+}
+
+impl Serialize for Node<'_, AnnoAttribute> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let AnnoAttribute { name, value } = &self.1.node;
+        let mut map = self.begin(serializer, "annotation_attribute")?;
+        map.serialize_entry("name", name.either(|name| name, |name| self.text(name)))?;
+        map.serialize_entry("value", &value.map(|value| self.text(value)))?;
+        map.end()
+    }
+}
+
+impl Serialize for Node<'_, LangVariant> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match &self.1.node {
+            LangVariant::Empty => self.begin(serializer, "lang_empty")?.end(),
+            LangVariant::OneWay { from, lang, to } => {
+                let mut map = self.begin(serializer, "lang_one_way")?;
+                map.serialize_entry("from", &self.tokens(from))?;
+                map.serialize_entry("lang", &Node(self.0, &**lang))?;
+                map.serialize_entry("to", &self.tokens(to))?;
+                map.end()
+            }
+            LangVariant::Text { text } => {
+                let mut map = self.begin(serializer, "lang_text")?;
+                map.serialize_entry("content", &self.tokens(text))?;
+                map.end()
+            }
+            LangVariant::TwoWay { lang, text } => {
+                let mut map = self.begin(serializer, "lang_two_way")?;
+                map.serialize_entry("lang", &Node(self.0, lang))?;
+                map.serialize_entry("content", &self.tokens(text))?;
+                map.end()
+            }
+        }
+    }
+}
+
+impl Serialize for Node<'_, Token> {
+    #[expect(clippy::too_many_lines, reason = "this is just a big switch")]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match &self.1.node {
+            Token::Autolink { target, content } => {
+                let mut map = self.begin(serializer, "autolink")?;
+                map.serialize_entry("target", &self.tokens(target))?;
+                map.serialize_entry("content", &self.tokens(content))?;
+                map.end()
+            }
+            Token::BehaviorSwitch { name } => {
+                let mut map = self.begin(serializer, "behavior_switch")?;
+                map.serialize_entry("name", name)?;
+                map.end()
+            }
+            Token::Comment { content, unclosed } => {
+                let mut map = self.begin(serializer, "comment")?;
+                map.serialize_entry("content", self.text(*content))?;
+                map.serialize_entry("unclosed", unclosed)?;
+                map.end()
+            }
+            Token::EndAnnotation { name } => {
+                let mut map = self.begin(serializer, "end_annotation")?;
+                map.serialize_entry("name", name.either(|name| name, |name| self.text(name)))?;
+                map.end()
+            }
+            Token::EndInclude(mode) => {
+                let mut map = self.begin(serializer, "end_include")?;
+                map.serialize_entry("mode", inclusion_mode(*mode))?;
+                map.end()
+            }
+            Token::EndTag { name } => {
+                let mut map = self.begin(serializer, "end_tag")?;
+                map.serialize_entry("name", self.text(*name))?;
+                map.end()
+            }
+            Token::Entity { value } => {
+                let mut map = self.begin(serializer, "entity")?;
+                map.serialize_entry("value", value)?;
+                map.end()
+            }
+            Token::Extension {
+                name,
+                attributes,
+                content,
+            } => {
+                let mut map = self.begin(serializer, "extension")?;
+                map.serialize_entry("name", self.text(*name))?;
+                map.serialize_entry("attributes", &self.arguments(attributes))?;
+                map.serialize_entry("content", &content.map(|content| self.text(content)))?;
+                map.end()
+            }
+            Token::ExternalLink { target, content } => {
+                let mut map = self.begin(serializer, "external_link")?;
+                map.serialize_entry("target", &self.tokens(target))?;
+                map.serialize_entry("content", &self.tokens(content))?;
+                map.end()
+            }
+            Token::Generated(value) => {
+                let mut map = self.begin(serializer, "generated")?;
+                map.serialize_entry("value", value)?;
+                map.end()
+            }
+            Token::Heading { level, content } => {
+                let mut map = self.begin(serializer, "heading")?;
+                map.serialize_entry("level", &u8::from(*level))?;
+                map.serialize_entry("content", &self.tokens(content))?;
+                map.end()
+            }
+            Token::HorizontalRule { line_content } => {
+                let mut map = self.begin(serializer, "horizontal_rule")?;
+                map.serialize_entry("line_content", line_content)?;
+                map.end()
+            }
+            Token::LangVariant {
+                flags,
+                variants,
+                raw,
+            } => {
+                let mut map = self.begin(serializer, "lang_variant")?;
+                map.serialize_entry(
+                    "flags",
+                    &flags.as_ref().map(|flags| FlagsJson(self.0, flags)),
+                )?;
+                map.serialize_entry("variants", &Nodes(self.0, variants))?;
+                map.serialize_entry("raw", raw)?;
+                map.end()
+            }
+            Token::Link {
+                target,
+                content,
+                trail,
+            } => {
+                let mut map = self.begin(serializer, "link")?;
+                map.serialize_entry("target", &self.tokens(target))?;
+                map.serialize_entry("arguments", &self.arguments(content))?;
+                map.serialize_entry("trail", &trail.map(|trail| self.text(trail)))?;
+                map.end()
+            }
+            Token::ListItem { bullets, content } => {
+                let mut map = self.begin(serializer, "list_item")?;
+                map.serialize_entry("bullets", self.text(*bullets))?;
+                map.serialize_entry("content", &self.tokens(content))?;
+                map.end()
+            }
+            Token::NewLine => self.begin(serializer, "new_line")?.end(),
+            Token::Parameter { name, default } => {
+                let mut map = self.begin(serializer, "parameter")?;
+                map.serialize_entry("name", &self.tokens(name))?;
+                map.serialize_entry(
+                    "default",
+                    &default.as_deref().map(|default| self.tokens(default)),
+                )?;
+                map.end()
+            }
+            Token::Redirect { link } => {
+                let mut map = self.begin(serializer, "redirect")?;
+                map.serialize_entry("link", &Node(self.0, &**link))?;
+                map.end()
+            }
+            Token::StartAnnotation { name, attributes } => {
+                let mut map = self.begin(serializer, "start_annotation")?;
+                map.serialize_entry("name", self.text(*name))?;
+                map.serialize_entry("attributes", &Nodes(self.0, attributes))?;
+                map.end()
+            }
+            Token::StartInclude(mode) => {
+                let mut map = self.begin(serializer, "start_include")?;
+                map.serialize_entry("mode", inclusion_mode(*mode))?;
+                map.end()
+            }
+            Token::StartTag {
+                name,
+                attributes,
+                self_closing,
+            } => {
+                let mut map = self.begin(serializer, "start_tag")?;
+                map.serialize_entry("name", self.text(*name))?;
+                map.serialize_entry("attributes", &self.arguments(attributes))?;
+                map.serialize_entry("self_closing", self_closing)?;
+                map.end()
+            }
+            Token::StripMarker(marker) => {
+                let mut map = self.begin(serializer, "strip_marker")?;
+                map.serialize_entry("marker", self.text(*marker))?;
+                map.end()
+            }
+            Token::TableCaption { attributes } => {
+                self.table(serializer, "table_caption", attributes)
+            }
+            Token::TableData { attributes } => self.table(serializer, "table_data", attributes),
+            Token::TableEnd => self.begin(serializer, "table_end")?.end(),
+            Token::TableHeading { attributes } => {
+                self.table(serializer, "table_heading", attributes)
+            }
+            Token::TableRow { attributes } => self.table(serializer, "table_row", attributes),
+            Token::TableStart { attributes } => self.table(serializer, "table_start", attributes),
+            Token::Template { target, arguments } => {
+                let mut map = self.begin(serializer, "template")?;
+                map.serialize_entry("target", &self.tokens(target))?;
+                map.serialize_entry("arguments", &self.arguments(arguments))?;
+                map.end()
+            }
+            Token::Text => self.begin(serializer, "text")?.end(),
+            Token::TextStyle(style) => {
+                let mut map = self.begin(serializer, "text_style")?;
+                map.serialize_entry(
+                    "style",
+                    match style {
+                        TextStyle::Bold(..) => "bold",
+                        TextStyle::BoldItalic => "bold_italic",
+                        TextStyle::Italic => "italic",
+                    },
+                )?;
+                map.end()
+            }
+        }
+    }
+}
+
+impl Node<'_, Token> {
+    /// Serialises a table part with attributes.
+    fn table<S: Serializer>(
+        &self,
+        serializer: S,
+        kind: &str,
+        attributes: &[Spanned<Argument>],
+    ) -> Result<S::Ok, S::Error> {
+        let mut map = self.begin(serializer, kind)?;
+        map.serialize_entry("attributes", &self.arguments(attributes))?;
+        map.end()
+    }
+}
+
+/// A serialisable [`LangFlags`].
+struct FlagsJson<'a>(&'a FileMap<'a>, &'a LangFlags);
+
+impl Serialize for FlagsJson<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (key, mut flags) = match self.1 {
+            LangFlags::Combined(variants) => (
+                "combined",
+                variants
+                    .iter()
+                    .map(|variant| self.0[variant.into_range()].to_owned())
+                    .collect::<Vec<_>>(),
+            ),
+            LangFlags::Common(flags) => (
+                "common",
+                flags
+                    .iter()
+                    .map(|flag| match *flag {
+                        LangFlags::DOLLAR_S => "$S".to_owned(),
+                        LangFlags::DOLLAR_PLUS => "$+".to_owned(),
+                        flag => flag.to_string(),
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+        };
+        // Hash sets have no stable order, so sort to keep output deterministic
+        flags.sort_unstable();
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(key, &flags)?;
+        map.end()
+    }
+}
+
+/// A serialisable [`Span`] with line and column information.
+struct SpanJson<'a>(&'a FileMap<'a>, Span);
+
+impl Serialize for SpanJson<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("start", &PositionJson(self.0, self.1.start))?;
+        map.serialize_entry("end", &PositionJson(self.0, self.1.end))?;
+        map.end()
+    }
+}
+
+/// A serialisable source position.
+struct PositionJson<'a>(&'a FileMap<'a>, usize);
+
+impl Serialize for PositionJson<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let position = self.0.find_line_col(self.1);
+        let mut map = serializer.serialize_map(Some(3))?;
+        map.serialize_entry("offset", &position.offset)?;
+        map.serialize_entry("line", &position.line)?;
+        map.serialize_entry("column", &position.column)?;
+        map.end()
+    }
+}
+
+/// Returns the tag name for an inclusion mode.
+fn inclusion_mode(mode: InclusionMode) -> &'static str {
+    match mode {
+        InclusionMode::IncludeOnly => "includeonly",
+        InclusionMode::NoInclude => "noinclude",
+        InclusionMode::OnlyInclude => "onlyinclude",
+    }
+}
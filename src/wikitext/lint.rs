@@ -0,0 +1,589 @@
+//! A Wikitext linter, roughly equivalent to the MediaWiki Linter extension.
+//!
+//! Only the page’s own source is linted. Problems which are created by
+//! templates are the responsibility of the templates, and also cannot be
+//! traced back to a location in the page anyway.
+
+use super::{
+    Argument, FileMap, HeadingLevel, Output, Span, Spanned, Token, VOID_TAGS,
+    visit::{self, Visitor},
+};
+use crate::title::{Namespace, Title};
+use core::convert::Infallible;
+use std::collections::{HashMap, hash_map::Entry};
+
+/// A lint category. The names are the same as the ones used by the Linter
+/// extension, where an equivalent exists.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Category {
+    /// More than one caption was given to a media link, so all but the last
+    /// one will disappear.
+    BogusImageOptions,
+    /// The same `id` attribute was used more than once.
+    DuplicateIds,
+    /// Content inside of a table but outside of any cell, which browsers will
+    /// move to before the table.
+    Fostered,
+    /// A tag which was closed by the end tag of a different element.
+    MisnestedTag,
+    /// A tag which was never closed.
+    MissingEndTag,
+    /// A tag which is obsolete in HTML5.
+    ObsoleteTag,
+    /// A non-void tag which was written as self-closing.
+    SelfClosedTag,
+    /// Table markup which is not inside of any table.
+    StrayTableMarkup,
+    /// An end tag with no corresponding start tag.
+    StrippedTag,
+}
+
+impl Category {
+    /// The machine-readable name of the category.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Category::BogusImageOptions => "bogus-image-options",
+            Category::DuplicateIds => "duplicate-ids",
+            Category::Fostered => "fostered",
+            Category::MisnestedTag => "misnested-tag",
+            Category::MissingEndTag => "missing-end-tag",
+            Category::ObsoleteTag => "obsolete-tag",
+            Category::SelfClosedTag => "self-closed-tag",
+            Category::StrayTableMarkup => "stray-table-markup",
+            Category::StrippedTag => "stripped-tag",
+        }
+    }
+}
+
+impl core::fmt::Display for Category {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A 1-indexed position in the source text.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Serialize)]
+pub(crate) struct Position {
+    /// The line number.
+    pub line: usize,
+    /// The column number, in characters.
+    pub column: usize,
+}
+
+/// A single lint problem.
+#[derive(Clone, Debug, serde::Serialize)]
+pub(crate) struct Issue {
+    /// The kind of problem.
+    pub category: Category,
+    /// A human-readable description of the problem.
+    pub detail: String,
+    /// The position of the end of the problem.
+    pub end: Position,
+    /// The byte range of the problem in the source text.
+    pub range: core::ops::Range<usize>,
+    /// The position of the start of the problem.
+    pub start: Position,
+}
+
+/// Lints a parsed Wikitext document, returning all of the problems in source
+/// order.
+pub(crate) fn lint(source: &FileMap<'_>, output: &Output) -> Vec<Issue> {
+    let mut linter = Linter {
+        fostered_depth: 0,
+        ids: HashMap::new(),
+        issues: Vec::new(),
+        source,
+        stack: Vec::new(),
+    };
+    let Ok(()) = linter.visit_output(output);
+    linter.close_until(|_| false);
+
+    let mut issues = linter
+        .issues
+        .into_iter()
+        .map(|(category, span, detail)| {
+            let start = source.find_line_col(span.start);
+            let end = source.find_line_col(span.end);
+            Issue {
+                category,
+                detail,
+                end: Position {
+                    line: end.line,
+                    column: end.column,
+                },
+                range: span.into_range(),
+                start: Position {
+                    line: start.line,
+                    column: start.column,
+                },
+            }
+        })
+        .collect::<Vec<_>>();
+    issues.sort_by_key(|issue| (issue.range.start, issue.category));
+    issues
+}
+
+/// Tags which are obsolete in HTML5 but still allowed by Wikitext.
+static OBSOLETE_TAGS: phf::Set<&str> = phf::phf_set! {
+    "big", "center", "font", "rb", "rtc", "strike", "tt",
+};
+
+/// Tags with optional end tags, which are never reported as unclosed.
+static OPTIONAL_END_TAGS: phf::Set<&str> = phf::phf_set! {
+    "caption", "colgroup", "dd", "dt", "li", "option", "p", "rp", "rt",
+    "tbody", "td", "tfoot", "th", "thead", "tr",
+};
+
+/// Tags which are allowed between the cells of a table.
+static TABLE_TAGS: phf::Set<&str> = phf::phf_set! {
+    "caption", "col", "colgroup", "tbody", "td", "tfoot", "th", "thead", "tr",
+};
+
+/// Media options which are recognised without a value.
+static MEDIA_KEYWORDS: phf::Set<&str> = phf::phf_set! {
+    "baseline", "border", "bottom", "center", "frame", "framed", "frameless",
+    "left", "loop", "middle", "muted", "none", "right", "sub", "super",
+    "text-bottom", "text-top", "thumb", "thumbnail", "top", "upright",
+};
+
+/// Media options which are recognised with a value.
+static MEDIA_NAMED: phf::Set<&str> = phf::phf_set! {
+    "alt", "border", "class", "lang", "link", "lossy", "page", "start",
+    "thumbtime", "upright",
+};
+
+/// An open element.
+#[derive(Debug)]
+enum Open {
+    /// A block which implicitly closes any tags opened inside of it, like a
+    /// heading or list item.
+    Barrier,
+    /// An HTML tag.
+    Tag {
+        /// The lowercased tag name.
+        name: String,
+        /// The location of the start tag.
+        span: Span,
+    },
+    /// A Wikitext table.
+    Table {
+        /// Whether a cell or caption is currently open.
+        in_cell: bool,
+    },
+}
+
+/// The linting visitor.
+struct Linter<'tt> {
+    /// While greater than zero, content is already inside of something which
+    /// was reported as fostered.
+    fostered_depth: usize,
+    /// All `id` attributes seen so far.
+    ids: HashMap<&'tt str, Span>,
+    /// The collected issues.
+    issues: Vec<(Category, Span, String)>,
+    /// The source text.
+    source: &'tt FileMap<'tt>,
+    /// The currently open elements.
+    stack: Vec<Open>,
+}
+
+impl<'tt> Linter<'tt> {
+    /// Records duplicate `id` attributes.
+    fn check_ids(&mut self, attributes: &'tt [Spanned<Argument>]) {
+        for attribute in attributes {
+            if let Some(name) = attribute.name()
+                && let Some(name) = self.plain_text(name)
+                && name.trim_ascii().eq_ignore_ascii_case("id")
+                && let Some(value) = self.plain_text(attribute.value())
+                && let value = value.trim_ascii()
+                && !value.is_empty()
+            {
+                match self.ids.entry(value) {
+                    Entry::Occupied(first) => {
+                        let line = self.source.find_line_col(first.get().start).line;
+                        self.issues.push((
+                            Category::DuplicateIds,
+                            attribute.span,
+                            format!("id “{value}” was already used on line {line}"),
+                        ));
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(attribute.span);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks for more than one caption in a media link.
+    fn check_media_options(&mut self, content: &'tt [Spanned<Argument>]) {
+        let mut captions = Vec::new();
+        for argument in content {
+            if let Some(name) = argument.name() {
+                if let Some(name) = self.plain_text(name)
+                    && MEDIA_NAMED.contains(name.trim_ascii())
+                {
+                    continue;
+                }
+            } else if let Some(value) = self.plain_text(argument.value()) {
+                let value = value.trim_ascii();
+                if MEDIA_KEYWORDS.contains(value)
+                    || value.ends_with("px")
+                    || value.starts_with("upright ")
+                {
+                    continue;
+                }
+            } else {
+                // It is a template or something; who knows what it will be?
+                continue;
+            }
+            captions.push(argument.span);
+        }
+
+        if let Some((_, bogus)) = captions.split_last() {
+            for span in bogus {
+                self.issues.push((
+                    Category::BogusImageOptions,
+                    *span,
+                    format!(
+                        "“{}” will be replaced by a later caption",
+                        &self.source[span.into_range()]
+                    ),
+                ));
+            }
+        }
+    }
+
+    /// Pops open elements until `stop` returns true, reporting any unclosed
+    /// tags. The element which stopped the loop is not removed.
+    fn close_until(&mut self, stop: impl Fn(&Open) -> bool) {
+        while let Some(open) = self.stack.last() {
+            if stop(open) {
+                break;
+            }
+            if let Some(Open::Tag { name, span }) = self.stack.pop() {
+                self.issues.push((
+                    Category::MissingEndTag,
+                    span,
+                    format!("<{name}> is never closed"),
+                ));
+            }
+        }
+    }
+
+    /// Checks for content after a table start or row that was discarded by the
+    /// parser, which merges the discarded content into the span of the table
+    /// token instead of emitting it.
+    fn check_discarded(&mut self, span: Span) {
+        let text = &self.source[span.into_range()];
+        let Some(mut pos) = text.find('\n') else {
+            return;
+        };
+
+        while pos < text.len() {
+            let rest = &text[pos..];
+            let line_end = rest.find('\n').map_or(text.len(), |end| pos + end);
+            if rest.starts_with(|c: char| c.is_ascii_whitespace()) {
+                pos += 1;
+            } else if rest.starts_with("<!--") {
+                pos = rest.find("-->").map_or(text.len(), |end| pos + end + 3);
+            } else if rest.starts_with("|-") {
+                // An earlier empty row that was merged into a later one
+                pos = line_end;
+            } else {
+                let start = span.start + pos;
+                let end = span.start + pos + text[pos..line_end].trim_ascii_end().len();
+                let content = &self.source[start..end];
+                let limit = content.floor_char_boundary(40);
+                self.issues.push((
+                    Category::Fostered,
+                    Span::new(start, end),
+                    format!(
+                        "“{}{}” is inside of a table but not inside of a cell",
+                        &content[..limit],
+                        if limit < content.len() { "…" } else { "" }
+                    ),
+                ));
+                pos = line_end;
+            }
+        }
+    }
+
+    /// Finds the index of the innermost table which is still open in the
+    /// current block.
+    fn find_table(&self) -> Option<usize> {
+        self.stack
+            .iter()
+            .rposition(|open| matches!(open, Open::Table { .. } | Open::Barrier))
+            .filter(|index| matches!(self.stack[*index], Open::Table { .. }))
+    }
+
+    /// Returns true if new content would be placed inside of a table but
+    /// outside of a cell.
+    fn is_fostering(&self) -> bool {
+        self.fostered_depth == 0
+            && matches!(self.stack.last(), Some(Open::Table { in_cell: false }))
+    }
+
+    /// Returns the source text of a list of tokens, if it contains only text.
+    fn plain_text(&self, tokens: &[Spanned<Token>]) -> Option<&'tt str> {
+        if tokens.iter().all(|token| matches!(token.node, Token::Text)) {
+            Some(match (tokens.first(), tokens.last()) {
+                (Some(first), Some(last)) => &self.source[first.span.start..last.span.end],
+                _ => "",
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Handles a table cell, caption, or row.
+    fn table_part(&mut self, span: Span, in_cell: bool) {
+        if let Some(index) = self.find_table() {
+            self.close_until(|open| matches!(open, Open::Table { .. }));
+            self.stack[index] = Open::Table { in_cell };
+        } else {
+            self.issues.push((
+                Category::StrayTableMarkup,
+                span,
+                format!(
+                    "“{}” is not inside of a table",
+                    self.source[span.into_range()].trim_ascii()
+                ),
+            ));
+        }
+    }
+
+    /// Visits the content of a block which implicitly closes its tags.
+    fn visit_block(&mut self, content: &'tt [Spanned<Token>]) -> Result<(), Infallible> {
+        self.stack.push(Open::Barrier);
+        self.visit_tokens(content)?;
+        self.close_until(|open| matches!(open, Open::Barrier));
+        self.stack.pop();
+        Ok(())
+    }
+}
+
+impl<'tt> Visitor<'tt, Infallible> for Linter<'tt> {
+    fn source(&self) -> &'tt str {
+        self.source
+    }
+
+    fn visit_end_tag(&mut self, span: Span, name: &'tt str) -> Result<(), Infallible> {
+        let name = name.to_ascii_lowercase();
+        if VOID_TAGS.contains(name.as_str()) || OPTIONAL_END_TAGS.contains(name.as_str()) {
+            return Ok(());
+        }
+
+        let found = self
+            .stack
+            .iter()
+            .rposition(|open| match open {
+                Open::Tag { name: open, .. } => *open == name,
+                Open::Barrier | Open::Table { .. } => true,
+            })
+            .filter(|index| matches!(self.stack[*index], Open::Tag { .. }));
+
+        if let Some(index) = found {
+            for open in self.stack.drain(index + 1..) {
+                if let Open::Tag { name: inner, span } = open {
+                    self.issues.push((
+                        Category::MisnestedTag,
+                        span,
+                        format!("<{inner}> is closed by </{name}>"),
+                    ));
+                }
+            }
+            self.stack.pop();
+        } else {
+            self.issues.push((
+                Category::StrippedTag,
+                span,
+                format!("</{name}> has no start tag"),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn visit_heading(
+        &mut self,
+        _span: Span,
+        _level: HeadingLevel,
+        content: &'tt [Spanned<Token>],
+    ) -> Result<(), Infallible> {
+        self.visit_block(content)
+    }
+
+    fn visit_link(
+        &mut self,
+        span: Span,
+        target: &'tt [Spanned<Token>],
+        content: &'tt [Spanned<Argument>],
+        trail: Option<&'tt str>,
+    ) -> Result<(), Infallible> {
+        if let Some(target) = self.plain_text(target)
+            && Title::new(target, None).namespace().id == Namespace::FILE
+        {
+            self.check_media_options(content);
+        }
+        visit::visit_link(self, span, target, content, trail)
+    }
+
+    fn visit_list_item(
+        &mut self,
+        _span: Span,
+        _bullets: &'tt str,
+        content: &'tt [Spanned<Token>],
+    ) -> Result<(), Infallible> {
+        self.visit_block(content)
+    }
+
+    fn visit_start_tag(
+        &mut self,
+        span: Span,
+        name: &str,
+        attributes: &'tt [Spanned<Argument>],
+        self_closing: bool,
+    ) -> Result<(), Infallible> {
+        let name = name.to_ascii_lowercase();
+        self.check_ids(attributes);
+
+        if OBSOLETE_TAGS.contains(name.as_str()) {
+            self.issues
+                .push((Category::ObsoleteTag, span, format!("<{name}> is obsolete")));
+        }
+
+        if !TABLE_TAGS.contains(name.as_str()) && self.is_fostering() {
+            self.issues.push((
+                Category::Fostered,
+                span,
+                format!("<{name}> is inside of a table but not inside of a cell"),
+            ));
+        }
+
+        if VOID_TAGS.contains(name.as_str()) {
+            return Ok(());
+        }
+
+        if self_closing {
+            self.issues.push((
+                Category::SelfClosedTag,
+                span,
+                format!("<{name}/> is not a void element, so it is treated as <{name}>"),
+            ));
+        }
+
+        if !OPTIONAL_END_TAGS.contains(name.as_str()) {
+            self.stack.push(Open::Tag { name, span });
+        }
+
+        Ok(())
+    }
+
+    fn visit_table_caption(
+        &mut self,
+        span: Span,
+        attributes: &'tt [Spanned<Argument>],
+    ) -> Result<(), Infallible> {
+        self.check_ids(attributes);
+        self.table_part(span, true);
+        Ok(())
+    }
+
+    fn visit_table_data(
+        &mut self,
+        span: Span,
+        attributes: &'tt [Spanned<Argument>],
+    ) -> Result<(), Infallible> {
+        self.check_ids(attributes);
+        self.table_part(span, true);
+        Ok(())
+    }
+
+    fn visit_table_end(&mut self, span: Span) -> Result<(), Infallible> {
+        if self.find_table().is_some() {
+            self.close_until(|open| matches!(open, Open::Table { .. }));
+            self.stack.pop();
+        } else {
+            self.issues.push((
+                Category::StrayTableMarkup,
+                span,
+                "“|}” is not inside of a table".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn visit_table_heading(
+        &mut self,
+        span: Span,
+        attributes: &'tt [Spanned<Argument>],
+    ) -> Result<(), Infallible> {
+        self.check_ids(attributes);
+        self.table_part(span, true);
+        Ok(())
+    }
+
+    fn visit_table_row(
+        &mut self,
+        span: Span,
+        attributes: &'tt [Spanned<Argument>],
+    ) -> Result<(), Infallible> {
+        self.check_ids(attributes);
+        self.table_part(span, false);
+        self.check_discarded(span);
+        Ok(())
+    }
+
+    fn visit_table_start(
+        &mut self,
+        span: Span,
+        attributes: &'tt [Spanned<Argument>],
+    ) -> Result<(), Infallible> {
+        self.check_ids(attributes);
+        if self.is_fostering() {
+            self.issues.push((
+                Category::Fostered,
+                span,
+                "table is inside of a table but not inside of a cell".to_owned(),
+            ));
+        }
+        self.stack.push(Open::Table { in_cell: false });
+        self.check_discarded(span);
+        Ok(())
+    }
+
+    fn visit_token(&mut self, token: &'tt Spanned<Token>) -> Result<(), Infallible> {
+        let fostered = self.is_fostering()
+            && match &token.node {
+                Token::Text => !self.source[token.span.into_range()].trim_ascii().is_empty(),
+                Token::Link { target, .. } => self.plain_text(target).is_none_or(|target| {
+                    Title::new(target, None).namespace().id != Namespace::CATEGORY
+                }),
+                Token::Autolink { .. } | Token::Entity { .. } | Token::ExternalLink { .. } => true,
+                _ => false,
+            };
+
+        if fostered {
+            let text = self.source[token.span.into_range()].trim_ascii();
+            let limit = text.floor_char_boundary(40);
+            self.issues.push((
+                Category::Fostered,
+                token.span,
+                format!(
+                    "“{}{}” is inside of a table but not inside of a cell",
+                    &text[..limit],
+                    if limit < text.len() { "…" } else { "" }
+                ),
+            ));
+            self.fostered_depth += 1;
+            visit::visit_token(self, token)?;
+            self.fostered_depth -= 1;
+            Ok(())
+        } else {
+            visit::visit_token(self, token)
+        }
+    }
+}
@@ -14,7 +14,10 @@ mod codemap;
 mod config;
 pub(crate) mod helpers;
 mod inspectors;
+pub(crate) mod json;
+pub(crate) mod lint;
 mod parser;
+pub(crate) mod serializer;
 #[cfg(test)]
 mod tests;
 pub(crate) mod visit;
@@ -0,0 +1,642 @@
+//! Serialisation of token trees back into Wikitext.
+//!
+//! The serialiser is given the source text, the tree that the parser originally
+//! produced from it, and a tree to serialise, which is normally a modified copy
+//! of the original tree. Any node which is unchanged from the original tree is
+//! emitted as its original source text, so serialising an unmodified tree gives
+//! back the original input byte-for-byte.
+//!
+//! Nodes which were changed are rebuilt from their parts. The syntax between
+//! the parts (`{{`, `|`, `]]`, whitespace between attributes, and so on) is
+//! copied from the source if it is still intact, and otherwise replaced with
+//! the canonical syntax for that kind of node. The content between two nodes
+//! which were neighbours in the original tree is always copied as-is, which is
+//! how things that the parser discards (like extra template parameter default
+//! values) survive a round trip.
+//!
+//! New nodes should be made with the [`builder`](super::builder) helpers,
+//! which append the new text to the end of the source so the new spans are
+//! valid. Replacing text with [`Token::Generated`] also works.
+
+use super::{Argument, LangFlags, LangVariant, Output, Span, Spanned, Token};
+use std::{borrow::Cow, collections::HashMap};
+
+/// Serialises `tokens` using the source text and original parser output that
+/// the tokens were derived from.
+pub(crate) fn serialize(source: &str, original: &Output, tokens: &[Spanned<Token>]) -> String {
+    Serializer::new(source, original).serialize(tokens)
+}
+
+/// A Wikitext serialiser.
+pub(crate) struct Serializer<'a> {
+    /// Unmodified arguments.
+    arguments: Originals<'a, Argument>,
+    /// The end of the original document.
+    end: usize,
+    /// The output buffer.
+    out: String,
+    /// The source text.
+    source: &'a str,
+    /// Unmodified tokens.
+    tokens: Originals<'a, Token>,
+}
+
+impl<'a> Serializer<'a> {
+    /// Creates a new serialiser for trees derived from the given original
+    /// parser output.
+    pub fn new(source: &'a str, original: &'a Output) -> Self {
+        let mut serializer = Self {
+            arguments: Originals::default(),
+            end: original.root.last().map_or(0, |token| token.span.end),
+            out: String::new(),
+            source,
+            tokens: Originals::default(),
+        };
+        serializer.index_tokens(&original.root);
+        serializer
+    }
+
+    /// Serialises a root token list.
+    pub fn serialize(&mut self, tokens: &[Spanned<Token>]) -> String {
+        self.out.clear();
+        let mut parts = Vec::with_capacity(tokens.len() + 1);
+        push_tokens(&mut parts, tokens);
+        parts.push(Part::Glue(Glue::Exact(Cow::Borrowed(""))));
+        self.write_parts(Span::new(0, self.end), &parts);
+        core::mem::take(&mut self.out)
+    }
+
+    /// Records all arguments in the original tree.
+    fn index_arguments(&mut self, arguments: &'a [Spanned<Argument>]) {
+        self.arguments.insert(arguments);
+        for argument in arguments {
+            self.index_tokens(&argument.content);
+        }
+    }
+
+    /// Records all tokens in the original tree.
+    fn index_tokens(&mut self, tokens: &'a [Spanned<Token>]) {
+        self.tokens.insert(tokens);
+        for token in tokens {
+            match &token.node {
+                Token::Autolink { target, content } | Token::ExternalLink { target, content } => {
+                    self.index_tokens(target);
+                    self.index_tokens(content);
+                }
+                Token::Extension { attributes, .. }
+                | Token::StartTag { attributes, .. }
+                | Token::TableCaption { attributes }
+                | Token::TableData { attributes }
+                | Token::TableHeading { attributes }
+                | Token::TableRow { attributes }
+                | Token::TableStart { attributes } => self.index_arguments(attributes),
+                Token::Heading { content, .. } | Token::ListItem { content, .. } => {
+                    self.index_tokens(content);
+                }
+                Token::LangVariant { variants, .. } => {
+                    for variant in variants {
+                        match &variant.node {
+                            LangVariant::Empty => {}
+                            LangVariant::OneWay { from, lang, to } => {
+                                self.index_tokens(from);
+                                self.index_tokens(core::slice::from_ref(&**lang));
+                                self.index_tokens(to);
+                            }
+                            LangVariant::Text { text } => self.index_tokens(text),
+                            LangVariant::TwoWay { lang, text } => {
+                                self.index_tokens(core::slice::from_ref(lang));
+                                self.index_tokens(text);
+                            }
+                        }
+                    }
+                }
+                Token::Link {
+                    target, content, ..
+                } => {
+                    self.index_tokens(target);
+                    self.index_arguments(content);
+                }
+                Token::Parameter { name, default } => {
+                    self.index_tokens(name);
+                    if let Some(default) = default {
+                        self.index_tokens(default);
+                    }
+                }
+                Token::Redirect { link } => self.index_tokens(core::slice::from_ref(&**link)),
+                Token::Template { target, arguments } => {
+                    self.index_tokens(target);
+                    self.index_arguments(arguments);
+                }
+                Token::BehaviorSwitch { .. }
+                | Token::Comment { .. }
+                | Token::EndAnnotation { .. }
+                | Token::EndInclude(..)
+                | Token::EndTag { .. }
+                | Token::Entity { .. }
+                | Token::Generated(..)
+                | Token::HorizontalRule { .. }
+                | Token::NewLine
+                | Token::StartAnnotation { .. }
+                | Token::StartInclude(..)
+                | Token::StripMarker(..)
+                | Token::TableEnd
+                | Token::Text
+                | Token::TextStyle(..) => {}
+            }
+        }
+    }
+
+    /// Returns true if the two parts were next to each other in the original
+    /// tree and are both unmodified.
+    fn is_adjacent(&self, prev: &Part<'_>, next: &Part<'_>) -> bool {
+        match (prev, next) {
+            (Part::Token(prev), Part::Token(next)) => {
+                self.tokens.next_of(prev) == Some(Some(next.span)) && self.tokens.contains(next)
+            }
+            (Part::Argument(prev), Part::Argument(next)) => {
+                self.arguments.next_of(prev) == Some(Some(next.span))
+                    && self.arguments.contains(next)
+            }
+            _ => false,
+        }
+    }
+
+    /// Writes an argument.
+    fn write_argument(&mut self, argument: &Spanned<Argument>) {
+        if self.arguments.contains(argument) {
+            self.out.push_str(&self.source[argument.span.into_range()]);
+        } else {
+            let mut parts = Vec::with_capacity(argument.content.len() + 1);
+            push_tokens(&mut parts, &argument.content);
+            parts.push(Part::Glue(Glue::Exact(Cow::Borrowed(""))));
+            self.write_parts(argument.span, &parts);
+        }
+    }
+
+    /// Writes a list of parts making up a node with the given span.
+    ///
+    /// Glue between parts is copied from the source if it is intact, and
+    /// otherwise the canonical form of the glue is used. Parts which are not
+    /// separated by glue are treated as if they are separated by empty glue.
+    fn write_parts(&mut self, span: Span, parts: &[Part<'_>]) {
+        // The end of the previous part, which is the start of the next gap.
+        let mut cursor = span.start;
+        let mut prev = None;
+        let mut index = 0;
+        while index < parts.len() {
+            let (glue, next_index) = match &parts[index] {
+                Part::Glue(glue) => (Some(glue), index + 1),
+                _ => (None, index),
+            };
+
+            let next = parts.get(next_index);
+            if let (Some(glue), Some(Part::Glue(_))) = (glue, next) {
+                // Only the last of several consecutive glues can take the gap
+                self.out.push_str(glue.canonical());
+                index = next_index;
+                continue;
+            }
+
+            let next_start = next.and_then(Part::start).unwrap_or(span.end);
+
+            let gap = (span.start <= cursor && cursor <= next_start && next_start <= span.end)
+                .then(|| self.source.get(cursor..next_start))
+                .flatten();
+
+            let adjacent =
+                matches!((prev, next), (Some(prev), Some(next)) if self.is_adjacent(prev, next));
+            match (gap, glue) {
+                (Some(gap), _) if adjacent => self.out.push_str(gap),
+                (Some(gap), Some(glue)) if glue.is_intact(gap) => self.out.push_str(gap),
+                (Some(gap), None) if is_trivia(gap) => self.out.push_str(gap),
+                (_, Some(glue)) => self.out.push_str(glue.canonical()),
+                (_, None) => {}
+            }
+
+            let Some(next) = next else {
+                break;
+            };
+
+            self.write_part(next);
+            cursor = next.end().unwrap_or(cursor);
+            prev = Some(next);
+            index = next_index + 1;
+        }
+    }
+
+    /// Writes a single non-glue part.
+    fn write_part(&mut self, part: &Part<'_>) {
+        match part {
+            Part::Argument(argument) => self.write_argument(argument),
+            Part::Glue(glue) => self.out.push_str(glue.canonical()),
+            Part::Span(span) => self.out.push_str(&self.source[span.into_range()]),
+            Part::Token(token) => self.write_token(token),
+        }
+    }
+
+    /// Writes a token.
+    #[expect(clippy::too_many_lines, reason = "this is just a big switch")]
+    fn write_token(&mut self, token: &Spanned<Token>) {
+        if self.tokens.contains(token) {
+            self.out.push_str(&self.source[token.span.into_range()]);
+            return;
+        }
+
+        let mut parts = Vec::new();
+        match &token.node {
+            Token::Autolink { target, content } => {
+                push_tokens(
+                    &mut parts,
+                    if content.is_empty() { target } else { content },
+                );
+            }
+            Token::Comment { content, unclosed } => {
+                parts.push(exact("<!--"));
+                parts.push(Part::Span(*content));
+                parts.push(exact(if *unclosed { "" } else { "-->" }));
+            }
+            Token::EndTag { name } => {
+                parts.push(exact("</"));
+                parts.push(Part::Span(*name));
+                parts.push(exact(">"));
+            }
+            Token::Extension {
+                name,
+                attributes,
+                content,
+            } => {
+                parts.push(exact("<"));
+                parts.push(Part::Span(*name));
+                push_arguments(&mut parts, attributes, " ");
+                if let Some(content) = content {
+                    parts.push(exact(">"));
+                    parts.push(Part::Span(*content));
+                    let name = &self.source[name.into_range()];
+                    parts.push(Part::Glue(Glue::Exact(Cow::Owned(format!("</{name}>")))));
+                } else {
+                    parts.push(exact("/>"));
+                }
+            }
+            Token::ExternalLink { target, content } => {
+                parts.push(exact("["));
+                push_tokens(&mut parts, target);
+                if !content.is_empty() {
+                    parts.push(exact(" "));
+                    push_tokens(&mut parts, content);
+                }
+                parts.push(exact("]"));
+            }
+            Token::Generated(text) => {
+                self.out.push_str(text);
+                return;
+            }
+            Token::Heading { level, content } => {
+                let marker = "=".repeat(u8::from(*level).into());
+                parts.push(Part::Glue(Glue::Exact(Cow::Owned(marker.clone()))));
+                push_tokens(&mut parts, content);
+                parts.push(Part::Glue(Glue::Exact(Cow::Owned(marker))));
+            }
+            Token::LangVariant {
+                flags,
+                variants,
+                raw: _,
+            } => {
+                self.out.push_str("-{");
+                if let Some(flags) = flags {
+                    self.write_lang_flags(flags);
+                    self.out.push('|');
+                }
+                for (index, variant) in variants.iter().enumerate() {
+                    if index != 0 {
+                        self.out.push(';');
+                    }
+                    self.write_variant(variant);
+                }
+                self.out.push_str("}-");
+                return;
+            }
+            Token::Link {
+                target,
+                content,
+                trail,
+            } => {
+                parts.push(exact("[["));
+                push_tokens(&mut parts, target);
+                push_arguments(&mut parts, content, "|");
+                parts.push(exact("]]"));
+                if let Some(trail) = trail {
+                    parts.push(Part::Span(*trail));
+                }
+            }
+            Token::ListItem { bullets, content } => {
+                parts.push(Part::Span(*bullets));
+                push_tokens(&mut parts, content);
+            }
+            Token::Parameter { name, default } => {
+                parts.push(exact("{{{"));
+                push_tokens(&mut parts, name);
+                if let Some(default) = default {
+                    parts.push(exact("|"));
+                    push_tokens(&mut parts, default);
+                }
+                parts.push(exact("}}}"));
+            }
+            Token::Redirect { link } => {
+                parts.push(Part::Glue(Glue::Any("#REDIRECT ")));
+                parts.push(Part::Token(link));
+            }
+            Token::StartTag {
+                name,
+                attributes,
+                self_closing,
+            } => {
+                parts.push(exact("<"));
+                parts.push(Part::Span(*name));
+                push_arguments(&mut parts, attributes, " ");
+                parts.push(exact(if *self_closing { "/>" } else { ">" }));
+            }
+            Token::TableCaption { attributes } => {
+                push_table_part(&mut parts, ("|+", "{}!|+"), attributes, true);
+            }
+            Token::TableData { attributes } => {
+                push_table_part(&mut parts, ("|", "{}!|"), attributes, true);
+            }
+            Token::TableHeading { attributes } => {
+                push_table_part(&mut parts, ("!", "{}!|"), attributes, true);
+            }
+            Token::TableRow { attributes } => {
+                push_table_part(&mut parts, ("|-", "{}!|-"), attributes, false);
+            }
+            Token::TableStart { attributes } => {
+                push_table_part(&mut parts, ("{|", "{}!|"), attributes, false);
+            }
+            Token::Template { target, arguments } => {
+                parts.push(exact("{{"));
+                push_tokens(&mut parts, target);
+                push_arguments(&mut parts, arguments, "|");
+                parts.push(exact("}}"));
+            }
+            Token::BehaviorSwitch { .. }
+            | Token::EndAnnotation { .. }
+            | Token::EndInclude(..)
+            | Token::Entity { .. }
+            | Token::HorizontalRule { .. }
+            | Token::NewLine
+            | Token::StartAnnotation { .. }
+            | Token::StartInclude(..)
+            | Token::StripMarker(..)
+            | Token::TableEnd
+            | Token::Text
+            | Token::TextStyle(..) => {
+                self.out.push_str(&self.source[token.span.into_range()]);
+                return;
+            }
+        }
+
+        self.write_parts(token.span, &parts);
+    }
+
+    /// Writes language conversion flags.
+    fn write_lang_flags(&mut self, flags: &LangFlags) {
+        match flags {
+            LangFlags::Combined(variants) => {
+                let mut variants = variants.iter().copied().collect::<Vec<_>>();
+                variants.sort_unstable_by_key(|span| span.start);
+                for (index, variant) in variants.into_iter().enumerate() {
+                    if index != 0 {
+                        self.out.push(';');
+                    }
+                    self.out.push_str(&self.source[variant.into_range()]);
+                }
+            }
+            LangFlags::Common(flags) => {
+                let mut flags = flags.iter().copied().collect::<Vec<_>>();
+                flags.sort_unstable();
+                for (index, flag) in flags.into_iter().enumerate() {
+                    if index != 0 {
+                        self.out.push(';');
+                    }
+                    match flag {
+                        LangFlags::DOLLAR_S => self.out.push_str("$S"),
+                        LangFlags::DOLLAR_PLUS => self.out.push_str("$+"),
+                        flag => self.out.push(flag),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Writes a language conversion variant.
+    fn write_variant(&mut self, variant: &Spanned<LangVariant>) {
+        let mut parts = Vec::new();
+        match &variant.node {
+            LangVariant::Empty => {}
+            LangVariant::OneWay { from, lang, to } => {
+                push_tokens(&mut parts, from);
+                parts.push(exact("=>"));
+                parts.push(Part::Token(lang));
+                parts.push(exact(":"));
+                push_tokens(&mut parts, to);
+            }
+            LangVariant::Text { text } => push_tokens(&mut parts, text),
+            LangVariant::TwoWay { lang, text } => {
+                parts.push(Part::Token(lang));
+                parts.push(exact(":"));
+                push_tokens(&mut parts, text);
+            }
+        }
+        parts.push(exact(""));
+        self.write_parts(variant.span, &parts);
+    }
+}
+
+/// Nodes from the original tree, keyed by span.
+struct Originals<'a, T> {
+    /// The nodes. Each entry also contains the span of the node which followed
+    /// it in the same list, if one exists.
+    nodes: HashMap<Span, Vec<(&'a Spanned<T>, Option<Span>)>>,
+}
+
+impl<T> Default for Originals<'_, T> {
+    fn default() -> Self {
+        Self {
+            nodes: HashMap::new(),
+        }
+    }
+}
+
+impl<'a, T: PartialEq> Originals<'a, T> {
+    /// Returns true if the node is identical to a node in the original tree.
+    fn contains(&self, node: &Spanned<T>) -> bool {
+        self.next_of(node).is_some()
+    }
+
+    /// Records a list of original nodes.
+    fn insert(&mut self, list: &'a [Spanned<T>]) {
+        for (index, node) in list.iter().enumerate() {
+            let next = list.get(index + 1).map(|next| next.span);
+            self.nodes.entry(node.span).or_default().push((node, next));
+        }
+    }
+
+    /// If the node is identical to a node in the original tree, returns the
+    /// span of the node that followed it.
+    fn next_of(&self, node: &Spanned<T>) -> Option<Option<Span>> {
+        self.nodes.get(&node.span).and_then(|candidates| {
+            candidates
+                .iter()
+                .find_map(|(original, next)| (*original == node).then_some(*next))
+        })
+    }
+}
+
+/// A part of a node which is being rebuilt.
+enum Part<'a> {
+    /// An argument.
+    Argument(&'a Spanned<Argument>),
+    /// Syntax between other parts.
+    Glue(Glue),
+    /// Source text.
+    Span(Span),
+    /// A token.
+    Token(&'a Spanned<Token>),
+}
+
+impl Part<'_> {
+    /// The end of the part in the source text, if it has one.
+    fn end(&self) -> Option<usize> {
+        match self {
+            Part::Argument(argument) => Some(argument.span.end),
+            Part::Glue(_) => None,
+            Part::Span(span) => Some(span.end),
+            Part::Token(token) => Some(token.span.end),
+        }
+    }
+
+    /// The start of the part in the source text, if it has one.
+    fn start(&self) -> Option<usize> {
+        match self {
+            Part::Argument(argument) => Some(argument.span.start),
+            Part::Glue(_) => None,
+            Part::Span(span) => Some(span.start),
+            Part::Token(token) => Some(token.span.start),
+        }
+    }
+}
+
+/// Syntax which appears between the parts of a node.
+enum Glue {
+    /// Any non-empty source text is acceptable.
+    Any(&'static str),
+    /// Source text must be the same as the canonical text, ignoring case,
+    /// whitespace, comments, and inclusion control tags.
+    Exact(Cow<'static, str>),
+    /// Source text must consist only of the given characters, ignoring
+    /// whitespace, comments, and inclusion control tags.
+    Syntax(&'static str, &'static str),
+}
+
+impl Glue {
+    /// The canonical text of the glue.
+    fn canonical(&self) -> &str {
+        match self {
+            Glue::Any(canonical) | Glue::Syntax(canonical, _) => canonical,
+            Glue::Exact(canonical) => &**canonical,
+        }
+    }
+
+    /// Returns true if the given source text is acceptable for this glue.
+    fn is_intact(&self, gap: &str) -> bool {
+        match self {
+            Glue::Any(_) => !gap.is_empty(),
+            Glue::Exact(canonical) => {
+                (canonical.is_empty() || !gap.is_empty())
+                    && strip_trivia(gap).eq_ignore_ascii_case(&strip_trivia(canonical))
+            }
+            Glue::Syntax(_, chars) => {
+                let gap = strip_trivia(gap);
+                !gap.is_empty() && gap.chars().all(|c| chars.contains(c))
+            }
+        }
+    }
+}
+
+/// Tags which may be discarded by the parser between tokens.
+const INCLUSION_TAGS: &[&str] = &[
+    "<includeonly>",
+    "</includeonly>",
+    "<noinclude>",
+    "</noinclude>",
+    "<onlyinclude>",
+    "</onlyinclude>",
+];
+
+/// Returns true if the text contains only whitespace, comments, and inclusion
+/// control tags.
+fn is_trivia(text: &str) -> bool {
+    strip_trivia(text).is_empty()
+}
+
+/// Removes whitespace, comments, and inclusion control tags from the text.
+fn strip_trivia(mut text: &str) -> String {
+    let mut out = String::new();
+    while let Some(c) = text.chars().next() {
+        if c.is_ascii_whitespace() {
+            text = &text[1..];
+        } else if let Some(rest) = text.strip_prefix("<!--") {
+            text = rest.find("-->").map_or("", |end| &rest[end + 3..]);
+        } else if let Some(tag) = INCLUSION_TAGS.iter().find(|tag| {
+            text.get(..tag.len())
+                .is_some_and(|start| start.eq_ignore_ascii_case(tag))
+        }) {
+            text = &text[tag.len()..];
+        } else {
+            out.push(c);
+            text = &text[c.len_utf8()..];
+        }
+    }
+    out
+}
+
+/// Creates a glue part with exact canonical text.
+fn exact(canonical: &'static str) -> Part<'static> {
+    Part::Glue(Glue::Exact(Cow::Borrowed(canonical)))
+}
+
+/// Adds a list of arguments to the parts list, each preceded by the separator.
+fn push_arguments<'a>(
+    parts: &mut Vec<Part<'a>>,
+    arguments: &'a [Spanned<Argument>],
+    separator: &'static str,
+) {
+    for argument in arguments {
+        parts.push(exact(separator));
+        parts.push(Part::Argument(argument));
+    }
+}
+
+/// Adds a table part to the parts list.
+fn push_table_part<'a>(
+    parts: &mut Vec<Part<'a>>,
+    (canonical, chars): (&'static str, &'static str),
+    attributes: &'a [Spanned<Argument>],
+    is_cell: bool,
+) {
+    parts.push(Part::Glue(Glue::Syntax(canonical, chars)));
+    for (index, attribute) in attributes.iter().enumerate() {
+        if index != 0 {
+            parts.push(exact(" "));
+        }
+        parts.push(Part::Argument(attribute));
+    }
+    if is_cell && !attributes.is_empty() {
+        parts.push(exact("|"));
+    } else {
+        parts.push(exact(""));
+    }
+}
+
+/// Adds a list of tokens to the parts list.
+fn push_tokens<'a>(parts: &mut Vec<Part<'a>>, tokens: &'a [Spanned<Token>]) {
+    parts.extend(tokens.iter().map(Part::Token));
+}
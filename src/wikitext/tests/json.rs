@@ -0,0 +1,79 @@
+use super::*;
+use crate::wikitext::json::{VERSION, to_json};
+use serde_json::json;
+
+#[track_caller]
+fn run_json(input: &str) -> serde_json::Value {
+    let source = FileMap::new(input);
+    let output = run_test(input);
+    serde_json::to_value(to_json(&source, &output)).unwrap()
+}
+
+#[test]
+fn json_schema() {
+    assert_eq!(
+        run_json("a\n{{b|c=d}}"),
+        json!({
+            "version": VERSION,
+            "has_onlyinclude": false,
+            "root": [
+                {
+                    "type": "text",
+                    "span": {
+                        "start": { "offset": 0, "line": 1, "column": 1 },
+                        "end": { "offset": 2, "line": 2, "column": 1 },
+                    },
+                    "source": "a\n",
+                },
+                {
+                    "type": "template",
+                    "span": {
+                        "start": { "offset": 2, "line": 2, "column": 1 },
+                        "end": { "offset": 11, "line": 2, "column": 10 },
+                    },
+                    "source": "{{b|c=d}}",
+                    "target": [
+                        {
+                            "type": "text",
+                            "span": {
+                                "start": { "offset": 4, "line": 2, "column": 3 },
+                                "end": { "offset": 5, "line": 2, "column": 4 },
+                            },
+                            "source": "b",
+                        },
+                    ],
+                    "arguments": [
+                        {
+                            "type": "argument",
+                            "span": {
+                                "start": { "offset": 6, "line": 2, "column": 5 },
+                                "end": { "offset": 9, "line": 2, "column": 8 },
+                            },
+                            "source": "c=d",
+                            "name": [
+                                {
+                                    "type": "text",
+                                    "span": {
+                                        "start": { "offset": 6, "line": 2, "column": 5 },
+                                        "end": { "offset": 7, "line": 2, "column": 6 },
+                                    },
+                                    "source": "c",
+                                },
+                            ],
+                            "value": [
+                                {
+                                    "type": "text",
+                                    "span": {
+                                        "start": { "offset": 8, "line": 2, "column": 7 },
+                                        "end": { "offset": 9, "line": 2, "column": 8 },
+                                    },
+                                    "source": "d",
+                                },
+                            ],
+                        },
+                    ],
+                },
+            ],
+        })
+    );
+}
@@ -0,0 +1,57 @@
+use super::*;
+use crate::wikitext::lint::{Category, lint};
+
+#[track_caller]
+fn run_lint(input: &str) -> Vec<Category> {
+    let source = FileMap::new(input);
+    let output = run_test(input);
+    lint(&source, &output)
+        .into_iter()
+        .map(|issue| issue.category)
+        .collect()
+}
+
+#[test]
+fn lint_clean() {
+    assert_eq!(
+        run_lint("<div id=\"a\">''hello''</div>\n{|\n|-\n| cell\n|}"),
+        []
+    );
+}
+
+#[test]
+fn lint_tags() {
+    assert_eq!(
+        run_lint("<div><span>a</div></b>"),
+        [Category::MisnestedTag, Category::StrippedTag]
+    );
+    assert_eq!(
+        run_lint("<center/>"),
+        [
+            Category::MissingEndTag,
+            Category::ObsoleteTag,
+            Category::SelfClosedTag
+        ]
+    );
+}
+
+#[test]
+fn lint_fostered() {
+    assert_eq!(run_lint("{|\nfostered\n|}"), [Category::Fostered]);
+}
+
+#[test]
+fn lint_duplicate_ids() {
+    assert_eq!(
+        run_lint("<span id=a></span><span id=\"a\"></span>"),
+        [Category::DuplicateIds]
+    );
+}
+
+#[test]
+fn lint_media_options() {
+    assert_eq!(
+        run_lint("[[File:Example.png|thumb|first|200px|second]]"),
+        [Category::BogusImageOptions]
+    );
+}
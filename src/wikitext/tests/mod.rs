@@ -6,6 +6,9 @@ use crate::{
 use std::{fs::File, io::Read as _, path::Path};
 
 mod extras;
+mod json;
+mod lint;
+mod serializer;
 mod test_parser;
 
 const BASE_DIR: &str = "./src/wikitext/tests";
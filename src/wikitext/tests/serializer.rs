@@ -0,0 +1,89 @@
+use super::*;
+use crate::wikitext::{builder::tok_text, serializer::serialize};
+
+#[track_caller]
+fn round_trip(input: &str) {
+    let output = run_test(input);
+    assert_eq!(serialize(input, &output, &output.root), input);
+}
+
+/// Parses the input, lets `edit` modify a copy of the first template in it,
+/// and serialises the result.
+#[track_caller]
+fn edit_template(
+    input: &str,
+    edit: impl FnOnce(&mut String, &mut Vec<Spanned<Token>>, &mut Vec<Spanned<Argument>>),
+) -> String {
+    let mut source = input.to_owned();
+    let output = run_test(input);
+    let mut root = output.root.clone();
+    let template = root
+        .iter_mut()
+        .find_map(|token| match &mut token.node {
+            Token::Template { target, arguments } => Some((target, arguments)),
+            _ => None,
+        })
+        .expect("input should contain a template");
+    edit(&mut source, template.0, template.1);
+    serialize(&source, &output, &root)
+}
+
+#[test]
+fn serializer_round_trip() {
+    round_trip("Hello ''world''.\n\n== Heading ==\n* a\n** b\n");
+    round_trip("{{Template|a=1| b |{{{param|default}}}}} [[Link|text]]s");
+    round_trip("<div class=\"a\" id=b>x</div><ref name=\"r\">y</ref><br/>");
+    round_trip("{| class=\"wikitable\"\n|-\n! a !! b\n|-\n| style=\"x\" | c || d\n|}\n");
+    round_trip("[https://example.com example] <!-- comment --> &amp; __NOTOC__");
+}
+
+#[test]
+fn serializer_rename_template() {
+    assert_eq!(
+        edit_template("x {{Foo|a=1|b}} y", |source, target, _| {
+            *target = vec![tok_text(source, "Bar")];
+        }),
+        "x {{Bar|a=1|b}} y"
+    );
+}
+
+#[test]
+fn serializer_remove_argument() {
+    assert_eq!(
+        edit_template("{{Foo|a=1|b|c=3}}", |_, _, arguments| {
+            arguments.remove(1);
+        }),
+        "{{Foo|a=1|c=3}}"
+    );
+}
+
+#[test]
+fn serializer_rewrite_argument() {
+    assert_eq!(
+        edit_template("{{Foo| a = 1 |b}}", |source, _, arguments| {
+            let argument = &mut arguments[0].node;
+            let value = argument.delimiter.unwrap() + 1;
+            argument.content.truncate(value);
+            argument.content.push(tok_text(source, "2 "));
+        }),
+        "{{Foo| a = 2 |b}}"
+    );
+}
+
+#[test]
+fn serializer_add_argument() {
+    assert_eq!(
+        edit_template("{{Foo|a}}", |source, _, arguments| {
+            arguments.push(Spanned::new(
+                Argument {
+                    content: vec![tok_text(source, "b")],
+                    delimiter: None,
+                    terminator: None,
+                },
+                source.len() - 1,
+                source.len(),
+            ));
+        }),
+        "{{Foo|a|b}}"
+    );
+}